@@ -0,0 +1,1516 @@
+use crate::{
+    build_14_bit_value_from_two_7_bit_values, extract_high_7_bit_value_from_14_bit_value,
+    extract_low_7_bit_value_from_14_bit_value, ManufacturerId, PropertyExchangeChunk,
+    PropertyExchangeMessageType, SysExError, SysExMessage, TryFromGreaterError,
+    UnexpectedSysExMessageError, SYS_EX_END, SYS_EX_START, U14, U7,
+};
+use core::convert::TryFrom;
+use core::fmt;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde_repr")]
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// Message version of MIDI-CI 1.1.
+pub const CI_VERSION_1_1: U7 = U7(0x01);
+
+/// Message version of MIDI-CI 1.2, the latest version supported by this crate.
+pub const CI_VERSION_1_2: U7 = U7(0x02);
+
+/// Device ID which addresses the whole function block (or the whole port in MIDI-CI 1.1) instead
+/// of a single channel.
+pub const CI_FUNCTION_BLOCK_DEVICE_ID: U7 = U7(0x7F);
+
+/// Sub-ID #1 of MIDI-CI messages.
+const MIDI_CI: u8 = 0x0D;
+
+const PROFILE_INQUIRY: u8 = 0x20;
+const PROFILE_INQUIRY_REPLY: u8 = 0x21;
+const SET_PROFILE_ON: u8 = 0x22;
+const SET_PROFILE_OFF: u8 = 0x23;
+const PROFILE_ENABLED_REPORT: u8 = 0x24;
+const PROFILE_DISABLED_REPORT: u8 = 0x25;
+const PROPERTY_EXCHANGE_CAPABILITIES_INQUIRY: u8 = 0x30;
+const PROPERTY_EXCHANGE_CAPABILITIES_REPLY: u8 = 0x31;
+const PROCESS_INQUIRY_CAPABILITIES_INQUIRY: u8 = 0x40;
+const PROCESS_INQUIRY_CAPABILITIES_REPLY: u8 = 0x41;
+const MIDI_MESSAGE_REPORT_INQUIRY: u8 = 0x42;
+const MIDI_MESSAGE_REPORT_REPLY: u8 = 0x43;
+const MIDI_MESSAGE_REPORT_END: u8 = 0x44;
+const DISCOVERY: u8 = 0x70;
+const DISCOVERY_REPLY: u8 = 0x71;
+const ACK: u8 = 0x7D;
+const INVALIDATE_MUID: u8 = 0x7E;
+const NAK: u8 = 0x7F;
+
+/// A MIDI-CI Unique Identifier (MUID), a random 28-bit number which identifies a MIDI-CI device
+/// for the duration of a session.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::Muid;
+///
+/// let muid = Muid::from_random(0x1234_5678);
+/// assert_eq!(muid.get(), 0x0234_5678);
+/// assert!(!muid.is_reserved());
+/// assert!(Muid::BROADCAST.is_reserved());
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u32")
+)]
+pub struct Muid(u32);
+
+impl Muid {
+    /// The MUID which addresses all devices, e.g. used as destination of Discovery messages.
+    pub const BROADCAST: Muid = Muid(0x0FFF_FFFF);
+
+    /// The largest valid MUID.
+    pub const MAX: Muid = Muid(0x0FFF_FFFF);
+
+    /// Start of the reserved MUID range (including [`BROADCAST`]).
+    ///
+    /// [`BROADCAST`]: #associatedconstant.BROADCAST
+    const RESERVED_START: u32 = 0x0FFF_FF00;
+
+    /// Creates a MUID.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `value` has more than 28 bits.
+    pub fn new(value: u32) -> Muid {
+        assert!(value <= Self::MAX.0, "MUID must have 28 bits");
+        Muid(value)
+    }
+
+    /// Creates a MUID which is not reserved from the given random number.
+    ///
+    /// Use this in environments which provide their own source of randomness.
+    pub fn from_random(random: u32) -> Muid {
+        Muid((random & Self::MAX.0) % Self::RESERVED_START)
+    }
+
+    /// Generates a random MUID which is not reserved.
+    #[cfg(feature = "std")]
+    pub fn random() -> Muid {
+        use std::collections::hash_map::RandomState;
+        use std::hash::{BuildHasher, Hasher};
+        use std::time::{SystemTime, UNIX_EPOCH};
+        let mut hasher = RandomState::new().build_hasher();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        hasher.write_u128(nanos);
+        Muid::from_random(hasher.finish() as u32)
+    }
+
+    /// Returns the value.
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns whether this MUID is reserved and therefore can't be used by a device.
+    pub fn is_reserved(&self) -> bool {
+        self.0 >= Self::RESERVED_START
+    }
+}
+
+impl TryFrom<u32> for Muid {
+    type Error = TryFromGreaterError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value > Self::MAX.0 {
+            return Err(TryFromGreaterError(()));
+        }
+        Ok(Muid(value))
+    }
+}
+
+/// The header of each MIDI-CI message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct CiHeader {
+    device_id: U7,
+    version: U7,
+    source: Muid,
+    destination: Muid,
+}
+
+impl CiHeader {
+    /// Creates a header.
+    ///
+    /// The device ID is either a channel (0 - 15) or [`CI_FUNCTION_BLOCK_DEVICE_ID`]. The version
+    /// determines which fields are written, e.g. use [`CI_VERSION_1_1`] for talking to older
+    /// devices.
+    ///
+    /// [`CI_FUNCTION_BLOCK_DEVICE_ID`]: constant.CI_FUNCTION_BLOCK_DEVICE_ID.html
+    /// [`CI_VERSION_1_1`]: constant.CI_VERSION_1_1.html
+    pub fn new(device_id: U7, version: U7, source: Muid, destination: Muid) -> CiHeader {
+        CiHeader {
+            device_id,
+            version,
+            source,
+            destination,
+        }
+    }
+
+    /// Returns the device ID.
+    pub fn device_id(&self) -> U7 {
+        self.device_id
+    }
+
+    /// Returns the message version.
+    pub fn version(&self) -> U7 {
+        self.version
+    }
+
+    /// Returns the MUID of the sender.
+    pub fn source(&self) -> Muid {
+        self.source
+    }
+
+    /// Returns the MUID of the receiver.
+    pub fn destination(&self) -> Muid {
+        self.destination
+    }
+
+    fn has_1_2_fields(&self) -> bool {
+        self.version >= CI_VERSION_1_2
+    }
+}
+
+/// The MIDI-CI categories supported by a device, as reported in Discovery messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CiCategories(u8);
+
+impl CiCategories {
+    /// Protocol Negotiation (deprecated since MIDI-CI 1.2).
+    pub const PROTOCOL_NEGOTIATION: CiCategories = CiCategories(0x02);
+    /// Profile Configuration.
+    pub const PROFILE_CONFIGURATION: CiCategories = CiCategories(0x04);
+    /// Property Exchange.
+    pub const PROPERTY_EXCHANGE: CiCategories = CiCategories(0x08);
+    /// Process Inquiry.
+    pub const PROCESS_INQUIRY: CiCategories = CiCategories(0x10);
+
+    /// Creates categories from the given bitmap.
+    pub fn from_bits(bits: U7) -> CiCategories {
+        CiCategories(bits.get())
+    }
+
+    /// Returns the bitmap.
+    pub fn bits(&self) -> U7 {
+        U7(self.0)
+    }
+
+    /// Returns whether all of the given categories are contained.
+    pub fn contains(&self, other: CiCategories) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the union of these and the given categories.
+    pub fn with(&self, other: CiCategories) -> CiCategories {
+        CiCategories(self.0 | other.0)
+    }
+}
+
+/// Information about a device, contained in Discovery and Reply to Discovery messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct CiDeviceInfo {
+    manufacturer_id: ManufacturerId,
+    family: U14,
+    model: U14,
+    software_revision_level: [U7; 4],
+    categories: CiCategories,
+    max_sys_ex_size: u32,
+}
+
+impl CiDeviceInfo {
+    /// Creates device information.
+    ///
+    /// `max_sys_ex_size` is the maximum size of System Exclusive messages the device can receive.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `max_sys_ex_size` has more than 28 bits.
+    pub fn new(
+        manufacturer_id: ManufacturerId,
+        family: U14,
+        model: U14,
+        software_revision_level: [U7; 4],
+        categories: CiCategories,
+        max_sys_ex_size: u32,
+    ) -> CiDeviceInfo {
+        assert!(
+            max_sys_ex_size < 1 << 28,
+            "maximum System Exclusive size must have 28 bits"
+        );
+        CiDeviceInfo {
+            manufacturer_id,
+            family,
+            model,
+            software_revision_level,
+            categories,
+            max_sys_ex_size,
+        }
+    }
+
+    /// Returns the manufacturer ID.
+    pub fn manufacturer_id(&self) -> ManufacturerId {
+        self.manufacturer_id
+    }
+
+    /// Returns the device family.
+    pub fn family(&self) -> U14 {
+        self.family
+    }
+
+    /// Returns the device family model number.
+    pub fn model(&self) -> U14 {
+        self.model
+    }
+
+    /// Returns the software revision level. Its format is manufacturer-specific.
+    pub fn software_revision_level(&self) -> [U7; 4] {
+        self.software_revision_level
+    }
+
+    /// Returns the supported MIDI-CI categories.
+    pub fn categories(&self) -> CiCategories {
+        self.categories
+    }
+
+    /// Returns the maximum size of System Exclusive messages the device can receive.
+    pub fn max_sys_ex_size(&self) -> u32 {
+        self.max_sys_ex_size
+    }
+}
+
+/// The content of ACK and NAK messages.
+///
+/// In MIDI-CI 1.1, NAK messages don't have any content. When parsing such a message, all fields
+/// are zero.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct CiAcknowledgement<'a> {
+    original_sub_id_2: U7,
+    status_code: U7,
+    status_data: U7,
+    details: [U7; 5],
+    message_text: &'a [u8],
+}
+
+impl<'a> CiAcknowledgement<'a> {
+    /// Creates the content of an ACK or NAK message which refers to the message with the given
+    /// sub-ID #2.
+    ///
+    /// The message text is supposed to be ASCII and is written as is. If it contains bytes greater
+    /// than 127, writing the message fails.
+    pub fn new(
+        original_sub_id_2: U7,
+        status_code: U7,
+        status_data: U7,
+        details: [U7; 5],
+        message_text: &'a [u8],
+    ) -> CiAcknowledgement<'a> {
+        CiAcknowledgement {
+            original_sub_id_2,
+            status_code,
+            status_data,
+            details,
+            message_text,
+        }
+    }
+
+    /// Returns the sub-ID #2 of the message which is acknowledged.
+    pub fn original_sub_id_2(&self) -> U7 {
+        self.original_sub_id_2
+    }
+
+    /// Returns the status code (0 for ACK and NAK without further information).
+    pub fn status_code(&self) -> U7 {
+        self.status_code
+    }
+
+    /// Returns the status data.
+    pub fn status_data(&self) -> U7 {
+        self.status_data
+    }
+
+    /// Returns details which depend on the original message.
+    pub fn details(&self) -> [U7; 5] {
+        self.details
+    }
+
+    /// Returns the human-readable message text.
+    pub fn message_text(&self) -> &'a [u8] {
+        self.message_text
+    }
+}
+
+/// The ID of a MIDI-CI Profile, made up by 5 bytes.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProfileId([U7; 5]);
+
+impl ProfileId {
+    /// Creates a profile ID.
+    pub fn new(bytes: [U7; 5]) -> ProfileId {
+        ProfileId(bytes)
+    }
+
+    /// Returns the bytes.
+    pub fn bytes(&self) -> [U7; 5] {
+        self.0
+    }
+
+    /// Returns whether this is a standard profile defined by the MIDI Association (as opposed to a
+    /// manufacturer-specific profile).
+    pub fn is_standard(&self) -> bool {
+        self.0[0] == U7(0x7E)
+    }
+
+    fn from_slice(bytes: &[u8]) -> ProfileId {
+        ProfileId([
+            U7(bytes[0]),
+            U7(bytes[1]),
+            U7(bytes[2]),
+            U7(bytes[3]),
+            U7(bytes[4]),
+        ])
+    }
+}
+
+/// A list of profile IDs as contained in a Reply to Profile Inquiry message.
+///
+/// Either borrowed from a slice of profile IDs (when creating a message) or from the raw bytes of
+/// a parsed message. In both cases, no allocation is necessary.
+#[derive(Copy, Clone)]
+pub struct ProfileIds<'a>(ProfileIdsRepr<'a>);
+
+#[derive(Copy, Clone)]
+enum ProfileIdsRepr<'a> {
+    Ids(&'a [ProfileId]),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> ProfileIds<'a> {
+    /// Creates a list from the given profile IDs.
+    pub fn new(ids: &'a [ProfileId]) -> ProfileIds<'a> {
+        ProfileIds(ProfileIdsRepr::Ids(ids))
+    }
+
+    /// Returns the number of profile IDs.
+    pub fn len(&self) -> usize {
+        match self.0 {
+            ProfileIdsRepr::Ids(ids) => ids.len(),
+            ProfileIdsRepr::Bytes(bytes) => bytes.len() / 5,
+        }
+    }
+
+    /// Returns whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the profile IDs.
+    pub fn iter(&self) -> impl Iterator<Item = ProfileId> + 'a {
+        let (ids, bytes) = match self.0 {
+            ProfileIdsRepr::Ids(ids) => (ids, &[][..]),
+            ProfileIdsRepr::Bytes(bytes) => (&[][..], bytes),
+        };
+        ids.iter()
+            .copied()
+            .chain(bytes.chunks(5).map(ProfileId::from_slice))
+    }
+}
+
+impl<'a> Default for ProfileIds<'a> {
+    fn default() -> Self {
+        ProfileIds::new(&[])
+    }
+}
+
+impl<'a> PartialEq for ProfileIds<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<'a> Eq for ProfileIds<'a> {}
+
+impl<'a> fmt::Debug for ProfileIds<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// The Property Exchange capabilities of a device.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PropertyExchangeCapabilities {
+    simultaneous_requests: U7,
+    major_version: U7,
+    minor_version: U7,
+}
+
+impl PropertyExchangeCapabilities {
+    /// Creates Property Exchange capabilities.
+    ///
+    /// The version refers to the Property Exchange specification (e.g. 0.0 for MIDI-CI 1.1
+    /// devices).
+    pub fn new(
+        simultaneous_requests: U7,
+        major_version: U7,
+        minor_version: U7,
+    ) -> PropertyExchangeCapabilities {
+        PropertyExchangeCapabilities {
+            simultaneous_requests,
+            major_version,
+            minor_version,
+        }
+    }
+
+    /// Returns the number of simultaneous Property Exchange requests supported by the device.
+    pub fn simultaneous_requests(&self) -> U7 {
+        self.simultaneous_requests
+    }
+
+    /// Returns the major version of the Property Exchange specification.
+    pub fn major_version(&self) -> U7 {
+        self.major_version
+    }
+
+    /// Returns the minor version of the Property Exchange specification.
+    pub fn minor_version(&self) -> U7 {
+        self.minor_version
+    }
+}
+
+/// Determines which data a device sends in response to a MIDI Message Report inquiry.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, IntoPrimitive, TryFromPrimitive,
+)]
+#[cfg_attr(feature = "serde_repr", derive(Serialize_repr, Deserialize_repr))]
+#[repr(u8)]
+pub enum MidiMessageReportDataControl {
+    /// No data, just the reply and the end message.
+    NoData = 0x00,
+    /// Only messages whose values differ from their defaults.
+    NonDefaultValues = 0x01,
+    /// All messages.
+    Full = 0x7F,
+}
+
+/// The message types which are requested in a MIDI Message Report inquiry or which are going to
+/// be sent according to its reply, each given as bitmap.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct MidiMessageReportTypes {
+    system_messages: U7,
+    channel_controller_messages: U7,
+    note_data_messages: U7,
+}
+
+impl MidiMessageReportTypes {
+    /// Creates MIDI Message Report message types from the given bitmaps.
+    pub fn new(
+        system_messages: U7,
+        channel_controller_messages: U7,
+        note_data_messages: U7,
+    ) -> MidiMessageReportTypes {
+        MidiMessageReportTypes {
+            system_messages,
+            channel_controller_messages,
+            note_data_messages,
+        }
+    }
+
+    /// Returns the bitmap of system messages (0x01 = MTC Quarter Frame, 0x02 = Song Position,
+    /// 0x04 = Song Select).
+    pub fn system_messages(&self) -> U7 {
+        self.system_messages
+    }
+
+    /// Returns the bitmap of channel controller messages (0x01 = Pitch Bend, 0x02 = Control
+    /// Change, 0x04 = RPN, 0x08 = NRPN, 0x10 = Program Change, 0x20 = Channel Pressure).
+    pub fn channel_controller_messages(&self) -> U7 {
+        self.channel_controller_messages
+    }
+
+    /// Returns the bitmap of note data messages (0x01 = Notes, 0x02 = Polyphonic Key Pressure,
+    /// 0x04 = Per-Note Pitch Bend, 0x08 = Registered Per-Note Controllers, 0x10 = Assignable
+    /// Per-Note Controllers).
+    pub fn note_data_messages(&self) -> U7 {
+        self.note_data_messages
+    }
+}
+
+/// A MIDI-CI (Capability Inquiry) message, transmitted as Universal Non-Real Time System
+/// Exclusive message with sub-ID #1 `0D`.
+///
+/// Each message is preceded by a [`CiHeader`] which contains the device ID, the message version
+/// and the MUIDs of sender and receiver. The version in the header determines which fields are
+/// written. When parsing, fields which have been introduced in MIDI-CI 1.2 are only read if the
+/// message has at least this version. Additional fields of messages with a version newer than 1.2
+/// are ignored, so that devices supporting future versions can still be understood.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{u14, u7};
+/// use helgoboss_midi::{
+///     CiCategories, CiDeviceInfo, CiHeader, CiMessage, ManufacturerId, Muid,
+///     CI_FUNCTION_BLOCK_DEVICE_ID, CI_VERSION_1_2,
+/// };
+///
+/// let header = CiHeader::new(
+///     CI_FUNCTION_BLOCK_DEVICE_ID,
+///     CI_VERSION_1_2,
+///     Muid::new(0x0123_4567),
+///     Muid::BROADCAST,
+/// );
+/// let discovery = CiMessage::Discovery {
+///     device_info: CiDeviceInfo::new(
+///         ManufacturerId::NON_COMMERCIAL,
+///         u14(1),
+///         u14(2),
+///         [u7(0), u7(0), u7(1), u7(0)],
+///         CiCategories::PROFILE_CONFIGURATION.with(CiCategories::PROPERTY_EXCHANGE),
+///         512,
+///     ),
+///     output_path_id: u7(0),
+/// };
+/// let mut buffer = [0; 64];
+/// let msg = discovery.write(&header, &mut buffer).unwrap();
+/// assert_eq!(&msg.as_bytes()[..6], &[0xF0, 0x7E, 0x7F, 0x0D, 0x70, 0x02]);
+/// assert_eq!(CiMessage::parse(msg), Ok((header, discovery)));
+/// ```
+///
+/// [`CiHeader`]: struct.CiHeader.html
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CiMessage<'a> {
+    /// Discovery (sent to [`Muid::BROADCAST`]).
+    ///
+    /// [`Muid::BROADCAST`]: struct.Muid.html#associatedconstant.BROADCAST
+    Discovery {
+        device_info: CiDeviceInfo,
+        /// Added in MIDI-CI 1.2.
+        output_path_id: U7,
+    },
+    /// Reply to Discovery.
+    DiscoveryReply {
+        device_info: CiDeviceInfo,
+        /// Added in MIDI-CI 1.2.
+        output_path_id: U7,
+        /// The function block of the replying device or 0x7F if none. Added in MIDI-CI 1.2.
+        function_block: U7,
+    },
+    /// Invalidate MUID (sent to [`Muid::BROADCAST`]).
+    ///
+    /// [`Muid::BROADCAST`]: struct.Muid.html#associatedconstant.BROADCAST
+    InvalidateMuid { target_muid: Muid },
+    /// ACK. Added in MIDI-CI 1.2.
+    Ack(CiAcknowledgement<'a>),
+    /// NAK.
+    Nak(CiAcknowledgement<'a>),
+    /// Profile Inquiry.
+    ProfileInquiry,
+    /// Reply to Profile Inquiry.
+    ProfileInquiryReply {
+        enabled_profiles: ProfileIds<'a>,
+        disabled_profiles: ProfileIds<'a>,
+    },
+    /// Set Profile On.
+    SetProfileOn {
+        profile_id: ProfileId,
+        /// Number of channels to enable (0 for single-channel profiles). Added in MIDI-CI 1.2.
+        channel_count: U14,
+    },
+    /// Set Profile Off.
+    SetProfileOff { profile_id: ProfileId },
+    /// Profile Enabled Report.
+    ProfileEnabledReport {
+        profile_id: ProfileId,
+        /// Added in MIDI-CI 1.2.
+        channel_count: U14,
+    },
+    /// Profile Disabled Report.
+    ProfileDisabledReport {
+        profile_id: ProfileId,
+        /// Added in MIDI-CI 1.2.
+        channel_count: U14,
+    },
+    /// Inquiry: Property Exchange Capabilities.
+    PropertyExchangeCapabilitiesInquiry(PropertyExchangeCapabilities),
+    /// Reply to Property Exchange Capabilities.
+    PropertyExchangeCapabilitiesReply(PropertyExchangeCapabilities),
+    /// Inquiry: Process Inquiry Capabilities. Added in MIDI-CI 1.2.
+    ProcessInquiryCapabilitiesInquiry,
+    /// Reply to Process Inquiry Capabilities. Added in MIDI-CI 1.2.
+    ProcessInquiryCapabilitiesReply {
+        /// Bitmap of supported features (0x01 = MIDI Message Report).
+        supported_features: U7,
+    },
+    /// Inquiry: MIDI Message Report, which asks the device to send messages reflecting its current
+    /// state. Added in MIDI-CI 1.2.
+    MidiMessageReportInquiry {
+        data_control: MidiMessageReportDataControl,
+        message_types: MidiMessageReportTypes,
+    },
+    /// Reply to MIDI Message Report, containing the requested message types which the device is
+    /// going to send. Added in MIDI-CI 1.2.
+    MidiMessageReportReply(MidiMessageReportTypes),
+    /// End of MIDI Message Report, sent after the reported messages. Added in MIDI-CI 1.2.
+    MidiMessageReportEnd,
+    /// Property Exchange message (Get, Set, Subscription or Notify).
+    ///
+    /// See [`PropertyExchangeChunker`] for splitting large property data into several messages.
+    ///
+    /// [`PropertyExchangeChunker`]: struct.PropertyExchangeChunker.html
+    PropertyExchange {
+        message_type: PropertyExchangeMessageType,
+        chunk: PropertyExchangeChunk<'a>,
+    },
+}
+
+impl<'a> CiMessage<'a> {
+    /// Returns the sub-ID #2 which identifies this message.
+    pub fn sub_id_2(&self) -> U7 {
+        use CiMessage::*;
+        let sub_id_2 = match self {
+            Discovery { .. } => DISCOVERY,
+            DiscoveryReply { .. } => DISCOVERY_REPLY,
+            InvalidateMuid { .. } => INVALIDATE_MUID,
+            Ack(_) => ACK,
+            Nak(_) => NAK,
+            ProfileInquiry => PROFILE_INQUIRY,
+            ProfileInquiryReply { .. } => PROFILE_INQUIRY_REPLY,
+            SetProfileOn { .. } => SET_PROFILE_ON,
+            SetProfileOff { .. } => SET_PROFILE_OFF,
+            ProfileEnabledReport { .. } => PROFILE_ENABLED_REPORT,
+            ProfileDisabledReport { .. } => PROFILE_DISABLED_REPORT,
+            PropertyExchangeCapabilitiesInquiry(_) => PROPERTY_EXCHANGE_CAPABILITIES_INQUIRY,
+            PropertyExchangeCapabilitiesReply(_) => PROPERTY_EXCHANGE_CAPABILITIES_REPLY,
+            ProcessInquiryCapabilitiesInquiry => PROCESS_INQUIRY_CAPABILITIES_INQUIRY,
+            ProcessInquiryCapabilitiesReply { .. } => PROCESS_INQUIRY_CAPABILITIES_REPLY,
+            MidiMessageReportInquiry { .. } => MIDI_MESSAGE_REPORT_INQUIRY,
+            MidiMessageReportReply(_) => MIDI_MESSAGE_REPORT_REPLY,
+            MidiMessageReportEnd => MIDI_MESSAGE_REPORT_END,
+            PropertyExchange { message_type, .. } => (*message_type).into(),
+        };
+        U7(sub_id_2)
+    }
+
+    /// Writes this message with the given header as System Exclusive message into the given
+    /// buffer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small or if the manufacturer ID or message text are
+    /// invalid.
+    pub fn write<'b>(
+        &self,
+        header: &CiHeader,
+        buffer: &'b mut [u8],
+    ) -> Result<SysExMessage<'b>, SysExError> {
+        use CiMessage::*;
+        let mut writer = Writer {
+            buffer: &mut *buffer,
+            count: 0,
+        };
+        writer.bytes(&[
+            SYS_EX_START,
+            0x7E,
+            header.device_id.get(),
+            MIDI_CI,
+            self.sub_id_2().get(),
+            header.version.get(),
+        ])?;
+        writer.u28(header.source.0)?;
+        writer.u28(header.destination.0)?;
+        let has_1_2_fields = header.has_1_2_fields();
+        match self {
+            Discovery {
+                device_info,
+                output_path_id,
+            } => {
+                writer.device_info(device_info)?;
+                if has_1_2_fields {
+                    writer.u7(*output_path_id)?;
+                }
+            }
+            DiscoveryReply {
+                device_info,
+                output_path_id,
+                function_block,
+            } => {
+                writer.device_info(device_info)?;
+                if has_1_2_fields {
+                    writer.u7(*output_path_id)?;
+                    writer.u7(*function_block)?;
+                }
+            }
+            InvalidateMuid { target_muid } => writer.u28(target_muid.0)?,
+            Ack(acknowledgement) => writer.acknowledgement(acknowledgement)?,
+            Nak(acknowledgement) => {
+                if has_1_2_fields {
+                    writer.acknowledgement(acknowledgement)?;
+                }
+            }
+            ProfileInquiry | ProcessInquiryCapabilitiesInquiry | MidiMessageReportEnd => {}
+            ProfileInquiryReply {
+                enabled_profiles,
+                disabled_profiles,
+            } => {
+                writer.profile_ids(enabled_profiles)?;
+                writer.profile_ids(disabled_profiles)?;
+            }
+            SetProfileOn {
+                profile_id,
+                channel_count,
+            }
+            | ProfileEnabledReport {
+                profile_id,
+                channel_count,
+            }
+            | ProfileDisabledReport {
+                profile_id,
+                channel_count,
+            } => {
+                writer.profile_id(profile_id)?;
+                if has_1_2_fields {
+                    writer.u14(*channel_count)?;
+                }
+            }
+            SetProfileOff { profile_id } => {
+                writer.profile_id(profile_id)?;
+                if has_1_2_fields {
+                    writer.u14(U14::MIN)?;
+                }
+            }
+            PropertyExchangeCapabilitiesInquiry(capabilities)
+            | PropertyExchangeCapabilitiesReply(capabilities) => {
+                writer.u7(capabilities.simultaneous_requests)?;
+                if has_1_2_fields {
+                    writer.u7(capabilities.major_version)?;
+                    writer.u7(capabilities.minor_version)?;
+                }
+            }
+            ProcessInquiryCapabilitiesReply { supported_features } => {
+                writer.u7(*supported_features)?
+            }
+            MidiMessageReportInquiry {
+                data_control,
+                message_types,
+            } => {
+                writer.bytes(&[u8::from(*data_control)])?;
+                writer.midi_message_report_types(message_types)?;
+            }
+            MidiMessageReportReply(message_types) => {
+                writer.midi_message_report_types(message_types)?
+            }
+            PropertyExchange { chunk, .. } => writer.property_exchange_chunk(chunk)?,
+        }
+        writer.bytes(&[SYS_EX_END])?;
+        let count = writer.count;
+        let buffer: &'b [u8] = buffer;
+        SysExMessage::from_bytes(&buffer[..count])
+    }
+
+    /// Interprets the given System Exclusive message as MIDI-CI message and returns it together
+    /// with its header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a supported MIDI-CI message or its content doesn't
+    /// match its version.
+    pub fn parse(
+        msg: SysExMessage<'a>,
+    ) -> Result<(CiHeader, CiMessage<'a>), UnexpectedSysExMessageError> {
+        use CiMessage::*;
+        if msg.manufacturer_id() != ManufacturerId::UNIVERSAL_NON_REAL_TIME {
+            return Err(UnexpectedSysExMessageError(()));
+        }
+        let mut reader = Reader {
+            data: msg.payload(),
+        };
+        let device_id = reader.u7()?;
+        if reader.u7()?.get() != MIDI_CI {
+            return Err(UnexpectedSysExMessageError(()));
+        }
+        let sub_id_2 = reader.u7()?.get();
+        let version = reader.u7()?;
+        let source = Muid(reader.u28()?);
+        let destination = Muid(reader.u28()?);
+        let header = CiHeader::new(device_id, version, source, destination);
+        let has_1_2_fields = header.has_1_2_fields();
+        let optional_u7 = |reader: &mut Reader<'a>| {
+            if has_1_2_fields {
+                reader.u7()
+            } else {
+                Ok(U7::MIN)
+            }
+        };
+        let optional_u14 = |reader: &mut Reader<'a>| {
+            if has_1_2_fields {
+                reader.u14()
+            } else {
+                Ok(U14::MIN)
+            }
+        };
+        let msg = match sub_id_2 {
+            DISCOVERY => Discovery {
+                device_info: reader.device_info()?,
+                output_path_id: optional_u7(&mut reader)?,
+            },
+            DISCOVERY_REPLY => DiscoveryReply {
+                device_info: reader.device_info()?,
+                output_path_id: optional_u7(&mut reader)?,
+                function_block: if has_1_2_fields {
+                    reader.u7()?
+                } else {
+                    U7(0x7F)
+                },
+            },
+            INVALIDATE_MUID => InvalidateMuid {
+                target_muid: Muid(reader.u28()?),
+            },
+            ACK => Ack(reader.acknowledgement()?),
+            NAK => Nak(if has_1_2_fields {
+                reader.acknowledgement()?
+            } else {
+                Default::default()
+            }),
+            PROFILE_INQUIRY => ProfileInquiry,
+            PROFILE_INQUIRY_REPLY => ProfileInquiryReply {
+                enabled_profiles: reader.profile_ids()?,
+                disabled_profiles: reader.profile_ids()?,
+            },
+            SET_PROFILE_ON => SetProfileOn {
+                profile_id: reader.profile_id()?,
+                channel_count: optional_u14(&mut reader)?,
+            },
+            SET_PROFILE_OFF => {
+                let profile_id = reader.profile_id()?;
+                optional_u14(&mut reader)?;
+                SetProfileOff { profile_id }
+            }
+            PROFILE_ENABLED_REPORT => ProfileEnabledReport {
+                profile_id: reader.profile_id()?,
+                channel_count: optional_u14(&mut reader)?,
+            },
+            PROFILE_DISABLED_REPORT => ProfileDisabledReport {
+                profile_id: reader.profile_id()?,
+                channel_count: optional_u14(&mut reader)?,
+            },
+            PROPERTY_EXCHANGE_CAPABILITIES_INQUIRY => PropertyExchangeCapabilitiesInquiry(
+                reader.property_exchange_capabilities(has_1_2_fields)?,
+            ),
+            PROPERTY_EXCHANGE_CAPABILITIES_REPLY => PropertyExchangeCapabilitiesReply(
+                reader.property_exchange_capabilities(has_1_2_fields)?,
+            ),
+            PROCESS_INQUIRY_CAPABILITIES_INQUIRY => ProcessInquiryCapabilitiesInquiry,
+            PROCESS_INQUIRY_CAPABILITIES_REPLY => ProcessInquiryCapabilitiesReply {
+                supported_features: reader.u7()?,
+            },
+            MIDI_MESSAGE_REPORT_INQUIRY => MidiMessageReportInquiry {
+                data_control: MidiMessageReportDataControl::try_from(reader.u7()?.get())
+                    .map_err(|_| UnexpectedSysExMessageError(()))?,
+                message_types: reader.midi_message_report_types()?,
+            },
+            MIDI_MESSAGE_REPORT_REPLY => {
+                MidiMessageReportReply(reader.midi_message_report_types()?)
+            }
+            MIDI_MESSAGE_REPORT_END => MidiMessageReportEnd,
+            _ => match PropertyExchangeMessageType::try_from(sub_id_2) {
+                Ok(message_type) => PropertyExchange {
+                    message_type,
+                    chunk: reader.property_exchange_chunk()?,
+                },
+                Err(_) => return Err(UnexpectedSysExMessageError(())),
+            },
+        };
+        if !reader.data.is_empty() && version <= CI_VERSION_1_2 {
+            return Err(UnexpectedSysExMessageError(()));
+        }
+        Ok((header, msg))
+    }
+}
+
+/// Writes MIDI-CI fields into a buffer.
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    count: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), SysExError> {
+        let end = self.count + bytes.len();
+        if self.buffer.len() < end {
+            return Err(SysExError::BufferTooSmall);
+        }
+        self.buffer[self.count..end].copy_from_slice(bytes);
+        self.count = end;
+        Ok(())
+    }
+
+    fn u7(&mut self, value: U7) -> Result<(), SysExError> {
+        self.bytes(&[value.get()])
+    }
+
+    /// Writes a 14-bit value, LSB first.
+    fn u14(&mut self, value: U14) -> Result<(), SysExError> {
+        self.bytes(&[
+            extract_low_7_bit_value_from_14_bit_value(value).get(),
+            extract_high_7_bit_value_from_14_bit_value(value).get(),
+        ])
+    }
+
+    /// Writes a 28-bit value, LSB first.
+    fn u28(&mut self, value: u32) -> Result<(), SysExError> {
+        self.bytes(&[
+            (value & 0x7F) as u8,
+            ((value >> 7) & 0x7F) as u8,
+            ((value >> 14) & 0x7F) as u8,
+            ((value >> 21) & 0x7F) as u8,
+        ])
+    }
+
+    fn device_info(&mut self, info: &CiDeviceInfo) -> Result<(), SysExError> {
+        // MIDI-CI always uses 3 bytes for the manufacturer ID.
+        let manufacturer_id = match info.manufacturer_id {
+            ManufacturerId::Standard(b) if b != U7::MIN => [b.get(), 0, 0],
+            ManufacturerId::Extended(b1, b2) => [0, b1.get(), b2.get()],
+            _ => return Err(SysExError::InvalidManufacturerId),
+        };
+        self.bytes(&manufacturer_id)?;
+        self.u14(info.family)?;
+        self.u14(info.model)?;
+        for b in info.software_revision_level.iter() {
+            self.u7(*b)?;
+        }
+        self.u7(info.categories.bits())?;
+        self.u28(info.max_sys_ex_size)
+    }
+
+    fn acknowledgement(&mut self, ack: &CiAcknowledgement) -> Result<(), SysExError> {
+        self.u7(ack.original_sub_id_2)?;
+        self.u7(ack.status_code)?;
+        self.u7(ack.status_data)?;
+        for b in ack.details.iter() {
+            self.u7(*b)?;
+        }
+        self.u14(length(ack.message_text)?)?;
+        self.bytes(ack.message_text)
+    }
+
+    fn profile_id(&mut self, id: &ProfileId) -> Result<(), SysExError> {
+        for b in id.0.iter() {
+            self.u7(*b)?;
+        }
+        Ok(())
+    }
+
+    fn profile_ids(&mut self, ids: &ProfileIds) -> Result<(), SysExError> {
+        if ids.len() > usize::from(U14::MAX.get()) {
+            return Err(SysExError::InvalidDataByte);
+        }
+        self.u14(U14(ids.len() as u16))?;
+        for id in ids.iter() {
+            self.profile_id(&id)?;
+        }
+        Ok(())
+    }
+
+    fn midi_message_report_types(
+        &mut self,
+        types: &MidiMessageReportTypes,
+    ) -> Result<(), SysExError> {
+        // The byte after the system messages is reserved
+        self.bytes(&[types.system_messages.get(), 0x00])?;
+        self.u7(types.channel_controller_messages)?;
+        self.u7(types.note_data_messages)
+    }
+
+    fn property_exchange_chunk(&mut self, chunk: &PropertyExchangeChunk) -> Result<(), SysExError> {
+        self.u7(chunk.request_id())?;
+        self.u14(length(chunk.header_data())?)?;
+        self.bytes(chunk.header_data())?;
+        self.u14(chunk.chunk_count())?;
+        self.u14(chunk.chunk_number())?;
+        self.u14(length(chunk.property_data())?)?;
+        self.bytes(chunk.property_data())
+    }
+}
+
+/// Returns the length of a variable-length field, which must fit into 14 bits.
+fn length(bytes: &[u8]) -> Result<U14, SysExError> {
+    if bytes.len() > usize::from(U14::MAX.get()) {
+        return Err(SysExError::InvalidDataByte);
+    }
+    Ok(U14(bytes.len() as u16))
+}
+
+/// Reads MIDI-CI fields from the payload of a System Exclusive message.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], UnexpectedSysExMessageError> {
+        if self.data.len() < count {
+            return Err(UnexpectedSysExMessageError(()));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u7(&mut self) -> Result<U7, UnexpectedSysExMessageError> {
+        // The bytes of a System Exclusive message are guaranteed to be 7-bit.
+        Ok(U7(self.bytes(1)?[0]))
+    }
+
+    fn u14(&mut self) -> Result<U14, UnexpectedSysExMessageError> {
+        let lsb = self.u7()?;
+        let msb = self.u7()?;
+        Ok(build_14_bit_value_from_two_7_bit_values(msb, lsb))
+    }
+
+    fn u28(&mut self) -> Result<u32, UnexpectedSysExMessageError> {
+        let bytes = self.bytes(4)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, b| (value << 7) | u32::from(*b)))
+    }
+
+    fn device_info(&mut self) -> Result<CiDeviceInfo, UnexpectedSysExMessageError> {
+        let manufacturer_id = match *self.bytes(3)? {
+            [0, b1, b2] => ManufacturerId::Extended(U7(b1), U7(b2)),
+            [b, 0, 0] if b != 0 => ManufacturerId::Standard(U7(b)),
+            _ => return Err(UnexpectedSysExMessageError(())),
+        };
+        let family = self.u14()?;
+        let model = self.u14()?;
+        let software_revision_level = [self.u7()?, self.u7()?, self.u7()?, self.u7()?];
+        let categories = CiCategories::from_bits(self.u7()?);
+        let max_sys_ex_size = self.u28()?;
+        Ok(CiDeviceInfo::new(
+            manufacturer_id,
+            family,
+            model,
+            software_revision_level,
+            categories,
+            max_sys_ex_size,
+        ))
+    }
+
+    fn acknowledgement(&mut self) -> Result<CiAcknowledgement<'a>, UnexpectedSysExMessageError> {
+        let original_sub_id_2 = self.u7()?;
+        let status_code = self.u7()?;
+        let status_data = self.u7()?;
+        let details = [self.u7()?, self.u7()?, self.u7()?, self.u7()?, self.u7()?];
+        let text_length = self.u14()?.get();
+        let message_text = self.bytes(usize::from(text_length))?;
+        Ok(CiAcknowledgement::new(
+            original_sub_id_2,
+            status_code,
+            status_data,
+            details,
+            message_text,
+        ))
+    }
+
+    fn profile_id(&mut self) -> Result<ProfileId, UnexpectedSysExMessageError> {
+        Ok(ProfileId::from_slice(self.bytes(5)?))
+    }
+
+    fn profile_ids(&mut self) -> Result<ProfileIds<'a>, UnexpectedSysExMessageError> {
+        let count = self.u14()?.get();
+        let bytes = self.bytes(5 * usize::from(count))?;
+        Ok(ProfileIds(ProfileIdsRepr::Bytes(bytes)))
+    }
+
+    fn property_exchange_chunk(
+        &mut self,
+    ) -> Result<PropertyExchangeChunk<'a>, UnexpectedSysExMessageError> {
+        let request_id = self.u7()?;
+        let header_length = self.u14()?.get();
+        let header_data = self.bytes(usize::from(header_length))?;
+        let chunk_count = self.u14()?;
+        let chunk_number = self.u14()?;
+        let property_data_length = self.u14()?.get();
+        let property_data = self.bytes(usize::from(property_data_length))?;
+        Ok(PropertyExchangeChunk::new(
+            request_id,
+            header_data,
+            chunk_count,
+            chunk_number,
+            property_data,
+        ))
+    }
+
+    fn property_exchange_capabilities(
+        &mut self,
+        has_1_2_fields: bool,
+    ) -> Result<PropertyExchangeCapabilities, UnexpectedSysExMessageError> {
+        let simultaneous_requests = self.u7()?;
+        let (major_version, minor_version) = if has_1_2_fields {
+            (self.u7()?, self.u7()?)
+        } else {
+            (U7::MIN, U7::MIN)
+        };
+        Ok(PropertyExchangeCapabilities::new(
+            simultaneous_requests,
+            major_version,
+            minor_version,
+        ))
+    }
+
+    fn midi_message_report_types(
+        &mut self,
+    ) -> Result<MidiMessageReportTypes, UnexpectedSysExMessageError> {
+        let system_messages = self.u7()?;
+        // Reserved
+        self.u7()?;
+        Ok(MidiMessageReportTypes::new(
+            system_messages,
+            self.u7()?,
+            self.u7()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{u14, u7};
+
+    fn header(version: U7) -> CiHeader {
+        CiHeader::new(
+            CI_FUNCTION_BLOCK_DEVICE_ID,
+            version,
+            Muid::new(0x0123_4567),
+            Muid::new(0x0765_4321),
+        )
+    }
+
+    fn profile_id(last: u8) -> ProfileId {
+        ProfileId::new([u7(0x7E), u7(0x00), u7(0x01), u7(0x02), u7(last)])
+    }
+
+    fn round_trip(header: CiHeader, msg: CiMessage, expected_data: &[u8]) {
+        let mut buffer = [0; 128];
+        let sys_ex = msg.write(&header, &mut buffer).unwrap();
+        assert_eq!(
+            &sys_ex.as_bytes()[14..sys_ex.as_bytes().len() - 1],
+            expected_data
+        );
+        assert_eq!(CiMessage::parse(sys_ex), Ok((header, msg)));
+    }
+
+    #[test]
+    fn muid() {
+        // Given
+        // When
+        // Then
+        assert_eq!(Muid::from_random(0xFFFF_FFFF), Muid::new(0xFF));
+        assert!(!Muid::from_random(0x0FFF_FF00).is_reserved());
+        assert!(Muid::new(0x0FFF_FF00).is_reserved());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn random_muid() {
+        // Given
+        // When
+        let muid = Muid::random();
+        // Then
+        assert!(!muid.is_reserved());
+    }
+
+    #[test]
+    #[should_panic]
+    fn muid_too_large() {
+        // Given
+        // When
+        // Then
+        Muid::new(0x1000_0000);
+    }
+
+    #[test]
+    fn muid_try_from() {
+        // Given
+        // When
+        // Then
+        assert_eq!(Muid::try_from(0x0FFF_FFFF), Ok(Muid::MAX));
+        assert_eq!(Muid::try_from(0x1000_0000), Err(TryFromGreaterError(())));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_muid() {
+        // Given
+        // When
+        // Then
+        assert_eq!(
+            serde_json::from_str::<Muid>("268435455").unwrap(),
+            Muid::MAX
+        );
+        assert!(serde_json::from_str::<Muid>("268435456").is_err());
+    }
+
+    #[test]
+    fn header_bytes() {
+        // Given
+        let mut buffer = [0; 32];
+        // When
+        let msg = CiMessage::ProfileInquiry
+            .write(&header(CI_VERSION_1_2), &mut buffer)
+            .unwrap();
+        // Then
+        assert_eq!(
+            msg.as_bytes(),
+            &[
+                0xF0, 0x7E, 0x7F, 0x0D, 0x20, 0x02, 0x67, 0x0A, 0x0D, 0x09, 0x21, 0x06, 0x15, 0x3B,
+                0xF7
+            ]
+        );
+    }
+
+    #[test]
+    fn discovery_versions() {
+        // Given
+        let device_info = CiDeviceInfo::new(
+            ManufacturerId::Standard(u7(0x41)),
+            u14(0x0102),
+            u14(3),
+            [u7(1), u7(2), u7(3), u7(4)],
+            CiCategories::PROFILE_CONFIGURATION,
+            0x0000_4000,
+        );
+        let device_info_bytes = [
+            0x41, 0x00, 0x00, 0x02, 0x02, 0x03, 0x00, 0x01, 0x02, 0x03, 0x04, 0x04, 0x00, 0x00,
+            0x01, 0x00,
+        ];
+        // When
+        // Then
+        round_trip(
+            header(CI_VERSION_1_1),
+            CiMessage::DiscoveryReply {
+                device_info,
+                output_path_id: u7(0),
+                function_block: u7(0x7F),
+            },
+            &device_info_bytes,
+        );
+        let mut v2_bytes = device_info_bytes.to_vec();
+        v2_bytes.extend_from_slice(&[0x00, 0x05]);
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::DiscoveryReply {
+                device_info,
+                output_path_id: u7(0),
+                function_block: u7(5),
+            },
+            &v2_bytes,
+        );
+    }
+
+    #[test]
+    fn newer_version() {
+        // Given
+        let mut buffer = [0; 64];
+        let msg = CiMessage::SetProfileOn {
+            profile_id: profile_id(1),
+            channel_count: u14(1),
+        }
+        .write(&header(CI_VERSION_1_2), &mut buffer)
+        .unwrap();
+        let mut bytes = msg.as_bytes().to_vec();
+        // When
+        // Version 3 with an additional byte
+        bytes[5] = 0x03;
+        bytes.insert(bytes.len() - 1, 0x11);
+        let parsed = CiMessage::parse(SysExMessage::from_bytes(&bytes).unwrap());
+        // Then
+        assert_eq!(
+            parsed,
+            Ok((
+                header(U7::new(3)),
+                CiMessage::SetProfileOn {
+                    profile_id: profile_id(1),
+                    channel_count: u14(1),
+                }
+            ))
+        );
+        bytes[5] = 0x02;
+        assert_eq!(
+            CiMessage::parse(SysExMessage::from_bytes(&bytes).unwrap()),
+            Err(UnexpectedSysExMessageError(()))
+        );
+    }
+
+    #[test]
+    fn acknowledgements() {
+        // Given
+        let ack = CiAcknowledgement::new(u7(0x34), u7(0x00), u7(0x00), [u7(0); 5], b"OK");
+        // When
+        // Then
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::Ack(ack),
+            &[
+                0x34, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, b'O', b'K',
+            ],
+        );
+        round_trip(
+            header(CI_VERSION_1_1),
+            CiMessage::Nak(Default::default()),
+            &[],
+        );
+    }
+
+    #[test]
+    fn invalidate_muid() {
+        // Given
+        // When
+        // Then
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::InvalidateMuid {
+                target_muid: Muid::new(0x0FFF_FFFE),
+            },
+            &[0x7E, 0x7F, 0x7F, 0x7F],
+        );
+    }
+
+    #[test]
+    fn profiles() {
+        // Given
+        let enabled = [profile_id(1), profile_id(2)];
+        // When
+        // Then
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::ProfileInquiryReply {
+                enabled_profiles: ProfileIds::new(&enabled),
+                disabled_profiles: ProfileIds::default(),
+            },
+            &[
+                0x02, 0x00, 0x7E, 0x00, 0x01, 0x02, 0x01, 0x7E, 0x00, 0x01, 0x02, 0x02, 0x00, 0x00,
+            ],
+        );
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::SetProfileOff {
+                profile_id: profile_id(3),
+            },
+            &[0x7E, 0x00, 0x01, 0x02, 0x03, 0x00, 0x00],
+        );
+        round_trip(
+            header(CI_VERSION_1_1),
+            CiMessage::ProfileEnabledReport {
+                profile_id: profile_id(3),
+                channel_count: U14::MIN,
+            },
+            &[0x7E, 0x00, 0x01, 0x02, 0x03],
+        );
+    }
+
+    #[test]
+    fn property_exchange_and_process_inquiry() {
+        // Given
+        // When
+        // Then
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::PropertyExchangeCapabilitiesReply(PropertyExchangeCapabilities::new(
+                u7(4),
+                u7(0),
+                u7(1),
+            )),
+            &[0x04, 0x00, 0x01],
+        );
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::ProcessInquiryCapabilitiesReply {
+                supported_features: u7(1),
+            },
+            &[0x01],
+        );
+    }
+
+    #[test]
+    fn midi_message_report() {
+        // Given
+        let message_types = MidiMessageReportTypes::new(u7(0x07), u7(0x3F), u7(0x03));
+        // When
+        // Then
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::MidiMessageReportInquiry {
+                data_control: MidiMessageReportDataControl::NonDefaultValues,
+                message_types,
+            },
+            &[0x01, 0x07, 0x00, 0x3F, 0x03],
+        );
+        round_trip(
+            header(CI_VERSION_1_2),
+            CiMessage::MidiMessageReportReply(message_types),
+            &[0x07, 0x00, 0x3F, 0x03],
+        );
+        round_trip(header(CI_VERSION_1_2), CiMessage::MidiMessageReportEnd, &[]);
+    }
+
+    #[test]
+    fn midi_message_report_invalid_data_control() {
+        // Given
+        let bytes = [
+            0xF0, 0x7E, 0x7F, 0x0D, 0x42, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x02, 0x07, 0x00, 0x3F, 0x03, 0xF7,
+        ];
+        let msg = SysExMessage::from_bytes(&bytes).unwrap();
+        // When
+        let result = CiMessage::parse(msg);
+        // Then
+        assert_eq!(result, Err(UnexpectedSysExMessageError(())));
+    }
+
+    #[test]
+    fn unexpected() {
+        // Given
+        let msgs: [&[u8]; 3] = [
+            // Identity Request
+            &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7],
+            // Header too short
+            &[0xF0, 0x7E, 0x7F, 0x0D, 0x20, 0x02, 0x00, 0xF7],
+            // Unknown sub-ID #2
+            &[
+                0xF0, 0x7E, 0x7F, 0x0D, 0x10, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0xF7,
+            ],
+        ];
+        // When
+        // Then
+        for bytes in msgs.iter() {
+            let msg = SysExMessage::from_bytes(bytes).unwrap();
+            assert_eq!(CiMessage::parse(msg), Err(UnexpectedSysExMessageError(())));
+        }
+    }
+
+    #[test]
+    fn invalid_message_text() {
+        // Given
+        let ack = CiAcknowledgement::new(u7(0x34), u7(0), u7(0), [u7(0); 5], &[0x80]);
+        let mut buffer = [0; 64];
+        // When
+        let result = CiMessage::Nak(ack).write(&header(CI_VERSION_1_2), &mut buffer);
+        // Then
+        assert_eq!(result, Err(SysExError::InvalidDataByte));
+    }
+}
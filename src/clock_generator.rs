@@ -0,0 +1,535 @@
+use crate::{ShortMessageFactory, SongPosition, TransportState, TIMING_CLOCKS_PER_QUARTER_NOTE};
+use core::time::Duration;
+
+/// A short message together with the exact time at which it is due.
+///
+/// The time has nanosecond resolution, which allows for sub-sample accurate scheduling at any
+/// sample rate.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TimedShortMessage<T> {
+    time: Duration,
+    message: T,
+}
+
+impl<T> TimedShortMessage<T> {
+    /// Creates a message which is due at the given time.
+    pub fn new(time: Duration, message: T) -> TimedShortMessage<T> {
+        TimedShortMessage { time, message }
+    }
+
+    /// Returns the time at which the message is due.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Returns the message.
+    pub fn message(&self) -> &T {
+        &self.message
+    }
+
+    /// Consumes this value and returns the message.
+    pub fn into_message(self) -> T {
+        self.message
+    }
+
+    /// Returns the offset of the due time from the given block start time in samples, including
+    /// the fractional part.
+    ///
+    /// The offset is negative if the message is due before the block start.
+    pub fn offset_in_samples(&self, block_start: Duration, sample_rate: f64) -> f64 {
+        (self.time.as_secs_f64() - block_start.as_secs_f64()) * sample_rate
+    }
+}
+
+/// Generator for MIDI Timing Clock, e.g. for acting as clock master.
+///
+/// The generator is given a tempo and asked for the messages which are due until a certain
+/// (monotonically advancing) time. It yields Timing Clock messages at 24 per quarter note, each one
+/// together with its exact due time.
+///
+/// Timing Clock messages are sent continuously, even when stopped, so that receivers can lock to
+/// the tempo in advance. Transport changes are communicated according to the MIDI 1.0
+/// specification:
+///
+/// - [`start`] sends Start and restarts the song from position 0.
+/// - [`stop`] sends Stop.
+/// - [`continue`] sends Continue, which makes receivers resume at the current song position.
+/// - [`locate`] sends a Song Position Pointer message. When playing, it's preceded by Stop and
+///   followed by Continue because receivers are not required to relocate while playing.
+///
+/// After starting, continuing or locating while playing, the following Timing Clock message is due
+/// immediately and marks the first tick played. Stopping and locating while stopped don't change
+/// the timing of Timing Clock messages. Transport messages are yielded by the next call of
+/// [`generate`] and are due at the time passed to the previous call of [`generate`] (the current
+/// time).
+///
+/// The generator doesn't allocate and can be used in a real-time thread.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{start, timing_clock};
+/// use helgoboss_midi::{ClockGenerator, RawShortMessage, TimedShortMessage};
+/// use std::time::Duration;
+///
+/// // 125 BPM => 1 Timing Clock message every 20 ms
+/// let mut generator = ClockGenerator::new(125.0);
+/// let messages: Vec<TimedShortMessage<RawShortMessage>> =
+///     generator.generate(Duration::from_millis(30)).collect();
+/// assert_eq!(
+///     messages,
+///     vec![
+///         TimedShortMessage::new(Duration::from_millis(0), timing_clock()),
+///         TimedShortMessage::new(Duration::from_millis(20), timing_clock()),
+///     ]
+/// );
+///
+/// generator.start();
+/// let messages: Vec<TimedShortMessage<RawShortMessage>> =
+///     generator.generate(Duration::from_millis(50)).collect();
+/// assert_eq!(
+///     messages,
+///     vec![
+///         TimedShortMessage::new(Duration::from_millis(30), start()),
+///         TimedShortMessage::new(Duration::from_millis(30), timing_clock()),
+///     ]
+/// );
+/// let offset = messages[1].offset_in_samples(Duration::from_millis(25), 48000.0);
+/// assert_eq!(offset.round(), 240.0);
+/// ```
+///
+/// [`start`]: #method.start
+/// [`stop`]: #method.stop
+/// [`continue`]: #method.continue
+/// [`locate`]: #method.locate
+/// [`generate`]: #method.generate
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ClockGenerator {
+    bpm: f64,
+    transport_state: TransportState,
+    /// Time passed to the most recent call of `generate`.
+    now: Duration,
+    /// Time of the tick from which following tick times are calculated.
+    anchor_time: Duration,
+    /// Number of ticks generated since the anchor tick.
+    ticks_since_anchor: u64,
+    /// Song position in ticks which is marked by the next Timing Clock message when playing.
+    tick: u64,
+    pending_stop: bool,
+    pending_song_position_pointer: Option<SongPosition>,
+    pending_start_or_continue: Option<PendingPlay>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum PendingPlay {
+    Start,
+    Continue,
+}
+
+impl ClockGenerator {
+    /// The highest supported tempo in beats (quarter notes) per minute.
+    ///
+    /// At this tempo, Timing Clock messages are due every 62.5 microseconds.
+    pub const MAX_BPM: f64 = 40_000.0;
+
+    /// Creates a stopped generator with the given tempo in beats (quarter notes) per minute.
+    ///
+    /// The first Timing Clock message is due at time zero.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the tempo is not positive or greater than [`MAX_BPM`].
+    ///
+    /// [`MAX_BPM`]: #associatedconstant.MAX_BPM
+    pub fn new(bpm: f64) -> ClockGenerator {
+        assert_valid_bpm(bpm);
+        ClockGenerator {
+            bpm,
+            transport_state: TransportState::Stopped,
+            now: Duration::from_secs(0),
+            anchor_time: Duration::from_secs(0),
+            ticks_since_anchor: 0,
+            tick: 0,
+            pending_stop: false,
+            pending_song_position_pointer: None,
+            pending_start_or_continue: None,
+        }
+    }
+
+    /// Returns the tempo in beats (quarter notes) per minute.
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Changes the tempo.
+    ///
+    /// The new tempo applies to the interval between the most recently generated Timing Clock
+    /// message and the next one.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the tempo is not positive or greater than [`MAX_BPM`].
+    ///
+    /// [`MAX_BPM`]: #associatedconstant.MAX_BPM
+    pub fn set_bpm(&mut self, bpm: f64) {
+        assert_valid_bpm(bpm);
+        if self.ticks_since_anchor > 0 {
+            self.anchor_time = self.tick_time(self.ticks_since_anchor - 1);
+            self.ticks_since_anchor = 1;
+        }
+        self.bpm = bpm;
+        if self.tick_time(self.ticks_since_anchor) < self.now {
+            self.reanchor();
+        }
+    }
+
+    /// Returns the current transport state.
+    pub fn transport_state(&self) -> TransportState {
+        self.transport_state
+    }
+
+    /// Returns the song position in Timing Clock ticks, that is, the position which will be marked
+    /// by the next Timing Clock message when playing.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Starts playing from the beginning of the song.
+    pub fn start(&mut self) {
+        self.transport_state = TransportState::Playing;
+        self.tick = 0;
+        self.pending_song_position_pointer = None;
+        self.pending_start_or_continue = Some(PendingPlay::Start);
+        self.reanchor();
+    }
+
+    /// Continues playing from the current song position.
+    ///
+    /// Does nothing if already playing.
+    pub fn r#continue(&mut self) {
+        if self.transport_state == TransportState::Playing {
+            return;
+        }
+        self.transport_state = TransportState::Playing;
+        self.pending_start_or_continue = Some(PendingPlay::Continue);
+        self.reanchor();
+    }
+
+    /// Stops playing.
+    ///
+    /// Does nothing if already stopped.
+    pub fn stop(&mut self) {
+        if self.transport_state == TransportState::Stopped {
+            return;
+        }
+        self.transport_state = TransportState::Stopped;
+        if self.pending_start_or_continue.take().is_none() {
+            self.pending_stop = true;
+        }
+    }
+
+    /// Jumps to the given song position.
+    ///
+    /// When playing, the next Timing Clock message is due immediately. When stopped, Timing Clock
+    /// messages continue at their usual interval.
+    pub fn locate(&mut self, position: SongPosition) {
+        self.tick = position.to_ticks();
+        self.pending_song_position_pointer = Some(position);
+        if self.transport_state == TransportState::Playing {
+            if self.pending_start_or_continue.is_none() {
+                self.pending_stop = true;
+            }
+            self.pending_start_or_continue = Some(PendingPlay::Continue);
+            self.reanchor();
+        }
+    }
+
+    /// Returns all messages which are due before the given time and which have not been returned
+    /// yet.
+    ///
+    /// Pending transport messages come first.
+    pub fn generate<T: ShortMessageFactory>(&mut self, until: Duration) -> ClockMessages<'_, T> {
+        let now = self.now;
+        if until > self.now {
+            self.now = until;
+        }
+        ClockMessages {
+            generator: self,
+            now,
+            until,
+            p: core::marker::PhantomData,
+        }
+    }
+
+    fn reanchor(&mut self) {
+        self.anchor_time = self.now;
+        self.ticks_since_anchor = 0;
+    }
+
+    fn tick_time(&self, ticks_since_anchor: u64) -> Duration {
+        let interval_in_nanos =
+            60_000_000_000.0 / (self.bpm * f64::from(TIMING_CLOCKS_PER_QUARTER_NOTE));
+        // Round to nearest nanosecond
+        let nanos = (ticks_since_anchor as f64 * interval_in_nanos + 0.5) as u64;
+        self.anchor_time + Duration::from_nanos(nanos)
+    }
+
+    fn next_pending_message<T: ShortMessageFactory>(&mut self) -> Option<T> {
+        if self.pending_stop {
+            self.pending_stop = false;
+            return Some(T::stop());
+        }
+        if let Some(position) = self.pending_song_position_pointer.take() {
+            return Some(T::song_position_pointer(position.into()));
+        }
+        let msg = match self.pending_start_or_continue.take()? {
+            PendingPlay::Start => T::start(),
+            PendingPlay::Continue => T::r#continue(),
+        };
+        Some(msg)
+    }
+}
+
+fn assert_valid_bpm(bpm: f64) {
+    assert!(
+        bpm.is_finite() && bpm > 0.0 && bpm <= ClockGenerator::MAX_BPM,
+        "tempo must be positive and not greater than the maximum tempo"
+    );
+}
+
+/// An iterator over messages which are due, returned by [`ClockGenerator::generate`].
+///
+/// [`ClockGenerator::generate`]: struct.ClockGenerator.html#method.generate
+#[derive(Debug)]
+pub struct ClockMessages<'a, T> {
+    generator: &'a mut ClockGenerator,
+    now: Duration,
+    until: Duration,
+    p: core::marker::PhantomData<T>,
+}
+
+impl<'a, T: ShortMessageFactory> Iterator for ClockMessages<'a, T> {
+    type Item = TimedShortMessage<T>;
+
+    fn next(&mut self) -> Option<TimedShortMessage<T>> {
+        if let Some(msg) = self.generator.next_pending_message() {
+            return Some(TimedShortMessage::new(self.now, msg));
+        }
+        let time = self.generator.tick_time(self.generator.ticks_since_anchor);
+        if time >= self.until {
+            return None;
+        }
+        self.generator.ticks_since_anchor += 1;
+        if self.generator.transport_state == TransportState::Playing {
+            self.generator.tick += 1;
+        }
+        Some(TimedShortMessage::new(time, T::timing_clock()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{r#continue, song_position_pointer, start, stop, timing_clock, u14};
+    use crate::{ClockScanner, ClockScannerEvent, RawShortMessage};
+
+    fn millis(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    fn generate(generator: &mut ClockGenerator, until: u64) -> Vec<(u64, RawShortMessage)> {
+        generator
+            .generate(millis(until))
+            .map(|m: TimedShortMessage<RawShortMessage>| {
+                (m.time().as_millis() as u64, m.into_message())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timing() {
+        // Given
+        let mut generator = ClockGenerator::new(125.0);
+        // When
+        let messages_1 = generate(&mut generator, 0);
+        let messages_2 = generate(&mut generator, 40);
+        let messages_3 = generate(&mut generator, 41);
+        let messages_4 = generate(&mut generator, 1000);
+        // Then
+        assert_eq!(messages_1, vec![]);
+        assert_eq!(messages_2, vec![(0, timing_clock()), (20, timing_clock())]);
+        assert_eq!(messages_3, vec![(40, timing_clock())]);
+        assert_eq!(messages_4.len(), 47);
+        assert_eq!(messages_4.last(), Some(&(980, timing_clock())));
+    }
+
+    #[test]
+    fn sub_sample_offsets() {
+        // Given
+        let mut generator = ClockGenerator::new(120.0);
+        // When
+        let messages: Vec<TimedShortMessage<RawShortMessage>> =
+            generator.generate(millis(50)).collect();
+        // Then
+        let offsets: Vec<_> = messages
+            .iter()
+            .map(|m| m.offset_in_samples(millis(10), 48000.0))
+            .collect();
+        assert_eq!(offsets.len(), 3);
+        assert!((offsets[0] + 480.0).abs() < 0.001);
+        assert!((offsets[1] - 520.0).abs() < 0.001);
+        assert!((offsets[2] - 1520.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn tempo_change() {
+        // Given
+        let mut generator = ClockGenerator::new(125.0);
+        generate(&mut generator, 30);
+        // When
+        generator.set_bpm(62.5);
+        let messages = generate(&mut generator, 100);
+        // Then
+        assert_eq!(messages, vec![(60, timing_clock())]);
+        assert_eq!(generator.bpm(), 62.5);
+    }
+
+    #[test]
+    fn tempo_change_to_faster_tempo() {
+        // Given
+        let mut generator = ClockGenerator::new(62.5);
+        generate(&mut generator, 70);
+        // When
+        generator.set_bpm(250.0);
+        let messages = generate(&mut generator, 85);
+        // Then
+        assert_eq!(messages, vec![(70, timing_clock()), (80, timing_clock())]);
+    }
+
+    #[test]
+    fn transport() {
+        // Given
+        let mut generator = ClockGenerator::new(125.0);
+        generate(&mut generator, 10);
+        // When
+        generator.start();
+        let messages_1 = generate(&mut generator, 50);
+        generator.stop();
+        let messages_2 = generate(&mut generator, 70);
+        generator.r#continue();
+        let messages_3 = generate(&mut generator, 80);
+        // Then
+        assert_eq!(
+            messages_1,
+            vec![(10, start()), (10, timing_clock()), (30, timing_clock())]
+        );
+        assert_eq!(messages_2, vec![(50, stop()), (50, timing_clock())]);
+        assert_eq!(messages_3, vec![(70, r#continue()), (70, timing_clock())]);
+        assert_eq!(generator.transport_state(), TransportState::Playing);
+        assert_eq!(generator.tick(), 3);
+    }
+
+    #[test]
+    fn redundant_transport_changes() {
+        // Given
+        let mut generator = ClockGenerator::new(125.0);
+        // When
+        generator.stop();
+        generator.start();
+        generator.r#continue();
+        generator.stop();
+        let messages = generate(&mut generator, 1);
+        // Then
+        assert_eq!(messages, vec![(0, timing_clock())]);
+        assert_eq!(generator.transport_state(), TransportState::Stopped);
+    }
+
+    #[test]
+    fn locate_while_stopped() {
+        // Given
+        let mut generator = ClockGenerator::new(125.0);
+        generate(&mut generator, 10);
+        // When
+        generator.locate(SongPosition::new(u14(8)));
+        let messages = generate(&mut generator, 30);
+        // Then
+        assert_eq!(
+            messages,
+            vec![(10, song_position_pointer(8)), (20, timing_clock())]
+        );
+        assert_eq!(generator.tick(), 48);
+    }
+
+    #[test]
+    fn locate_while_playing() {
+        // Given
+        let mut generator = ClockGenerator::new(125.0);
+        generator.start();
+        generate(&mut generator, 10);
+        // When
+        generator.locate(SongPosition::new(u14(4)));
+        let messages = generate(&mut generator, 20);
+        // Then
+        assert_eq!(
+            messages,
+            vec![
+                (10, stop()),
+                (10, song_position_pointer(4)),
+                (10, r#continue()),
+                (10, timing_clock())
+            ]
+        );
+        assert_eq!(generator.tick(), 25);
+    }
+
+    #[test]
+    fn round_trip_with_scanner() {
+        // Given
+        let mut generator = ClockGenerator::new(100.0);
+        let mut scanner = ClockScanner::new();
+        // When
+        generator.start();
+        let events: Vec<_> = generator
+            .generate::<RawShortMessage>(Duration::from_secs(2))
+            .filter_map(|m| scanner.feed(m.message(), m.time()))
+            .collect();
+        // Then
+        assert_eq!(
+            events,
+            vec![
+                ClockScannerEvent::Started,
+                ClockScannerEvent::Beat { beat: 0 },
+                ClockScannerEvent::Beat { beat: 1 },
+                ClockScannerEvent::Beat { beat: 2 },
+                ClockScannerEvent::Beat { beat: 3 }
+            ]
+        );
+        assert!((scanner.bpm().unwrap() - 100.0).abs() < 0.001);
+        assert_eq!(scanner.tick(), Some(generator.tick() - 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_tempo() {
+        ClockGenerator::new(0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn infinite_tempo() {
+        ClockGenerator::new(f64::INFINITY);
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_high_tempo() {
+        let mut generator = ClockGenerator::new(120.0);
+        generator.set_bpm(ClockGenerator::MAX_BPM * 2.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn nan_tempo() {
+        ClockGenerator::new(f64::NAN);
+    }
+}
@@ -0,0 +1,417 @@
+use crate::{ShortMessage, ShortMessageType};
+use core::time::Duration;
+
+/// Number of Timing Clock messages per quarter note.
+pub const TIMING_CLOCKS_PER_QUARTER_NOTE: u8 = 24;
+
+/// Longest interval between two Timing Clock messages which is still considered as continuous
+/// clock (corresponds to 10 BPM).
+const MAX_CLOCK_INTERVAL_IN_SECS: f64 = 0.25;
+
+/// State of a transport controlled by System Real Time messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TransportState {
+    Stopped,
+    Playing,
+}
+
+impl Default for TransportState {
+    fn default() -> Self {
+        TransportState::Stopped
+    }
+}
+
+/// Something that the [`ClockScanner`] detected.
+///
+/// [`ClockScanner`]: struct.ClockScanner.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ClockScannerEvent {
+    /// Transport has been started from the beginning of the song.
+    Started,
+    /// Transport has been continued from the position at which it was stopped.
+    Continued,
+    /// Transport has been stopped.
+    Stopped,
+    /// The first Timing Clock message of a beat (quarter note) has been received while playing.
+    Beat {
+        /// Number of the beat, counted from the beginning of the song (starting with 0).
+        beat: u64,
+    },
+}
+
+/// Scanner for deriving tempo, transport state and phase from a stream of System Real Time
+/// messages.
+///
+/// Each message needs to be fed together with the time at which it has been received. The tempo is
+/// derived from the intervals between Timing Clock messages (24 per quarter note). Deviations from
+/// the current estimate which are within the jitter tolerance are smoothed using an exponential
+/// moving average, larger deviations are taken as tempo change and followed immediately.
+///
+/// The phase is counted in Timing Clock ticks since Start. As demanded by the MIDI 1.0
+/// specification, the first Timing Clock message after Start or Continue marks the (next) tick.
+///
+/// The scanner doesn't allocate and can be used in a real-time thread.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{start, timing_clock};
+/// use helgoboss_midi::{ClockScanner, ClockScannerEvent, TransportState};
+/// use std::time::Duration;
+///
+/// let mut scanner = ClockScanner::new();
+/// assert_eq!(
+///     scanner.feed(&start(), Duration::from_millis(0)),
+///     Some(ClockScannerEvent::Started)
+/// );
+/// // 120 BPM => 1 Timing Clock message every 20.833 ms
+/// let mut events = vec![];
+/// for i in 0..30 {
+///     let timestamp = Duration::from_micros(i * 20_833);
+///     events.extend(scanner.feed(&timing_clock(), timestamp));
+/// }
+/// assert_eq!(
+///     events,
+///     vec![
+///         ClockScannerEvent::Beat { beat: 0 },
+///         ClockScannerEvent::Beat { beat: 1 }
+///     ]
+/// );
+/// assert_eq!(scanner.transport_state(), TransportState::Playing);
+/// assert_eq!(scanner.bpm().unwrap().round(), 120.0);
+/// assert_eq!(scanner.beat(), Some(1));
+/// assert_eq!(scanner.tick_in_beat(), Some(5));
+/// ```
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ClockScanner {
+    smoothing_factor: f64,
+    jitter_tolerance: f64,
+    transport_state: TransportState,
+    /// Tick of the most recent Timing Clock message received while playing.
+    tick: Option<u64>,
+    last_clock_time: Option<Duration>,
+    /// Smoothed interval between two Timing Clock messages in seconds.
+    clock_interval: Option<f64>,
+}
+
+impl Default for ClockScanner {
+    fn default() -> Self {
+        ClockScanner::with_settings(
+            ClockScanner::DEFAULT_SMOOTHING_FACTOR,
+            ClockScanner::DEFAULT_JITTER_TOLERANCE,
+        )
+    }
+}
+
+impl ClockScanner {
+    /// Smoothing factor used by [`new`](#method.new).
+    pub const DEFAULT_SMOOTHING_FACTOR: f64 = 0.1;
+
+    /// Jitter tolerance used by [`new`](#method.new).
+    pub const DEFAULT_JITTER_TOLERANCE: f64 = 0.25;
+
+    /// Creates a new scanner with default settings.
+    pub fn new() -> ClockScanner {
+        Default::default()
+    }
+
+    /// Creates a new scanner with the given settings.
+    ///
+    /// The smoothing factor determines how much weight a new Timing Clock interval gets in the
+    /// tempo estimate. `1.0` means no smoothing at all, values closer to `0.0` mean more smoothing
+    /// but slower reaction to gradual tempo changes.
+    ///
+    /// The jitter tolerance is the relative deviation of a Timing Clock interval from the current
+    /// estimate (e.g. `0.25` for 25%) up to which the deviation is considered as jitter. Larger
+    /// deviations are considered as tempo change and reset the estimate.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the smoothing factor is not within `(0.0, 1.0]` or if the jitter
+    /// tolerance is negative.
+    pub fn with_settings(smoothing_factor: f64, jitter_tolerance: f64) -> ClockScanner {
+        assert!(
+            smoothing_factor > 0.0 && smoothing_factor <= 1.0,
+            "smoothing factor must be within (0.0, 1.0]"
+        );
+        assert!(
+            jitter_tolerance >= 0.0,
+            "jitter tolerance must not be negative"
+        );
+        ClockScanner {
+            smoothing_factor,
+            jitter_tolerance,
+            transport_state: Default::default(),
+            tick: None,
+            last_clock_time: None,
+            clock_interval: None,
+        }
+    }
+
+    /// Feeds the scanner a single short message together with the time at which it has been
+    /// received.
+    ///
+    /// Timestamps are expected to increase monotonically. They can be relative to an arbitrary
+    /// point in time.
+    ///
+    /// Returns an event if the message changed the transport state or marked a beat.
+    pub fn feed(
+        &mut self,
+        msg: &impl ShortMessage,
+        timestamp: Duration,
+    ) -> Option<ClockScannerEvent> {
+        use ShortMessageType::*;
+        match msg.r#type() {
+            TimingClock => self.process_timing_clock(timestamp),
+            Start => {
+                self.transport_state = TransportState::Playing;
+                self.tick = None;
+                Some(ClockScannerEvent::Started)
+            }
+            Continue => {
+                self.transport_state = TransportState::Playing;
+                Some(ClockScannerEvent::Continued)
+            }
+            Stop => {
+                if self.transport_state == TransportState::Stopped {
+                    return None;
+                }
+                self.transport_state = TransportState::Stopped;
+                Some(ClockScannerEvent::Stopped)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the current transport state.
+    pub fn transport_state(&self) -> TransportState {
+        self.transport_state
+    }
+
+    /// Returns the smoothed tempo in beats (quarter notes) per minute.
+    ///
+    /// Returns `None` if not enough Timing Clock messages have been received yet or if the clock
+    /// has been interrupted.
+    pub fn bpm(&self) -> Option<f64> {
+        let interval = self.clock_interval?;
+        Some(60.0 / (interval * f64::from(TIMING_CLOCKS_PER_QUARTER_NOTE)))
+    }
+
+    /// Returns the number of Timing Clock ticks since the beginning of the song at the time of the
+    /// most recent Timing Clock message.
+    ///
+    /// Returns `None` if no Timing Clock message has been received since Start.
+    pub fn tick(&self) -> Option<u64> {
+        self.tick
+    }
+
+    /// Returns the number of the current beat (quarter note), counted from the beginning of the
+    /// song.
+    pub fn beat(&self) -> Option<u64> {
+        Some(self.tick? / u64::from(TIMING_CLOCKS_PER_QUARTER_NOTE))
+    }
+
+    /// Returns the Timing Clock tick within the current beat (from 0 to 23).
+    pub fn tick_in_beat(&self) -> Option<u8> {
+        Some((self.tick? % u64::from(TIMING_CLOCKS_PER_QUARTER_NOTE)) as u8)
+    }
+
+    /// Resets the scanner discarding transport state, phase and tempo estimate.
+    ///
+    /// Settings are kept.
+    pub fn reset(&mut self) {
+        *self = ClockScanner::with_settings(self.smoothing_factor, self.jitter_tolerance);
+    }
+
+    fn process_timing_clock(&mut self, timestamp: Duration) -> Option<ClockScannerEvent> {
+        self.update_tempo(timestamp);
+        if self.transport_state != TransportState::Playing {
+            return None;
+        }
+        let tick = self.tick.map(|t| t + 1).unwrap_or(0);
+        self.tick = Some(tick);
+        let ticks_per_beat = u64::from(TIMING_CLOCKS_PER_QUARTER_NOTE);
+        if tick % ticks_per_beat != 0 {
+            return None;
+        }
+        Some(ClockScannerEvent::Beat {
+            beat: tick / ticks_per_beat,
+        })
+    }
+
+    fn update_tempo(&mut self, timestamp: Duration) {
+        let last_clock_time = self.last_clock_time.replace(timestamp);
+        let interval = match last_clock_time.and_then(|t| timestamp.checked_sub(t)) {
+            None => return,
+            Some(i) => i.as_secs_f64(),
+        };
+        if interval <= 0.0 || interval > MAX_CLOCK_INTERVAL_IN_SECS {
+            // Clock has been interrupted or timestamps are broken
+            self.clock_interval = None;
+            return;
+        }
+        let new_estimate = match self.clock_interval {
+            None => interval,
+            Some(estimate) => {
+                let deviation = interval - estimate;
+                let abs_deviation = if deviation < 0.0 {
+                    -deviation
+                } else {
+                    deviation
+                };
+                if abs_deviation > estimate * self.jitter_tolerance {
+                    interval
+                } else {
+                    estimate + self.smoothing_factor * deviation
+                }
+            }
+        };
+        self.clock_interval = Some(new_estimate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{note_on, r#continue, start, stop, timing_clock};
+
+    fn assert_bpm(scanner: &ClockScanner, expected: f64) {
+        let bpm = scanner.bpm().unwrap();
+        assert!((bpm - expected).abs() < 0.001, "{}", bpm);
+    }
+
+    fn micros(value: u64) -> Duration {
+        Duration::from_micros(value)
+    }
+
+    fn feed_clocks(
+        scanner: &mut ClockScanner,
+        start_time: u64,
+        interval: u64,
+        count: u64,
+    ) -> Vec<ClockScannerEvent> {
+        (0..count)
+            .filter_map(|i| scanner.feed(&timing_clock(), micros(start_time + i * interval)))
+            .collect()
+    }
+
+    #[test]
+    fn transport() {
+        // Given
+        let mut scanner = ClockScanner::new();
+        // When
+        let event_1 = scanner.feed(&stop(), micros(0));
+        let events_1 = feed_clocks(&mut scanner, 0, 20_000, 10);
+        let event_2 = scanner.feed(&start(), micros(200_000));
+        let events_2 = feed_clocks(&mut scanner, 200_000, 20_000, 30);
+        let event_3 = scanner.feed(&stop(), micros(800_000));
+        let events_3 = feed_clocks(&mut scanner, 800_000, 20_000, 10);
+        let event_4 = scanner.feed(&r#continue(), micros(1_000_000));
+        let events_4 = feed_clocks(&mut scanner, 1_000_000, 20_000, 20);
+        // Then
+        assert_eq!(event_1, None);
+        assert_eq!(events_1, vec![]);
+        assert_eq!(event_2, Some(ClockScannerEvent::Started));
+        assert_eq!(
+            events_2,
+            vec![
+                ClockScannerEvent::Beat { beat: 0 },
+                ClockScannerEvent::Beat { beat: 1 }
+            ]
+        );
+        assert_eq!(event_3, Some(ClockScannerEvent::Stopped));
+        assert_eq!(events_3, vec![]);
+        assert_eq!(event_4, Some(ClockScannerEvent::Continued));
+        assert_eq!(events_4, vec![ClockScannerEvent::Beat { beat: 2 }]);
+        assert_eq!(scanner.transport_state(), TransportState::Playing);
+        assert_eq!(scanner.tick(), Some(49));
+        assert_eq!(scanner.beat(), Some(2));
+        assert_eq!(scanner.tick_in_beat(), Some(1));
+    }
+
+    #[test]
+    fn restart() {
+        // Given
+        let mut scanner = ClockScanner::new();
+        scanner.feed(&start(), micros(0));
+        feed_clocks(&mut scanner, 0, 20_000, 30);
+        // When
+        scanner.feed(&start(), micros(600_000));
+        let events = feed_clocks(&mut scanner, 600_000, 20_000, 1);
+        // Then
+        assert_eq!(events, vec![ClockScannerEvent::Beat { beat: 0 }]);
+        assert_eq!(scanner.tick(), Some(0));
+    }
+
+    #[test]
+    fn tempo_smoothing() {
+        // Given
+        let mut scanner = ClockScanner::with_settings(0.5, 0.2);
+        // When
+        // 125 BPM with alternating jitter of 1 ms
+        for i in 0..48 {
+            let jitter = if i % 2 == 0 { 0 } else { 1000 };
+            scanner.feed(&timing_clock(), micros(i * 20_000 + jitter));
+        }
+        // Then
+        let bpm = scanner.bpm().unwrap();
+        assert!((bpm - 125.0).abs() < 5.0, "{}", bpm);
+    }
+
+    #[test]
+    fn tempo_change() {
+        // Given
+        let mut scanner = ClockScanner::new();
+        feed_clocks(&mut scanner, 0, 20_000, 24);
+        // When
+        // 62.5 BPM
+        feed_clocks(&mut scanner, 500_000, 40_000, 2);
+        // Then
+        assert_bpm(&scanner, 62.5);
+    }
+
+    #[test]
+    fn interruption() {
+        // Given
+        let mut scanner = ClockScanner::new();
+        feed_clocks(&mut scanner, 0, 20_000, 24);
+        // When
+        scanner.feed(&timing_clock(), micros(5_000_000));
+        let bpm_1 = scanner.bpm();
+        scanner.feed(&timing_clock(), micros(5_020_000));
+        // Then
+        assert_eq!(bpm_1, None);
+        assert_bpm(&scanner, 125.0);
+    }
+
+    #[test]
+    fn ignore_other_messages() {
+        // Given
+        let mut scanner = ClockScanner::new();
+        // When
+        let event = scanner.feed(&note_on(0, 64, 100), micros(0));
+        // Then
+        assert_eq!(event, None);
+        assert_eq!(scanner.bpm(), None);
+        assert_eq!(scanner.tick(), None);
+    }
+
+    #[test]
+    fn reset() {
+        // Given
+        let mut scanner = ClockScanner::with_settings(1.0, 0.0);
+        scanner.feed(&start(), micros(0));
+        feed_clocks(&mut scanner, 0, 20_000, 5);
+        // When
+        scanner.reset();
+        // Then
+        assert_eq!(scanner, ClockScanner::with_settings(1.0, 0.0));
+        assert_eq!(scanner.transport_state(), TransportState::Stopped);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_smoothing_factor() {
+        ClockScanner::with_settings(0.0, 0.1);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    build_first_word, bytes_to_words, words_to_bytes, Channel, UmpFormat, UmpMessageType, U4,
+};
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Status bank of Flex Data messages for setup and performance events.
+const SETUP_AND_PERFORMANCE_BANK: u8 = 0x00;
+
+/// Status bank of Flex Data messages containing metadata text.
+const METADATA_TEXT_BANK: u8 = 0x01;
+
+/// Status bank of Flex Data messages containing performance text events.
+const PERFORMANCE_TEXT_BANK: u8 = 0x02;
+
+/// The destination of a Flex Data message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FlexDataAddress {
+    /// The message applies to the given channel within the group.
+    Channel(Channel),
+    /// The message applies to the whole group.
+    Group,
+}
+
+/// A Flex Data message, carried by a 128-bit UMP message of type 0xD.
+///
+/// Flex Data messages are the UMP way of transmitting information such as tempo, time signature
+/// and texts which is found in meta events of Standard MIDI Files.
+///
+/// This enum is usually contained in [`StructuredUmpMessage::FlexData`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{
+///     FlexDataAddress, FlexDataMessage, StructuredUmpMessage, UmpFormat, UmpMessage, U4,
+/// };
+///
+/// let msg = StructuredUmpMessage::FlexData {
+///     group: U4::new(0),
+///     format: UmpFormat::Complete,
+///     address: FlexDataAddress::Group,
+///     message: FlexDataMessage::SetTempo {
+///         ten_nanoseconds_per_quarter_note: 50_000_000,
+///     },
+/// };
+/// assert_eq!(msg.to_words(), [0xD010_0000, 50_000_000, 0, 0]);
+/// ```
+///
+/// [`StructuredUmpMessage::FlexData`]: enum.StructuredUmpMessage.html#variant.FlexData
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FlexDataMessage {
+    SetTempo {
+        /// Duration of a quarter note in units of 10 nanoseconds.
+        ten_nanoseconds_per_quarter_note: u32,
+    },
+    SetTimeSignature {
+        numerator: u8,
+        /// The denominator expressed as power of 2, e.g. 3 for an eighth note.
+        denominator_exponent: u8,
+        thirty_second_notes_per_beat: u8,
+    },
+    SetMetronome {
+        clocks_per_primary_click: u8,
+        bar_accent_1: u8,
+        bar_accent_2: u8,
+        bar_accent_3: u8,
+        subdivision_clicks_1: u8,
+        subdivision_clicks_2: u8,
+    },
+    /// Metadata text such as project name or composer, identified by status.
+    MetadataText {
+        status: u8,
+        /// UTF-8 text, padded with zeros.
+        text: [u8; 12],
+    },
+    /// Performance text such as lyrics, identified by status.
+    PerformanceText {
+        status: u8,
+        /// UTF-8 text, padded with zeros.
+        text: [u8; 12],
+    },
+    /// Any other Flex Data message.
+    Other {
+        status_bank: u8,
+        status: u8,
+        data: [u32; 3],
+    },
+}
+
+impl FlexDataMessage {
+    /// Decodes a UMP message of type 0xD.
+    ///
+    /// Returns `None` if the address is reserved.
+    pub(crate) fn decode(words: &[u32; 4]) -> Option<(UmpFormat, FlexDataAddress, Self)> {
+        use FlexDataMessage::*;
+        let [word_0, word_1, word_2, word_3] = *words;
+        let format = UmpFormat::try_from(((word_0 >> 22) & 0x03) as u8).ok()?;
+        let address = match (word_0 >> 20) & 0x03 {
+            0 => FlexDataAddress::Channel(Channel(((word_0 >> 16) & 0x0F) as u8)),
+            1 => FlexDataAddress::Group,
+            _ => return None,
+        };
+        let status_bank = (word_0 >> 8) as u8;
+        let status = word_0 as u8;
+        let byte = |word: u32, index: u32| (word >> (24 - 8 * index)) as u8;
+        let msg = match (status_bank, status) {
+            (SETUP_AND_PERFORMANCE_BANK, 0x00) => SetTempo {
+                ten_nanoseconds_per_quarter_note: word_1,
+            },
+            (SETUP_AND_PERFORMANCE_BANK, 0x01) => SetTimeSignature {
+                numerator: byte(word_1, 0),
+                denominator_exponent: byte(word_1, 1),
+                thirty_second_notes_per_beat: byte(word_1, 2),
+            },
+            (SETUP_AND_PERFORMANCE_BANK, 0x02) => SetMetronome {
+                clocks_per_primary_click: byte(word_1, 0),
+                bar_accent_1: byte(word_1, 1),
+                bar_accent_2: byte(word_1, 2),
+                bar_accent_3: byte(word_1, 3),
+                subdivision_clicks_1: byte(word_2, 0),
+                subdivision_clicks_2: byte(word_2, 1),
+            },
+            (METADATA_TEXT_BANK, _) => MetadataText {
+                status,
+                text: text(words),
+            },
+            (PERFORMANCE_TEXT_BANK, _) => PerformanceText {
+                status,
+                text: text(words),
+            },
+            _ => Other {
+                status_bank,
+                status,
+                data: [word_1, word_2, word_3],
+            },
+        };
+        Some((format, address, msg))
+    }
+
+    /// Encodes this message as UMP message of type 0xD.
+    pub(crate) fn encode(
+        &self,
+        group: U4,
+        format: UmpFormat,
+        address: FlexDataAddress,
+    ) -> [u32; 4] {
+        use FlexDataMessage::*;
+        let bytes = |b0: u8, b1: u8, b2: u8, b3: u8| u32::from_be_bytes([b0, b1, b2, b3]);
+        let (status_bank, status, data) = match *self {
+            SetTempo {
+                ten_nanoseconds_per_quarter_note,
+            } => (
+                SETUP_AND_PERFORMANCE_BANK,
+                0x00,
+                [ten_nanoseconds_per_quarter_note, 0, 0],
+            ),
+            SetTimeSignature {
+                numerator,
+                denominator_exponent,
+                thirty_second_notes_per_beat,
+            } => (
+                SETUP_AND_PERFORMANCE_BANK,
+                0x01,
+                [
+                    bytes(
+                        numerator,
+                        denominator_exponent,
+                        thirty_second_notes_per_beat,
+                        0,
+                    ),
+                    0,
+                    0,
+                ],
+            ),
+            SetMetronome {
+                clocks_per_primary_click,
+                bar_accent_1,
+                bar_accent_2,
+                bar_accent_3,
+                subdivision_clicks_1,
+                subdivision_clicks_2,
+            } => (
+                SETUP_AND_PERFORMANCE_BANK,
+                0x02,
+                [
+                    bytes(
+                        clocks_per_primary_click,
+                        bar_accent_1,
+                        bar_accent_2,
+                        bar_accent_3,
+                    ),
+                    bytes(subdivision_clicks_1, subdivision_clicks_2, 0, 0),
+                    0,
+                ],
+            ),
+            MetadataText { status, text } => (METADATA_TEXT_BANK, status, text_to_words(&text)),
+            PerformanceText { status, text } => {
+                (PERFORMANCE_TEXT_BANK, status, text_to_words(&text))
+            }
+            Other {
+                status_bank,
+                status,
+                data,
+            } => (status_bank, status, data),
+        };
+        let (address_bits, channel) = match address {
+            FlexDataAddress::Channel(channel) => (0, channel.get()),
+            FlexDataAddress::Group => (1, 0),
+        };
+        let word_0 = build_first_word(UmpMessageType::FlexData, group)
+            | (u32::from(u8::from(format)) << 22)
+            | (address_bits << 20)
+            | (u32::from(channel) << 16)
+            | (u32::from(status_bank) << 8)
+            | u32::from(status);
+        [word_0, data[0], data[1], data[2]]
+    }
+}
+
+fn text(words: &[u32; 4]) -> [u8; 12] {
+    let mut text = [0; 12];
+    text.copy_from_slice(&words_to_bytes(words)[4..]);
+    text
+}
+
+fn text_to_words(text: &[u8; 12]) -> [u32; 3] {
+    let mut bytes = [0; 16];
+    bytes[4..].copy_from_slice(text);
+    let [_, word_1, word_2, word_3] = bytes_to_words(&bytes);
+    [word_1, word_2, word_3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn encode_decode() {
+        // Given
+        let cases = [
+            (
+                UmpFormat::Complete,
+                FlexDataAddress::Channel(channel(3)),
+                FlexDataMessage::SetTimeSignature {
+                    numerator: 6,
+                    denominator_exponent: 3,
+                    thirty_second_notes_per_beat: 8,
+                },
+                [0xD203_0001, 0x0603_0800, 0, 0],
+            ),
+            (
+                UmpFormat::Start,
+                FlexDataAddress::Group,
+                FlexDataMessage::MetadataText {
+                    status: 0x01,
+                    text: *b"Hello World!",
+                },
+                [0xD250_0101, 0x4865_6C6C, 0x6F20_576F, 0x726C_6421],
+            ),
+            (
+                UmpFormat::End,
+                FlexDataAddress::Group,
+                FlexDataMessage::Other {
+                    status_bank: 0x00,
+                    status: 0x05,
+                    data: [0x1000_0000, 0, 0],
+                },
+                [0xD2D0_0005, 0x1000_0000, 0, 0],
+            ),
+        ];
+        // When
+        // Then
+        for (format, address, msg, words) in cases.iter() {
+            assert_eq!(msg.encode(u4(2), *format, *address), *words);
+            assert_eq!(
+                FlexDataMessage::decode(words),
+                Some((*format, *address, *msg))
+            );
+        }
+    }
+
+    #[test]
+    fn reserved_address() {
+        // Given
+        // When
+        // Then
+        assert_eq!(FlexDataMessage::decode(&[0xD020_0000, 0, 0, 0]), None);
+    }
+}
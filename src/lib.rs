@@ -9,19 +9,40 @@
 //!     - Short messages (3 bytes)
 //!     - 14-bit Control Change messages
 //!     - (N)RPN messages
+//!     - System Exclusive messages
+//!     - Identity Request and Identity Reply messages
+//!     - MIDI Machine Control (MMC) messages
+//!     - MIDI-CI (Capability Inquiry) messages, including Property Exchange
+//!     - MIDI 2.0 Universal MIDI Packet (UMP) messages (Utility, System, MIDI 1.0 and MIDI 2.0
+//!       Channel Voice, System Exclusive 7 and 8, Mixed Data Set, Flex Data and UMP Stream)
+//! - Translation between MIDI 1.0 and MIDI 2.0 Channel Voice messages
+//! - Typed MIDI 2.0 per-note messages and translation of MPE streams into per-note messages
+//! - MPE zone configuration via the MPE Configuration Message (MCM)
+//! - Channel allocator for generating MPE output (with note stealing)
+//! - Tracker for interpreting MPE input as notes with pitch offset, timbre and pressure
+//! - Parser for reassembling UMP messages from a stream of words or big-endian bytes
+//! - Segmenter for splitting System Exclusive messages into UMP messages
 //! - Scanners for extracting 14-bit Control Change and (N)RPN messages from a stream of short
 //!   messages
+//! - Scanner for extracting System Exclusive messages from a stream of raw MIDI bytes
+//! - Scanner for assembling MIDI Time Code Quarter Frame messages into time code positions
+//! - Generator for MIDI Time Code Quarter Frame and Full Frame messages
+//! - SMPTE time code arithmetic (including drop-frame)
+//! - Scanner for deriving tempo, transport state and phase from MIDI Timing Clock messages
+//! - Generator for MIDI Timing Clock and Song Position Pointer messages
+//! - Tracker for the current musical position based on Song Position Pointer, Start, Continue,
+//!   Stop and Timing Clock messages
+//! - Reader (zero-copy) and writer for Standard MIDI Files (formats 0, 1 and 2)
+//! - Typed meta events (tempo, time signature, key signature, text events, ...)
+//! - Tempo map for converting between ticks, seconds and bars/beats
+//! - Parser for extracting short messages from a stream of raw MIDI bytes (supports running status
+//!   and interleaved System Real Time messages)
+//! - Encoder for writing short messages to a stream of raw MIDI bytes (supports running status)
 //! - Suitable for real-time usage (no heap allocation, no dynamic dispatch, no locking)
 //! - Unified API to work with different short message data structures (see
 //!   [`ShortMessage`](trait.ShortMessage.html) trait)
 //! - Uses wording which is as close as possible to the [MIDI 1.0 specification](https://www.midi.org/specifications-old/category/midi-1-0-detailed-specifications)
 //!
-//! # Not yet implemented
-//!
-//! Data structures and utilities for dealing with System Exclusive messages are not yet
-//! implemented. They will be added eventually as separate structures on top of the
-//! existing ones (similar to (N)RPN and 14-bit Control Change).
-//!
 //! # Examples
 //!
 //! See how to ...
@@ -31,10 +52,41 @@
 //! - [Create and inspect 14-bit Control Change
 //!   messages](struct.ControlChange14BitMessage.html#example)
 //! - [Create and inspect (N)RPN messages](struct.ParameterNumberMessage.html#example)
+//! - [Create and inspect System Exclusive messages](struct.SysExMessage.html#example)
+//! - [Detect devices via Identity Request](struct.IdentityRequest.html#example)
+//! - [Create and inspect MIDI Machine Control messages](enum.MachineControlCommand.html#example)
+//! - [Create and inspect MIDI-CI messages](enum.CiMessage.html#example)
+//! - [Send large Property Exchange data in chunks](struct.PropertyExchangeChunker.html#example)
+//! - [Reassemble Property Exchange chunks](struct.PropertyExchangeAssembler.html#example)
+//! - [Create and inspect UMP messages](struct.RawUmpMessage.html#example)
+//! - [Easily match UMP messages](enum.StructuredUmpMessage.html#example)
+//! - [Translate MIDI 1.0 to MIDI 2.0](struct.Midi1ToMidi2Translator.html#example)
+//! - [Create and inspect per-note messages](enum.PerNoteMessage.html#example)
+//! - [Translate MPE to MIDI 2.0 per-note messages](struct.MpeToPerNoteTranslator.html#example)
+//! - [Configure MPE zones](struct.MpeZoneConfig.html#example)
+//! - [Track the MPE zone layout](struct.MpeZoneLayout.html#example)
+//! - [Allocate MPE member channels for outgoing notes](struct.MpeChannelAllocator.html#example)
+//! - [Track notes and expression of MPE input](struct.MpeInputTracker.html#example)
+//! - [Parse stream of UMP bytes](struct.UmpParser.html#example)
+//! - [Send System Exclusive messages as UMP messages](struct.SysEx7Segmenter.html#example)
 //! - [Create MIDI messages with minimum boilerplate](test_util/index.html#example)
 //! - [Scan stream for 14-bit Control Change
 //!   messages](struct.ControlChange14BitMessageScanner.html#example)
 //! - [Scan stream for (N)RPN messages](struct.ParameterNumberMessageScanner.html#example)
+//! - [Scan stream for System Exclusive messages](struct.SysExScanner.html#example)
+//! - [Scan stream for MIDI Time Code positions](struct.MtcScanner.html#example)
+//! - [Generate MIDI Time Code](struct.MtcGenerator.html#example)
+//! - [Calculate with SMPTE time code](struct.Timecode.html#example)
+//! - [Follow MIDI Timing Clock](struct.ClockScanner.html#example)
+//! - [Generate MIDI Timing Clock](struct.ClockGenerator.html#example)
+//! - [Convert song positions](struct.SongPosition.html#example)
+//! - [Track the current musical position](struct.TransportTracker.html#example)
+//! - [Read Standard MIDI Files](struct.SmfReader.html#example)
+//! - [Write Standard MIDI Files](struct.SmfWriter.html#example)
+//! - [Decode and encode meta events](enum.MetaEvent.html#example)
+//! - [Convert between ticks, seconds and bars/beats](struct.TempoMap.html#example)
+//! - [Parse stream of raw MIDI bytes](struct.ShortMessageParser.html#example)
+//! - [Write short messages as raw MIDI bytes](struct.ShortMessageEncoder.html#example)
 #[macro_use]
 mod newtype_macros;
 pub use newtype_macros::*;
@@ -45,6 +97,12 @@ pub use short_message::*;
 mod short_message_factory;
 pub use short_message_factory::*;
 
+mod short_message_parser;
+pub use short_message_parser::*;
+
+mod short_message_encoder;
+pub use short_message_encoder::*;
+
 mod structured_short_message;
 pub use structured_short_message::*;
 
@@ -63,6 +121,119 @@ pub use parameter_number_message::*;
 mod parameter_number_message_scanner;
 pub use parameter_number_message_scanner::*;
 
+mod sys_ex_message;
+pub use sys_ex_message::*;
+
+mod sys_ex_scanner;
+pub use sys_ex_scanner::*;
+
+mod universal_sys_ex_message;
+pub use universal_sys_ex_message::*;
+
+mod machine_control_message;
+pub use machine_control_message::*;
+
+mod ci_message;
+pub use ci_message::*;
+
+mod property_exchange;
+pub use property_exchange::*;
+
+mod timecode;
+pub use timecode::*;
+
+mod mtc_scanner;
+pub use mtc_scanner::*;
+
+mod mtc_full_frame_message;
+pub use mtc_full_frame_message::*;
+
+mod mtc_generator;
+pub use mtc_generator::*;
+
+mod clock_scanner;
+pub use clock_scanner::*;
+
+mod clock_generator;
+pub use clock_generator::*;
+
+mod song_position;
+pub use song_position::*;
+
+mod transport_tracker;
+pub use transport_tracker::*;
+
+mod smf;
+pub use smf::*;
+
+mod smf_reader;
+pub use smf_reader::*;
+
+mod meta_event;
+pub use meta_event::*;
+
+mod ump_message;
+pub use ump_message::*;
+
+mod raw_ump_message;
+pub use raw_ump_message::*;
+
+mod structured_ump_message;
+pub use structured_ump_message::*;
+
+mod midi2_channel_voice_message;
+pub use midi2_channel_voice_message::*;
+
+mod per_note_message;
+pub use per_note_message::*;
+
+mod flex_data_message;
+pub use flex_data_message::*;
+
+mod ump_stream_message;
+pub use ump_stream_message::*;
+
+mod midi2_translation;
+pub use midi2_translation::*;
+
+mod mpe_translation;
+pub use mpe_translation::*;
+
+mod mpe_zone_config;
+pub use mpe_zone_config::*;
+
+mod mpe_channel_allocator;
+pub use mpe_channel_allocator::*;
+
+mod mpe_input_tracker;
+pub use mpe_input_tracker::*;
+
+mod ump_parser;
+pub use ump_parser::*;
+
+mod ump_sys_ex_segmenter;
+pub use ump_sys_ex_segmenter::*;
+
+#[cfg(feature = "std")]
+mod smf_writer;
+#[cfg(feature = "std")]
+pub use smf_writer::*;
+
+#[cfg(feature = "std")]
+mod tempo_map;
+#[cfg(feature = "std")]
+pub use tempo_map::*;
+
+#[cfg(feature = "std")]
+mod property_exchange_assembler;
+#[cfg(feature = "std")]
+pub use property_exchange_assembler::*;
+
+#[cfg(all(feature = "std", feature = "serde"))]
+mod property_exchange_header;
+#[cfg(all(feature = "std", feature = "serde"))]
+pub use property_exchange_header::*;
+
 #[cfg(feature = "std")]
 mod polling_parameter_number_message_scanner;
 #[cfg(feature = "std")]
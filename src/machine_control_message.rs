@@ -0,0 +1,466 @@
+use crate::{
+    parse_universal_message, write_universal_message, ManufacturerId, SysExError, SysExMessage,
+    TimeCodeType, UnexpectedSysExMessageError, U7,
+};
+use core::convert::TryFrom;
+
+/// Sub-ID #1 of MIDI Machine Control command messages.
+const COMMAND: u8 = 0x06;
+
+/// Sub-ID #1 of MIDI Machine Control response messages.
+const RESPONSE: u8 = 0x07;
+
+/// Byte of the Locate command.
+const LOCATE: u8 = 0x44;
+
+/// Sub-command of the Locate command which locates to a target time.
+const LOCATE_TARGET: u8 = 0x01;
+
+/// Information field containing the selected time code.
+const SELECTED_TIME_CODE: u8 = 0x01;
+
+/// A MIDI Machine Control (MMC) command, transmitted as Universal Real Time System Exclusive
+/// message with sub-ID #1 `06`.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{
+///     MachineControlCommand, MachineControlTime, SysExMessage, TimeCodeType, ALL_CALL_DEVICE_ID,
+/// };
+///
+/// let mut buffer = [0; 16];
+/// let msg = MachineControlCommand::Play
+///     .write(ALL_CALL_DEVICE_ID, &mut buffer)
+///     .unwrap();
+/// assert_eq!(msg.as_bytes(), &[0xF0, 0x7F, 0x7F, 0x06, 0x02, 0xF7]);
+///
+/// let msg = SysExMessage::from_bytes(&[
+///     0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x21, 0x02, 0x03, 0x04, 0x00, 0xF7,
+/// ])
+/// .unwrap();
+/// let (device_id, command) = MachineControlCommand::parse(msg).unwrap();
+/// assert_eq!(device_id, ALL_CALL_DEVICE_ID);
+/// assert_eq!(
+///     command,
+///     MachineControlCommand::Locate(MachineControlTime::new(TimeCodeType::Fps25, 1, 2, 3, 4, 0))
+/// );
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MachineControlCommand {
+    /// Stops playback or recording.
+    Stop,
+    /// Starts playback.
+    Play,
+    /// Starts playback as soon as the device is ready, e.g. after having located.
+    DeferredPlay,
+    /// Winds forward at maximum speed.
+    FastForward,
+    /// Winds backward at maximum speed.
+    Rewind,
+    /// Punches in recording (or starts playing and recording if stopped).
+    RecordStrobe,
+    /// Punches out recording.
+    RecordExit,
+    /// Enters record-ready state without moving.
+    RecordPause,
+    /// Pauses playback or recording.
+    Pause,
+    /// Ejects the medium.
+    Eject,
+    /// Makes the device follow (chase) the time code of a master.
+    Chase,
+    /// Resets the command error state of the device.
+    CommandErrorReset,
+    /// Resets the MIDI Machine Control state of the device.
+    MmcReset,
+    /// Locates to the given target time.
+    Locate(MachineControlTime),
+}
+
+impl MachineControlCommand {
+    /// Writes this command as System Exclusive message addressed to the given device into the
+    /// given buffer and returns it.
+    ///
+    /// Use [`ALL_CALL_DEVICE_ID`] in order to address all devices.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small.
+    ///
+    /// [`ALL_CALL_DEVICE_ID`]: constant.ALL_CALL_DEVICE_ID.html
+    pub fn write<'a>(
+        &self,
+        device_id: U7,
+        buffer: &'a mut [u8],
+    ) -> Result<SysExMessage<'a>, SysExError> {
+        use MachineControlCommand::*;
+        let simple_command = match self {
+            Stop => 0x01,
+            Play => 0x02,
+            DeferredPlay => 0x03,
+            FastForward => 0x04,
+            Rewind => 0x05,
+            RecordStrobe => 0x06,
+            RecordExit => 0x07,
+            RecordPause => 0x08,
+            Pause => 0x09,
+            Eject => 0x0A,
+            Chase => 0x0B,
+            CommandErrorReset => 0x0C,
+            MmcReset => 0x0D,
+            Locate(time) => {
+                let mut data = [0; 7];
+                data[..2].copy_from_slice(&[6, LOCATE_TARGET]);
+                time.write(&mut data[2..]);
+                return write_mmc_message(buffer, device_id, COMMAND, LOCATE, &data);
+            }
+        };
+        write_mmc_message(buffer, device_id, COMMAND, simple_command, &[])
+    }
+
+    /// Interprets the given System Exclusive message as MIDI Machine Control command and returns
+    /// it together with the ID of the addressed device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a MIDI Machine Control command message or contains
+    /// more than one command.
+    pub fn parse(
+        msg: SysExMessage<'_>,
+    ) -> Result<(U7, MachineControlCommand), UnexpectedSysExMessageError> {
+        use MachineControlCommand::*;
+        let (device_id, sub_id_1, command, data) =
+            parse_universal_message(msg, ManufacturerId::UNIVERSAL_REAL_TIME)?;
+        if sub_id_1 != COMMAND {
+            return Err(UnexpectedSysExMessageError(()));
+        }
+        let command = match (command, data) {
+            (0x01, []) => Stop,
+            (0x02, []) => Play,
+            (0x03, []) => DeferredPlay,
+            (0x04, []) => FastForward,
+            (0x05, []) => Rewind,
+            (0x06, []) => RecordStrobe,
+            (0x07, []) => RecordExit,
+            (0x08, []) => RecordPause,
+            (0x09, []) => Pause,
+            (0x0A, []) => Eject,
+            (0x0B, []) => Chase,
+            (0x0C, []) => CommandErrorReset,
+            (0x0D, []) => MmcReset,
+            (LOCATE, [6, LOCATE_TARGET, time @ ..]) => Locate(MachineControlTime::parse(time)?),
+            _ => return Err(UnexpectedSysExMessageError(())),
+        };
+        Ok((device_id, command))
+    }
+}
+
+/// A MIDI Machine Control (MMC) response, transmitted as Universal Real Time System Exclusive
+/// message with sub-ID #1 `07`.
+///
+/// Only the responses which are relevant for transport synchronization are supported.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MachineControlResponse {
+    /// Reports the current time code of the device.
+    SelectedTimeCode(MachineControlTime),
+}
+
+impl MachineControlResponse {
+    /// Writes this response as System Exclusive message sent by the given device into the given
+    /// buffer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small.
+    pub fn write<'a>(
+        &self,
+        device_id: U7,
+        buffer: &'a mut [u8],
+    ) -> Result<SysExMessage<'a>, SysExError> {
+        match self {
+            MachineControlResponse::SelectedTimeCode(time) => {
+                let mut data = [0; 5];
+                time.write(&mut data);
+                write_mmc_message(buffer, device_id, RESPONSE, SELECTED_TIME_CODE, &data)
+            }
+        }
+    }
+
+    /// Interprets the given System Exclusive message as MIDI Machine Control response and returns
+    /// it together with the ID of the sending device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a supported MIDI Machine Control response message
+    /// or contains more than one response.
+    pub fn parse(
+        msg: SysExMessage<'_>,
+    ) -> Result<(U7, MachineControlResponse), UnexpectedSysExMessageError> {
+        match parse_universal_message(msg, ManufacturerId::UNIVERSAL_REAL_TIME)? {
+            (device_id, RESPONSE, SELECTED_TIME_CODE, time) => {
+                let response =
+                    MachineControlResponse::SelectedTimeCode(MachineControlTime::parse(time)?);
+                Ok((device_id, response))
+            }
+            _ => Err(UnexpectedSysExMessageError(())),
+        }
+    }
+}
+
+/// A time as used in MIDI Machine Control messages, e.g. the target of a Locate command.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MachineControlTime {
+    time_code_type: TimeCodeType,
+    hours: u8,
+    minutes: u8,
+    seconds: u8,
+    frames: u8,
+    subframes: u8,
+}
+
+impl MachineControlTime {
+    /// Creates a time.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if one of the given values is out of range, e.g. if `frames` is
+    /// greater than 24 for time code type [`Fps25`] or `subframes` is greater than 99.
+    ///
+    /// Use [`try_new`] if the values are not known to be valid.
+    ///
+    /// [`Fps25`]: enum.TimeCodeType.html#variant.Fps25
+    /// [`try_new`]: #method.try_new
+    pub fn new(
+        time_code_type: TimeCodeType,
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        subframes: u8,
+    ) -> MachineControlTime {
+        MachineControlTime::try_new(time_code_type, hours, minutes, seconds, frames, subframes)
+            .expect("MMC time out of range")
+    }
+
+    /// Returns the time code type.
+    pub fn time_code_type(&self) -> TimeCodeType {
+        self.time_code_type
+    }
+
+    /// Returns the hours (0 - 23).
+    pub fn hours(&self) -> u8 {
+        self.hours
+    }
+
+    /// Returns the minutes (0 - 59).
+    pub fn minutes(&self) -> u8 {
+        self.minutes
+    }
+
+    /// Returns the seconds (0 - 59).
+    pub fn seconds(&self) -> u8 {
+        self.seconds
+    }
+
+    /// Returns the frames (0 - 23, 0 - 24 or 0 - 29, depending on the time code type).
+    pub fn frames(&self) -> u8 {
+        self.frames
+    }
+
+    /// Returns the fractional frames in 1/100 frame (0 - 99).
+    pub fn subframes(&self) -> u8 {
+        self.subframes
+    }
+
+    /// Creates a time if all of the given values are in range.
+    ///
+    /// Returns `None` if one of the values is out of range (see [`new`]).
+    ///
+    /// [`new`]: #method.new
+    pub fn try_new(
+        time_code_type: TimeCodeType,
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        subframes: u8,
+    ) -> Option<MachineControlTime> {
+        let frames_per_second = match time_code_type {
+            TimeCodeType::Fps24 => 24,
+            TimeCodeType::Fps25 => 25,
+            TimeCodeType::Fps30DropFrame | TimeCodeType::Fps30NonDrop => 30,
+        };
+        if hours > 23 || minutes > 59 || seconds > 59 || frames >= frames_per_second {
+            return None;
+        }
+        if subframes > 99 {
+            return None;
+        }
+        let time = MachineControlTime {
+            time_code_type,
+            hours,
+            minutes,
+            seconds,
+            frames,
+            subframes,
+        };
+        Some(time)
+    }
+
+    /// Writes this time in the 5-byte standard time code format (`hr mn sc fr ff`).
+    fn write(&self, buffer: &mut [u8]) {
+        buffer[..5].copy_from_slice(&[
+            (u8::from(self.time_code_type) << 5) | self.hours,
+            self.minutes,
+            self.seconds,
+            self.frames,
+            self.subframes,
+        ]);
+    }
+
+    /// Parses a time in the 5-byte standard time code format, ignoring flag bits.
+    fn parse(data: &[u8]) -> Result<MachineControlTime, UnexpectedSysExMessageError> {
+        match *data {
+            [hr, mn, sc, fr, ff] => MachineControlTime::try_new(
+                TimeCodeType::try_from((hr >> 5) & 0b11).expect("impossible"),
+                hr & 0b0001_1111,
+                mn & 0b0011_1111,
+                sc & 0b0011_1111,
+                fr & 0b0001_1111,
+                ff,
+            )
+            .ok_or(UnexpectedSysExMessageError(())),
+            _ => Err(UnexpectedSysExMessageError(())),
+        }
+    }
+}
+
+fn write_mmc_message<'a>(
+    buffer: &'a mut [u8],
+    device_id: U7,
+    sub_id_1: u8,
+    sub_id_2: u8,
+    data: &[u8],
+) -> Result<SysExMessage<'a>, SysExError> {
+    write_universal_message(
+        buffer,
+        ManufacturerId::UNIVERSAL_REAL_TIME,
+        device_id,
+        sub_id_1,
+        sub_id_2,
+        data,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::u7;
+
+    #[test]
+    fn simple_commands_round_trip() {
+        // Given
+        use MachineControlCommand::*;
+        let commands = [
+            (Stop, 0x01),
+            (Play, 0x02),
+            (DeferredPlay, 0x03),
+            (FastForward, 0x04),
+            (Rewind, 0x05),
+            (RecordStrobe, 0x06),
+            (RecordExit, 0x07),
+            (RecordPause, 0x08),
+            (Pause, 0x09),
+            (Eject, 0x0A),
+            (Chase, 0x0B),
+            (CommandErrorReset, 0x0C),
+            (MmcReset, 0x0D),
+        ];
+        let mut buffer = [0; 16];
+        // When
+        // Then
+        for (command, byte) in commands.iter() {
+            let msg = command.write(u7(0x10), &mut buffer).unwrap();
+            assert_eq!(msg.as_bytes(), &[0xF0, 0x7F, 0x10, 0x06, *byte, 0xF7]);
+            assert_eq!(MachineControlCommand::parse(msg), Ok((u7(0x10), *command)));
+        }
+    }
+
+    #[test]
+    fn locate() {
+        // Given
+        let time = MachineControlTime::new(TimeCodeType::Fps30DropFrame, 23, 59, 58, 29, 99);
+        let command = MachineControlCommand::Locate(time);
+        let mut buffer = [0; 16];
+        // When
+        let msg = command.write(u7(0x7F), &mut buffer).unwrap();
+        // Then
+        assert_eq!(
+            msg.as_bytes(),
+            &[0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x57, 59, 58, 29, 99, 0xF7]
+        );
+        assert_eq!(MachineControlCommand::parse(msg), Ok((u7(0x7F), command)));
+        assert_eq!(time.time_code_type(), TimeCodeType::Fps30DropFrame);
+        assert_eq!(time.hours(), 23);
+        assert_eq!(time.minutes(), 59);
+        assert_eq!(time.seconds(), 58);
+        assert_eq!(time.frames(), 29);
+        assert_eq!(time.subframes(), 99);
+    }
+
+    #[test]
+    fn selected_time_code_response() {
+        // Given
+        let time = MachineControlTime::new(TimeCodeType::Fps24, 1, 0, 0, 23, 0);
+        let response = MachineControlResponse::SelectedTimeCode(time);
+        let mut buffer = [0; 16];
+        // When
+        let msg = response.write(u7(0x01), &mut buffer).unwrap();
+        // Then
+        assert_eq!(
+            msg.as_bytes(),
+            &[0xF0, 0x7F, 0x01, 0x07, 0x01, 0x01, 0, 0, 23, 0, 0xF7]
+        );
+        assert_eq!(MachineControlResponse::parse(msg), Ok((u7(0x01), response)));
+    }
+
+    #[test]
+    fn unexpected() {
+        // Given
+        let msgs: [&[u8]; 5] = [
+            &[0xF0, 0x7E, 0x7F, 0x06, 0x02, 0xF7],
+            &[0xF0, 0x7F, 0x7F, 0x06, 0x02, 0x03, 0xF7],
+            &[0xF0, 0x7F, 0x7F, 0x06, 0x7A, 0xF7],
+            &[0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x00, 0x00, 0xF7],
+            &[
+                0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x20, 0, 0, 25, 0, 0xF7,
+            ],
+        ];
+        // When
+        // Then
+        for bytes in msgs.iter() {
+            let msg = SysExMessage::from_bytes(bytes).unwrap();
+            assert_eq!(
+                MachineControlCommand::parse(msg),
+                Err(UnexpectedSysExMessageError(()))
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn time_out_of_range() {
+        MachineControlTime::new(TimeCodeType::Fps24, 0, 0, 0, 24, 0);
+    }
+
+    #[test]
+    fn try_new() {
+        // Given
+        // When
+        // Then
+        assert!(MachineControlTime::try_new(TimeCodeType::Fps24, 23, 59, 59, 23, 99).is_some());
+        assert!(MachineControlTime::try_new(TimeCodeType::Fps24, 0, 0, 0, 24, 0).is_none());
+        assert!(MachineControlTime::try_new(TimeCodeType::Fps30NonDrop, 0, 0, 0, 0, 100).is_none());
+    }
+}
@@ -0,0 +1,633 @@
+use crate::{BufferTooSmallError, Channel, SmfEventKind, TimeCodeType, Timecode, END_OF_TRACK};
+use core::convert::TryFrom;
+
+/// An error which can occur when trying to decode a meta event.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "invalid meta event")]
+pub struct InvalidMetaEventError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidMetaEventError {}
+
+const SEQUENCE_NUMBER: u8 = 0x00;
+const TEXT: u8 = 0x01;
+const COPYRIGHT: u8 = 0x02;
+const TRACK_NAME: u8 = 0x03;
+const INSTRUMENT_NAME: u8 = 0x04;
+const LYRIC: u8 = 0x05;
+const MARKER: u8 = 0x06;
+const CUE_POINT: u8 = 0x07;
+const CHANNEL_PREFIX: u8 = 0x20;
+const PORT_PREFIX: u8 = 0x21;
+const TEMPO: u8 = 0x51;
+const SMPTE_OFFSET: u8 = 0x54;
+const TIME_SIGNATURE: u8 = 0x58;
+const KEY_SIGNATURE: u8 = 0x59;
+const SEQUENCER_SPECIFIC: u8 = 0x7F;
+
+/// A meta event as contained in Standard MIDI Files.
+///
+/// Text payloads are exposed as raw bytes because the specification doesn't mandate a particular
+/// character encoding.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{MetaEvent, Tempo};
+///
+/// // Decode
+/// let event = MetaEvent::decode(0x51, &[0x07, 0xA1, 0x20]).unwrap();
+/// assert_eq!(event, MetaEvent::Tempo(Tempo::new(500_000)));
+/// if let MetaEvent::Tempo(tempo) = event {
+///     assert_eq!(tempo.bpm(), 120.0);
+/// }
+///
+/// // Encode
+/// let event = MetaEvent::TrackName(b"Bass");
+/// let mut buffer = [0; 16];
+/// let len = event.write_data(&mut buffer).unwrap();
+/// assert_eq!(event.meta_type(), 0x03);
+/// assert_eq!(&buffer[..len], b"Bass");
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MetaEvent<'a> {
+    /// Number of a sequence in a format 2 file or of the pattern referenced by a cue message.
+    SequenceNumber(u16),
+    /// Any kind of text.
+    Text(&'a [u8]),
+    /// Copyright notice.
+    Copyright(&'a [u8]),
+    /// Name of the sequence (in the first track of format 0 and 1 files) or of the track.
+    TrackName(&'a [u8]),
+    /// Description of the instrumentation used in the track.
+    InstrumentName(&'a [u8]),
+    /// Lyric to be sung, usually one syllable per event.
+    Lyric(&'a [u8]),
+    /// Name of a point in the sequence, e.g. a rehearsal letter or section name.
+    Marker(&'a [u8]),
+    /// Description of something happening on a film or video screen or stage at this point.
+    CuePoint(&'a [u8]),
+    /// The MIDI channel which following meta and System Exclusive events are associated with.
+    ChannelPrefix(Channel),
+    /// The MIDI port (cable) which the events of the track are sent to.
+    PortPrefix(u8),
+    /// End of the track. Mandatory as last event of each track.
+    EndOfTrack,
+    /// Tempo change.
+    Tempo(Tempo),
+    /// SMPTE time at which the track is supposed to start.
+    SmpteOffset(SmpteOffset),
+    /// Time signature change.
+    TimeSignature(TimeSignature),
+    /// Key signature change.
+    KeySignature(KeySignature),
+    /// Manufacturer-specific data, starting with the manufacturer ID.
+    SequencerSpecific(&'a [u8]),
+    /// A meta event of a type not known to this library.
+    Unknown { meta_type: u8, data: &'a [u8] },
+}
+
+impl<'a> MetaEvent<'a> {
+    /// Decodes the meta event with the given type and data (without length).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data doesn't match the format required for the given type.
+    pub fn decode(meta_type: u8, data: &'a [u8]) -> Result<MetaEvent<'a>, InvalidMetaEventError> {
+        use MetaEvent::*;
+        let event = match (meta_type, data) {
+            (SEQUENCE_NUMBER, &[msb, lsb]) => SequenceNumber(u16::from_be_bytes([msb, lsb])),
+            (TEXT, _) => Text(data),
+            (COPYRIGHT, _) => Copyright(data),
+            (TRACK_NAME, _) => TrackName(data),
+            (INSTRUMENT_NAME, _) => InstrumentName(data),
+            (LYRIC, _) => Lyric(data),
+            (MARKER, _) => Marker(data),
+            (CUE_POINT, _) => CuePoint(data),
+            (CHANNEL_PREFIX, &[channel]) => {
+                ChannelPrefix(Channel::try_from(channel).map_err(|_| InvalidMetaEventError(()))?)
+            }
+            (PORT_PREFIX, &[port]) => PortPrefix(port),
+            (END_OF_TRACK, &[]) => EndOfTrack,
+            (TEMPO, &[b1, b2, b3]) => {
+                let microseconds_per_quarter_note = u32::from_be_bytes([0, b1, b2, b3]);
+                if microseconds_per_quarter_note == 0 {
+                    return Err(InvalidMetaEventError(()));
+                }
+                Tempo(self::Tempo::new(microseconds_per_quarter_note))
+            }
+            (SMPTE_OFFSET, &[hr, mn, sc, fr, ff]) => {
+                let rate = TimeCodeType::try_from((hr >> 5) & 0b11).expect("impossible");
+                let timecode = Timecode::try_new(hr & 0b0001_1111, mn, sc, fr, rate)
+                    .ok_or(InvalidMetaEventError(()))?;
+                if ff > 99 {
+                    return Err(InvalidMetaEventError(()));
+                }
+                SmpteOffset(self::SmpteOffset::new(timecode, ff))
+            }
+            (TIME_SIGNATURE, &[nn, dd, cc, bb]) => {
+                if nn == 0 || dd > 15 {
+                    return Err(InvalidMetaEventError(()));
+                }
+                TimeSignature(self::TimeSignature::new(nn, dd, cc, bb))
+            }
+            (KEY_SIGNATURE, &[sf, mi]) => {
+                let accidentals = sf as i8;
+                if !(-7..=7).contains(&accidentals) || mi > 1 {
+                    return Err(InvalidMetaEventError(()));
+                }
+                KeySignature(self::KeySignature::new(accidentals, mi == 1))
+            }
+            (SEQUENCER_SPECIFIC, _) => SequencerSpecific(data),
+            (SEQUENCE_NUMBER, _)
+            | (CHANNEL_PREFIX, _)
+            | (PORT_PREFIX, _)
+            | (END_OF_TRACK, _)
+            | (TEMPO, _)
+            | (SMPTE_OFFSET, _)
+            | (TIME_SIGNATURE, _)
+            | (KEY_SIGNATURE, _) => return Err(InvalidMetaEventError(())),
+            _ => Unknown { meta_type, data },
+        };
+        Ok(event)
+    }
+
+    /// Returns the type byte of this meta event.
+    pub fn meta_type(&self) -> u8 {
+        use MetaEvent::*;
+        match self {
+            SequenceNumber(_) => SEQUENCE_NUMBER,
+            Text(_) => TEXT,
+            Copyright(_) => COPYRIGHT,
+            TrackName(_) => TRACK_NAME,
+            InstrumentName(_) => INSTRUMENT_NAME,
+            Lyric(_) => LYRIC,
+            Marker(_) => MARKER,
+            CuePoint(_) => CUE_POINT,
+            ChannelPrefix(_) => CHANNEL_PREFIX,
+            PortPrefix(_) => PORT_PREFIX,
+            EndOfTrack => END_OF_TRACK,
+            Tempo(_) => TEMPO,
+            SmpteOffset(_) => SMPTE_OFFSET,
+            TimeSignature(_) => TIME_SIGNATURE,
+            KeySignature(_) => KEY_SIGNATURE,
+            SequencerSpecific(_) => SEQUENCER_SPECIFIC,
+            Unknown { meta_type, .. } => *meta_type,
+        }
+    }
+
+    /// Returns the number of data bytes of this meta event.
+    pub fn data_len(&self) -> usize {
+        match self.fixed_data() {
+            Ok((_, len)) => len,
+            Err(data) => data.len(),
+        }
+    }
+
+    /// Writes the data of this meta event (without type and length) to the beginning of the given
+    /// buffer.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small.
+    pub fn write_data(&self, buffer: &mut [u8]) -> Result<usize, BufferTooSmallError> {
+        let fixed_data = self.fixed_data();
+        let data = match &fixed_data {
+            Ok((bytes, len)) => &bytes[..*len],
+            Err(data) => *data,
+        };
+        if buffer.len() < data.len() {
+            return Err(BufferTooSmallError(()));
+        }
+        buffer[..data.len()].copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    /// Returns the data of fixed-size meta events as bytes and length or the data of
+    /// variable-size meta events as slice.
+    fn fixed_data(&self) -> Result<([u8; 5], usize), &'a [u8]> {
+        use MetaEvent::*;
+        let result = match *self {
+            SequenceNumber(n) => {
+                let [msb, lsb] = n.to_be_bytes();
+                ([msb, lsb, 0, 0, 0], 2)
+            }
+            Text(data)
+            | Copyright(data)
+            | TrackName(data)
+            | InstrumentName(data)
+            | Lyric(data)
+            | Marker(data)
+            | CuePoint(data)
+            | SequencerSpecific(data)
+            | Unknown { data, .. } => return Err(data),
+            ChannelPrefix(channel) => ([channel.get(), 0, 0, 0, 0], 1),
+            PortPrefix(port) => ([port, 0, 0, 0, 0], 1),
+            EndOfTrack => ([0; 5], 0),
+            Tempo(tempo) => {
+                let [_, b1, b2, b3] = tempo.microseconds_per_quarter_note().to_be_bytes();
+                ([b1, b2, b3, 0, 0], 3)
+            }
+            SmpteOffset(offset) => {
+                let tc = offset.timecode();
+                (
+                    [
+                        (u8::from(tc.rate()) << 5) | tc.hours(),
+                        tc.minutes(),
+                        tc.seconds(),
+                        tc.frames(),
+                        offset.fractional_frames(),
+                    ],
+                    5,
+                )
+            }
+            TimeSignature(ts) => (
+                [
+                    ts.numerator(),
+                    ts.denominator_exponent(),
+                    ts.clocks_per_metronome_click(),
+                    ts.thirty_second_notes_per_quarter_note(),
+                    0,
+                ],
+                4,
+            ),
+            KeySignature(ks) => ([ks.accidentals() as u8, ks.is_minor() as u8, 0, 0, 0], 2),
+        };
+        Ok(result)
+    }
+}
+
+impl<'a> TryFrom<SmfEventKind<'a>> for MetaEvent<'a> {
+    type Error = InvalidMetaEventError;
+
+    fn try_from(kind: SmfEventKind<'a>) -> Result<Self, Self::Error> {
+        match kind {
+            SmfEventKind::Meta { meta_type, data } => MetaEvent::decode(meta_type, data),
+            _ => Err(InvalidMetaEventError(())),
+        }
+    }
+}
+
+/// A tempo, expressed in microseconds per quarter note as in Standard MIDI Files.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Tempo {
+    microseconds_per_quarter_note: u32,
+}
+
+impl Tempo {
+    /// Default tempo of Standard MIDI Files (120 beats per minute).
+    pub const DEFAULT: Tempo = Tempo {
+        microseconds_per_quarter_note: 500_000,
+    };
+
+    /// Creates a tempo from the given number of microseconds per quarter note.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the value is zero or doesn't fit into 24 bits.
+    pub fn new(microseconds_per_quarter_note: u32) -> Tempo {
+        assert!(
+            microseconds_per_quarter_note > 0 && microseconds_per_quarter_note <= 0x00FF_FFFF,
+            "tempo must be between 1 and 16777215 microseconds per quarter note"
+        );
+        Tempo {
+            microseconds_per_quarter_note,
+        }
+    }
+
+    /// Creates the tempo closest to the given beats (quarter notes) per minute.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the tempo is too fast or too slow to be expressed.
+    pub fn from_bpm(bpm: f64) -> Tempo {
+        Tempo::new((60_000_000.0 / bpm + 0.5) as u32)
+    }
+
+    /// Returns the number of microseconds per quarter note.
+    pub fn microseconds_per_quarter_note(&self) -> u32 {
+        self.microseconds_per_quarter_note
+    }
+
+    /// Returns the beats (quarter notes) per minute.
+    pub fn bpm(&self) -> f64 {
+        60_000_000.0 / f64::from(self.microseconds_per_quarter_note)
+    }
+}
+
+impl Default for Tempo {
+    fn default() -> Self {
+        Tempo::DEFAULT
+    }
+}
+
+/// A SMPTE time, including fractional frames, as contained in SMPTE Offset meta events.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SmpteOffset {
+    timecode: Timecode,
+    fractional_frames: u8,
+}
+
+impl SmpteOffset {
+    /// Creates a SMPTE offset.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the fractional frames are greater than 99.
+    pub fn new(timecode: Timecode, fractional_frames: u8) -> SmpteOffset {
+        assert!(
+            fractional_frames <= 99,
+            "fractional frames must not exceed 99"
+        );
+        SmpteOffset {
+            timecode,
+            fractional_frames,
+        }
+    }
+
+    /// Returns the time code.
+    pub fn timecode(&self) -> Timecode {
+        self.timecode
+    }
+
+    /// Returns the fractional frames in 1/100 frames.
+    pub fn fractional_frames(&self) -> u8 {
+        self.fractional_frames
+    }
+}
+
+/// A time signature as contained in Time Signature meta events.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TimeSignature {
+    numerator: u8,
+    denominator_exponent: u8,
+    clocks_per_metronome_click: u8,
+    thirty_second_notes_per_quarter_note: u8,
+}
+
+impl TimeSignature {
+    /// Creates a time signature.
+    ///
+    /// The denominator is expressed as power of 2, e.g. 3 for eighth notes. The number of MIDI
+    /// clocks per metronome click is usually 24 (once per quarter note) and the number of notated
+    /// 32nd notes per quarter note is usually 8.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the numerator is zero or the denominator exponent is greater than
+    /// 15.
+    pub fn new(
+        numerator: u8,
+        denominator_exponent: u8,
+        clocks_per_metronome_click: u8,
+        thirty_second_notes_per_quarter_note: u8,
+    ) -> TimeSignature {
+        assert!(numerator > 0, "numerator must not be zero");
+        assert!(
+            denominator_exponent <= 15,
+            "denominator exponent must not exceed 15"
+        );
+        TimeSignature {
+            numerator,
+            denominator_exponent,
+            clocks_per_metronome_click,
+            thirty_second_notes_per_quarter_note,
+        }
+    }
+
+    /// Creates a time signature with the usual metronome settings (one click per quarter note, 8
+    /// 32nd notes per quarter note).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the numerator is zero or the denominator is not a power of 2 up to
+    /// 32768.
+    pub fn simple(numerator: u8, denominator: u16) -> TimeSignature {
+        assert!(
+            denominator.is_power_of_two(),
+            "denominator must be a power of 2"
+        );
+        TimeSignature::new(numerator, denominator.trailing_zeros() as u8, 24, 8)
+    }
+
+    /// Returns the numerator.
+    pub fn numerator(&self) -> u8 {
+        self.numerator
+    }
+
+    /// Returns the denominator as power of 2.
+    pub fn denominator_exponent(&self) -> u8 {
+        self.denominator_exponent
+    }
+
+    /// Returns the denominator, e.g. 8 for eighth notes.
+    pub fn denominator(&self) -> u16 {
+        1 << self.denominator_exponent
+    }
+
+    /// Returns the number of MIDI clocks per metronome click.
+    pub fn clocks_per_metronome_click(&self) -> u8 {
+        self.clocks_per_metronome_click
+    }
+
+    /// Returns the number of notated 32nd notes per quarter note.
+    pub fn thirty_second_notes_per_quarter_note(&self) -> u8 {
+        self.thirty_second_notes_per_quarter_note
+    }
+}
+
+impl Default for TimeSignature {
+    /// Returns 4/4, the default time signature of Standard MIDI Files.
+    fn default() -> Self {
+        TimeSignature::simple(4, 4)
+    }
+}
+
+/// A key signature as contained in Key Signature meta events.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct KeySignature {
+    accidentals: i8,
+    is_minor: bool,
+}
+
+impl KeySignature {
+    /// Creates a key signature from the number of sharps (positive) or flats (negative).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of accidentals is not between -7 and 7.
+    pub fn new(accidentals: i8, is_minor: bool) -> KeySignature {
+        assert!(
+            (-7..=7).contains(&accidentals),
+            "number of accidentals must be between -7 and 7"
+        );
+        KeySignature {
+            accidentals,
+            is_minor,
+        }
+    }
+
+    /// Returns the number of sharps (positive) or flats (negative).
+    pub fn accidentals(&self) -> i8 {
+        self.accidentals
+    }
+
+    /// Returns whether this is a minor key.
+    pub fn is_minor(&self) -> bool {
+        self.is_minor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::channel;
+
+    fn round_trip(event: MetaEvent, expected_type: u8, expected_data: &[u8]) {
+        let mut buffer = [0; 16];
+        let len = event.write_data(&mut buffer).unwrap();
+        assert_eq!(event.meta_type(), expected_type);
+        assert_eq!(event.data_len(), expected_data.len());
+        assert_eq!(&buffer[..len], expected_data);
+        assert_eq!(MetaEvent::decode(expected_type, expected_data), Ok(event));
+    }
+
+    #[test]
+    fn text_events() {
+        round_trip(MetaEvent::Text(b"abc"), 0x01, b"abc");
+        round_trip(MetaEvent::Copyright(b"(c)"), 0x02, b"(c)");
+        round_trip(MetaEvent::TrackName(b"Drums"), 0x03, b"Drums");
+        round_trip(MetaEvent::InstrumentName(b"Kit"), 0x04, b"Kit");
+        round_trip(MetaEvent::Lyric(b"la"), 0x05, b"la");
+        round_trip(MetaEvent::Marker(b"Verse"), 0x06, b"Verse");
+        round_trip(MetaEvent::CuePoint(b""), 0x07, b"");
+    }
+
+    #[test]
+    fn fixed_size_events() {
+        round_trip(MetaEvent::SequenceNumber(0x1234), 0x00, &[0x12, 0x34]);
+        round_trip(MetaEvent::ChannelPrefix(channel(9)), 0x20, &[9]);
+        round_trip(MetaEvent::PortPrefix(2), 0x21, &[2]);
+        round_trip(MetaEvent::EndOfTrack, 0x2F, &[]);
+        round_trip(
+            MetaEvent::Tempo(Tempo::new(0x0F4240)),
+            0x51,
+            &[0x0F, 0x42, 0x40],
+        );
+        round_trip(
+            MetaEvent::SmpteOffset(SmpteOffset::new(
+                Timecode::new(1, 2, 3, 4, TimeCodeType::Fps30DropFrame),
+                50,
+            )),
+            0x54,
+            &[0x41, 2, 3, 4, 50],
+        );
+        round_trip(
+            MetaEvent::TimeSignature(TimeSignature::new(6, 3, 36, 8)),
+            0x58,
+            &[6, 3, 36, 8],
+        );
+        round_trip(
+            MetaEvent::KeySignature(KeySignature::new(-3, true)),
+            0x59,
+            &[0xFD, 1],
+        );
+    }
+
+    #[test]
+    fn other_events() {
+        round_trip(
+            MetaEvent::SequencerSpecific(&[0x00, 0x00, 0x41, 1]),
+            0x7F,
+            &[0x00, 0x00, 0x41, 1],
+        );
+        round_trip(
+            MetaEvent::Unknown {
+                meta_type: 0x60,
+                data: &[1, 2],
+            },
+            0x60,
+            &[1, 2],
+        );
+    }
+
+    #[test]
+    fn invalid_events() {
+        // Given
+        let cases: [(u8, &[u8]); 10] = [
+            (0x00, &[1]),
+            (0x51, &[0, 0, 0]),
+            (0x20, &[16]),
+            (0x2F, &[0]),
+            (0x51, &[1, 2]),
+            (0x54, &[0, 60, 0, 0, 0]),
+            (0x54, &[0, 0, 0, 0, 100]),
+            (0x58, &[0, 2, 24, 8]),
+            (0x59, &[8, 0]),
+            (0x59, &[0, 2]),
+        ];
+        // When
+        // Then
+        for (meta_type, data) in cases.iter() {
+            assert_eq!(
+                MetaEvent::decode(*meta_type, data),
+                Err(InvalidMetaEventError(()))
+            );
+        }
+    }
+
+    #[test]
+    fn from_smf_event_kind() {
+        // Given
+        let kind = SmfEventKind::Meta {
+            meta_type: 0x59,
+            data: &[2, 0],
+        };
+        // When
+        let event = MetaEvent::try_from(kind);
+        // Then
+        assert_eq!(
+            event,
+            Ok(MetaEvent::KeySignature(KeySignature::new(2, false)))
+        );
+        assert_eq!(
+            MetaEvent::try_from(SmfEventKind::SysEx(&[])),
+            Err(InvalidMetaEventError(()))
+        );
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        // Given
+        let event = MetaEvent::Marker(b"Chorus");
+        let mut buffer = [0; 4];
+        // When
+        let result = event.write_data(&mut buffer);
+        // Then
+        assert_eq!(result, Err(BufferTooSmallError(())));
+    }
+
+    #[test]
+    fn tempo() {
+        // Given
+        // When
+        let tempo = Tempo::from_bpm(140.0);
+        // Then
+        assert_eq!(tempo.microseconds_per_quarter_note(), 428_571);
+        assert!((tempo.bpm() - 140.0).abs() < 0.001);
+        assert_eq!(Tempo::default().bpm(), 120.0);
+    }
+
+    #[test]
+    fn time_signature() {
+        // Given
+        // When
+        let ts = TimeSignature::simple(7, 8);
+        // Then
+        assert_eq!(ts, TimeSignature::new(7, 3, 24, 8));
+        assert_eq!(ts.denominator(), 8);
+        assert_eq!(TimeSignature::default(), TimeSignature::new(4, 2, 24, 8));
+    }
+}
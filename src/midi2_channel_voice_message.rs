@@ -0,0 +1,1030 @@
+use crate::{
+    build_14_bit_value_from_two_7_bit_values, build_first_word, controller_numbers,
+    downscale_u16_to_u7, downscale_u32_to_u14, downscale_u32_to_u7,
+    extract_high_7_bit_value_from_14_bit_value, extract_low_7_bit_value_from_14_bit_value,
+    upscale_u14_to_u32, upscale_u7_to_u16, upscale_u7_to_u32, Channel, ControllerNumber,
+    DataEntryByteOrder, DataType, KeyNumber, ParameterNumberMessage, ShortMessage,
+    ShortMessageFactory, StructuredShortMessage, UmpMessageType, U14, U4, U7,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A MIDI 2.0 Channel Voice message, carried by a 64-bit UMP message of type 4.
+///
+/// Compared to MIDI 1.0, velocities have 16 bits and most other values 32 bits. In addition,
+/// there are per-note controllers, per-note pitch bend and relative controllers, and registered and
+/// assignable controllers ((N)RPNs) are single messages.
+///
+/// This enum is usually contained in [`StructuredUmpMessage::Midi2ChannelVoice`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{
+///     Channel, KeyNumber, Midi2ChannelVoiceMessage, StructuredUmpMessage, UmpMessage, U4,
+/// };
+///
+/// let msg = StructuredUmpMessage::Midi2ChannelVoice {
+///     group: U4::new(0),
+///     message: Midi2ChannelVoiceMessage::NoteOn {
+///         channel: Channel::new(2),
+///         key_number: KeyNumber::new(60),
+///         velocity: 0xC000,
+///         attribute_type: 0,
+///         attribute_data: 0,
+///     },
+/// };
+/// assert_eq!(msg.to_words(), [0x4092_3C00, 0xC000_0000, 0, 0]);
+/// ```
+///
+/// [`StructuredUmpMessage::Midi2ChannelVoice`]:
+/// enum.StructuredUmpMessage.html#variant.Midi2ChannelVoice
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Midi2ChannelVoiceMessage {
+    /// Registered per-note controller, e.g. one of the [`registered_per_note_controllers`].
+    ///
+    /// [`registered_per_note_controllers`]: registered_per_note_controllers/index.html
+    RegisteredPerNoteController {
+        channel: Channel,
+        key_number: KeyNumber,
+        index: u8,
+        value: u32,
+    },
+    /// Assignable (manufacturer or user defined) per-note controller.
+    AssignablePerNoteController {
+        channel: Channel,
+        key_number: KeyNumber,
+        index: u8,
+        value: u32,
+    },
+    /// Registered Controller (RPN).
+    RegisteredController {
+        channel: Channel,
+        bank: U7,
+        index: U7,
+        value: u32,
+    },
+    /// Assignable Controller (NRPN).
+    AssignableController {
+        channel: Channel,
+        bank: U7,
+        index: U7,
+        value: u32,
+    },
+    /// Relative change of a Registered Controller (RPN), where the value is a signed increment.
+    RelativeRegisteredController {
+        channel: Channel,
+        bank: U7,
+        index: U7,
+        value: i32,
+    },
+    /// Relative change of an Assignable Controller (NRPN), where the value is a signed increment.
+    RelativeAssignableController {
+        channel: Channel,
+        bank: U7,
+        index: U7,
+        value: i32,
+    },
+    /// Per-note pitch bend, where `0x8000_0000` is the center.
+    PerNotePitchBend {
+        channel: Channel,
+        key_number: KeyNumber,
+        value: u32,
+    },
+    /// Note Off with 16-bit velocity and optional attribute (attribute type 0 means none).
+    NoteOff {
+        channel: Channel,
+        key_number: KeyNumber,
+        velocity: u16,
+        attribute_type: u8,
+        attribute_data: u16,
+    },
+    /// Note On with 16-bit velocity and optional attribute (attribute type 0 means none).
+    ///
+    /// Unlike in MIDI 1.0, a velocity of 0 doesn't mean Note Off.
+    NoteOn {
+        channel: Channel,
+        key_number: KeyNumber,
+        velocity: u16,
+        attribute_type: u8,
+        attribute_data: u16,
+    },
+    /// Polyphonic (per-note) pressure.
+    PolyPressure {
+        channel: Channel,
+        key_number: KeyNumber,
+        pressure: u32,
+    },
+    /// Control Change with 32-bit value.
+    ControlChange {
+        channel: Channel,
+        controller_number: ControllerNumber,
+        value: u32,
+    },
+    /// Program change, optionally combined with a bank select (bank MSB in the upper 7 bits).
+    ProgramChange {
+        channel: Channel,
+        program_number: U7,
+        bank: Option<U14>,
+    },
+    /// Channel pressure.
+    ChannelPressure { channel: Channel, pressure: u32 },
+    /// Pitch bend, where `0x8000_0000` is the center.
+    PitchBend { channel: Channel, value: u32 },
+    /// Per-note management, which detaches per-note controllers from a previously received note
+    /// and/or resets them to their defaults.
+    PerNoteManagement {
+        channel: Channel,
+        key_number: KeyNumber,
+        detach: bool,
+        reset: bool,
+    },
+}
+
+impl Midi2ChannelVoiceMessage {
+    /// Returns the channel of this message.
+    pub fn channel(&self) -> Channel {
+        use Midi2ChannelVoiceMessage::*;
+        match *self {
+            RegisteredPerNoteController { channel, .. }
+            | AssignablePerNoteController { channel, .. }
+            | RegisteredController { channel, .. }
+            | AssignableController { channel, .. }
+            | RelativeRegisteredController { channel, .. }
+            | RelativeAssignableController { channel, .. }
+            | PerNotePitchBend { channel, .. }
+            | NoteOff { channel, .. }
+            | NoteOn { channel, .. }
+            | PolyPressure { channel, .. }
+            | ControlChange { channel, .. }
+            | ProgramChange { channel, .. }
+            | ChannelPressure { channel, .. }
+            | PitchBend { channel, .. }
+            | PerNoteManagement { channel, .. } => channel,
+        }
+    }
+
+    /// Returns the key number of this message if applicable.
+    pub fn key_number(&self) -> Option<KeyNumber> {
+        use Midi2ChannelVoiceMessage::*;
+        match *self {
+            RegisteredPerNoteController { key_number, .. }
+            | AssignablePerNoteController { key_number, .. }
+            | PerNotePitchBend { key_number, .. }
+            | NoteOff { key_number, .. }
+            | NoteOn { key_number, .. }
+            | PolyPressure { key_number, .. }
+            | PerNoteManagement { key_number, .. } => Some(key_number),
+            _ => None,
+        }
+    }
+
+    /// Translates the given MIDI 1.0 channel message into a MIDI 2.0 Channel Voice message by
+    /// scaling up all values (default translation of the MIDI 2.0 specification).
+    ///
+    /// A Note On with velocity 0 is translated into a Note Off with velocity 0x8000. Control Change
+    /// messages are translated one by one, so (N)RPN sequences and Bank Select are not taken into
+    /// account. Use [`Midi1ToMidi2Translator`] for that.
+    ///
+    /// Returns `None` if the given message is not a channel message.
+    ///
+    /// [`Midi1ToMidi2Translator`]: struct.Midi1ToMidi2Translator.html
+    pub fn from_short_message(msg: &impl ShortMessage) -> Option<Self> {
+        use Midi2ChannelVoiceMessage::*;
+        let msg = match msg.to_structured() {
+            StructuredShortMessage::NoteOff {
+                channel,
+                key_number,
+                velocity,
+            } => NoteOff {
+                channel,
+                key_number,
+                velocity: upscale_u7_to_u16(velocity),
+                attribute_type: 0,
+                attribute_data: 0,
+            },
+            StructuredShortMessage::NoteOn {
+                channel,
+                key_number,
+                velocity,
+            } => {
+                if velocity == U7::MIN {
+                    NoteOff {
+                        channel,
+                        key_number,
+                        velocity: 0x8000,
+                        attribute_type: 0,
+                        attribute_data: 0,
+                    }
+                } else {
+                    NoteOn {
+                        channel,
+                        key_number,
+                        velocity: upscale_u7_to_u16(velocity),
+                        attribute_type: 0,
+                        attribute_data: 0,
+                    }
+                }
+            }
+            StructuredShortMessage::PolyphonicKeyPressure {
+                channel,
+                key_number,
+                pressure_amount,
+            } => PolyPressure {
+                channel,
+                key_number,
+                pressure: upscale_u7_to_u32(pressure_amount),
+            },
+            StructuredShortMessage::ControlChange {
+                channel,
+                controller_number,
+                control_value,
+            } => ControlChange {
+                channel,
+                controller_number,
+                value: upscale_u7_to_u32(control_value),
+            },
+            StructuredShortMessage::ProgramChange {
+                channel,
+                program_number,
+            } => ProgramChange {
+                channel,
+                program_number,
+                bank: None,
+            },
+            StructuredShortMessage::ChannelPressure {
+                channel,
+                pressure_amount,
+            } => ChannelPressure {
+                channel,
+                pressure: upscale_u7_to_u32(pressure_amount),
+            },
+            StructuredShortMessage::PitchBendChange {
+                channel,
+                pitch_bend_value,
+            } => PitchBend {
+                channel,
+                value: upscale_u14_to_u32(pitch_bend_value),
+            },
+            _ => return None,
+        };
+        Some(msg)
+    }
+
+    /// Translates the given (N)RPN message into a MIDI 2.0 Registered/Assignable Controller
+    /// message.
+    ///
+    /// The parameter number MSB becomes the bank and the LSB the index. Data increment and
+    /// decrement are translated into relative controllers, where one increment step corresponds to
+    /// one step of a 14-bit value (`1 << 18`).
+    pub fn from_parameter_number_message(msg: ParameterNumberMessage) -> Self {
+        use Midi2ChannelVoiceMessage::*;
+        let channel = msg.channel();
+        let bank = extract_high_7_bit_value_from_14_bit_value(msg.number());
+        let index = extract_low_7_bit_value_from_14_bit_value(msg.number());
+        let relative_value = (i32::from(msg.value().get())) << RELATIVE_STEP_BITS;
+        match (msg.data_type(), msg.is_registered()) {
+            (DataType::DataEntry, is_registered) => {
+                let value = if msg.is_14_bit() {
+                    upscale_u14_to_u32(msg.value())
+                } else {
+                    upscale_u7_to_u32(U7(msg.value().get() as u8))
+                };
+                if is_registered {
+                    RegisteredController {
+                        channel,
+                        bank,
+                        index,
+                        value,
+                    }
+                } else {
+                    AssignableController {
+                        channel,
+                        bank,
+                        index,
+                        value,
+                    }
+                }
+            }
+            (data_type, true) => RelativeRegisteredController {
+                channel,
+                bank,
+                index,
+                value: signed(data_type, relative_value),
+            },
+            (data_type, false) => RelativeAssignableController {
+                channel,
+                bank,
+                index,
+                value: signed(data_type, relative_value),
+            },
+        }
+    }
+
+    /// Translates this message into up to 4 MIDI 1.0 short messages by scaling down all values
+    /// (default translation of the MIDI 2.0 specification).
+    ///
+    /// - Note On messages whose velocity would become 0 get velocity 1.
+    /// - Registered/Assignable Controller messages become 14-bit (N)RPN messages with
+    ///   [`LsbFirst`] data entry byte order, which is understood by
+    ///   [`ParameterNumberMessageScanner`].
+    /// - Relative controllers become data increment/decrement messages (see
+    ///   [`from_parameter_number_message`]). The amount is rounded away from zero, so even a tiny
+    ///   change results in a step of 1.
+    /// - A Program Change with bank is preceded by Bank Select MSB and LSB.
+    ///
+    /// Messages which can't be represented in MIDI 1.0 (per-note controllers, per-note pitch bend
+    /// and per-note management) result in no short messages at all. Attributes are dropped.
+    ///
+    /// [`LsbFirst`]: enum.DataEntryByteOrder.html#variant.LsbFirst
+    /// [`ParameterNumberMessageScanner`]: struct.ParameterNumberMessageScanner.html
+    /// [`from_parameter_number_message`]: #method.from_parameter_number_message
+    pub fn to_short_messages<T: ShortMessageFactory>(&self) -> [Option<T>; 4] {
+        use Midi2ChannelVoiceMessage::*;
+        let single = |msg: T| [Some(msg), None, None, None];
+        let number = |bank: U7, index: U7| build_14_bit_value_from_two_7_bit_values(bank, index);
+        let parameter_number_message = match *self {
+            RegisteredPerNoteController { .. }
+            | AssignablePerNoteController { .. }
+            | PerNotePitchBend { .. }
+            | PerNoteManagement { .. } => return [None, None, None, None],
+            NoteOff {
+                channel,
+                key_number,
+                velocity,
+                ..
+            } => {
+                return single(T::note_off(
+                    channel,
+                    key_number,
+                    downscale_u16_to_u7(velocity),
+                ))
+            }
+            NoteOn {
+                channel,
+                key_number,
+                velocity,
+                ..
+            } => {
+                let velocity = downscale_u16_to_u7(velocity).max(U7(1));
+                return single(T::note_on(channel, key_number, velocity));
+            }
+            PolyPressure {
+                channel,
+                key_number,
+                pressure,
+            } => {
+                return single(T::polyphonic_key_pressure(
+                    channel,
+                    key_number,
+                    downscale_u32_to_u7(pressure),
+                ))
+            }
+            ControlChange {
+                channel,
+                controller_number,
+                value,
+            } => {
+                return single(T::control_change(
+                    channel,
+                    controller_number,
+                    downscale_u32_to_u7(value),
+                ))
+            }
+            ProgramChange {
+                channel,
+                program_number,
+                bank,
+            } => {
+                let program_change = T::program_change(channel, program_number);
+                return match bank {
+                    None => single(program_change),
+                    Some(bank) => [
+                        Some(T::control_change(
+                            channel,
+                            controller_numbers::BANK_SELECT,
+                            extract_high_7_bit_value_from_14_bit_value(bank),
+                        )),
+                        Some(T::control_change(
+                            channel,
+                            controller_numbers::BANK_SELECT_LSB,
+                            extract_low_7_bit_value_from_14_bit_value(bank),
+                        )),
+                        Some(program_change),
+                        None,
+                    ],
+                };
+            }
+            ChannelPressure { channel, pressure } => {
+                return single(T::channel_pressure(channel, downscale_u32_to_u7(pressure)))
+            }
+            PitchBend { channel, value } => {
+                return single(T::pitch_bend_change(channel, downscale_u32_to_u14(value)))
+            }
+            RegisteredController {
+                channel,
+                bank,
+                index,
+                value,
+            } => ParameterNumberMessage::registered_14_bit(
+                channel,
+                number(bank, index),
+                downscale_u32_to_u14(value),
+            ),
+            AssignableController {
+                channel,
+                bank,
+                index,
+                value,
+            } => ParameterNumberMessage::non_registered_14_bit(
+                channel,
+                number(bank, index),
+                downscale_u32_to_u14(value),
+            ),
+            RelativeRegisteredController {
+                channel,
+                bank,
+                index,
+                value,
+            } => {
+                let amount = relative_amount(value);
+                if value < 0 {
+                    ParameterNumberMessage::registered_decrement(
+                        channel,
+                        number(bank, index),
+                        amount,
+                    )
+                } else {
+                    ParameterNumberMessage::registered_increment(
+                        channel,
+                        number(bank, index),
+                        amount,
+                    )
+                }
+            }
+            RelativeAssignableController {
+                channel,
+                bank,
+                index,
+                value,
+            } => {
+                let amount = relative_amount(value);
+                if value < 0 {
+                    ParameterNumberMessage::non_registered_decrement(
+                        channel,
+                        number(bank, index),
+                        amount,
+                    )
+                } else {
+                    ParameterNumberMessage::non_registered_increment(
+                        channel,
+                        number(bank, index),
+                        amount,
+                    )
+                }
+            }
+        };
+        parameter_number_message.to_short_messages(DataEntryByteOrder::LsbFirst)
+    }
+
+    /// Decodes the first two words of a UMP message of type 4.
+    ///
+    /// Returns `None` if the opcode is reserved or a 7-bit field has its upper bit set.
+    pub(crate) fn decode(words: &[u32; 4]) -> Option<Self> {
+        use Midi2ChannelVoiceMessage::*;
+        let [word_0, word_1, _, _] = *words;
+        let opcode = (word_0 >> 20) & 0x0F;
+        let channel = Channel(((word_0 >> 16) & 0x0F) as u8);
+        let byte_2 = (word_0 >> 8) as u8;
+        let byte_3 = word_0 as u8;
+        let u7 = |byte: u8| if byte < 0x80 { Some(U7(byte)) } else { None };
+        let key_number = || u7(byte_2).map(KeyNumber::from);
+        let msg = match opcode {
+            0x0 => RegisteredPerNoteController {
+                channel,
+                key_number: key_number()?,
+                index: byte_3,
+                value: word_1,
+            },
+            0x1 => AssignablePerNoteController {
+                channel,
+                key_number: key_number()?,
+                index: byte_3,
+                value: word_1,
+            },
+            0x2 => RegisteredController {
+                channel,
+                bank: u7(byte_2)?,
+                index: u7(byte_3)?,
+                value: word_1,
+            },
+            0x3 => AssignableController {
+                channel,
+                bank: u7(byte_2)?,
+                index: u7(byte_3)?,
+                value: word_1,
+            },
+            0x4 => RelativeRegisteredController {
+                channel,
+                bank: u7(byte_2)?,
+                index: u7(byte_3)?,
+                value: word_1 as i32,
+            },
+            0x5 => RelativeAssignableController {
+                channel,
+                bank: u7(byte_2)?,
+                index: u7(byte_3)?,
+                value: word_1 as i32,
+            },
+            0x6 => PerNotePitchBend {
+                channel,
+                key_number: key_number()?,
+                value: word_1,
+            },
+            0x8 => NoteOff {
+                channel,
+                key_number: key_number()?,
+                velocity: (word_1 >> 16) as u16,
+                attribute_type: byte_3,
+                attribute_data: word_1 as u16,
+            },
+            0x9 => NoteOn {
+                channel,
+                key_number: key_number()?,
+                velocity: (word_1 >> 16) as u16,
+                attribute_type: byte_3,
+                attribute_data: word_1 as u16,
+            },
+            0xA => PolyPressure {
+                channel,
+                key_number: key_number()?,
+                pressure: word_1,
+            },
+            0xB => ControlChange {
+                channel,
+                controller_number: u7(byte_2)?.into(),
+                value: word_1,
+            },
+            0xC => {
+                let bank = if byte_3 & 0x01 == 0 {
+                    None
+                } else {
+                    let msb = u7((word_1 >> 8) as u8)?;
+                    let lsb = u7(word_1 as u8)?;
+                    Some(U14((u16::from(msb.get()) << 7) | u16::from(lsb.get())))
+                };
+                ProgramChange {
+                    channel,
+                    program_number: u7((word_1 >> 24) as u8)?,
+                    bank,
+                }
+            }
+            0xD => ChannelPressure {
+                channel,
+                pressure: word_1,
+            },
+            0xE => PitchBend {
+                channel,
+                value: word_1,
+            },
+            0xF => PerNoteManagement {
+                channel,
+                key_number: key_number()?,
+                detach: byte_3 & 0x02 != 0,
+                reset: byte_3 & 0x01 != 0,
+            },
+            _ => return None,
+        };
+        Some(msg)
+    }
+
+    /// Encodes this message as UMP message of type 4 with the given group.
+    pub(crate) fn encode(&self, group: U4) -> [u32; 4] {
+        use Midi2ChannelVoiceMessage::*;
+        let (opcode, byte_2, byte_3, word_1): (u8, u8, u8, u32) = match *self {
+            RegisteredPerNoteController {
+                key_number,
+                index,
+                value,
+                ..
+            } => (0x0, key_number.get(), index, value),
+            AssignablePerNoteController {
+                key_number,
+                index,
+                value,
+                ..
+            } => (0x1, key_number.get(), index, value),
+            RegisteredController {
+                bank, index, value, ..
+            } => (0x2, bank.get(), index.get(), value),
+            AssignableController {
+                bank, index, value, ..
+            } => (0x3, bank.get(), index.get(), value),
+            RelativeRegisteredController {
+                bank, index, value, ..
+            } => (0x4, bank.get(), index.get(), value as u32),
+            RelativeAssignableController {
+                bank, index, value, ..
+            } => (0x5, bank.get(), index.get(), value as u32),
+            PerNotePitchBend {
+                key_number, value, ..
+            } => (0x6, key_number.get(), 0, value),
+            NoteOff {
+                key_number,
+                velocity,
+                attribute_type,
+                attribute_data,
+                ..
+            } => (
+                0x8,
+                key_number.get(),
+                attribute_type,
+                (u32::from(velocity) << 16) | u32::from(attribute_data),
+            ),
+            NoteOn {
+                key_number,
+                velocity,
+                attribute_type,
+                attribute_data,
+                ..
+            } => (
+                0x9,
+                key_number.get(),
+                attribute_type,
+                (u32::from(velocity) << 16) | u32::from(attribute_data),
+            ),
+            PolyPressure {
+                key_number,
+                pressure,
+                ..
+            } => (0xA, key_number.get(), 0, pressure),
+            ControlChange {
+                controller_number,
+                value,
+                ..
+            } => (0xB, controller_number.get(), 0, value),
+            ProgramChange {
+                program_number,
+                bank,
+                ..
+            } => {
+                let (flags, bank_bytes) = match bank {
+                    None => (0x00, 0),
+                    Some(bank) => {
+                        let bank = u32::from(bank.get());
+                        (0x01, ((bank >> 7) << 8) | (bank & 0x7F))
+                    }
+                };
+                (
+                    0xC,
+                    0,
+                    flags,
+                    (u32::from(program_number.get()) << 24) | bank_bytes,
+                )
+            }
+            ChannelPressure { pressure, .. } => (0xD, 0, 0, pressure),
+            PitchBend { value, .. } => (0xE, 0, 0, value),
+            PerNoteManagement {
+                key_number,
+                detach,
+                reset,
+                ..
+            } => (
+                0xF,
+                key_number.get(),
+                ((detach as u8) << 1) | reset as u8,
+                0,
+            ),
+        };
+        let word_0 = build_first_word(UmpMessageType::Midi2ChannelVoice, group)
+            | (u32::from(opcode) << 20)
+            | (u32::from(self.channel().get()) << 16)
+            | (u32::from(byte_2) << 8)
+            | u32::from(byte_3);
+        [word_0, word_1, 0, 0]
+    }
+}
+
+/// Number of bits by which a relative controller value is shifted per MIDI 1.0 increment step.
+const RELATIVE_STEP_BITS: u32 = 18;
+
+fn signed(data_type: DataType, value: i32) -> i32 {
+    if data_type == DataType::DataDecrement {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Rounds away from zero so that small changes don't get lost.
+fn relative_amount(value: i32) -> U7 {
+    let step_size = 1u32 << RELATIVE_STEP_BITS;
+    let steps = (value.unsigned_abs() + step_size - 1) >> RELATIVE_STEP_BITS;
+    U7(steps.min(127) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::RawShortMessage;
+
+    fn round_trip(msg: Midi2ChannelVoiceMessage, expected_words: [u32; 2]) {
+        let words = msg.encode(U4::new(3));
+        assert_eq!(words, [expected_words[0], expected_words[1], 0, 0]);
+        assert_eq!(Midi2ChannelVoiceMessage::decode(&words), Some(msg));
+    }
+
+    #[test]
+    fn encode_decode() {
+        // Given
+        // When
+        // Then
+        use Midi2ChannelVoiceMessage::*;
+        round_trip(
+            NoteOn {
+                channel: channel(1),
+                key_number: key_number(64),
+                velocity: 0xFFFF,
+                attribute_type: 3,
+                attribute_data: 0x1234,
+            },
+            [0x4391_4003, 0xFFFF_1234],
+        );
+        round_trip(
+            NoteOff {
+                channel: channel(15),
+                key_number: key_number(0),
+                velocity: 0,
+                attribute_type: 0,
+                attribute_data: 0,
+            },
+            [0x438F_0000, 0x0000_0000],
+        );
+        round_trip(
+            RegisteredPerNoteController {
+                channel: channel(0),
+                key_number: key_number(60),
+                index: 7,
+                value: 0x8000_0000,
+            },
+            [0x4300_3C07, 0x8000_0000],
+        );
+        round_trip(
+            AssignableController {
+                channel: channel(2),
+                bank: u7(1),
+                index: u7(2),
+                value: 42,
+            },
+            [0x4332_0102, 42],
+        );
+        round_trip(
+            RelativeRegisteredController {
+                channel: channel(2),
+                bank: u7(0),
+                index: u7(0),
+                value: -1,
+            },
+            [0x4342_0000, 0xFFFF_FFFF],
+        );
+        round_trip(
+            ControlChange {
+                channel: channel(0),
+                controller_number: controller_number(74),
+                value: 0x1234_5678,
+            },
+            [0x43B0_4A00, 0x1234_5678],
+        );
+        round_trip(
+            ProgramChange {
+                channel: channel(0),
+                program_number: u7(5),
+                bank: Some(u14(0x81)),
+            },
+            [0x43C0_0001, 0x0500_0101],
+        );
+        round_trip(
+            ProgramChange {
+                channel: channel(0),
+                program_number: u7(5),
+                bank: None,
+            },
+            [0x43C0_0000, 0x0500_0000],
+        );
+        round_trip(
+            PitchBend {
+                channel: channel(4),
+                value: 0x8000_0000,
+            },
+            [0x43E4_0000, 0x8000_0000],
+        );
+        round_trip(
+            PerNoteManagement {
+                channel: channel(4),
+                key_number: key_number(61),
+                detach: true,
+                reset: false,
+            },
+            [0x43F4_3D02, 0],
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        // Given
+        // When
+        // Then
+        // Reserved opcode
+        assert_eq!(
+            Midi2ChannelVoiceMessage::decode(&[0x4070_0000, 0, 0, 0]),
+            None
+        );
+        // Key number with upper bit set
+        assert_eq!(
+            Midi2ChannelVoiceMessage::decode(&[0x4090_8000, 0, 0, 0]),
+            None
+        );
+    }
+
+    #[test]
+    fn from_short_message() {
+        // Given
+        use Midi2ChannelVoiceMessage::*;
+        // When
+        // Then
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_short_message(&note_on(1, 60, 127)),
+            Some(NoteOn {
+                channel: channel(1),
+                key_number: key_number(60),
+                velocity: 0xFFFF,
+                attribute_type: 0,
+                attribute_data: 0,
+            })
+        );
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_short_message(&note_on(1, 60, 0)),
+            Some(NoteOff {
+                channel: channel(1),
+                key_number: key_number(60),
+                velocity: 0x8000,
+                attribute_type: 0,
+                attribute_data: 0,
+            })
+        );
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_short_message(&control_change(2, 7, 64)),
+            Some(ControlChange {
+                channel: channel(2),
+                controller_number: controller_number(7),
+                value: 0x8000_0000,
+            })
+        );
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_short_message(&pitch_bend_change(0, 8192)),
+            Some(PitchBend {
+                channel: channel(0),
+                value: 0x8000_0000,
+            })
+        );
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_short_message(&timing_clock()),
+            None
+        );
+    }
+
+    #[test]
+    fn from_parameter_number_message() {
+        // Given
+        use Midi2ChannelVoiceMessage::*;
+        // When
+        // Then
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_parameter_number_message(rpn_14_bit(3, 0x0081, 0x2000)),
+            RegisteredController {
+                channel: channel(3),
+                bank: u7(1),
+                index: u7(1),
+                value: 0x8000_0000,
+            }
+        );
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_parameter_number_message(nrpn(3, 5, 127)),
+            AssignableController {
+                channel: channel(3),
+                bank: u7(0),
+                index: u7(5),
+                value: 0xFFFF_FFFF,
+            }
+        );
+        assert_eq!(
+            Midi2ChannelVoiceMessage::from_parameter_number_message(
+                ParameterNumberMessage::registered_decrement(channel(3), u14(2), u7(2))
+            ),
+            RelativeRegisteredController {
+                channel: channel(3),
+                bank: u7(0),
+                index: u7(2),
+                value: -(2 << 18),
+            }
+        );
+    }
+
+    #[test]
+    fn to_short_messages() {
+        // Given
+        use Midi2ChannelVoiceMessage::*;
+        // When
+        // Then
+        assert_eq!(
+            NoteOn {
+                channel: channel(1),
+                key_number: key_number(60),
+                velocity: 0x0100,
+                attribute_type: 0,
+                attribute_data: 0,
+            }
+            .to_short_messages(),
+            [Some(note_on(1, 60, 1)), None, None, None]
+        );
+        assert_eq!(
+            ProgramChange {
+                channel: channel(4),
+                program_number: u7(10),
+                bank: Some(u14(0x0081)),
+            }
+            .to_short_messages(),
+            [
+                Some(control_change(4, 0, 1)),
+                Some(control_change(4, 32, 1)),
+                Some(program_change(4, 10)),
+                None
+            ]
+        );
+        assert_eq!(
+            AssignableController {
+                channel: channel(3),
+                bank: u7(1),
+                index: u7(2),
+                value: 0x8000_0000,
+            }
+            .to_short_messages::<RawShortMessage>(),
+            nrpn_14_bit(3, 0x0082, 0x2000).to_short_messages(DataEntryByteOrder::LsbFirst)
+        );
+        assert_eq!(
+            RelativeAssignableController {
+                channel: channel(3),
+                bank: u7(1),
+                index: u7(2),
+                value: -(3 << 18),
+            }
+            .to_short_messages(),
+            ParameterNumberMessage::non_registered_decrement(channel(3), u14(0x0082), u7(3))
+                .to_short_messages::<RawShortMessage>(DataEntryByteOrder::MsbFirst)
+        );
+        assert_eq!(
+            RelativeRegisteredController {
+                channel: channel(3),
+                bank: u7(0),
+                index: u7(2),
+                value: 1,
+            }
+            .to_short_messages(),
+            ParameterNumberMessage::registered_increment(channel(3), u14(2), u7(1))
+                .to_short_messages::<RawShortMessage>(DataEntryByteOrder::MsbFirst)
+        );
+        assert_eq!(
+            RelativeRegisteredController {
+                channel: channel(3),
+                bank: u7(0),
+                index: u7(2),
+                value: -(2 << 18) - 1,
+            }
+            .to_short_messages(),
+            ParameterNumberMessage::registered_decrement(channel(3), u14(2), u7(3))
+                .to_short_messages::<RawShortMessage>(DataEntryByteOrder::MsbFirst)
+        );
+        assert_eq!(
+            RelativeAssignableController {
+                channel: channel(3),
+                bank: u7(1),
+                index: u7(2),
+                value: i32::MIN,
+            }
+            .to_short_messages(),
+            ParameterNumberMessage::non_registered_decrement(channel(3), u14(0x0082), u7(127))
+                .to_short_messages::<RawShortMessage>(DataEntryByteOrder::MsbFirst)
+        );
+        assert_eq!(
+            PerNotePitchBend {
+                channel: channel(3),
+                key_number: key_number(60),
+                value: 0,
+            }
+            .to_short_messages::<RawShortMessage>(),
+            [None, None, None, None]
+        );
+    }
+}
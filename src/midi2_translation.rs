@@ -0,0 +1,427 @@
+use crate::{
+    controller_numbers, Midi2ChannelVoiceMessage, ParameterNumberMessageScanner, ShortMessage,
+    StructuredShortMessage, U14, U7,
+};
+
+/// Scales the given value up to a higher resolution using the min-center-max algorithm of the
+/// MIDI 2.0 specification.
+///
+/// The minimum, center and maximum values of the source range are mapped exactly to the minimum,
+/// center and maximum values of the destination range. Values above the center are spread evenly
+/// by repeating their lower bits.
+///
+/// # Panics
+///
+/// This function panics if `source_bits` is 0 or greater than `destination_bits`, if
+/// `destination_bits` is greater than 32 or if `value` doesn't fit into `source_bits`.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::scale_up;
+///
+/// assert_eq!(scale_up(0, 7, 16), 0x0000);
+/// assert_eq!(scale_up(64, 7, 16), 0x8000);
+/// assert_eq!(scale_up(127, 7, 16), 0xFFFF);
+/// assert_eq!(scale_up(0x2000, 14, 32), 0x8000_0000);
+/// ```
+pub fn scale_up(value: u32, source_bits: u8, destination_bits: u8) -> u32 {
+    assert!(
+        source_bits > 0 && source_bits <= destination_bits && destination_bits <= 32,
+        "invalid bit counts"
+    );
+    assert!(
+        u64::from(value) < 1 << source_bits,
+        "value doesn't fit into source bits"
+    );
+    let scale_bits = destination_bits - source_bits;
+    let shifted_value = ((u64::from(value)) << scale_bits) as u32;
+    let source_center = 1 << (source_bits - 1);
+    if value <= source_center {
+        return shifted_value;
+    }
+    let repeat_bits = source_bits - 1;
+    let repeat_mask = (1 << repeat_bits) - 1;
+    let mut repeat_value = value & repeat_mask;
+    if scale_bits > repeat_bits {
+        repeat_value <<= scale_bits - repeat_bits;
+    } else {
+        repeat_value >>= repeat_bits - scale_bits;
+    }
+    let mut result = shifted_value;
+    while repeat_value != 0 {
+        result |= repeat_value;
+        repeat_value >>= repeat_bits;
+    }
+    result
+}
+
+/// Scales the given value down to a lower resolution by dropping the lower bits.
+///
+/// This is the counterpart of [`scale_up`].
+///
+/// # Panics
+///
+/// This function panics if `destination_bits` is greater than `source_bits` or if `source_bits`
+/// is greater than 32.
+///
+/// [`scale_up`]: fn.scale_up.html
+pub fn scale_down(value: u32, source_bits: u8, destination_bits: u8) -> u32 {
+    assert!(
+        destination_bits <= source_bits && source_bits <= 32,
+        "invalid bit counts"
+    );
+    ((u64::from(value)) >> (source_bits - destination_bits)) as u32
+}
+
+/// Scales a 7-bit value up to 16 bits (e.g. a velocity).
+pub fn upscale_u7_to_u16(value: U7) -> u16 {
+    scale_up(value.into(), 7, 16) as u16
+}
+
+/// Scales a 7-bit value up to 32 bits (e.g. a control value).
+pub fn upscale_u7_to_u32(value: U7) -> u32 {
+    scale_up(value.into(), 7, 32)
+}
+
+/// Scales a 14-bit value up to 32 bits (e.g. a pitch bend value).
+pub fn upscale_u14_to_u32(value: U14) -> u32 {
+    scale_up(value.into(), 14, 32)
+}
+
+/// Scales a 16-bit value down to 7 bits.
+pub fn downscale_u16_to_u7(value: u16) -> U7 {
+    U7(scale_down(value.into(), 16, 7) as u8)
+}
+
+/// Scales a 32-bit value down to 7 bits.
+pub fn downscale_u32_to_u7(value: u32) -> U7 {
+    U7(scale_down(value, 32, 7) as u8)
+}
+
+/// Scales a 32-bit value down to 14 bits.
+pub fn downscale_u32_to_u14(value: u32) -> U14 {
+    U14(scale_down(value, 32, 14) as u16)
+}
+
+/// Translates a stream of MIDI 1.0 short messages into MIDI 2.0 Channel Voice messages according to
+/// the default translation of the MIDI 2.0 specification.
+///
+/// In addition to the stateless translation done by
+/// [`Midi2ChannelVoiceMessage::from_short_message`], this translator takes care of messages which
+/// span multiple short messages:
+///
+/// - (N)RPN sequences (as recognized by [`ParameterNumberMessageScanner`]) are translated into
+///   single MIDI 2.0 Registered/Assignable Controller messages or their relative counterparts.
+/// - Bank Select MSB/LSB are remembered per channel and combined with the next Program Change into
+///   a MIDI 2.0 Program Change with bank.
+///
+/// The Control Change messages which make up those sequences are swallowed. Data Entry and Data
+/// Increment/Decrement messages received while no parameter number is selected on their channel
+/// are translated into plain Control Change messages.
+///
+/// For the reverse direction, see [`Midi2ChannelVoiceMessage::to_short_messages`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{channel, control_change, program_change, u14, u7};
+/// use helgoboss_midi::{Midi1ToMidi2Translator, Midi2ChannelVoiceMessage};
+///
+/// let mut translator = Midi1ToMidi2Translator::new();
+/// assert_eq!(translator.feed(&control_change(0, 0, 1)), None);
+/// assert_eq!(translator.feed(&control_change(0, 32, 2)), None);
+/// assert_eq!(
+///     translator.feed(&program_change(0, 5)),
+///     Some(Midi2ChannelVoiceMessage::ProgramChange {
+///         channel: channel(0),
+///         program_number: u7(5),
+///         bank: Some(u14(130)),
+///     })
+/// );
+/// assert_eq!(translator.feed(&control_change(0, 101, 0)), None);
+/// assert_eq!(translator.feed(&control_change(0, 100, 0)), None);
+/// assert_eq!(
+///     translator.feed(&control_change(0, 6, 2)),
+///     Some(Midi2ChannelVoiceMessage::RegisteredController {
+///         channel: channel(0),
+///         bank: u7(0),
+///         index: u7(0),
+///         value: 0x0400_0000,
+///     })
+/// );
+/// ```
+///
+/// [`Midi2ChannelVoiceMessage::from_short_message`]:
+/// enum.Midi2ChannelVoiceMessage.html#method.from_short_message
+/// [`Midi2ChannelVoiceMessage::to_short_messages`]:
+/// enum.Midi2ChannelVoiceMessage.html#method.to_short_messages
+/// [`ParameterNumberMessageScanner`]: struct.ParameterNumberMessageScanner.html
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Midi1ToMidi2Translator {
+    parameter_number_message_scanner: ParameterNumberMessageScanner,
+    bank_by_channel: [Bank; 16],
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+struct Bank {
+    msb: Option<U7>,
+    lsb: Option<U7>,
+}
+
+impl Midi1ToMidi2Translator {
+    /// Creates a new translator.
+    pub fn new() -> Midi1ToMidi2Translator {
+        Default::default()
+    }
+
+    /// Feeds the translator a single short message.
+    ///
+    /// Returns the MIDI 2.0 Channel Voice message if the given message completes one. Returns
+    /// `None` for system messages and for Control Change messages which are part of an (N)RPN
+    /// sequence or a Bank Select. Data Entry and Data Increment/Decrement are only considered part
+    /// of an (N)RPN sequence if a parameter number has been selected on the same channel before.
+    pub fn feed(&mut self, msg: &impl ShortMessage) -> Option<Midi2ChannelVoiceMessage> {
+        use controller_numbers::*;
+        if let Some(parameter_number_message) = self.parameter_number_message_scanner.feed(msg) {
+            return Some(Midi2ChannelVoiceMessage::from_parameter_number_message(
+                parameter_number_message,
+            ));
+        }
+        match msg.to_structured() {
+            StructuredShortMessage::ControlChange {
+                channel,
+                controller_number,
+                control_value,
+            } => {
+                let bank = &mut self.bank_by_channel[usize::from(channel)];
+                match controller_number {
+                    BANK_SELECT => bank.msb = Some(control_value),
+                    BANK_SELECT_LSB => bank.lsb = Some(control_value),
+                    DATA_ENTRY_MSB | DATA_ENTRY_MSB_LSB | DATA_INCREMENT | DATA_DECREMENT
+                        if self
+                            .parameter_number_message_scanner
+                            .has_parameter_number(channel) => {}
+                    NON_REGISTERED_PARAMETER_NUMBER_LSB
+                    | NON_REGISTERED_PARAMETER_NUMBER_MSB
+                    | REGISTERED_PARAMETER_NUMBER_LSB
+                    | REGISTERED_PARAMETER_NUMBER_MSB => {}
+                    _ => return Midi2ChannelVoiceMessage::from_short_message(msg),
+                }
+                None
+            }
+            StructuredShortMessage::ProgramChange {
+                channel,
+                program_number,
+            } => {
+                let bank = self.bank_by_channel[usize::from(channel)];
+                Some(Midi2ChannelVoiceMessage::ProgramChange {
+                    channel,
+                    program_number,
+                    bank: bank.msb.map(|msb| {
+                        U14((u16::from(msb.get()) << 7) | u16::from(bank.lsb.unwrap_or_default()))
+                    }),
+                })
+            }
+            _ => Midi2ChannelVoiceMessage::from_short_message(msg),
+        }
+    }
+
+    /// Resets the translator discarding all intermediate (N)RPN progress and bank selections.
+    pub fn reset(&mut self) {
+        self.parameter_number_message_scanner.reset();
+        self.bank_by_channel = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::{DataEntryByteOrder, ParameterNumberMessage, RawShortMessage};
+
+    #[test]
+    fn scaling() {
+        // Given
+        // When
+        // Then
+        assert_eq!(scale_up(0, 7, 32), 0);
+        assert_eq!(scale_up(1, 7, 32), 0x0200_0000);
+        assert_eq!(scale_up(63, 7, 32), 0x7E00_0000);
+        assert_eq!(scale_up(64, 7, 32), 0x8000_0000);
+        assert_eq!(scale_up(65, 7, 32), 0x8208_2082);
+        assert_eq!(scale_up(127, 7, 32), 0xFFFF_FFFF);
+        assert_eq!(scale_up(0x3FFF, 14, 32), 0xFFFF_FFFF);
+        assert_eq!(scale_up(0x2001, 14, 32), 0x8004_0020);
+        assert_eq!(scale_up(1, 1, 8), 0x80);
+        assert_eq!(upscale_u7_to_u16(u7(127)), 0xFFFF);
+        assert_eq!(upscale_u7_to_u16(u7(100)), 0xC924);
+        for v in 0..=127 {
+            assert_eq!(downscale_u32_to_u7(upscale_u7_to_u32(u7(v))), u7(v));
+            assert_eq!(downscale_u16_to_u7(upscale_u7_to_u16(u7(v))), u7(v));
+        }
+        for v in 0..=0x3FFF {
+            assert_eq!(downscale_u32_to_u14(upscale_u14_to_u32(u14(v))), u14(v));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn scale_up_value_too_large() {
+        scale_up(128, 7, 16);
+    }
+
+    #[test]
+    fn translate_simple_messages() {
+        // Given
+        let mut translator = Midi1ToMidi2Translator::new();
+        // When
+        // Then
+        assert_eq!(
+            translator.feed(&note_on(1, 60, 64)),
+            Some(Midi2ChannelVoiceMessage::NoteOn {
+                channel: channel(1),
+                key_number: key_number(60),
+                velocity: 0x8000,
+                attribute_type: 0,
+                attribute_data: 0,
+            })
+        );
+        assert_eq!(
+            translator.feed(&control_change(1, 74, 127)),
+            Some(Midi2ChannelVoiceMessage::ControlChange {
+                channel: channel(1),
+                controller_number: controller_number(74),
+                value: 0xFFFF_FFFF,
+            })
+        );
+        assert_eq!(
+            translator.feed(&program_change(1, 3)),
+            Some(Midi2ChannelVoiceMessage::ProgramChange {
+                channel: channel(1),
+                program_number: u7(3),
+                bank: None,
+            })
+        );
+        assert_eq!(translator.feed(&timing_clock()), None);
+    }
+
+    #[test]
+    fn translate_parameter_number_messages() {
+        // Given
+        let mut translator = Midi1ToMidi2Translator::new();
+        let msg = ParameterNumberMessage::non_registered_14_bit(channel(2), u14(420), u14(0x2000));
+        let short_messages: [Option<RawShortMessage>; 4] =
+            msg.to_short_messages(DataEntryByteOrder::LsbFirst);
+        // When
+        let results: Vec<_> = short_messages
+            .iter()
+            .flatten()
+            .map(|m| translator.feed(m))
+            .collect();
+        // Then
+        assert_eq!(
+            results,
+            vec![
+                None,
+                None,
+                None,
+                Some(Midi2ChannelVoiceMessage::AssignableController {
+                    channel: channel(2),
+                    bank: u7(3),
+                    index: u7(36),
+                    value: 0x8000_0000,
+                })
+            ]
+        );
+        assert_eq!(
+            translator.feed(&control_change(2, 96, 1)),
+            Some(Midi2ChannelVoiceMessage::RelativeAssignableController {
+                channel: channel(2),
+                bank: u7(3),
+                index: u7(36),
+                value: 1 << 18,
+            })
+        );
+    }
+
+    #[test]
+    fn data_entry_without_parameter_number() {
+        // Given
+        let mut translator = Midi1ToMidi2Translator::new();
+        // When
+        // Then
+        for cc in [6, 38, 96, 97].iter() {
+            assert_eq!(
+                translator.feed(&control_change(3, *cc, 127)),
+                Some(Midi2ChannelVoiceMessage::ControlChange {
+                    channel: channel(3),
+                    controller_number: controller_number(*cc),
+                    value: 0xFFFF_FFFF,
+                })
+            );
+        }
+        assert_eq!(translator.feed(&control_change(3, 101, 0)), None);
+        assert_eq!(
+            translator.feed(&control_change(3, 6, 1)),
+            Some(Midi2ChannelVoiceMessage::ControlChange {
+                channel: channel(3),
+                controller_number: controller_number(6),
+                value: upscale_u7_to_u32(u7(1)),
+            })
+        );
+        assert_eq!(translator.feed(&control_change(3, 100, 0)), None);
+        assert_eq!(translator.feed(&control_change(3, 38, 0)), None);
+        assert!(translator.feed(&control_change(3, 6, 1)).is_some());
+        assert_eq!(
+            translator.feed(&control_change(4, 96, 1)),
+            Some(Midi2ChannelVoiceMessage::ControlChange {
+                channel: channel(4),
+                controller_number: controller_number(96),
+                value: upscale_u7_to_u32(u7(1)),
+            })
+        );
+    }
+
+    #[test]
+    fn bank_select_is_remembered_per_channel() {
+        // Given
+        let mut translator = Midi1ToMidi2Translator::new();
+        // When
+        translator.feed(&control_change(0, 0, 1));
+        // Then
+        assert_eq!(
+            translator.feed(&program_change(0, 0)),
+            Some(Midi2ChannelVoiceMessage::ProgramChange {
+                channel: channel(0),
+                program_number: u7(0),
+                bank: Some(u14(128)),
+            })
+        );
+        assert_eq!(
+            translator.feed(&program_change(0, 1)),
+            Some(Midi2ChannelVoiceMessage::ProgramChange {
+                channel: channel(0),
+                program_number: u7(1),
+                bank: Some(u14(128)),
+            })
+        );
+        assert_eq!(
+            translator.feed(&program_change(1, 1)),
+            Some(Midi2ChannelVoiceMessage::ProgramChange {
+                channel: channel(1),
+                program_number: u7(1),
+                bank: None,
+            })
+        );
+        translator.reset();
+        assert_eq!(
+            translator.feed(&program_change(0, 1)),
+            Some(Midi2ChannelVoiceMessage::ProgramChange {
+                channel: channel(0),
+                program_number: u7(1),
+                bank: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{controller_numbers, Channel, KeyNumber, MpeZoneConfig, ShortMessageFactory, U14, U7};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The strategy which an [`MpeChannelAllocator`] uses to choose a free member channel for a new
+/// note.
+///
+/// [`MpeChannelAllocator`]: struct.MpeChannelAllocator.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MpeChannelAllocationStrategy {
+    /// Chooses the free channel which has not been used for the longest time. This gives the
+    /// release phase of previous notes as much time as possible and is recommended by the MPE
+    /// specification.
+    LeastRecentlyUsed,
+    /// Chooses the next free channel after the one which was allocated last.
+    RoundRobin,
+    /// Chooses the free channel which is closest to the master channel.
+    LowestFree,
+}
+
+/// The initial expression of a note which is played via MPE.
+///
+/// The default is no pitch bend, a timbre of 64 and no pressure.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MpeNoteExpression {
+    /// Pitch bend value (8192 is the center).
+    pub pitch_bend: U14,
+    /// Timbre (Control Change 74).
+    pub timbre: U7,
+    /// Pressure (Channel Pressure).
+    pub pressure: U7,
+}
+
+impl Default for MpeNoteExpression {
+    fn default() -> Self {
+        MpeNoteExpression {
+            pitch_bend: U14(8192),
+            timbre: U7(64),
+            pressure: U7::MIN,
+        }
+    }
+}
+
+/// Allocates member channels of an MPE zone for outgoing notes.
+///
+/// Each note which is started with [`note_on`] is placed on its own member channel of the zone.
+/// Subsequent expression messages for that note (pitch bend, timbre and pressure) are addressed by
+/// key number and sent on the allocated channel. If all member channels are occupied, the oldest
+/// note is stolen, that is, it's ended with a Note Off before the new note starts on its channel.
+/// Starting a note with a key number which is already playing ends the playing note first and
+/// reuses its channel.
+///
+/// All short messages are created via [`ShortMessageFactory`], so this works with any short message
+/// type. No heap allocation takes place.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{
+///     channel_pressure, control_change, key_number, note_off, note_on, pitch_bend_change, u14,
+///     u7,
+/// };
+/// use helgoboss_midi::{
+///     MpeChannelAllocationStrategy, MpeChannelAllocator, MpeNoteExpression, MpeZone,
+///     MpeZoneConfig, RawShortMessage,
+/// };
+///
+/// let zone = MpeZoneConfig::new(MpeZone::Lower, 2);
+/// let mut allocator = MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::LowestFree);
+/// let messages: [Option<RawShortMessage>; 5] =
+///     allocator.note_on(key_number(60), u7(100), MpeNoteExpression::default());
+/// assert_eq!(
+///     messages,
+///     [
+///         None,
+///         Some(pitch_bend_change(1, 8192)),
+///         Some(control_change(1, 74, 64)),
+///         Some(channel_pressure(1, 0)),
+///         Some(note_on(1, 60, 100)),
+///     ]
+/// );
+/// assert_eq!(
+///     allocator.pitch_bend(key_number(60), u14(9000)),
+///     Some(pitch_bend_change(1, 9000))
+/// );
+/// assert_eq!(
+///     allocator.note_off(key_number(60), u7(0)),
+///     Some(note_off(1, 60, 0))
+/// );
+/// ```
+///
+/// [`note_on`]: #method.note_on
+/// [`ShortMessageFactory`]: trait.ShortMessageFactory.html
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MpeChannelAllocator {
+    zone: MpeZoneConfig,
+    strategy: MpeChannelAllocationStrategy,
+    channels: [MemberChannel; 16],
+    clock: u64,
+    last_allocated_channel: Option<Channel>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+struct MemberChannel {
+    key_number: Option<KeyNumber>,
+    /// Point in time when the channel was last allocated or released.
+    last_used: u64,
+}
+
+/// Velocity of the Note Off message which is sent when stealing a note.
+const STEAL_VELOCITY: U7 = U7(64);
+
+impl MpeChannelAllocator {
+    /// Creates an allocator for the member channels of the given zone.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the given zone has no member channels.
+    pub fn new(zone: MpeZoneConfig, strategy: MpeChannelAllocationStrategy) -> MpeChannelAllocator {
+        assert!(zone.is_enabled(), "MPE zone has no member channels");
+        MpeChannelAllocator {
+            zone,
+            strategy,
+            channels: Default::default(),
+            clock: 0,
+            last_allocated_channel: None,
+        }
+    }
+
+    /// Returns the zone whose member channels are allocated.
+    pub fn zone(&self) -> MpeZoneConfig {
+        self.zone
+    }
+
+    /// Returns the allocation strategy.
+    pub fn strategy(&self) -> MpeChannelAllocationStrategy {
+        self.strategy
+    }
+
+    /// Starts a note on a newly allocated member channel.
+    ///
+    /// Returns the short messages which need to be sent in the given order:
+    ///
+    /// 1. Note Off for the note which had to be stolen, if any
+    /// 2. Pitch Bend
+    /// 3. Control Change 74 (timbre)
+    /// 4. Channel Pressure
+    /// 5. Note On
+    ///
+    /// The expression messages are sent before the Note On so the note starts with the desired
+    /// expression, as recommended by the MPE specification.
+    pub fn note_on<T: ShortMessageFactory>(
+        &mut self,
+        key_number: KeyNumber,
+        velocity: U7,
+        expression: MpeNoteExpression,
+    ) -> [Option<T>; 5] {
+        let (channel, stolen_key_number) = self.allocate(key_number);
+        [
+            stolen_key_number.map(|k| T::note_off(channel, k, STEAL_VELOCITY)),
+            Some(T::pitch_bend_change(channel, expression.pitch_bend)),
+            Some(T::control_change(
+                channel,
+                controller_numbers::SOUND_CONTROLLER_5,
+                expression.timbre,
+            )),
+            Some(T::channel_pressure(channel, expression.pressure)),
+            Some(T::note_on(channel, key_number, velocity)),
+        ]
+    }
+
+    /// Ends the note with the given key number and releases its member channel.
+    ///
+    /// Returns the Note Off message or `None` if no note with that key number is playing.
+    pub fn note_off<T: ShortMessageFactory>(
+        &mut self,
+        key_number: KeyNumber,
+        velocity: U7,
+    ) -> Option<T> {
+        let channel = self.channel(key_number)?;
+        self.clock += 1;
+        self.channels[usize::from(channel)] = MemberChannel {
+            key_number: None,
+            last_used: self.clock,
+        };
+        Some(T::note_off(channel, key_number, velocity))
+    }
+
+    /// Returns the Pitch Bend message for the note with the given key number or `None` if no such
+    /// note is playing.
+    pub fn pitch_bend<T: ShortMessageFactory>(
+        &self,
+        key_number: KeyNumber,
+        pitch_bend_value: U14,
+    ) -> Option<T> {
+        let channel = self.channel(key_number)?;
+        Some(T::pitch_bend_change(channel, pitch_bend_value))
+    }
+
+    /// Returns the timbre message (Control Change 74) for the note with the given key number or
+    /// `None` if no such note is playing.
+    pub fn timbre<T: ShortMessageFactory>(&self, key_number: KeyNumber, value: U7) -> Option<T> {
+        let channel = self.channel(key_number)?;
+        Some(T::control_change(
+            channel,
+            controller_numbers::SOUND_CONTROLLER_5,
+            value,
+        ))
+    }
+
+    /// Returns the Channel Pressure message for the note with the given key number or `None` if no
+    /// such note is playing.
+    pub fn pressure<T: ShortMessageFactory>(
+        &self,
+        key_number: KeyNumber,
+        pressure_amount: U7,
+    ) -> Option<T> {
+        let channel = self.channel(key_number)?;
+        Some(T::channel_pressure(channel, pressure_amount))
+    }
+
+    /// Returns the member channel on which the note with the given key number is playing.
+    pub fn channel(&self, key_number: KeyNumber) -> Option<Channel> {
+        self.zone
+            .member_channels()
+            .find(|ch| self.channels[usize::from(*ch)].key_number == Some(key_number))
+    }
+
+    /// Returns the number of currently playing notes.
+    pub fn active_note_count(&self) -> usize {
+        self.zone
+            .member_channels()
+            .filter(|ch| self.channels[usize::from(*ch)].key_number.is_some())
+            .count()
+    }
+
+    /// Resets the allocator forgetting all playing notes without sending Note Off messages.
+    pub fn reset(&mut self) {
+        self.channels = Default::default();
+        self.clock = 0;
+        self.last_allocated_channel = None;
+    }
+
+    /// Allocates a channel for the given key number and returns it together with the key number of
+    /// the note which was playing on it before (if any).
+    fn allocate(&mut self, key_number: KeyNumber) -> (Channel, Option<KeyNumber>) {
+        let channel = self
+            .channel(key_number)
+            .or_else(|| self.find_free_channel())
+            .unwrap_or_else(|| self.find_oldest_channel());
+        self.clock += 1;
+        let member_channel = &mut self.channels[usize::from(channel)];
+        let stolen_key_number = member_channel.key_number;
+        *member_channel = MemberChannel {
+            key_number: Some(key_number),
+            last_used: self.clock,
+        };
+        self.last_allocated_channel = Some(channel);
+        (channel, stolen_key_number)
+    }
+
+    fn find_free_channel(&self) -> Option<Channel> {
+        let is_free = |ch: &Channel| self.channels[usize::from(*ch)].key_number.is_none();
+        match self.strategy {
+            MpeChannelAllocationStrategy::LeastRecentlyUsed => self
+                .zone
+                .member_channels()
+                .filter(is_free)
+                .min_by_key(|ch| self.channels[usize::from(*ch)].last_used),
+            MpeChannelAllocationStrategy::RoundRobin => {
+                let start = self
+                    .last_allocated_channel
+                    .and_then(|last| self.zone.member_channels().position(|ch| ch == last))
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                self.zone
+                    .member_channels()
+                    .skip(start)
+                    .chain(self.zone.member_channels().take(start))
+                    .find(is_free)
+            }
+            MpeChannelAllocationStrategy::LowestFree => self.zone.member_channels().find(is_free),
+        }
+    }
+
+    fn find_oldest_channel(&self) -> Channel {
+        self.zone
+            .member_channels()
+            .min_by_key(|ch| self.channels[usize::from(*ch)].last_used)
+            .expect("zone has no member channels")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::{MpeZone, RawShortMessage};
+
+    fn play(allocator: &mut MpeChannelAllocator, key: u8) -> [Option<RawShortMessage>; 5] {
+        allocator.note_on(key_number(key), u7(100), MpeNoteExpression::default())
+    }
+
+    fn channel_of(allocator: &MpeChannelAllocator, key: u8) -> Option<u8> {
+        allocator.channel(key_number(key)).map(|ch| ch.get())
+    }
+
+    #[test]
+    fn lowest_free() {
+        // Given
+        let zone = MpeZoneConfig::new(MpeZone::Upper, 3);
+        let mut allocator =
+            MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::LowestFree);
+        // When
+        play(&mut allocator, 60);
+        play(&mut allocator, 61);
+        allocator.note_off::<RawShortMessage>(key_number(60), u7(0));
+        play(&mut allocator, 62);
+        // Then
+        assert_eq!(channel_of(&allocator, 60), None);
+        assert_eq!(channel_of(&allocator, 61), Some(13));
+        assert_eq!(channel_of(&allocator, 62), Some(14));
+        assert_eq!(allocator.active_note_count(), 2);
+    }
+
+    #[test]
+    fn least_recently_used() {
+        // Given
+        let zone = MpeZoneConfig::new(MpeZone::Lower, 3);
+        let mut allocator =
+            MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::LeastRecentlyUsed);
+        // When
+        play(&mut allocator, 60);
+        play(&mut allocator, 61);
+        allocator.note_off::<RawShortMessage>(key_number(61), u7(0));
+        allocator.note_off::<RawShortMessage>(key_number(60), u7(0));
+        play(&mut allocator, 62);
+        play(&mut allocator, 63);
+        play(&mut allocator, 64);
+        // Then
+        assert_eq!(channel_of(&allocator, 62), Some(3));
+        assert_eq!(channel_of(&allocator, 63), Some(2));
+        assert_eq!(channel_of(&allocator, 64), Some(1));
+    }
+
+    #[test]
+    fn round_robin() {
+        // Given
+        let zone = MpeZoneConfig::new(MpeZone::Lower, 3);
+        let mut allocator =
+            MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::RoundRobin);
+        // When
+        play(&mut allocator, 60);
+        allocator.note_off::<RawShortMessage>(key_number(60), u7(0));
+        play(&mut allocator, 61);
+        allocator.note_off::<RawShortMessage>(key_number(61), u7(0));
+        play(&mut allocator, 62);
+        play(&mut allocator, 63);
+        play(&mut allocator, 64);
+        // Then
+        assert_eq!(channel_of(&allocator, 62), Some(3));
+        assert_eq!(channel_of(&allocator, 63), Some(1));
+        assert_eq!(channel_of(&allocator, 64), Some(2));
+    }
+
+    #[test]
+    fn steal_oldest_note() {
+        // Given
+        let zone = MpeZoneConfig::new(MpeZone::Lower, 2);
+        let mut allocator =
+            MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::LowestFree);
+        play(&mut allocator, 60);
+        play(&mut allocator, 61);
+        // When
+        let messages = play(&mut allocator, 62);
+        // Then
+        assert_eq!(messages[0], Some(note_off(1, 60, 64)));
+        assert_eq!(messages[4], Some(note_on(1, 62, 100)));
+        assert_eq!(channel_of(&allocator, 60), None);
+        assert_eq!(channel_of(&allocator, 62), Some(1));
+        assert_eq!(allocator.active_note_count(), 2);
+    }
+
+    #[test]
+    fn retrigger_same_key() {
+        // Given
+        let zone = MpeZoneConfig::new(MpeZone::Lower, 4);
+        let mut allocator =
+            MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::RoundRobin);
+        play(&mut allocator, 60);
+        // When
+        let messages = play(&mut allocator, 60);
+        // Then
+        assert_eq!(messages[0], Some(note_off(1, 60, 64)));
+        assert_eq!(messages[4], Some(note_on(1, 60, 100)));
+        assert_eq!(allocator.active_note_count(), 1);
+    }
+
+    #[test]
+    fn expression() {
+        // Given
+        let zone = MpeZoneConfig::new(MpeZone::Lower, 4);
+        let mut allocator =
+            MpeChannelAllocator::new(zone, MpeChannelAllocationStrategy::LowestFree);
+        play(&mut allocator, 60);
+        play(&mut allocator, 61);
+        // When
+        // Then
+        assert_eq!(
+            allocator.timbre(key_number(61), u7(10)),
+            Some(control_change(2, 74, 10))
+        );
+        assert_eq!(
+            allocator.pressure(key_number(61), u7(20)),
+            Some(channel_pressure(2, 20))
+        );
+        assert_eq!(
+            allocator.pitch_bend::<RawShortMessage>(key_number(62), u14(0)),
+            None
+        );
+        assert_eq!(
+            allocator.note_off::<RawShortMessage>(key_number(62), u7(0)),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn disabled_zone() {
+        MpeChannelAllocator::new(
+            MpeZoneConfig::new(MpeZone::Lower, 0),
+            MpeChannelAllocationStrategy::LowestFree,
+        );
+    }
+}
@@ -0,0 +1,564 @@
+use crate::{
+    controller_numbers, extract_high_7_bit_value_from_14_bit_value,
+    extract_low_7_bit_value_from_14_bit_value, Channel, DataType, KeyNumber, MpeZone,
+    MpeZoneLayout, ParameterNumberMessage, ParameterNumberMessageScanner, ShortMessage,
+    StructuredShortMessage, U14, U7,
+};
+
+/// Default pitch bend sensitivity of member channels in cents (48 semitones).
+const DEFAULT_MEMBER_PITCH_BEND_SENSITIVITY: u16 = 4800;
+/// Default pitch bend sensitivity of master channels in cents (2 semitones).
+const DEFAULT_MASTER_PITCH_BEND_SENSITIVITY: u16 = 200;
+const PITCH_BEND_SENSITIVITY_PARAMETER_NUMBER: U14 = U14(0);
+const PITCH_BEND_CENTER: U14 = U14(8192);
+const DEFAULT_TIMBRE: U7 = U7(64);
+
+/// Interprets incoming MPE controller input as notes with expression.
+///
+/// The tracker consumes short messages and keeps track of:
+///
+/// - the zone layout (configured with MPE Configuration Messages, see [`MpeZoneLayout`]),
+/// - the pitch bend sensitivity (RPN 0) of master and member channels of each zone (Data Entry
+///   MSB in semitones, optionally followed or preceded by Data Entry LSB in cents; Data
+///   Increment/Decrement is ignored),
+/// - the notes playing on each channel and the current pitch bend, timbre (Control Change 74) and
+///   pressure (Channel Pressure) of each channel.
+///
+/// The pitch offset of a note on a member channel is the sum of the member channel's pitch bend and
+/// the master channel's pitch bend, each scaled by its sensitivity. Notes played directly on the
+/// master channel only respond to the master channel's pitch bend. Messages on channels which don't
+/// belong to any zone are ignored. Receiving an MPE Configuration Message resets the notes and
+/// expression of the zone's channels and its pitch bend sensitivities to their defaults (48
+/// semitones for member channels, 2 semitones for the master channel).
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{
+///     channel, channel_pressure, control_change, key_number, note_on, pitch_bend_change, u7,
+/// };
+/// use helgoboss_midi::MpeInputTracker;
+///
+/// let mut tracker = MpeInputTracker::new();
+/// // MPE Configuration Message: Lower zone with 7 member channels
+/// tracker.feed(&control_change(0, 101, 0));
+/// tracker.feed(&control_change(0, 100, 6));
+/// tracker.feed(&control_change(0, 6, 7));
+/// // Note with expression on member channel 2
+/// tracker.feed(&pitch_bend_change(1, 16383));
+/// tracker.feed(&control_change(1, 74, 100));
+/// tracker.feed(&note_on(1, 60, 127));
+/// tracker.feed(&channel_pressure(1, 30));
+/// // Master pitch bend
+/// tracker.feed(&pitch_bend_change(0, 0));
+/// let notes: Vec<_> = tracker.active_notes().collect();
+/// assert_eq!(notes.len(), 1);
+/// assert_eq!(notes[0].channel(), channel(1));
+/// assert_eq!(notes[0].key_number(), key_number(60));
+/// assert_eq!(notes[0].pitch_offset(), 48.0 - 2.0);
+/// assert_eq!(notes[0].timbre(), u7(100));
+/// assert_eq!(notes[0].pressure(), u7(30));
+/// ```
+///
+/// [`MpeZoneLayout`]: struct.MpeZoneLayout.html
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct MpeInputTracker {
+    parameter_number_message_scanner: ParameterNumberMessageScanner,
+    layout: MpeZoneLayout,
+    zones: [ZoneState; 2],
+    channels: [ChannelState; 16],
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct ZoneState {
+    /// In cents.
+    master_pitch_bend_sensitivity: u16,
+    /// In cents.
+    member_pitch_bend_sensitivity: u16,
+}
+
+impl Default for ZoneState {
+    fn default() -> Self {
+        ZoneState {
+            master_pitch_bend_sensitivity: DEFAULT_MASTER_PITCH_BEND_SENSITIVITY,
+            member_pitch_bend_sensitivity: DEFAULT_MEMBER_PITCH_BEND_SENSITIVITY,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct ChannelState {
+    /// One bit for each key number.
+    notes: u128,
+    pitch_bend: U14,
+    timbre: U7,
+    pressure: U7,
+    /// The semitones of the last pitch bend sensitivity Data Entry, as long as RPN 0 is selected.
+    ///
+    /// A Data Entry LSB following the Data Entry MSB sets the cents.
+    pitch_bend_sensitivity_semitones: Option<U7>,
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        ChannelState {
+            notes: 0,
+            pitch_bend: PITCH_BEND_CENTER,
+            timbre: DEFAULT_TIMBRE,
+            pressure: U7::MIN,
+            pitch_bend_sensitivity_semitones: None,
+        }
+    }
+}
+
+impl MpeInputTracker {
+    /// Creates a tracker with both zones disabled.
+    ///
+    /// Zones are enabled as soon as MPE Configuration Messages are received.
+    pub fn new() -> MpeInputTracker {
+        Default::default()
+    }
+
+    /// Creates a tracker with the given zone layout, e.g. if the sender is known to not send MPE
+    /// Configuration Messages.
+    pub fn with_layout(layout: MpeZoneLayout) -> MpeInputTracker {
+        MpeInputTracker {
+            layout,
+            ..Default::default()
+        }
+    }
+
+    /// Feeds the tracker a single short message.
+    pub fn feed(&mut self, msg: &impl ShortMessage) {
+        if let Some(parameter_number_message) = self.parameter_number_message_scanner.feed(msg) {
+            self.process_parameter_number_message(&parameter_number_message);
+        }
+        let channel = match msg.channel() {
+            Some(c) => c,
+            None => return,
+        };
+        if self.layout.zone_of_channel(channel).is_none() {
+            return;
+        }
+        let state = &mut self.channels[usize::from(channel)];
+        match msg.to_structured() {
+            StructuredShortMessage::NoteOn {
+                key_number,
+                velocity,
+                ..
+            } if velocity > U7::MIN => {
+                state.notes |= 1 << u8::from(key_number);
+            }
+            StructuredShortMessage::NoteOn { key_number, .. }
+            | StructuredShortMessage::NoteOff { key_number, .. } => {
+                state.notes &= !(1 << u8::from(key_number));
+            }
+            StructuredShortMessage::PitchBendChange {
+                pitch_bend_value, ..
+            } => {
+                state.pitch_bend = pitch_bend_value;
+            }
+            StructuredShortMessage::ControlChange {
+                controller_number: controller_numbers::SOUND_CONTROLLER_5,
+                control_value,
+                ..
+            } => {
+                state.timbre = control_value;
+            }
+            StructuredShortMessage::ChannelPressure {
+                pressure_amount, ..
+            } => {
+                state.pressure = pressure_amount;
+            }
+            StructuredShortMessage::ControlChange {
+                controller_number: controller_numbers::DATA_ENTRY_MSB_LSB,
+                control_value,
+                ..
+            } => {
+                if let Some(semitones) = state.pitch_bend_sensitivity_semitones {
+                    let cents = u16::from(semitones.get()) * 100 + u16::from(control_value.get());
+                    self.set_pitch_bend_sensitivity(channel, cents);
+                }
+            }
+            StructuredShortMessage::ControlChange {
+                controller_number:
+                    controller_numbers::NON_REGISTERED_PARAMETER_NUMBER_LSB
+                    | controller_numbers::NON_REGISTERED_PARAMETER_NUMBER_MSB
+                    | controller_numbers::REGISTERED_PARAMETER_NUMBER_LSB
+                    | controller_numbers::REGISTERED_PARAMETER_NUMBER_MSB,
+                ..
+            } => {
+                state.pitch_bend_sensitivity_semitones = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the current zone layout.
+    pub fn layout(&self) -> MpeZoneLayout {
+        self.layout
+    }
+
+    /// Returns the pitch bend sensitivity of the master channel of the given zone in semitones.
+    pub fn master_pitch_bend_sensitivity(&self, zone: MpeZone) -> f64 {
+        cents_to_semitones(self.zone_state(zone).master_pitch_bend_sensitivity)
+    }
+
+    /// Returns the pitch bend sensitivity of the member channels of the given zone in semitones.
+    pub fn member_pitch_bend_sensitivity(&self, zone: MpeZone) -> f64 {
+        cents_to_semitones(self.zone_state(zone).member_pitch_bend_sensitivity)
+    }
+
+    /// Returns an iterator over all currently playing notes, ordered by channel and key number.
+    pub fn active_notes(&self) -> impl Iterator<Item = MpeNote> + '_ {
+        (0..16u8)
+            .filter_map(move |channel| {
+                let channel = Channel(channel);
+                let zone = self.layout.zone_of_channel(channel)?;
+                Some((channel, zone.zone()))
+            })
+            .flat_map(move |(channel, zone)| {
+                let notes = self.channels[usize::from(channel)].notes;
+                (0..128u8)
+                    .filter(move |key| notes & (1 << *key) != 0)
+                    .map(move |key| self.build_note(zone, channel, KeyNumber(key)))
+            })
+    }
+
+    /// Resets the tracker forgetting all notes, expression values, sensitivities and the zone
+    /// layout.
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+
+    fn process_parameter_number_message(&mut self, msg: &ParameterNumberMessage) {
+        if let Some(config) = self.layout.feed(msg) {
+            self.zones[zone_index(config.zone())] = Default::default();
+            // Forget notes and expression of all channels except the ones of the other zone
+            for (i, state) in self.channels.iter_mut().enumerate() {
+                let zone = self.layout.zone_of_channel(Channel(i as u8));
+                if zone.filter(|z| z.zone() != config.zone()).is_none() {
+                    *state = Default::default();
+                }
+            }
+            return;
+        }
+        if !msg.is_registered()
+            || msg.number() != PITCH_BEND_SENSITIVITY_PARAMETER_NUMBER
+            || msg.data_type() != DataType::DataEntry
+        {
+            return;
+        }
+        let (semitones, cents) = if msg.is_14_bit() {
+            (
+                extract_high_7_bit_value_from_14_bit_value(msg.value()),
+                extract_low_7_bit_value_from_14_bit_value(msg.value()),
+            )
+        } else {
+            (U7(msg.value().get() as u8), U7::MIN)
+        };
+        self.channels[usize::from(msg.channel())].pitch_bend_sensitivity_semitones =
+            Some(semitones);
+        self.set_pitch_bend_sensitivity(
+            msg.channel(),
+            u16::from(semitones.get()) * 100 + u16::from(cents.get()),
+        );
+    }
+
+    fn set_pitch_bend_sensitivity(&mut self, channel: Channel, cents: u16) {
+        let zone = match self.layout.zone_of_channel(channel) {
+            Some(z) => z,
+            None => return,
+        };
+        let zone_state = &mut self.zones[zone_index(zone.zone())];
+        if channel == zone.master_channel() {
+            zone_state.master_pitch_bend_sensitivity = cents;
+        } else {
+            zone_state.member_pitch_bend_sensitivity = cents;
+        }
+    }
+
+    fn zone_state(&self, zone: MpeZone) -> &ZoneState {
+        &self.zones[zone_index(zone)]
+    }
+
+    fn build_note(&self, zone: MpeZone, channel: Channel, key_number: KeyNumber) -> MpeNote {
+        let zone_state = self.zone_state(zone);
+        let master_channel = zone.master_channel();
+        let master_state = &self.channels[usize::from(master_channel)];
+        let master_offset = pitch_bend_to_semitones(
+            master_state.pitch_bend,
+            zone_state.master_pitch_bend_sensitivity,
+        );
+        let state = &self.channels[usize::from(channel)];
+        let pitch_offset = if channel == master_channel {
+            master_offset
+        } else {
+            master_offset
+                + pitch_bend_to_semitones(
+                    state.pitch_bend,
+                    zone_state.member_pitch_bend_sensitivity,
+                )
+        };
+        MpeNote {
+            zone,
+            channel,
+            key_number,
+            pitch_offset,
+            timbre: state.timbre,
+            pressure: state.pressure,
+        }
+    }
+}
+
+/// A note which is currently playing according to an [`MpeInputTracker`].
+///
+/// [`MpeInputTracker`]: struct.MpeInputTracker.html
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MpeNote {
+    zone: MpeZone,
+    channel: Channel,
+    key_number: KeyNumber,
+    pitch_offset: f64,
+    timbre: U7,
+    pressure: U7,
+}
+
+impl MpeNote {
+    /// Returns the zone in which the note is playing.
+    pub fn zone(&self) -> MpeZone {
+        self.zone
+    }
+
+    /// Returns the channel on which the note is playing.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Returns the key number of the note.
+    pub fn key_number(&self) -> KeyNumber {
+        self.key_number
+    }
+
+    /// Returns the current pitch offset in semitones, combining the pitch bend of the note's
+    /// channel and of the zone's master channel.
+    pub fn pitch_offset(&self) -> f64 {
+        self.pitch_offset
+    }
+
+    /// Returns the current pitch in semitones, that is, the key number plus the pitch offset.
+    pub fn pitch(&self) -> f64 {
+        f64::from(u8::from(self.key_number)) + self.pitch_offset
+    }
+
+    /// Returns the current timbre (Control Change 74) of the note's channel.
+    pub fn timbre(&self) -> U7 {
+        self.timbre
+    }
+
+    /// Returns the current pressure (Channel Pressure) of the note's channel.
+    pub fn pressure(&self) -> U7 {
+        self.pressure
+    }
+}
+
+fn zone_index(zone: MpeZone) -> usize {
+    match zone {
+        MpeZone::Lower => 0,
+        MpeZone::Upper => 1,
+    }
+}
+
+fn cents_to_semitones(cents: u16) -> f64 {
+    f64::from(cents) / 100.0
+}
+
+/// Maps the pitch bend value to the range -1.0 to 1.0 (both inclusive) and scales it by the given
+/// sensitivity.
+fn pitch_bend_to_semitones(pitch_bend: U14, sensitivity_in_cents: u16) -> f64 {
+    let center = i32::from(PITCH_BEND_CENTER.get());
+    let delta = i32::from(pitch_bend.get()) - center;
+    let max_delta = if delta > 0 { center - 1 } else { center };
+    f64::from(delta) / f64::from(max_delta) * cents_to_semitones(sensitivity_in_cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::{MpeZoneConfig, RawShortMessage};
+
+    fn tracker_with_zones(lower: u8, upper: u8) -> MpeInputTracker {
+        let mut layout = MpeZoneLayout::new();
+        layout.apply(MpeZoneConfig::new(MpeZone::Lower, lower));
+        layout.apply(MpeZoneConfig::new(MpeZone::Upper, upper));
+        MpeInputTracker::with_layout(layout)
+    }
+
+    fn feed_all(tracker: &mut MpeInputTracker, messages: &[RawShortMessage]) {
+        for msg in messages {
+            tracker.feed(msg);
+        }
+    }
+
+    #[test]
+    fn notes_on_member_channels() {
+        // Given
+        let mut tracker = tracker_with_zones(3, 3);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                note_on(1, 60, 100),
+                note_on(2, 64, 100),
+                note_on(14, 67, 100),
+                note_on(7, 70, 100),
+                note_on(2, 64, 0),
+                control_change(14, 74, 10),
+            ],
+        );
+        // Then
+        let notes: Vec<_> = tracker.active_notes().collect();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].zone(), MpeZone::Lower);
+        assert_eq!(notes[0].key_number(), key_number(60));
+        assert_eq!(notes[0].timbre(), u7(64));
+        assert_eq!(notes[0].pressure(), u7(0));
+        assert_eq!(notes[0].pitch_offset(), 0.0);
+        assert_eq!(notes[1].zone(), MpeZone::Upper);
+        assert_eq!(notes[1].channel(), channel(14));
+        assert_eq!(notes[1].timbre(), u7(10));
+    }
+
+    #[test]
+    fn pitch_bend() {
+        // Given
+        let mut tracker = tracker_with_zones(15, 0);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                note_on(0, 48, 100),
+                note_on(1, 60, 100),
+                note_on(2, 62, 100),
+                pitch_bend_change(1, 16383),
+                pitch_bend_change(2, 0),
+                pitch_bend_change(0, 16383),
+            ],
+        );
+        // Then
+        let pitches: Vec<_> = tracker.active_notes().map(|n| n.pitch()).collect();
+        assert_eq!(
+            pitches,
+            vec![48.0 + 2.0, 60.0 + 48.0 + 2.0, 62.0 - 48.0 + 2.0]
+        );
+    }
+
+    #[test]
+    fn pitch_bend_sensitivity() {
+        // Given
+        let mut tracker = tracker_with_zones(0, 4);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                // Member channel: 12 semitones, 50 cents
+                control_change(12, 101, 0),
+                control_change(12, 100, 0),
+                control_change(12, 38, 50),
+                control_change(12, 6, 12),
+                // Master channel: 7 semitones
+                control_change(15, 101, 0),
+                control_change(15, 100, 0),
+                control_change(15, 6, 7),
+                note_on(14, 60, 100),
+                pitch_bend_change(14, 0),
+            ],
+        );
+        // Then
+        assert_eq!(tracker.member_pitch_bend_sensitivity(MpeZone::Upper), 12.5);
+        assert_eq!(tracker.master_pitch_bend_sensitivity(MpeZone::Upper), 7.0);
+        assert_eq!(tracker.member_pitch_bend_sensitivity(MpeZone::Lower), 48.0);
+        let note = tracker.active_notes().next().unwrap();
+        assert_eq!(note.pitch_offset(), -12.5);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                control_change(15, 101, 0),
+                control_change(15, 100, 6),
+                control_change(15, 6, 4),
+            ],
+        );
+        // Then
+        assert_eq!(tracker.member_pitch_bend_sensitivity(MpeZone::Upper), 48.0);
+        assert_eq!(tracker.master_pitch_bend_sensitivity(MpeZone::Upper), 2.0);
+    }
+
+    #[test]
+    fn pitch_bend_sensitivity_msb_first() {
+        // Given
+        let mut tracker = tracker_with_zones(4, 0);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                control_change(1, 101, 0),
+                control_change(1, 100, 0),
+                control_change(1, 6, 24),
+                control_change(1, 38, 25),
+            ],
+        );
+        // Then
+        assert_eq!(tracker.member_pitch_bend_sensitivity(MpeZone::Lower), 24.25);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                control_change(1, 38, 50),
+                control_change(1, 96, 1),
+                control_change(1, 101, 0),
+                control_change(1, 100, 1),
+                control_change(1, 38, 0),
+            ],
+        );
+        // Then
+        assert_eq!(tracker.member_pitch_bend_sensitivity(MpeZone::Lower), 24.5);
+    }
+
+    #[test]
+    fn ignore_channels_outside_zones() {
+        // Given
+        let mut tracker = MpeInputTracker::new();
+        // When
+        feed_all(&mut tracker, &[note_on(1, 60, 100), timing_clock()]);
+        // Then
+        assert_eq!(tracker.active_notes().count(), 0);
+    }
+
+    #[test]
+    fn layout_change() {
+        // Given
+        let mut tracker = tracker_with_zones(7, 0);
+        feed_all(&mut tracker, &[note_on(7, 60, 100)]);
+        // When
+        feed_all(
+            &mut tracker,
+            &[
+                control_change(0, 101, 0),
+                control_change(0, 100, 6),
+                control_change(0, 6, 3),
+            ],
+        );
+        // Then
+        assert_eq!(
+            tracker.layout().lower_zone(),
+            Some(MpeZoneConfig::new(MpeZone::Lower, 3))
+        );
+        assert_eq!(tracker.active_notes().count(), 0);
+        // When
+        feed_all(&mut tracker, &[control_change(0, 6, 7)]);
+        // Then
+        assert_eq!(tracker.active_notes().count(), 0);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{
+    controller_numbers, registered_per_note_controllers, upscale_u14_to_u32, upscale_u7_to_u16,
+    upscale_u7_to_u32, Channel, KeyNumber, NoteAttribute, PerNoteMessage, ShortMessage,
+    StructuredShortMessage, U14, U7,
+};
+
+/// Translates an MPE-style MIDI 1.0 stream into MIDI 2.0 per-note messages keyed by key number.
+///
+/// MPE (MIDI Polyphonic Expression) plays each note on its own member channel so that the
+/// channel-wide Pitch Bend, Control Change 74 (timbre) and Channel Pressure messages of that
+/// channel only affect this note. MIDI 2.0 can express the same with per-note messages on a single
+/// channel. This translator remembers which note is playing on which member channel and maps:
+///
+/// - Note On/Off to per-note Note On/Off (velocity scaled up),
+/// - Pitch Bend to per-note pitch bend,
+/// - Control Change 74 to the registered per-note controller [`BRIGHTNESS`],
+/// - Channel Pressure to per-note pressure.
+///
+/// All resulting messages are sent on the channel given when creating the translator. Pitch bend,
+/// timbre and pressure values received on a member channel before a Note On (as recommended by
+/// MPE) are emitted right before the per-note Note On, so the note starts with the correct
+/// expression. Other messages are not translated and should be handled separately, for example
+/// with [`Midi1ToMidi2Translator`]. Only messages on member channels should be fed, not the ones on
+/// the master channel of an MPE zone.
+///
+/// If the same key number is played on two member channels at the same time, the resulting
+/// per-note messages can't be distinguished anymore.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{
+///     channel, channel_pressure, control_change, key_number, note_on, pitch_bend_change,
+/// };
+/// use helgoboss_midi::{
+///     registered_per_note_controllers, MpeToPerNoteTranslator, NoteAttribute, PerNoteMessage,
+/// };
+///
+/// let mut translator = MpeToPerNoteTranslator::new(channel(0));
+/// assert_eq!(
+///     translator.feed(&pitch_bend_change(2, 8192)),
+///     [None, None, None, None]
+/// );
+/// assert_eq!(
+///     translator.feed(&note_on(2, 60, 127)),
+///     [
+///         Some(PerNoteMessage::PitchBend {
+///             channel: channel(0),
+///             key_number: key_number(60),
+///             value: 0x8000_0000,
+///         }),
+///         None,
+///         None,
+///         Some(PerNoteMessage::NoteOn {
+///             channel: channel(0),
+///             key_number: key_number(60),
+///             velocity: 0xFFFF,
+///             attribute: NoteAttribute::None,
+///         }),
+///     ]
+/// );
+/// assert_eq!(
+///     translator.feed(&control_change(2, 74, 0)),
+///     [
+///         Some(PerNoteMessage::RegisteredController {
+///             channel: channel(0),
+///             key_number: key_number(60),
+///             index: registered_per_note_controllers::BRIGHTNESS,
+///             value: 0,
+///         }),
+///         None,
+///         None,
+///         None,
+///     ]
+/// );
+/// ```
+///
+/// [`BRIGHTNESS`]: registered_per_note_controllers/constant.BRIGHTNESS.html
+/// [`Midi1ToMidi2Translator`]: struct.Midi1ToMidi2Translator.html
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MpeToPerNoteTranslator {
+    channel: Channel,
+    member_channels: [MemberChannel; 16],
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+struct MemberChannel {
+    key_number: Option<KeyNumber>,
+    pitch_bend: Option<U14>,
+    timbre: Option<U7>,
+    pressure: Option<U7>,
+}
+
+impl MpeToPerNoteTranslator {
+    /// Creates a translator which emits per-note messages on the given channel.
+    pub fn new(channel: Channel) -> MpeToPerNoteTranslator {
+        MpeToPerNoteTranslator {
+            channel,
+            member_channels: Default::default(),
+        }
+    }
+
+    /// Returns the channel on which per-note messages are emitted.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Feeds the translator a single short message.
+    ///
+    /// Returns the resulting per-note messages in the order in which they should be sent. A Note On
+    /// results in up to 3 per-note controller messages (pitch bend, timbre, pressure) followed by
+    /// the per-note Note On. Returns only `None` values if the message is not translated or if it's
+    /// an expression message on a member channel which doesn't have a playing note yet.
+    pub fn feed(&mut self, msg: &impl ShortMessage) -> [Option<PerNoteMessage>; 4] {
+        let mut result = [None; 4];
+        match msg.to_structured() {
+            StructuredShortMessage::NoteOn {
+                channel,
+                key_number,
+                velocity,
+            } if velocity > U7::MIN => {
+                let member_channel = &mut self.member_channels[usize::from(channel)];
+                member_channel.key_number = Some(key_number);
+                let member_channel = *member_channel;
+                result[0] = member_channel
+                    .pitch_bend
+                    .map(|v| self.pitch_bend(key_number, v));
+                result[1] = member_channel.timbre.map(|v| self.timbre(key_number, v));
+                result[2] = member_channel
+                    .pressure
+                    .map(|v| self.pressure(key_number, v));
+                result[3] = Some(PerNoteMessage::NoteOn {
+                    channel: self.channel,
+                    key_number,
+                    velocity: upscale_u7_to_u16(velocity),
+                    attribute: NoteAttribute::None,
+                });
+            }
+            StructuredShortMessage::NoteOn {
+                channel,
+                key_number,
+                ..
+            } => {
+                result[0] = Some(self.note_off(channel, key_number, 0x8000));
+            }
+            StructuredShortMessage::NoteOff {
+                channel,
+                key_number,
+                velocity,
+            } => {
+                result[0] = Some(self.note_off(channel, key_number, upscale_u7_to_u16(velocity)));
+            }
+            StructuredShortMessage::PitchBendChange {
+                channel,
+                pitch_bend_value,
+            } => {
+                let member_channel = &mut self.member_channels[usize::from(channel)];
+                member_channel.pitch_bend = Some(pitch_bend_value);
+                let key_number = member_channel.key_number;
+                result[0] = key_number.map(|k| self.pitch_bend(k, pitch_bend_value));
+            }
+            StructuredShortMessage::ControlChange {
+                channel,
+                controller_number: controller_numbers::SOUND_CONTROLLER_5,
+                control_value,
+            } => {
+                let member_channel = &mut self.member_channels[usize::from(channel)];
+                member_channel.timbre = Some(control_value);
+                let key_number = member_channel.key_number;
+                result[0] = key_number.map(|k| self.timbre(k, control_value));
+            }
+            StructuredShortMessage::ChannelPressure {
+                channel,
+                pressure_amount,
+            } => {
+                let member_channel = &mut self.member_channels[usize::from(channel)];
+                member_channel.pressure = Some(pressure_amount);
+                let key_number = member_channel.key_number;
+                result[0] = key_number.map(|k| self.pressure(k, pressure_amount));
+            }
+            _ => {}
+        }
+        result
+    }
+
+    /// Returns the key number of the note which is currently playing on the given member channel.
+    pub fn key_number(&self, member_channel: Channel) -> Option<KeyNumber> {
+        self.member_channels[usize::from(member_channel)].key_number
+    }
+
+    /// Resets the translator forgetting all playing notes and expression values.
+    pub fn reset(&mut self) {
+        self.member_channels = Default::default();
+    }
+
+    fn note_off(
+        &mut self,
+        channel: Channel,
+        key_number: KeyNumber,
+        velocity: u16,
+    ) -> PerNoteMessage {
+        let member_channel = &mut self.member_channels[usize::from(channel)];
+        if member_channel.key_number == Some(key_number) {
+            member_channel.key_number = None;
+        }
+        PerNoteMessage::NoteOff {
+            channel: self.channel,
+            key_number,
+            velocity,
+            attribute: NoteAttribute::None,
+        }
+    }
+
+    fn pitch_bend(&self, key_number: KeyNumber, value: U14) -> PerNoteMessage {
+        PerNoteMessage::PitchBend {
+            channel: self.channel,
+            key_number,
+            value: upscale_u14_to_u32(value),
+        }
+    }
+
+    fn timbre(&self, key_number: KeyNumber, value: U7) -> PerNoteMessage {
+        PerNoteMessage::RegisteredController {
+            channel: self.channel,
+            key_number,
+            index: registered_per_note_controllers::BRIGHTNESS,
+            value: upscale_u7_to_u32(value),
+        }
+    }
+
+    fn pressure(&self, key_number: KeyNumber, value: U7) -> PerNoteMessage {
+        PerNoteMessage::Pressure {
+            channel: self.channel,
+            key_number,
+            pressure: upscale_u7_to_u32(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn polyphonic_expression() {
+        // Given
+        let mut translator = MpeToPerNoteTranslator::new(channel(0));
+        // When
+        let on_1 = translator.feed(&note_on(1, 60, 64));
+        let on_2 = translator.feed(&note_on(2, 64, 64));
+        let pressure_1 = translator.feed(&channel_pressure(1, 127));
+        let bend_2 = translator.feed(&pitch_bend_change(2, 0));
+        // Then
+        assert_eq!(on_1[3].map(|m| m.key_number()), Some(key_number(60)));
+        assert_eq!(on_2[3].map(|m| m.key_number()), Some(key_number(64)));
+        assert_eq!(
+            pressure_1,
+            [
+                Some(PerNoteMessage::Pressure {
+                    channel: channel(0),
+                    key_number: key_number(60),
+                    pressure: 0xFFFF_FFFF,
+                }),
+                None,
+                None,
+                None
+            ]
+        );
+        assert_eq!(
+            bend_2,
+            [
+                Some(PerNoteMessage::PitchBend {
+                    channel: channel(0),
+                    key_number: key_number(64),
+                    value: 0,
+                }),
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn expression_before_note_on() {
+        // Given
+        let mut translator = MpeToPerNoteTranslator::new(channel(15));
+        // When
+        let bend = translator.feed(&pitch_bend_change(3, 0x3000));
+        let timbre = translator.feed(&control_change(3, 74, 64));
+        let pressure = translator.feed(&channel_pressure(3, 0));
+        let on = translator.feed(&note_on(3, 48, 100));
+        // Then
+        assert_eq!(bend, [None; 4]);
+        assert_eq!(timbre, [None; 4]);
+        assert_eq!(pressure, [None; 4]);
+        assert_eq!(
+            on,
+            [
+                Some(PerNoteMessage::PitchBend {
+                    channel: channel(15),
+                    key_number: key_number(48),
+                    value: upscale_u14_to_u32(u14(0x3000)),
+                }),
+                Some(PerNoteMessage::RegisteredController {
+                    channel: channel(15),
+                    key_number: key_number(48),
+                    index: registered_per_note_controllers::BRIGHTNESS,
+                    value: 0x8000_0000,
+                }),
+                Some(PerNoteMessage::Pressure {
+                    channel: channel(15),
+                    key_number: key_number(48),
+                    pressure: 0,
+                }),
+                Some(PerNoteMessage::NoteOn {
+                    channel: channel(15),
+                    key_number: key_number(48),
+                    velocity: upscale_u7_to_u16(u7(100)),
+                    attribute: NoteAttribute::None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn note_off() {
+        // Given
+        let mut translator = MpeToPerNoteTranslator::new(channel(0));
+        translator.feed(&note_on(1, 60, 64));
+        // When
+        let off = translator.feed(&note_on(1, 60, 0));
+        let bend = translator.feed(&pitch_bend_change(1, 0));
+        // Then
+        assert_eq!(
+            off,
+            [
+                Some(PerNoteMessage::NoteOff {
+                    channel: channel(0),
+                    key_number: key_number(60),
+                    velocity: 0x8000,
+                    attribute: NoteAttribute::None,
+                }),
+                None,
+                None,
+                None
+            ]
+        );
+        assert_eq!(bend, [None; 4]);
+        assert_eq!(translator.key_number(channel(1)), None);
+    }
+
+    #[test]
+    fn ignore_other_messages() {
+        // Given
+        let mut translator = MpeToPerNoteTranslator::new(channel(0));
+        translator.feed(&note_on(1, 60, 64));
+        // When
+        // Then
+        assert_eq!(translator.feed(&control_change(1, 1, 64)), [None; 4]);
+        assert_eq!(translator.feed(&program_change(1, 1)), [None; 4]);
+        assert_eq!(translator.feed(&timing_clock()), [None; 4]);
+    }
+}
@@ -0,0 +1,518 @@
+use crate::{
+    extract_high_7_bit_value_from_14_bit_value, Channel, DataType, ParameterNumberMessage, U14, U7,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The registered parameter number of the MPE Configuration Message (MCM).
+pub const MPE_CONFIGURATION_PARAMETER_NUMBER: U14 = U14(6);
+
+/// An error which can occur when deserializing an MPE zone configuration or layout with too many
+/// member channels.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "MPE zone configuration is invalid")]
+pub struct InvalidMpeZoneError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidMpeZoneError {}
+
+/// One of the two MPE zones.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MpeZone {
+    /// Zone with master channel 1, member channels are allocated upwards from channel 2.
+    Lower,
+    /// Zone with master channel 16, member channels are allocated downwards from channel 15.
+    Upper,
+}
+
+impl MpeZone {
+    /// Returns the master channel of this zone.
+    pub fn master_channel(&self) -> Channel {
+        match self {
+            MpeZone::Lower => Channel(0),
+            MpeZone::Upper => Channel(15),
+        }
+    }
+}
+
+/// The configuration of an MPE zone as sent with the MPE Configuration Message (MCM).
+///
+/// The MCM is RPN 6 sent on the master channel of the zone. Its data entry MSB contains the number
+/// of member channels. A member channel count of 0 disables the zone.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{channel, control_change};
+/// use helgoboss_midi::{
+///     MpeZone, MpeZoneConfig, ParameterNumberMessageScanner, DataEntryByteOrder, RawShortMessage,
+/// };
+///
+/// let config = MpeZoneConfig::new(MpeZone::Upper, 3);
+/// assert_eq!(config.master_channel(), channel(15));
+/// assert_eq!(
+///     config.member_channels().collect::<Vec<_>>(),
+///     vec![channel(14), channel(13), channel(12)]
+/// );
+/// let short_messages: [Option<RawShortMessage>; 4] = config
+///     .to_parameter_number_message()
+///     .to_short_messages(DataEntryByteOrder::MsbFirst);
+/// assert_eq!(
+///     short_messages,
+///     [
+///         Some(control_change(15, 101, 0)),
+///         Some(control_change(15, 100, 6)),
+///         Some(control_change(15, 6, 3)),
+///         None,
+///     ]
+/// );
+/// let mut scanner = ParameterNumberMessageScanner::new();
+/// let parsed = short_messages
+///     .iter()
+///     .flatten()
+///     .find_map(|msg| scanner.feed(msg))
+///     .and_then(|msg| MpeZoneConfig::from_parameter_number_message(&msg));
+/// assert_eq!(parsed, Some(config));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "MpeZoneConfigData")
+)]
+pub struct MpeZoneConfig {
+    zone: MpeZone,
+    member_channel_count: u8,
+}
+
+/// Unvalidated zone configuration fields, used for deserialization.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct MpeZoneConfigData {
+    zone: MpeZone,
+    member_channel_count: u8,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<MpeZoneConfigData> for MpeZoneConfig {
+    type Error = InvalidMpeZoneError;
+
+    fn try_from(data: MpeZoneConfigData) -> Result<Self, Self::Error> {
+        if data.member_channel_count > 15 {
+            return Err(InvalidMpeZoneError(()));
+        }
+        Ok(MpeZoneConfig::new(data.zone, data.member_channel_count))
+    }
+}
+
+impl MpeZoneConfig {
+    /// Creates a zone configuration.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `member_channel_count` is greater than 15.
+    pub fn new(zone: MpeZone, member_channel_count: u8) -> MpeZoneConfig {
+        assert!(
+            member_channel_count <= 15,
+            "an MPE zone can't have more than 15 member channels"
+        );
+        MpeZoneConfig {
+            zone,
+            member_channel_count,
+        }
+    }
+
+    /// Interprets the given (N)RPN message as MPE Configuration Message.
+    ///
+    /// Returns `None` if the message is not RPN 6 with a data entry value, if it's not sent on
+    /// channel 1 or 16 or if the member channel count is greater than 15. If the message has a
+    /// 14-bit value, only the MSB is taken into account.
+    pub fn from_parameter_number_message(msg: &ParameterNumberMessage) -> Option<MpeZoneConfig> {
+        if !msg.is_registered()
+            || msg.number() != MPE_CONFIGURATION_PARAMETER_NUMBER
+            || msg.data_type() != DataType::DataEntry
+        {
+            return None;
+        }
+        let zone = match msg.channel().get() {
+            0 => MpeZone::Lower,
+            15 => MpeZone::Upper,
+            _ => return None,
+        };
+        let member_channel_count = if msg.is_14_bit() {
+            extract_high_7_bit_value_from_14_bit_value(msg.value())
+        } else {
+            U7(msg.value().get() as u8)
+        };
+        if member_channel_count > U7(15) {
+            return None;
+        }
+        Some(MpeZoneConfig::new(zone, member_channel_count.get()))
+    }
+
+    /// Returns the MPE Configuration Message (RPN 6 with 7-bit value) for this configuration.
+    pub fn to_parameter_number_message(&self) -> ParameterNumberMessage {
+        ParameterNumberMessage::registered_7_bit(
+            self.master_channel(),
+            MPE_CONFIGURATION_PARAMETER_NUMBER,
+            U7(self.member_channel_count),
+        )
+    }
+
+    /// Returns the zone.
+    pub fn zone(&self) -> MpeZone {
+        self.zone
+    }
+
+    /// Returns the number of member channels.
+    pub fn member_channel_count(&self) -> u8 {
+        self.member_channel_count
+    }
+
+    /// Returns whether this zone is enabled, that is, whether it has at least one member channel.
+    pub fn is_enabled(&self) -> bool {
+        self.member_channel_count > 0
+    }
+
+    /// Returns the master channel of this zone.
+    pub fn master_channel(&self) -> Channel {
+        self.zone.master_channel()
+    }
+
+    /// Returns the member channels of this zone, starting with the one next to the master channel.
+    pub fn member_channels(&self) -> impl Iterator<Item = Channel> {
+        let zone = self.zone;
+        (1..=self.member_channel_count).map(move |i| match zone {
+            MpeZone::Lower => Channel(i),
+            MpeZone::Upper => Channel(15 - i),
+        })
+    }
+
+    /// Returns whether the given channel is one of the member channels of this zone.
+    pub fn is_member_channel(&self, channel: Channel) -> bool {
+        let channel = channel.get();
+        match self.zone {
+            MpeZone::Lower => (1..=self.member_channel_count).contains(&channel),
+            MpeZone::Upper => channel < 15 && 15 - channel <= self.member_channel_count,
+        }
+    }
+}
+
+/// Keeps track of the current MPE zone layout by listening to MPE Configuration Messages.
+///
+/// Feed it with the output of a [`ParameterNumberMessageScanner`]. Initially, both zones are
+/// disabled. As defined by the MPE specification, configuring a zone shrinks or disables the other
+/// zone if both would otherwise overlap.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::control_change;
+/// use helgoboss_midi::{MpeZone, MpeZoneConfig, MpeZoneLayout, ParameterNumberMessageScanner};
+///
+/// let mut scanner = ParameterNumberMessageScanner::new();
+/// let mut layout = MpeZoneLayout::new();
+/// let messages = [
+///     control_change(15, 101, 0),
+///     control_change(15, 100, 6),
+///     control_change(15, 6, 4),
+///     control_change(0, 101, 0),
+///     control_change(0, 100, 6),
+///     control_change(0, 6, 12),
+/// ];
+/// for msg in messages.iter() {
+///     if let Some(msg) = scanner.feed(msg) {
+///         layout.feed(&msg);
+///     }
+/// }
+/// assert_eq!(layout.lower_zone(), Some(MpeZoneConfig::new(MpeZone::Lower, 12)));
+/// assert_eq!(layout.upper_zone(), Some(MpeZoneConfig::new(MpeZone::Upper, 2)));
+/// ```
+///
+/// [`ParameterNumberMessageScanner`]: struct.ParameterNumberMessageScanner.html
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "MpeZoneLayoutData")
+)]
+pub struct MpeZoneLayout {
+    lower_member_channel_count: u8,
+    upper_member_channel_count: u8,
+}
+
+/// Unvalidated layout fields, used for deserialization.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct MpeZoneLayoutData {
+    lower_member_channel_count: u8,
+    upper_member_channel_count: u8,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<MpeZoneLayoutData> for MpeZoneLayout {
+    type Error = InvalidMpeZoneError;
+
+    fn try_from(data: MpeZoneLayoutData) -> Result<Self, Self::Error> {
+        let lower = data.lower_member_channel_count;
+        let upper = data.upper_member_channel_count;
+        // If both zones are enabled, they must not overlap.
+        if lower > 15 || upper > 15 || (lower > 0 && upper > 0 && lower + upper > 14) {
+            return Err(InvalidMpeZoneError(()));
+        }
+        Ok(MpeZoneLayout {
+            lower_member_channel_count: lower,
+            upper_member_channel_count: upper,
+        })
+    }
+}
+
+impl MpeZoneLayout {
+    /// Creates a layout with both zones disabled.
+    pub fn new() -> MpeZoneLayout {
+        Default::default()
+    }
+
+    /// Feeds the layout a single (N)RPN message.
+    ///
+    /// Returns the applied zone configuration if the message is an MPE Configuration Message.
+    pub fn feed(&mut self, msg: &ParameterNumberMessage) -> Option<MpeZoneConfig> {
+        let config = MpeZoneConfig::from_parameter_number_message(msg)?;
+        self.apply(config);
+        Some(config)
+    }
+
+    /// Applies the given zone configuration, shrinking the other zone if necessary.
+    pub fn apply(&mut self, config: MpeZoneConfig) {
+        let count = config.member_channel_count;
+        let (this, other) = match config.zone {
+            MpeZone::Lower => (
+                &mut self.lower_member_channel_count,
+                &mut self.upper_member_channel_count,
+            ),
+            MpeZone::Upper => (
+                &mut self.upper_member_channel_count,
+                &mut self.lower_member_channel_count,
+            ),
+        };
+        *this = count;
+        if count > 0 {
+            // The zones share 16 channels, 2 of them being master channels.
+            *other = (*other).min(14u8.saturating_sub(count));
+        }
+    }
+
+    /// Returns the configuration of the lower zone if it's enabled.
+    pub fn lower_zone(&self) -> Option<MpeZoneConfig> {
+        self.zone(MpeZone::Lower)
+    }
+
+    /// Returns the configuration of the upper zone if it's enabled.
+    pub fn upper_zone(&self) -> Option<MpeZoneConfig> {
+        self.zone(MpeZone::Upper)
+    }
+
+    /// Returns the configuration of the given zone if it's enabled.
+    pub fn zone(&self, zone: MpeZone) -> Option<MpeZoneConfig> {
+        let count = match zone {
+            MpeZone::Lower => self.lower_member_channel_count,
+            MpeZone::Upper => self.upper_member_channel_count,
+        };
+        Some(MpeZoneConfig::new(zone, count)).filter(|c| c.is_enabled())
+    }
+
+    /// Returns the configuration of the enabled zone which the given channel belongs to, either as
+    /// master or as member channel.
+    pub fn zone_of_channel(&self, channel: Channel) -> Option<MpeZoneConfig> {
+        self.zones()
+            .find(|z| z.master_channel() == channel || z.is_member_channel(channel))
+    }
+
+    /// Returns the enabled zones.
+    pub fn zones(&self) -> impl Iterator<Item = MpeZoneConfig> {
+        self.lower_zone().into_iter().chain(self.upper_zone())
+    }
+
+    /// Resets the layout disabling both zones.
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn member_channels() {
+        // Given
+        let lower = MpeZoneConfig::new(MpeZone::Lower, 15);
+        let upper = MpeZoneConfig::new(MpeZone::Upper, 0);
+        // When
+        // Then
+        assert_eq!(lower.member_channels().count(), 15);
+        assert!(lower.is_member_channel(channel(15)));
+        assert!(!lower.is_member_channel(channel(0)));
+        assert!(!upper.is_enabled());
+        assert_eq!(upper.member_channels().count(), 0);
+        assert!(!upper.is_member_channel(channel(14)));
+        assert!(!upper.is_member_channel(channel(15)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_many_member_channels() {
+        MpeZoneConfig::new(MpeZone::Lower, 16);
+    }
+
+    #[test]
+    fn from_parameter_number_message() {
+        // Given
+        // When
+        // Then
+        assert_eq!(
+            MpeZoneConfig::from_parameter_number_message(
+                &ParameterNumberMessage::registered_7_bit(channel(0), u14(6), u7(5))
+            ),
+            Some(MpeZoneConfig::new(MpeZone::Lower, 5))
+        );
+        assert_eq!(
+            MpeZoneConfig::from_parameter_number_message(
+                &ParameterNumberMessage::registered_14_bit(channel(15), u14(6), u14(7 << 7))
+            ),
+            Some(MpeZoneConfig::new(MpeZone::Upper, 7))
+        );
+        assert_eq!(
+            MpeZoneConfig::from_parameter_number_message(
+                &ParameterNumberMessage::registered_7_bit(channel(1), u14(6), u7(5))
+            ),
+            None
+        );
+        assert_eq!(
+            MpeZoneConfig::from_parameter_number_message(
+                &ParameterNumberMessage::registered_7_bit(channel(0), u14(6), u7(16))
+            ),
+            None
+        );
+        assert_eq!(
+            MpeZoneConfig::from_parameter_number_message(
+                &ParameterNumberMessage::non_registered_7_bit(channel(0), u14(6), u7(5))
+            ),
+            None
+        );
+        assert_eq!(
+            MpeZoneConfig::from_parameter_number_message(
+                &ParameterNumberMessage::registered_increment(channel(0), u14(6), u7(1))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn layout() {
+        // Given
+        let mut layout = MpeZoneLayout::new();
+        // When
+        let applied = layout.feed(&ParameterNumberMessage::registered_7_bit(
+            channel(0),
+            u14(6),
+            u7(7),
+        ));
+        layout.apply(MpeZoneConfig::new(MpeZone::Upper, 7));
+        // Then
+        assert_eq!(applied, Some(MpeZoneConfig::new(MpeZone::Lower, 7)));
+        assert_eq!(layout.zones().count(), 2);
+        assert_eq!(
+            layout.zone_of_channel(channel(7)),
+            Some(MpeZoneConfig::new(MpeZone::Lower, 7))
+        );
+        assert_eq!(
+            layout.zone_of_channel(channel(8)),
+            Some(MpeZoneConfig::new(MpeZone::Upper, 7))
+        );
+        assert_eq!(
+            layout.zone_of_channel(channel(15)),
+            Some(MpeZoneConfig::new(MpeZone::Upper, 7))
+        );
+        assert_eq!(
+            layout.feed(&ParameterNumberMessage::registered_7_bit(
+                channel(0),
+                u14(7),
+                u7(7)
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn layout_overlap() {
+        // Given
+        let mut layout = MpeZoneLayout::new();
+        layout.apply(MpeZoneConfig::new(MpeZone::Lower, 10));
+        // When
+        layout.apply(MpeZoneConfig::new(MpeZone::Upper, 15));
+        // Then
+        assert_eq!(layout.lower_zone(), None);
+        assert_eq!(
+            layout.upper_zone(),
+            Some(MpeZoneConfig::new(MpeZone::Upper, 15))
+        );
+        assert_eq!(
+            layout.zone_of_channel(channel(0)).map(|z| z.zone()),
+            Some(MpeZone::Upper)
+        );
+        // When
+        layout.apply(MpeZoneConfig::new(MpeZone::Lower, 0));
+        // Then
+        assert_eq!(layout.upper_zone().unwrap().member_channel_count(), 15);
+        // When
+        layout.apply(MpeZoneConfig::new(MpeZone::Lower, 1));
+        // Then
+        assert_eq!(layout.upper_zone().unwrap().member_channel_count(), 13);
+    }
+
+    #[test]
+    fn upper_member_channels() {
+        // Given
+        let upper = MpeZoneConfig::new(MpeZone::Upper, 15);
+        // When
+        // Then
+        assert!(upper.is_member_channel(channel(0)));
+        assert!(upper.is_member_channel(channel(14)));
+        assert!(!upper.is_member_channel(channel(15)));
+        assert_eq!(
+            upper.member_channels().collect::<Vec<_>>(),
+            (0..=14).rev().map(channel).collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_invalid() {
+        // Given
+        let config = r#"{"zone":"Upper","member_channel_count":16}"#;
+        let layout = r#"{"lower_member_channel_count":8,"upper_member_channel_count":7}"#;
+        // When
+        // Then
+        assert!(serde_json::from_str::<MpeZoneConfig>(config).is_err());
+        assert!(serde_json::from_str::<MpeZoneLayout>(layout).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_valid() {
+        // Given
+        let mut layout = MpeZoneLayout::new();
+        layout.apply(MpeZoneConfig::new(MpeZone::Lower, 7));
+        layout.apply(MpeZoneConfig::new(MpeZone::Upper, 7));
+        // When
+        let json = serde_json::to_string(&layout).unwrap();
+        // Then
+        assert_eq!(
+            serde_json::from_str::<MpeZoneLayout>(&json).unwrap(),
+            layout
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    parse_universal_message, write_universal_message, ManufacturerId, SysExError, SysExMessage,
+    TimeCodeType, Timecode, UnexpectedSysExMessageError, U7,
+};
+use core::convert::TryFrom;
+
+/// Sub-ID #1 of MIDI Time Code messages.
+const MIDI_TIME_CODE: u8 = 0x01;
+
+/// Sub-ID #2 of Full Frame messages.
+const FULL_FRAME: u8 = 0x01;
+
+/// A MIDI Time Code Full Frame message, transmitted as Universal Real Time System Exclusive
+/// message (`F0 7F <device ID> 01 01 hr mn sc fr F7`).
+///
+/// Full Frame messages are used to communicate a time code position at once, e.g. after locating,
+/// instead of sending 8 Quarter Frame messages.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{MtcFullFrameMessage, TimeCodeType, Timecode, ALL_CALL_DEVICE_ID};
+/// use std::convert::TryFrom;
+///
+/// let msg = MtcFullFrameMessage::new(
+///     ALL_CALL_DEVICE_ID,
+///     Timecode::new(1, 2, 3, 4, TimeCodeType::Fps25),
+/// );
+/// let mut buffer = [0; MtcFullFrameMessage::BYTE_COUNT];
+/// let sys_ex_msg = msg.write(&mut buffer).unwrap();
+/// assert_eq!(
+///     sys_ex_msg.as_bytes(),
+///     &[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03, 0x04, 0xF7]
+/// );
+/// assert_eq!(MtcFullFrameMessage::try_from(sys_ex_msg), Ok(msg));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MtcFullFrameMessage {
+    device_id: U7,
+    timecode: Timecode,
+}
+
+impl MtcFullFrameMessage {
+    /// Number of bytes of this message, including start and end byte.
+    pub const BYTE_COUNT: usize = 10;
+
+    /// Creates a Full Frame message addressed to the given device.
+    pub fn new(device_id: U7, timecode: Timecode) -> MtcFullFrameMessage {
+        MtcFullFrameMessage {
+            device_id,
+            timecode,
+        }
+    }
+
+    /// Returns the ID of the addressed device.
+    pub fn device_id(&self) -> U7 {
+        self.device_id
+    }
+
+    /// Returns the time code position.
+    pub fn timecode(&self) -> Timecode {
+        self.timecode
+    }
+
+    /// Writes this message as System Exclusive message into the given buffer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small.
+    pub fn write<'a>(&self, buffer: &'a mut [u8]) -> Result<SysExMessage<'a>, SysExError> {
+        let tc = &self.timecode;
+        write_universal_message(
+            buffer,
+            ManufacturerId::UNIVERSAL_REAL_TIME,
+            self.device_id,
+            MIDI_TIME_CODE,
+            FULL_FRAME,
+            &[
+                (u8::from(tc.rate()) << 5) | tc.hours(),
+                tc.minutes(),
+                tc.seconds(),
+                tc.frames(),
+            ],
+        )
+    }
+}
+
+impl<'a> TryFrom<SysExMessage<'a>> for MtcFullFrameMessage {
+    type Error = UnexpectedSysExMessageError;
+
+    fn try_from(msg: SysExMessage<'a>) -> Result<Self, Self::Error> {
+        match parse_universal_message(msg, ManufacturerId::UNIVERSAL_REAL_TIME)? {
+            (device_id, MIDI_TIME_CODE, FULL_FRAME, [hr, mn, sc, fr]) => {
+                let rate = TimeCodeType::try_from((hr >> 5) & 0b11).expect("impossible");
+                let timecode = Timecode::try_new(hr & 0b0001_1111, *mn, *sc, *fr, rate)
+                    .ok_or(UnexpectedSysExMessageError(()))?;
+                Ok(MtcFullFrameMessage::new(device_id, timecode))
+            }
+            _ => Err(UnexpectedSysExMessageError(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::u7;
+
+    #[test]
+    fn round_trip() {
+        // Given
+        let msg = MtcFullFrameMessage::new(
+            u7(0x10),
+            Timecode::new(23, 59, 59, 29, TimeCodeType::Fps30DropFrame),
+        );
+        let mut buffer = [0; 16];
+        // When
+        let sys_ex_msg = msg.write(&mut buffer).unwrap();
+        // Then
+        assert_eq!(
+            sys_ex_msg.as_bytes(),
+            &[0xF0, 0x7F, 0x10, 0x01, 0x01, 0x57, 59, 59, 29, 0xF7]
+        );
+        assert_eq!(MtcFullFrameMessage::try_from(sys_ex_msg), Ok(msg));
+    }
+
+    #[test]
+    fn unexpected() {
+        // Given
+        let msgs: [&[u8]; 3] = [
+            &[0xF0, 0x7E, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03, 0x04, 0xF7],
+            &[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03, 0xF7],
+            &[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x21, 0x02, 0x03, 0x19, 0xF7],
+        ];
+        // When
+        // Then
+        for bytes in msgs.iter() {
+            let msg = SysExMessage::from_bytes(bytes).unwrap();
+            assert_eq!(
+                MtcFullFrameMessage::try_from(msg),
+                Err(UnexpectedSysExMessageError(()))
+            );
+        }
+    }
+}
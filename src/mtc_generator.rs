@@ -0,0 +1,323 @@
+use crate::{
+    real_frame_rate, MtcFullFrameMessage, ShortMessageFactory, TimeCodeQuarterFrame, TimeCodeType,
+    Timecode, U4, U7,
+};
+use core::time::Duration;
+
+/// Generator for MIDI Time Code, e.g. for making external gear chase an internal transport.
+///
+/// The generator is given a start position and then asked for the Quarter Frame messages which are
+/// due after a certain amount of elapsed time. Because elapsed time is always measured from the
+/// start position, rounding errors don't accumulate. Real time is mapped to frames using the real
+/// frame rate, that is, 29.97 frames per second for [`Fps30DropFrame`].
+///
+/// The generator doesn't allocate and can be used in a real-time thread.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::time_code_quarter_frame;
+/// use helgoboss_midi::{
+///     MtcGenerator, RawShortMessage, TimeCodeQuarterFrame, TimeCodeType, Timecode, U4,
+///     ALL_CALL_DEVICE_ID,
+/// };
+/// use std::time::Duration;
+///
+/// let mut generator = MtcGenerator::new(Timecode::new(1, 0, 0, 0, TimeCodeType::Fps25));
+/// // 4 quarter frames per frame, 25 frames per second => 1 quarter frame every 10 ms
+/// let messages: Vec<RawShortMessage> = generator.generate(Duration::from_millis(25)).collect();
+/// assert_eq!(
+///     messages,
+///     vec![
+///         time_code_quarter_frame(TimeCodeQuarterFrame::FrameCountLsNibble(U4::new(0))),
+///         time_code_quarter_frame(TimeCodeQuarterFrame::FrameCountMsNibble(U4::new(0))),
+///         time_code_quarter_frame(TimeCodeQuarterFrame::SecondsCountLsNibble(U4::new(0))),
+///     ]
+/// );
+///
+/// // Jump to another position
+/// let full_frame = generator.locate(
+///     Timecode::new(2, 0, 0, 0, TimeCodeType::Fps25),
+///     ALL_CALL_DEVICE_ID,
+/// );
+/// let mut buffer = [0; 16];
+/// let msg = full_frame.write(&mut buffer).unwrap();
+/// assert_eq!(msg.as_bytes(), &[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x22, 0, 0, 0, 0xF7]);
+/// ```
+///
+/// [`Fps30DropFrame`]: enum.TimeCodeType.html#variant.Fps30DropFrame
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MtcGenerator {
+    start: Timecode,
+    /// Number of quarter frames generated since the start position.
+    quarter_frame_count: u64,
+}
+
+impl MtcGenerator {
+    /// The maximum number of due Quarter Frame messages which are returned at once by
+    /// [`generate`], corresponding to 4 frames.
+    ///
+    /// [`generate`]: #method.generate
+    pub const MAX_BACKLOG: u64 = 16;
+
+    /// Creates a generator starting at the given position.
+    ///
+    /// The frame rate of the generated time code corresponds to the rate of the given position.
+    pub fn new(start: Timecode) -> MtcGenerator {
+        MtcGenerator {
+            start,
+            quarter_frame_count: 0,
+        }
+    }
+
+    /// Returns the start position.
+    pub fn start(&self) -> Timecode {
+        self.start
+    }
+
+    /// Returns the frame rate of the generated time code.
+    pub fn rate(&self) -> TimeCodeType {
+        self.start.rate()
+    }
+
+    /// Jumps to the given position, which becomes the new start position.
+    ///
+    /// Returns the Full Frame message which should be sent to the given device in order to
+    /// communicate the jump. The next call of [`generate`] should pass the time elapsed since the
+    /// jump.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the rate of the given position doesn't match the rate of the
+    /// generator.
+    ///
+    /// [`generate`]: #method.generate
+    pub fn locate(&mut self, timecode: Timecode, device_id: U7) -> MtcFullFrameMessage {
+        assert_eq!(timecode.rate(), self.rate(), "time code rate mismatch");
+        self.start = timecode;
+        self.quarter_frame_count = 0;
+        MtcFullFrameMessage::new(device_id, timecode)
+    }
+
+    /// Returns all Quarter Frame messages which are due until the given time elapsed since the
+    /// start position and which have not been returned yet.
+    ///
+    /// The first Quarter Frame message is due immediately at the start position. If more than
+    /// [`MAX_BACKLOG`] messages are due (e.g. because this method hasn't been called for a long
+    /// time), the missed ones are skipped and generation resumes with the first message of the
+    /// current 2-frame sequence, so that receivers resynchronize to the current position.
+    ///
+    /// [`MAX_BACKLOG`]: #associatedconstant.MAX_BACKLOG
+    pub fn generate<T: ShortMessageFactory>(
+        &mut self,
+        elapsed: Duration,
+    ) -> MtcQuarterFrames<'_, T> {
+        let (numerator, denominator) = real_frame_rate(self.rate());
+        // Number of quarter frames which are due (the first one is due at time 0)
+        let due_count =
+            (elapsed.as_nanos() * 4 * numerator / (1_000_000_000 * denominator)) as u64 + 1;
+        if due_count.saturating_sub(self.quarter_frame_count) > Self::MAX_BACKLOG {
+            // A complete position is transmitted with 8 Quarter Frame messages
+            self.quarter_frame_count = (due_count - 1) / 8 * 8;
+        }
+        MtcQuarterFrames {
+            generator: self,
+            due_count,
+            p: core::marker::PhantomData,
+        }
+    }
+
+    /// Returns the time, measured from the start position, at which the next Quarter Frame message
+    /// is due.
+    pub fn next_quarter_frame_time(&self) -> Duration {
+        let (numerator, denominator) = real_frame_rate(self.rate());
+        let nanos =
+            u128::from(self.quarter_frame_count) * 1_000_000_000 * denominator / (4 * numerator);
+        Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )
+    }
+
+    fn next_quarter_frame(&mut self) -> TimeCodeQuarterFrame {
+        use TimeCodeQuarterFrame::*;
+        let piece = self.quarter_frame_count % 8;
+        let timecode = self
+            .start
+            .add_frames(2 * (self.quarter_frame_count / 8) as i64);
+        self.quarter_frame_count += 1;
+        let low = |v: u8| U4(v & 0xF);
+        let high = |v: u8| U4(v >> 4);
+        match piece {
+            0 => FrameCountLsNibble(low(timecode.frames())),
+            1 => FrameCountMsNibble(high(timecode.frames())),
+            2 => SecondsCountLsNibble(low(timecode.seconds())),
+            3 => SecondsCountMsNibble(high(timecode.seconds())),
+            4 => MinutesCountLsNibble(low(timecode.minutes())),
+            5 => MinutesCountMsNibble(high(timecode.minutes())),
+            6 => HoursCountLsNibble(low(timecode.hours())),
+            _ => Last {
+                hours_count_ms_bit: timecode.hours() >= 16,
+                time_code_type: timecode.rate(),
+            },
+        }
+    }
+}
+
+/// An iterator over Quarter Frame messages which are due, returned by
+/// [`MtcGenerator::generate`].
+///
+/// [`MtcGenerator::generate`]: struct.MtcGenerator.html#method.generate
+#[derive(Debug)]
+pub struct MtcQuarterFrames<'a, T> {
+    generator: &'a mut MtcGenerator,
+    due_count: u64,
+    p: core::marker::PhantomData<T>,
+}
+
+impl<'a, T: ShortMessageFactory> Iterator for MtcQuarterFrames<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.generator.quarter_frame_count >= self.due_count {
+            return None;
+        }
+        Some(T::time_code_quarter_frame(
+            self.generator.next_quarter_frame(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::time_code_quarter_frame;
+    use crate::{MtcScanner, MtcScannerEvent, RawShortMessage};
+
+    #[test]
+    fn timing() {
+        // Given
+        let mut generator = MtcGenerator::new(Timecode::new(0, 0, 0, 0, TimeCodeType::Fps24));
+        // When
+        let count_1 = generator
+            .generate::<RawShortMessage>(Duration::from_millis(0))
+            .count();
+        let count_2 = generator
+            .generate::<RawShortMessage>(Duration::from_millis(0))
+            .count();
+        let count_3 = generator
+            .generate::<RawShortMessage>(Duration::from_secs(1))
+            .count();
+        // Then
+        assert_eq!(count_1, 1);
+        assert_eq!(count_2, 0);
+        assert_eq!(count_3, 1);
+        assert_eq!(
+            generator.next_quarter_frame_time(),
+            Duration::from_nanos(1_010_416_666)
+        );
+    }
+
+    #[test]
+    fn drop_frame_timing() {
+        // Given
+        let start = Timecode::new(0, 0, 0, 0, TimeCodeType::Fps30DropFrame);
+        let mut generator = MtcGenerator::new(start);
+        // When
+        let count = generator
+            .generate::<RawShortMessage>(Duration::from_secs(1001))
+            .count();
+        // Then
+        assert_eq!(count, 1);
+        assert_eq!(
+            generator.next_quarter_frame_time(),
+            Duration::from_nanos(1_001_008_341_666)
+        );
+    }
+
+    #[test]
+    fn round_trip_with_scanner() {
+        // Given
+        let start = Timecode::new(0, 0, 59, 24, TimeCodeType::Fps30DropFrame);
+        let mut generator = MtcGenerator::new(start);
+        let mut scanner = MtcScanner::new();
+        // When
+        let mut events = Vec::new();
+        for millis in (0..=500).step_by(10) {
+            events.extend(
+                generator
+                    .generate::<RawShortMessage>(Duration::from_millis(millis))
+                    .filter_map(|msg| scanner.feed(&msg)),
+            );
+        }
+        // Then
+        let positions: Vec<_> = events
+            .into_iter()
+            .map(|e| match e {
+                MtcScannerEvent::Position { timecode, .. } => timecode,
+                MtcScannerEvent::Dropout => panic!("dropout"),
+            })
+            .collect();
+        let tc = |s, f| {
+            let m = if s == 0 { 1 } else { 0 };
+            Timecode::new(0, m, s, f, TimeCodeType::Fps30DropFrame)
+        };
+        assert_eq!(
+            positions,
+            vec![
+                tc(59, 26),
+                tc(59, 28),
+                tc(0, 2),
+                tc(0, 4),
+                tc(0, 6),
+                tc(0, 8),
+                tc(0, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn locate() {
+        // Given
+        let mut generator = MtcGenerator::new(Timecode::new(0, 0, 0, 0, TimeCodeType::Fps25));
+        let _ = generator
+            .generate::<RawShortMessage>(Duration::from_secs(1))
+            .count();
+        // When
+        let target = Timecode::new(10, 0, 0, 0, TimeCodeType::Fps25);
+        let full_frame = generator.locate(target, crate::ALL_CALL_DEVICE_ID);
+        let count = generator
+            .generate::<RawShortMessage>(Duration::from_millis(0))
+            .count();
+        // Then
+        assert_eq!(full_frame.timecode(), target);
+        assert_eq!(generator.start(), target);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn skip_backlog() {
+        // Given
+        let mut generator = MtcGenerator::new(Timecode::new(0, 0, 0, 0, TimeCodeType::Fps25));
+        let _ = generator
+            .generate::<RawShortMessage>(Duration::from_millis(0))
+            .count();
+        // When
+        let short_gap = generator
+            .generate::<RawShortMessage>(Duration::from_millis(160))
+            .count();
+        let long_gap: Vec<RawShortMessage> =
+            generator.generate(Duration::from_millis(10_030)).collect();
+        // Then
+        assert_eq!(short_gap, 16);
+        assert_eq!(
+            long_gap,
+            vec![
+                time_code_quarter_frame(TimeCodeQuarterFrame::FrameCountLsNibble(U4(0))),
+                time_code_quarter_frame(TimeCodeQuarterFrame::FrameCountMsNibble(U4(0))),
+                time_code_quarter_frame(TimeCodeQuarterFrame::SecondsCountLsNibble(U4(10))),
+                time_code_quarter_frame(TimeCodeQuarterFrame::SecondsCountMsNibble(U4(0))),
+            ]
+        );
+    }
+}
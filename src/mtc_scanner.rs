@@ -0,0 +1,320 @@
+use crate::{ShortMessage, StructuredShortMessage, TimeCodeQuarterFrame, TimeCodeType, Timecode};
+use core::convert::TryFrom;
+
+/// Direction in which MIDI Time Code is running.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MtcDirection {
+    Forward,
+    Reverse,
+}
+
+/// Something that the [`MtcScanner`] detected.
+///
+/// [`MtcScanner`]: struct.MtcScanner.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MtcScannerEvent {
+    /// A complete time code position has been assembled.
+    Position {
+        /// The current position, already compensated for transmission latency.
+        timecode: Timecode,
+        direction: MtcDirection,
+    },
+    /// Quarter frames have been lost, arrived out of order or contained an invalid position. The
+    /// quarter frames received so far are discarded.
+    Dropout,
+}
+
+/// Scanner for assembling MIDI Time Code Quarter Frame messages into time code positions.
+///
+/// A complete position is transmitted in 8 quarter frames, that is, within 2 frames. The scanner
+/// emits a position whenever all 8 pieces have been received in sequence and adds (or, when
+/// running in reverse, subtracts) those 2 frames, so that the position corresponds to the time of
+/// the last received quarter frame.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::time_code_quarter_frame;
+/// use helgoboss_midi::TimeCodeQuarterFrame::*;
+/// use helgoboss_midi::{MtcDirection, MtcScanner, MtcScannerEvent, TimeCodeType, Timecode, U4};
+///
+/// let mut scanner = MtcScanner::new();
+/// let frames = [
+///     FrameCountLsNibble(U4::new(4)),
+///     FrameCountMsNibble(U4::new(0)),
+///     SecondsCountLsNibble(U4::new(3)),
+///     SecondsCountMsNibble(U4::new(0)),
+///     MinutesCountLsNibble(U4::new(2)),
+///     MinutesCountMsNibble(U4::new(0)),
+///     HoursCountLsNibble(U4::new(1)),
+/// ];
+/// for f in frames.iter() {
+///     assert_eq!(scanner.feed(&time_code_quarter_frame(*f)), None);
+/// }
+/// let result = scanner.feed(&time_code_quarter_frame(Last {
+///     hours_count_ms_bit: false,
+///     time_code_type: TimeCodeType::Fps25,
+/// }));
+/// assert_eq!(
+///     result,
+///     Some(MtcScannerEvent::Position {
+///         timecode: Timecode::new(1, 2, 3, 6, TimeCodeType::Fps25),
+///         direction: MtcDirection::Forward
+///     })
+/// );
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct MtcScanner {
+    /// Data of each piece (index = piece number).
+    pieces: [u8; 8],
+    /// Bit mask of pieces received in the current sequence.
+    received_pieces: u8,
+    last_piece: Option<u8>,
+    direction: Option<MtcDirection>,
+}
+
+impl MtcScanner {
+    /// Creates a new scanner.
+    pub fn new() -> MtcScanner {
+        Default::default()
+    }
+
+    /// Feeds the scanner a single short message.
+    ///
+    /// Returns an event if the message completed a time code position or revealed a dropout.
+    pub fn feed(&mut self, msg: &impl ShortMessage) -> Option<MtcScannerEvent> {
+        match msg.to_structured() {
+            StructuredShortMessage::TimeCodeQuarterFrame(frame) => self.process_frame(frame),
+            _ => None,
+        }
+    }
+
+    /// Returns the direction detected from the order of the most recent quarter frames.
+    pub fn direction(&self) -> Option<MtcDirection> {
+        self.direction
+    }
+
+    /// Resets the scanner discarding all intermediate scanning progress.
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+
+    fn process_frame(&mut self, frame: TimeCodeQuarterFrame) -> Option<MtcScannerEvent> {
+        use TimeCodeQuarterFrame::*;
+        let (piece, data) = match frame {
+            FrameCountLsNibble(v) => (0, v.get()),
+            FrameCountMsNibble(v) => (1, v.get()),
+            SecondsCountLsNibble(v) => (2, v.get()),
+            SecondsCountMsNibble(v) => (3, v.get()),
+            MinutesCountLsNibble(v) => (4, v.get()),
+            MinutesCountMsNibble(v) => (5, v.get()),
+            HoursCountLsNibble(v) => (6, v.get()),
+            Last {
+                hours_count_ms_bit,
+                time_code_type,
+            } => (
+                7,
+                (u8::from(time_code_type) << 1) | hours_count_ms_bit as u8,
+            ),
+        };
+        let previous_piece = self.last_piece.replace(piece);
+        self.pieces[usize::from(piece)] = data;
+        if let Some(previous_piece) = previous_piece {
+            let direction = if piece == (previous_piece + 1) % 8 {
+                MtcDirection::Forward
+            } else if piece == (previous_piece + 7) % 8 {
+                MtcDirection::Reverse
+            } else {
+                self.received_pieces = 1 << piece;
+                return Some(MtcScannerEvent::Dropout);
+            };
+            if self.direction.replace(direction) != Some(direction) {
+                // Direction changed. Pieces of the current sequence are not consistent.
+                self.received_pieces = 1 << previous_piece;
+            }
+        }
+        self.received_pieces |= 1 << piece;
+        let direction = self.direction?;
+        let last_piece_of_sequence = match direction {
+            MtcDirection::Forward => 7,
+            MtcDirection::Reverse => 0,
+        };
+        if piece != last_piece_of_sequence || self.received_pieces != 0xFF {
+            return None;
+        }
+        self.received_pieces = 0;
+        let event = match self.assemble_timecode() {
+            None => MtcScannerEvent::Dropout,
+            Some(timecode) => {
+                let latency_in_frames = match direction {
+                    MtcDirection::Forward => 2,
+                    MtcDirection::Reverse => -2,
+                };
+                MtcScannerEvent::Position {
+                    timecode: timecode.add_frames(latency_in_frames),
+                    direction,
+                }
+            }
+        };
+        Some(event)
+    }
+
+    fn assemble_timecode(&self) -> Option<Timecode> {
+        let p = &self.pieces;
+        let rate = TimeCodeType::try_from((p[7] >> 1) & 0b11).expect("impossible");
+        Timecode::try_new(
+            ((p[7] & 1) << 4) | p[6],
+            ((p[5] & 0b11) << 4) | p[4],
+            ((p[3] & 0b11) << 4) | p[2],
+            ((p[1] & 1) << 4) | p[0],
+            rate,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{note_on, time_code_quarter_frame, timing_clock};
+    use crate::{RawShortMessage, U7};
+
+    fn quarter_frames(timecode: Timecode) -> [RawShortMessage; 8] {
+        let rate = u8::from(timecode.rate());
+        let data = [
+            timecode.frames() & 0xF,
+            timecode.frames() >> 4,
+            timecode.seconds() & 0xF,
+            timecode.seconds() >> 4,
+            timecode.minutes() & 0xF,
+            timecode.minutes() >> 4,
+            timecode.hours() & 0xF,
+            (rate << 1) | (timecode.hours() >> 4),
+        ];
+        let mut messages = [timing_clock(); 8];
+        for (i, d) in data.iter().enumerate() {
+            let frame = TimeCodeQuarterFrame::from(U7::new(((i as u8) << 4) | d));
+            messages[i] = time_code_quarter_frame(frame);
+        }
+        messages
+    }
+
+    fn feed_all(scanner: &mut MtcScanner, messages: &[RawShortMessage]) -> Vec<MtcScannerEvent> {
+        messages.iter().filter_map(|m| scanner.feed(m)).collect()
+    }
+
+    #[test]
+    fn forward() {
+        // Given
+        let mut scanner = MtcScanner::new();
+        let tc_1 = Timecode::new(10, 59, 59, 22, TimeCodeType::Fps24);
+        let tc_2 = tc_1.add_frames(2);
+        // When
+        let result_1 = feed_all(&mut scanner, &quarter_frames(tc_1));
+        let result_2 = feed_all(&mut scanner, &[note_on(0, 64, 100), timing_clock()]);
+        let result_3 = feed_all(&mut scanner, &quarter_frames(tc_2));
+        // Then
+        assert_eq!(
+            result_1,
+            vec![MtcScannerEvent::Position {
+                timecode: Timecode::new(11, 0, 0, 0, TimeCodeType::Fps24),
+                direction: MtcDirection::Forward
+            }]
+        );
+        assert_eq!(result_2, vec![]);
+        assert_eq!(
+            result_3,
+            vec![MtcScannerEvent::Position {
+                timecode: Timecode::new(11, 0, 0, 2, TimeCodeType::Fps24),
+                direction: MtcDirection::Forward
+            }]
+        );
+        assert_eq!(scanner.direction(), Some(MtcDirection::Forward));
+    }
+
+    #[test]
+    fn forward_starting_mid_sequence() {
+        // Given
+        let mut scanner = MtcScanner::new();
+        let tc_1 = Timecode::new(0, 0, 1, 0, TimeCodeType::Fps25);
+        let tc_2 = tc_1.add_frames(2);
+        // When
+        let result_1 = feed_all(&mut scanner, &quarter_frames(tc_1)[3..]);
+        let result_2 = feed_all(&mut scanner, &quarter_frames(tc_2));
+        // Then
+        assert_eq!(result_1, vec![]);
+        assert_eq!(
+            result_2,
+            vec![MtcScannerEvent::Position {
+                timecode: Timecode::new(0, 0, 1, 4, TimeCodeType::Fps25),
+                direction: MtcDirection::Forward
+            }]
+        );
+    }
+
+    #[test]
+    fn reverse() {
+        // Given
+        let mut scanner = MtcScanner::new();
+        let tc = Timecode::new(0, 10, 0, 1, TimeCodeType::Fps30DropFrame);
+        let mut messages = quarter_frames(tc);
+        messages.reverse();
+        // When
+        let result = feed_all(&mut scanner, &messages);
+        // Then
+        assert_eq!(
+            result,
+            vec![MtcScannerEvent::Position {
+                timecode: Timecode::new(0, 9, 59, 29, TimeCodeType::Fps30DropFrame),
+                direction: MtcDirection::Reverse
+            }]
+        );
+        assert_eq!(scanner.direction(), Some(MtcDirection::Reverse));
+    }
+
+    #[test]
+    fn dropout() {
+        // Given
+        let mut scanner = MtcScanner::new();
+        let tc_1 = Timecode::new(1, 0, 0, 0, TimeCodeType::Fps30NonDrop);
+        let tc_2 = tc_1.add_frames(2);
+        let tc_3 = tc_2.add_frames(2);
+        let messages_1 = quarter_frames(tc_1);
+        // When
+        let result_1 = feed_all(&mut scanner, &messages_1[..3]);
+        let result_2 = feed_all(&mut scanner, &messages_1[4..]);
+        let result_3 = feed_all(&mut scanner, &quarter_frames(tc_2));
+        let result_4 = feed_all(&mut scanner, &quarter_frames(tc_3));
+        // Then
+        assert_eq!(result_1, vec![]);
+        assert_eq!(result_2, vec![MtcScannerEvent::Dropout]);
+        assert_eq!(
+            result_3,
+            vec![MtcScannerEvent::Position {
+                timecode: tc_2.add_frames(2),
+                direction: MtcDirection::Forward
+            }]
+        );
+        assert_eq!(
+            result_4,
+            vec![MtcScannerEvent::Position {
+                timecode: tc_3.add_frames(2),
+                direction: MtcDirection::Forward
+            }]
+        );
+    }
+
+    #[test]
+    fn reset() {
+        // Given
+        let mut scanner = MtcScanner::new();
+        let messages = quarter_frames(Timecode::new(1, 0, 0, 0, TimeCodeType::Fps25));
+        // When
+        let result_1 = feed_all(&mut scanner, &messages[..4]);
+        scanner.reset();
+        let result_2 = feed_all(&mut scanner, &messages[4..]);
+        // Then
+        assert_eq!(result_1, vec![]);
+        assert_eq!(result_2, vec![]);
+        assert_eq!(scanner.direction(), Some(MtcDirection::Forward));
+    }
+}
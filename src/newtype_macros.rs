@@ -58,7 +58,7 @@ This function panics if `value` is greater than ", $max, "."
                     {
                         assert!($name::is_valid(value), concat!("{} is not a valid ", stringify!($name), " value"), value);
                     }
-                    #[cfg(feature = "no_std")]
+                    #[cfg(not(feature = "std"))]
                     {
                         assert!($name::is_valid(value), concat!("not a valid ", stringify!($name), " value"));
                     }
@@ -74,6 +74,8 @@ This function panics if `value` is greater than ", $max, "."
 
 `value` must not be greater than ", $max, "."
                 ),
+                // Clippy doesn't see the "Safety" section generated by `doc_comment!`.
+                #[allow(clippy::missing_safety_doc)]
                 pub const unsafe fn new_unchecked(value: $repr) -> $name {
                     $name(value)
                 }
@@ -55,6 +55,14 @@ impl ParameterNumberMessageScanner {
         self.scanner_by_channel[usize::from(channel)].feed(msg)
     }
 
+    /// Returns whether a complete parameter number has been selected on the given channel, so that
+    /// Data Entry and Data Increment/Decrement messages result in (N)RPN messages.
+    pub(crate) fn has_parameter_number(&self, channel: Channel) -> bool {
+        self.scanner_by_channel[usize::from(channel)]
+            .build_number()
+            .is_some()
+    }
+
     /// Resets the scanner discarding all intermediate scanning progress.
     pub fn reset(&mut self) {
         for p in self.scanner_by_channel.iter_mut() {
@@ -0,0 +1,521 @@
+use crate::{Channel, KeyNumber, Midi2ChannelVoiceMessage};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A MIDI 2.0 per-note message, that is, a Channel Voice message which addresses one particular
+/// note (identified by its key number).
+///
+/// This is a typed view on the per-note variants of [`Midi2ChannelVoiceMessage`]. In contrast to
+/// the latter, note attributes are represented by [`NoteAttribute`] and per-note management flags
+/// are grouped in [`PerNoteManagement`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{
+///     registered_per_note_controllers, Channel, KeyNumber, Midi2ChannelVoiceMessage,
+///     NoteAttribute, PerNoteMessage,
+/// };
+///
+/// let msg = PerNoteMessage::NoteOn {
+///     channel: Channel::new(0),
+///     key_number: KeyNumber::new(60),
+///     velocity: 0xFFFF,
+///     attribute: NoteAttribute::Pitch7_9(60 << 9 | 0x100),
+/// };
+/// let channel_voice_message: Midi2ChannelVoiceMessage = msg.into();
+/// assert_eq!(
+///     channel_voice_message,
+///     Midi2ChannelVoiceMessage::NoteOn {
+///         channel: Channel::new(0),
+///         key_number: KeyNumber::new(60),
+///         velocity: 0xFFFF,
+///         attribute_type: 3,
+///         attribute_data: 0x7900,
+///     }
+/// );
+/// assert_eq!(
+///     PerNoteMessage::from_channel_voice_message(channel_voice_message),
+///     Some(msg)
+/// );
+/// let msg = PerNoteMessage::RegisteredController {
+///     channel: Channel::new(0),
+///     key_number: KeyNumber::new(60),
+///     index: registered_per_note_controllers::BRIGHTNESS,
+///     value: 0x8000_0000,
+/// };
+/// assert_eq!(msg.key_number(), KeyNumber::new(60));
+/// ```
+///
+/// [`Midi2ChannelVoiceMessage`]: enum.Midi2ChannelVoiceMessage.html
+/// [`NoteAttribute`]: enum.NoteAttribute.html
+/// [`PerNoteManagement`]: struct.PerNoteManagement.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PerNoteMessage {
+    /// Note Off with 16-bit velocity and typed attribute.
+    NoteOff {
+        channel: Channel,
+        key_number: KeyNumber,
+        velocity: u16,
+        attribute: NoteAttribute,
+    },
+    /// Note On with 16-bit velocity and typed attribute. A velocity of 0 doesn't mean Note Off.
+    NoteOn {
+        channel: Channel,
+        key_number: KeyNumber,
+        velocity: u16,
+        attribute: NoteAttribute,
+    },
+    /// Per-note pressure (called Poly Pressure in the MIDI 2.0 specification).
+    Pressure {
+        channel: Channel,
+        key_number: KeyNumber,
+        pressure: u32,
+    },
+    /// Registered per-note controller. See [`registered_per_note_controllers`] for the defined
+    /// indexes.
+    ///
+    /// [`registered_per_note_controllers`]: registered_per_note_controllers/index.html
+    RegisteredController {
+        channel: Channel,
+        key_number: KeyNumber,
+        index: u8,
+        value: u32,
+    },
+    /// Assignable per-note controller, whose meaning is up to the receiver.
+    AssignableController {
+        channel: Channel,
+        key_number: KeyNumber,
+        index: u8,
+        value: u32,
+    },
+    /// Per-note pitch bend, where `0x8000_0000` is the center.
+    PitchBend {
+        channel: Channel,
+        key_number: KeyNumber,
+        value: u32,
+    },
+    /// Per-note management, which detaches and/or resets the per-note controllers of the key
+    /// number.
+    Management {
+        channel: Channel,
+        key_number: KeyNumber,
+        management: PerNoteManagement,
+    },
+}
+
+impl PerNoteMessage {
+    /// Returns the typed per-note message for the given MIDI 2.0 Channel Voice message.
+    ///
+    /// Returns `None` if the given message doesn't address a particular note.
+    pub fn from_channel_voice_message(msg: Midi2ChannelVoiceMessage) -> Option<PerNoteMessage> {
+        use Midi2ChannelVoiceMessage as M;
+        use PerNoteMessage::*;
+        let msg = match msg {
+            M::NoteOff {
+                channel,
+                key_number,
+                velocity,
+                attribute_type,
+                attribute_data,
+            } => NoteOff {
+                channel,
+                key_number,
+                velocity,
+                attribute: NoteAttribute::from_raw(attribute_type, attribute_data),
+            },
+            M::NoteOn {
+                channel,
+                key_number,
+                velocity,
+                attribute_type,
+                attribute_data,
+            } => NoteOn {
+                channel,
+                key_number,
+                velocity,
+                attribute: NoteAttribute::from_raw(attribute_type, attribute_data),
+            },
+            M::PolyPressure {
+                channel,
+                key_number,
+                pressure,
+            } => Pressure {
+                channel,
+                key_number,
+                pressure,
+            },
+            M::RegisteredPerNoteController {
+                channel,
+                key_number,
+                index,
+                value,
+            } => RegisteredController {
+                channel,
+                key_number,
+                index,
+                value,
+            },
+            M::AssignablePerNoteController {
+                channel,
+                key_number,
+                index,
+                value,
+            } => AssignableController {
+                channel,
+                key_number,
+                index,
+                value,
+            },
+            M::PerNotePitchBend {
+                channel,
+                key_number,
+                value,
+            } => PitchBend {
+                channel,
+                key_number,
+                value,
+            },
+            M::PerNoteManagement {
+                channel,
+                key_number,
+                detach,
+                reset,
+            } => Management {
+                channel,
+                key_number,
+                management: PerNoteManagement { detach, reset },
+            },
+            _ => return None,
+        };
+        Some(msg)
+    }
+
+    /// Converts this message into the corresponding MIDI 2.0 Channel Voice message.
+    pub fn to_channel_voice_message(&self) -> Midi2ChannelVoiceMessage {
+        use Midi2ChannelVoiceMessage as M;
+        use PerNoteMessage::*;
+        match *self {
+            NoteOff {
+                channel,
+                key_number,
+                velocity,
+                attribute,
+            } => M::NoteOff {
+                channel,
+                key_number,
+                velocity,
+                attribute_type: attribute.attribute_type(),
+                attribute_data: attribute.data(),
+            },
+            NoteOn {
+                channel,
+                key_number,
+                velocity,
+                attribute,
+            } => M::NoteOn {
+                channel,
+                key_number,
+                velocity,
+                attribute_type: attribute.attribute_type(),
+                attribute_data: attribute.data(),
+            },
+            Pressure {
+                channel,
+                key_number,
+                pressure,
+            } => M::PolyPressure {
+                channel,
+                key_number,
+                pressure,
+            },
+            RegisteredController {
+                channel,
+                key_number,
+                index,
+                value,
+            } => M::RegisteredPerNoteController {
+                channel,
+                key_number,
+                index,
+                value,
+            },
+            AssignableController {
+                channel,
+                key_number,
+                index,
+                value,
+            } => M::AssignablePerNoteController {
+                channel,
+                key_number,
+                index,
+                value,
+            },
+            PitchBend {
+                channel,
+                key_number,
+                value,
+            } => M::PerNotePitchBend {
+                channel,
+                key_number,
+                value,
+            },
+            Management {
+                channel,
+                key_number,
+                management,
+            } => M::PerNoteManagement {
+                channel,
+                key_number,
+                detach: management.detach,
+                reset: management.reset,
+            },
+        }
+    }
+
+    /// Returns the channel of this message.
+    pub fn channel(&self) -> Channel {
+        use PerNoteMessage::*;
+        match *self {
+            NoteOff { channel, .. }
+            | NoteOn { channel, .. }
+            | Pressure { channel, .. }
+            | RegisteredController { channel, .. }
+            | AssignableController { channel, .. }
+            | PitchBend { channel, .. }
+            | Management { channel, .. } => channel,
+        }
+    }
+
+    /// Returns the key number of the note addressed by this message.
+    pub fn key_number(&self) -> KeyNumber {
+        use PerNoteMessage::*;
+        match *self {
+            NoteOff { key_number, .. }
+            | NoteOn { key_number, .. }
+            | Pressure { key_number, .. }
+            | RegisteredController { key_number, .. }
+            | AssignableController { key_number, .. }
+            | PitchBend { key_number, .. }
+            | Management { key_number, .. } => key_number,
+        }
+    }
+}
+
+impl From<PerNoteMessage> for Midi2ChannelVoiceMessage {
+    fn from(msg: PerNoteMessage) -> Self {
+        msg.to_channel_voice_message()
+    }
+}
+
+/// The attribute of a MIDI 2.0 Note On or Note Off message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NoteAttribute {
+    /// No attribute (attribute type 0). The attribute data is ignored.
+    None,
+    /// Manufacturer specific attribute (attribute type 1).
+    ManufacturerSpecific(u16),
+    /// Profile specific attribute (attribute type 2).
+    ProfileSpecific(u16),
+    /// Pitch of the note in 7.9 format (attribute type 3): The upper 7 bits contain the key number,
+    /// the lower 9 bits the fraction of a semitone.
+    Pitch7_9(u16),
+    /// Attribute type which is not defined by the MIDI 2.0 specification.
+    Other { attribute_type: u8, data: u16 },
+}
+
+impl Default for NoteAttribute {
+    fn default() -> Self {
+        NoteAttribute::None
+    }
+}
+
+impl NoteAttribute {
+    /// Creates a note attribute from the raw attribute type and data of a Note On or Note Off
+    /// message.
+    pub fn from_raw(attribute_type: u8, data: u16) -> NoteAttribute {
+        use NoteAttribute::*;
+        match attribute_type {
+            0 => None,
+            1 => ManufacturerSpecific(data),
+            2 => ProfileSpecific(data),
+            3 => Pitch7_9(data),
+            _ => Other {
+                attribute_type,
+                data,
+            },
+        }
+    }
+
+    /// Returns the raw attribute type.
+    pub fn attribute_type(&self) -> u8 {
+        use NoteAttribute::*;
+        match *self {
+            None => 0,
+            ManufacturerSpecific(_) => 1,
+            ProfileSpecific(_) => 2,
+            Pitch7_9(_) => 3,
+            Other { attribute_type, .. } => attribute_type,
+        }
+    }
+
+    /// Returns the raw attribute data (0 if there's no attribute).
+    pub fn data(&self) -> u16 {
+        use NoteAttribute::*;
+        match *self {
+            None => 0,
+            ManufacturerSpecific(data)
+            | ProfileSpecific(data)
+            | Pitch7_9(data)
+            | Other { data, .. } => data,
+        }
+    }
+}
+
+/// The options of a MIDI 2.0 Per-Note Management message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PerNoteManagement {
+    /// Detaches the per-note controllers from previously received notes with the same key number.
+    pub detach: bool,
+    /// Resets the per-note controllers of the key number to their default values.
+    pub reset: bool,
+}
+
+/// Contains the indexes of registered per-note controllers as defined by the MIDI 2.0
+/// specification.
+///
+/// Most of them correspond to the MIDI 1.0 controller numbers with the same meaning.
+pub mod registered_per_note_controllers {
+    /// Modulation.
+    pub const MODULATION: u8 = 1;
+    /// Breath.
+    pub const BREATH: u8 = 2;
+    /// Absolute pitch of the note in 7.25 format (7 bits key number, 25 bits fraction).
+    pub const PITCH_7_25: u8 = 3;
+    /// Volume.
+    pub const VOLUME: u8 = 7;
+    /// Balance.
+    pub const BALANCE: u8 = 8;
+    /// Pan.
+    pub const PAN: u8 = 10;
+    /// Expression.
+    pub const EXPRESSION: u8 = 11;
+    /// Sound Controller 1, by default Sound Variation.
+    pub const SOUND_VARIATION: u8 = 70;
+    /// Sound Controller 2, by default Timbre/Harmonic Intensity.
+    pub const TIMBRE: u8 = 71;
+    /// Sound Controller 3, by default Release Time.
+    pub const RELEASE_TIME: u8 = 72;
+    /// Sound Controller 4, by default Attack Time.
+    pub const ATTACK_TIME: u8 = 73;
+    /// Sound Controller 5, by default Brightness, also known as "timbre" or "slide" in MPE.
+    pub const BRIGHTNESS: u8 = 74;
+    /// Sound Controller 6, by default Decay Time.
+    pub const DECAY_TIME: u8 = 75;
+    /// Sound Controller 7, by default Vibrato Rate.
+    pub const VIBRATO_RATE: u8 = 76;
+    /// Sound Controller 8, by default Vibrato Depth.
+    pub const VIBRATO_DEPTH: u8 = 77;
+    /// Sound Controller 9, by default Vibrato Delay.
+    pub const VIBRATO_DELAY: u8 = 78;
+    /// Sound Controller 10, which has no default meaning.
+    pub const SOUND_CONTROLLER_10: u8 = 79;
+    /// Effects 1 Depth, by default Reverb Send Level.
+    pub const REVERB_SEND_LEVEL: u8 = 91;
+    /// Effects 2 Depth, by default Tremolo Depth.
+    pub const TREMOLO_DEPTH: u8 = 92;
+    /// Effects 3 Depth, by default Chorus Send Level.
+    pub const CHORUS_SEND_LEVEL: u8 = 93;
+    /// Effects 4 Depth, by default Celeste (Detune) Depth.
+    pub const CELESTE_DEPTH: u8 = 94;
+    /// Effects 5 Depth, by default Phaser Depth.
+    pub const PHASER_DEPTH: u8 = 95;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn note_attribute() {
+        // Given
+        // When
+        // Then
+        for attribute_type in 0..=255 {
+            let attribute = NoteAttribute::from_raw(attribute_type, 0x1234);
+            assert_eq!(attribute.attribute_type(), attribute_type);
+            assert_eq!(
+                attribute.data(),
+                if attribute_type == 0 { 0 } else { 0x1234 }
+            );
+        }
+        assert_eq!(NoteAttribute::from_raw(0, 5), NoteAttribute::None);
+        assert_eq!(
+            NoteAttribute::from_raw(3, 0x7800),
+            NoteAttribute::Pitch7_9(0x7800)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        // Given
+        let messages = [
+            PerNoteMessage::NoteOff {
+                channel: channel(1),
+                key_number: key_number(61),
+                velocity: 0x8000,
+                attribute: NoteAttribute::ManufacturerSpecific(7),
+            },
+            PerNoteMessage::Pressure {
+                channel: channel(1),
+                key_number: key_number(61),
+                pressure: 5,
+            },
+            PerNoteMessage::AssignableController {
+                channel: channel(1),
+                key_number: key_number(61),
+                index: 200,
+                value: 6,
+            },
+            PerNoteMessage::PitchBend {
+                channel: channel(1),
+                key_number: key_number(61),
+                value: 0x8000_0000,
+            },
+            PerNoteMessage::Management {
+                channel: channel(1),
+                key_number: key_number(61),
+                management: PerNoteManagement {
+                    detach: true,
+                    reset: false,
+                },
+            },
+        ];
+        for msg in messages.iter() {
+            // When
+            let channel_voice_message = msg.to_channel_voice_message();
+            // Then
+            assert_eq!(channel_voice_message.key_number(), Some(key_number(61)));
+            assert_eq!(
+                PerNoteMessage::from_channel_voice_message(channel_voice_message),
+                Some(*msg)
+            );
+        }
+    }
+
+    #[test]
+    fn not_per_note() {
+        // Given
+        let msg = Midi2ChannelVoiceMessage::PitchBend {
+            channel: channel(0),
+            value: 0,
+        };
+        // When
+        // Then
+        assert_eq!(PerNoteMessage::from_channel_voice_message(msg), None);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{BufferTooSmallError, U14, U7};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+#[cfg(feature = "serde_repr")]
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// The type of a MIDI-CI Property Exchange message, which corresponds to its sub-ID #2.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, IntoPrimitive, TryFromPrimitive,
+)]
+#[cfg_attr(feature = "serde_repr", derive(Serialize_repr, Deserialize_repr))]
+#[repr(u8)]
+pub enum PropertyExchangeMessageType {
+    /// Inquiry: Get Property Data.
+    GetPropertyDataInquiry = 0x34,
+    /// Reply to Get Property Data.
+    GetPropertyDataReply = 0x35,
+    /// Inquiry: Set Property Data.
+    SetPropertyDataInquiry = 0x36,
+    /// Reply to Set Property Data.
+    SetPropertyDataReply = 0x37,
+    /// Subscription.
+    SubscriptionInquiry = 0x38,
+    /// Reply to Subscription.
+    SubscriptionReply = 0x39,
+    /// Notify.
+    Notify = 0x3F,
+}
+
+/// A single chunk of a MIDI-CI Property Exchange message, contained in a [`CiMessage`].
+///
+/// Property data which doesn't fit into one System Exclusive message is split into several
+/// chunks, see [`PropertyExchangeChunker`]. The header data (usually JSON) is contained in the
+/// first chunk only.
+///
+/// [`CiMessage`]: enum.CiMessage.html
+/// [`PropertyExchangeChunker`]: struct.PropertyExchangeChunker.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PropertyExchangeChunk<'a> {
+    request_id: U7,
+    header_data: &'a [u8],
+    chunk_count: U14,
+    chunk_number: U14,
+    property_data: &'a [u8],
+}
+
+impl<'a> PropertyExchangeChunk<'a> {
+    /// Creates a chunk.
+    ///
+    /// Chunk numbers start at 1. Header data and property data must consist of 7-bit bytes
+    /// (otherwise writing the message fails), so binary property data needs to be encoded, e.g.
+    /// using [`mcoded7_encode`].
+    ///
+    /// [`mcoded7_encode`]: fn.mcoded7_encode.html
+    pub fn new(
+        request_id: U7,
+        header_data: &'a [u8],
+        chunk_count: U14,
+        chunk_number: U14,
+        property_data: &'a [u8],
+    ) -> PropertyExchangeChunk<'a> {
+        PropertyExchangeChunk {
+            request_id,
+            header_data,
+            chunk_count,
+            chunk_number,
+            property_data,
+        }
+    }
+
+    /// Returns the ID which relates inquiry and reply.
+    pub fn request_id(&self) -> U7 {
+        self.request_id
+    }
+
+    /// Returns the header data (usually JSON).
+    pub fn header_data(&self) -> &'a [u8] {
+        self.header_data
+    }
+
+    /// Returns the total number of chunks.
+    pub fn chunk_count(&self) -> U14 {
+        self.chunk_count
+    }
+
+    /// Returns the number of this chunk (starting at 1).
+    pub fn chunk_number(&self) -> U14 {
+        self.chunk_number
+    }
+
+    /// Returns the property data contained in this chunk.
+    pub fn property_data(&self) -> &'a [u8] {
+        self.property_data
+    }
+
+    /// Returns whether this is the last chunk.
+    pub fn is_last(&self) -> bool {
+        self.chunk_number >= self.chunk_count
+    }
+}
+
+/// Iterator which splits header and property data of a Property Exchange message into chunks.
+///
+/// Even if the property data is empty, there's at least one chunk.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{u14, u7};
+/// use helgoboss_midi::{
+///     CiHeader, CiMessage, Muid, PropertyExchangeChunker, PropertyExchangeMessageType,
+///     CI_FUNCTION_BLOCK_DEVICE_ID, CI_VERSION_1_2,
+/// };
+///
+/// let header = CiHeader::new(
+///     CI_FUNCTION_BLOCK_DEVICE_ID,
+///     CI_VERSION_1_2,
+///     Muid::new(0x0765_4321),
+///     Muid::new(0x0123_4567),
+/// );
+/// let property_data = br#"[{"title":"Init"},{"title":"Bass"}]"#;
+/// let chunks: Vec<_> =
+///     PropertyExchangeChunker::new(u7(1), br#"{"status":200}"#, property_data, 20).collect();
+/// assert_eq!(chunks.len(), 2);
+/// assert_eq!(chunks[1].chunk_number(), u14(2));
+/// let mut buffer = [0; 128];
+/// for chunk in chunks {
+///     let msg = CiMessage::PropertyExchange {
+///         message_type: PropertyExchangeMessageType::GetPropertyDataReply,
+///         chunk,
+///     };
+///     let sys_ex = msg.write(&header, &mut buffer).unwrap();
+///     // Send it ...
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PropertyExchangeChunker<'a> {
+    request_id: U7,
+    header_data: &'a [u8],
+    remaining_property_data: &'a [u8],
+    max_chunk_size: usize,
+    chunk_count: U14,
+    next_chunk_number: u16,
+}
+
+impl<'a> PropertyExchangeChunker<'a> {
+    /// Creates a chunker which puts at most `max_chunk_size` bytes of property data into one
+    /// chunk.
+    ///
+    /// The chunk size should be chosen in a way that the resulting System Exclusive messages don't
+    /// exceed the maximum System Exclusive size of the receiver.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `max_chunk_size` is zero or if the property data would need more
+    /// than 16383 chunks.
+    pub fn new(
+        request_id: U7,
+        header_data: &'a [u8],
+        property_data: &'a [u8],
+        max_chunk_size: usize,
+    ) -> PropertyExchangeChunker<'a> {
+        assert!(max_chunk_size > 0, "chunk size must not be zero");
+        let chunk_count = ((property_data.len() + max_chunk_size - 1) / max_chunk_size).max(1);
+        assert!(
+            chunk_count <= usize::from(U14::MAX.get()),
+            "too many chunks"
+        );
+        PropertyExchangeChunker {
+            request_id,
+            header_data,
+            remaining_property_data: property_data,
+            max_chunk_size,
+            chunk_count: U14(chunk_count as u16),
+            next_chunk_number: 1,
+        }
+    }
+}
+
+impl<'a> Iterator for PropertyExchangeChunker<'a> {
+    type Item = PropertyExchangeChunk<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_chunk_number > self.chunk_count.get() {
+            return None;
+        }
+        let (property_data, remaining) = self
+            .remaining_property_data
+            .split_at(self.remaining_property_data.len().min(self.max_chunk_size));
+        let header_data = if self.next_chunk_number == 1 {
+            self.header_data
+        } else {
+            &[]
+        };
+        let chunk = PropertyExchangeChunk::new(
+            self.request_id,
+            header_data,
+            self.chunk_count,
+            U14(self.next_chunk_number),
+            property_data,
+        );
+        self.remaining_property_data = remaining;
+        self.next_chunk_number += 1;
+        Some(chunk)
+    }
+}
+
+/// Returns the number of bytes which the given number of bytes takes when encoded with Mcoded7.
+pub fn mcoded7_encoded_len(decoded_len: usize) -> usize {
+    decoded_len + (decoded_len + 6) / 7
+}
+
+/// Returns the number of bytes which the given number of Mcoded7-encoded bytes takes when decoded.
+pub fn mcoded7_decoded_len(encoded_len: usize) -> usize {
+    encoded_len - (encoded_len + 7) / 8
+}
+
+/// Encodes arbitrary 8-bit bytes as 7-bit bytes using the Mcoded7 encoding of MIDI-CI Property
+/// Exchange and writes them to the beginning of the given buffer.
+///
+/// Each group of 7 bytes is preceded by a byte which contains their most significant bits.
+/// Returns the number of bytes written (see [`mcoded7_encoded_len`]).
+///
+/// # Errors
+///
+/// If the buffer is too small, an error is returned.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{mcoded7_decode, mcoded7_encode};
+///
+/// let mut encoded = [0; 16];
+/// let count = mcoded7_encode(&[0x81, 0x02, 0xFF], &mut encoded).unwrap();
+/// assert_eq!(&encoded[..count], &[0x50, 0x01, 0x02, 0x7F]);
+/// let mut decoded = [0; 16];
+/// let count = mcoded7_decode(&encoded[..count], &mut decoded).unwrap();
+/// assert_eq!(&decoded[..count], &[0x81, 0x02, 0xFF]);
+/// ```
+///
+/// [`mcoded7_encoded_len`]: fn.mcoded7_encoded_len.html
+pub fn mcoded7_encode(input: &[u8], buffer: &mut [u8]) -> Result<usize, BufferTooSmallError> {
+    let count = mcoded7_encoded_len(input.len());
+    if buffer.len() < count {
+        return Err(BufferTooSmallError(()));
+    }
+    for (group, output) in input.chunks(7).zip(buffer.chunks_mut(8)) {
+        let mut msbs = 0;
+        for (i, byte) in group.iter().enumerate() {
+            msbs |= (byte >> 7) << (6 - i);
+            output[i + 1] = byte & 0x7F;
+        }
+        output[0] = msbs;
+    }
+    Ok(count)
+}
+
+/// Decodes bytes encoded with Mcoded7 and writes them to the beginning of the given buffer.
+///
+/// Returns the number of bytes written (see [`mcoded7_decoded_len`]).
+///
+/// # Errors
+///
+/// If the buffer is too small, an error is returned.
+///
+/// [`mcoded7_decoded_len`]: fn.mcoded7_decoded_len.html
+pub fn mcoded7_decode(input: &[u8], buffer: &mut [u8]) -> Result<usize, BufferTooSmallError> {
+    let count = mcoded7_decoded_len(input.len());
+    if buffer.len() < count {
+        return Err(BufferTooSmallError(()));
+    }
+    for (group, output) in input.chunks(8).zip(buffer.chunks_mut(7)) {
+        let msbs = group[0];
+        for (i, byte) in group[1..].iter().enumerate() {
+            output[i] = (byte & 0x7F) | (((msbs >> (6 - i)) & 0x01) << 7);
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{u14, u7};
+
+    #[test]
+    fn chunker() {
+        // Given
+        let property_data: Vec<u8> = (0..25).collect();
+        // When
+        let chunks: Vec<_> =
+            PropertyExchangeChunker::new(u7(5), b"{}", &property_data, 10).collect();
+        // Then
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            chunks[0],
+            PropertyExchangeChunk::new(u7(5), b"{}", u14(3), u14(1), &property_data[..10])
+        );
+        assert_eq!(
+            chunks[1],
+            PropertyExchangeChunk::new(u7(5), &[], u14(3), u14(2), &property_data[10..20])
+        );
+        assert_eq!(
+            chunks[2],
+            PropertyExchangeChunk::new(u7(5), &[], u14(3), u14(3), &property_data[20..])
+        );
+        assert!(!chunks[1].is_last());
+        assert!(chunks[2].is_last());
+    }
+
+    #[test]
+    fn chunker_without_property_data() {
+        // Given
+        // When
+        let chunks: Vec<_> = PropertyExchangeChunker::new(u7(5), b"{}", &[], 10).collect();
+        // Then
+        assert_eq!(
+            chunks,
+            vec![PropertyExchangeChunk::new(
+                u7(5),
+                b"{}",
+                u14(1),
+                u14(1),
+                &[]
+            )]
+        );
+    }
+
+    #[test]
+    fn mcoded7() {
+        // Given
+        let input: Vec<u8> = (0..=255).rev().collect();
+        let mut encoded = vec![0; mcoded7_encoded_len(input.len())];
+        let mut decoded = vec![0; input.len()];
+        // When
+        let encoded_count = mcoded7_encode(&input, &mut encoded).unwrap();
+        let decoded_count = mcoded7_decode(&encoded, &mut decoded).unwrap();
+        // Then
+        assert_eq!(encoded_count, 293);
+        assert!(encoded.iter().all(|b| *b < 0x80));
+        assert_eq!(
+            &encoded[..8],
+            &[0x7F, 0x7F, 0x7E, 0x7D, 0x7C, 0x7B, 0x7A, 0x79]
+        );
+        assert_eq!(decoded_count, 256);
+        assert_eq!(mcoded7_decoded_len(encoded_count), 256);
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn mcoded7_buffer_too_small() {
+        // Given
+        let mut buffer = [0; 16];
+        // When
+        // Then
+        assert_eq!(
+            mcoded7_encode(&[0; 8], &mut buffer[..8]),
+            Err(BufferTooSmallError(()))
+        );
+        assert_eq!(
+            mcoded7_decode(&[0; 16], &mut buffer[..13]),
+            Err(BufferTooSmallError(()))
+        );
+    }
+}
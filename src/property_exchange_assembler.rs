@@ -0,0 +1,468 @@
+use crate::{Muid, PropertyExchangeChunk, PropertyExchangeMessageType, U7};
+use std::collections::HashMap;
+
+/// An error which can occur when reassembling chunks of Property Exchange messages.
+///
+/// The incomplete message is discarded when this error occurs.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+pub enum PropertyExchangeAssemblyError {
+    /// A chunk arrived with a chunk number other than the expected one (e.g. because a chunk got
+    /// lost) or with an invalid chunk count.
+    #[display(fmt = "unexpected Property Exchange chunk")]
+    UnexpectedChunk,
+    /// A chunk arrived whose message type or chunk count doesn't match the previous chunks with the
+    /// same request ID.
+    #[display(fmt = "inconsistent Property Exchange chunks")]
+    InconsistentChunk,
+    /// A first chunk arrived while the maximum number of incomplete messages was already reached.
+    #[display(fmt = "too many incomplete Property Exchange messages")]
+    TooManyIncompleteMessages,
+    /// The header and property data of a message exceed the maximum message size.
+    #[display(fmt = "Property Exchange message too large")]
+    MessageTooLarge,
+}
+
+impl std::error::Error for PropertyExchangeAssemblyError {}
+
+/// A complete Property Exchange message, reassembled from one or more chunks.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PropertyExchangeMessage {
+    source: Muid,
+    message_type: PropertyExchangeMessageType,
+    request_id: U7,
+    header_data: Vec<u8>,
+    property_data: Vec<u8>,
+}
+
+impl PropertyExchangeMessage {
+    /// Returns the MUID of the sender.
+    pub fn source(&self) -> Muid {
+        self.source
+    }
+
+    /// Returns the message type.
+    pub fn message_type(&self) -> PropertyExchangeMessageType {
+        self.message_type
+    }
+
+    /// Returns the ID which relates inquiry and reply.
+    pub fn request_id(&self) -> U7 {
+        self.request_id
+    }
+
+    /// Returns the header data (usually JSON).
+    pub fn header_data(&self) -> &[u8] {
+        &self.header_data
+    }
+
+    /// Returns the complete property data.
+    pub fn property_data(&self) -> &[u8] {
+        &self.property_data
+    }
+}
+
+/// Reassembles Property Exchange messages from chunks, which can arrive interleaved from several
+/// senders and for several requests.
+///
+/// Chunks of one message must arrive in order. Requests are distinguished by sender MUID and
+/// request ID. In order to limit memory usage, both the number of incomplete messages and the size
+/// of each message are capped (see [`with_limits`]).
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::u7;
+/// use helgoboss_midi::{
+///     CiMessage, Muid, PropertyExchangeAssembler, PropertyExchangeChunker,
+///     PropertyExchangeMessageType,
+/// };
+///
+/// let mut assembler = PropertyExchangeAssembler::new();
+/// let chunks = PropertyExchangeChunker::new(u7(1), br#"{"status":200}"#, b"0123456789", 4);
+/// let mut messages = Vec::new();
+/// for chunk in chunks {
+///     let msg = assembler
+///         .feed(
+///             Muid::new(0x0123_4567),
+///             PropertyExchangeMessageType::GetPropertyDataReply,
+///             &chunk,
+///         )
+///         .unwrap();
+///     messages.extend(msg);
+/// }
+/// assert_eq!(messages.len(), 1);
+/// assert_eq!(messages[0].header_data(), br#"{"status":200}"#);
+/// assert_eq!(messages[0].property_data(), b"0123456789");
+/// ```
+///
+/// [`with_limits`]: #method.with_limits
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PropertyExchangeAssembler {
+    incomplete_messages: HashMap<(Muid, U7), IncompleteMessage>,
+    max_incomplete_message_count: usize,
+    max_message_size: usize,
+}
+
+impl Default for PropertyExchangeAssembler {
+    fn default() -> Self {
+        PropertyExchangeAssembler::new()
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct IncompleteMessage {
+    message: PropertyExchangeMessage,
+    chunk_count: u16,
+    next_chunk_number: u16,
+}
+
+impl PropertyExchangeAssembler {
+    /// The default maximum number of messages which can be incomplete at the same time.
+    pub const DEFAULT_MAX_INCOMPLETE_MESSAGE_COUNT: usize = 16;
+
+    /// The default maximum number of header and property data bytes of one message.
+    pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+    /// Creates an assembler with the default limits.
+    pub fn new() -> PropertyExchangeAssembler {
+        PropertyExchangeAssembler::with_limits(
+            Self::DEFAULT_MAX_INCOMPLETE_MESSAGE_COUNT,
+            Self::DEFAULT_MAX_MESSAGE_SIZE,
+        )
+    }
+
+    /// Creates an assembler which keeps at most `max_incomplete_message_count` incomplete messages
+    /// and accepts messages with at most `max_message_size` bytes of header and property data.
+    pub fn with_limits(
+        max_incomplete_message_count: usize,
+        max_message_size: usize,
+    ) -> PropertyExchangeAssembler {
+        PropertyExchangeAssembler {
+            incomplete_messages: HashMap::new(),
+            max_incomplete_message_count,
+            max_message_size,
+        }
+    }
+
+    /// Feeds the assembler the given chunk, sent by the given device.
+    ///
+    /// Returns the complete message if this was its last chunk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the chunk doesn't fit to the previous chunks of the same request, if the
+    /// message gets too large or if the chunk would start a new incomplete message although the
+    /// maximum number of incomplete messages is reached. In each case, the incomplete message of
+    /// the same request is discarded.
+    pub fn feed(
+        &mut self,
+        source: Muid,
+        message_type: PropertyExchangeMessageType,
+        chunk: &PropertyExchangeChunk,
+    ) -> Result<Option<PropertyExchangeMessage>, PropertyExchangeAssemblyError> {
+        let key = (source, chunk.request_id());
+        let chunk_count = chunk.chunk_count().get();
+        let chunk_number = chunk.chunk_number().get();
+        let previous = self.incomplete_messages.remove(&key);
+        let mut incomplete = if chunk_number == 1 {
+            // A first chunk always starts a new message, even if an old one was still incomplete.
+            IncompleteMessage {
+                message: PropertyExchangeMessage {
+                    source,
+                    message_type,
+                    request_id: chunk.request_id(),
+                    header_data: chunk.header_data().to_vec(),
+                    property_data: Vec::new(),
+                },
+                chunk_count,
+                next_chunk_number: 1,
+            }
+        } else {
+            previous.ok_or(PropertyExchangeAssemblyError::UnexpectedChunk)?
+        };
+        if chunk_count == 0 || chunk_number != incomplete.next_chunk_number {
+            return Err(PropertyExchangeAssemblyError::UnexpectedChunk);
+        }
+        if incomplete.message.message_type != message_type || incomplete.chunk_count != chunk_count
+        {
+            return Err(PropertyExchangeAssemblyError::InconsistentChunk);
+        }
+        let size = incomplete.message.header_data.len()
+            + incomplete.message.property_data.len()
+            + chunk.property_data().len();
+        if size > self.max_message_size {
+            return Err(PropertyExchangeAssemblyError::MessageTooLarge);
+        }
+        incomplete
+            .message
+            .property_data
+            .extend_from_slice(chunk.property_data());
+        if chunk_number == chunk_count {
+            return Ok(Some(incomplete.message));
+        }
+        if self.incomplete_messages.len() >= self.max_incomplete_message_count {
+            return Err(PropertyExchangeAssemblyError::TooManyIncompleteMessages);
+        }
+        incomplete.next_chunk_number += 1;
+        self.incomplete_messages.insert(key, incomplete);
+        Ok(None)
+    }
+
+    /// Discards all incomplete messages.
+    pub fn reset(&mut self) {
+        self.incomplete_messages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{u14, u7};
+    use crate::{CiHeader, CiMessage, PropertyExchangeChunker, SysExMessage};
+
+    /// A Get Property Data transaction as sent over the wire: inquiry followed by a reply whose
+    /// property data is split into two chunks of at most 16 bytes.
+    const GET_TRANSCRIPT: [&[u8]; 3] = [
+        &[
+            0xF0, 0x7E, 0x7F, 0x0D, 0x34, 0x02, 0x21, 0x43, 0x14, 0x70, 0x67, 0x0A, 0x0D, 0x09,
+            0x07, 0x1A, 0x00, 0x7B, 0x22, 0x72, 0x65, 0x73, 0x6F, 0x75, 0x72, 0x63, 0x65, 0x22,
+            0x3A, 0x22, 0x50, 0x72, 0x6F, 0x67, 0x72, 0x61, 0x6D, 0x4C, 0x69, 0x73, 0x74, 0x22,
+            0x7D, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0xF7,
+        ],
+        &[
+            0xF0, 0x7E, 0x7F, 0x0D, 0x35, 0x02, 0x67, 0x0A, 0x0D, 0x09, 0x21, 0x43, 0x14, 0x70,
+            0x07, 0x0E, 0x00, 0x7B, 0x22, 0x73, 0x74, 0x61, 0x74, 0x75, 0x73, 0x22, 0x3A, 0x32,
+            0x30, 0x30, 0x7D, 0x02, 0x00, 0x01, 0x00, 0x10, 0x00, 0x5B, 0x7B, 0x22, 0x74, 0x69,
+            0x74, 0x6C, 0x65, 0x22, 0x3A, 0x22, 0x49, 0x6E, 0x69, 0x74, 0x22, 0xF7,
+        ],
+        &[
+            0xF0, 0x7E, 0x7F, 0x0D, 0x35, 0x02, 0x67, 0x0A, 0x0D, 0x09, 0x21, 0x43, 0x14, 0x70,
+            0x07, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x7D, 0x5D, 0xF7,
+        ],
+    ];
+
+    fn header(source: u32, destination: u32) -> CiHeader {
+        CiHeader::new(
+            crate::CI_FUNCTION_BLOCK_DEVICE_ID,
+            crate::CI_VERSION_1_2,
+            Muid::new(source),
+            Muid::new(destination),
+        )
+    }
+
+    fn write_transcript() -> Vec<Vec<u8>> {
+        let initiator = header(0x0E05_21A1, 0x0123_4567);
+        let responder = header(0x0123_4567, 0x0E05_21A1);
+        let mut buffer = [0; 256];
+        let mut transcript = Vec::new();
+        let inquiry = CiMessage::PropertyExchange {
+            message_type: PropertyExchangeMessageType::GetPropertyDataInquiry,
+            chunk: PropertyExchangeChunk::new(
+                u7(7),
+                br#"{"resource":"ProgramList"}"#,
+                u14(1),
+                u14(1),
+                &[],
+            ),
+        };
+        transcript.push(
+            inquiry
+                .write(&initiator, &mut buffer)
+                .unwrap()
+                .as_bytes()
+                .to_vec(),
+        );
+        let chunks =
+            PropertyExchangeChunker::new(u7(7), br#"{"status":200}"#, br#"[{"title":"Init"}]"#, 16);
+        for chunk in chunks {
+            let reply = CiMessage::PropertyExchange {
+                message_type: PropertyExchangeMessageType::GetPropertyDataReply,
+                chunk,
+            };
+            transcript.push(
+                reply
+                    .write(&responder, &mut buffer)
+                    .unwrap()
+                    .as_bytes()
+                    .to_vec(),
+            );
+        }
+        transcript
+    }
+
+    #[test]
+    fn write_get_transcript() {
+        // Given
+        // When
+        let transcript = write_transcript();
+        // Then
+        assert_eq!(
+            transcript,
+            GET_TRANSCRIPT
+                .iter()
+                .map(|m| m.to_vec())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn assemble_get_transcript() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::new();
+        // When
+        let messages: Vec<_> = GET_TRANSCRIPT
+            .iter()
+            .filter_map(|bytes| {
+                let sys_ex = SysExMessage::from_bytes(bytes).unwrap();
+                match CiMessage::parse(sys_ex).unwrap() {
+                    (
+                        header,
+                        CiMessage::PropertyExchange {
+                            message_type,
+                            chunk,
+                        },
+                    ) => assembler
+                        .feed(header.source(), message_type, &chunk)
+                        .unwrap(),
+                    _ => panic!("unexpected message"),
+                }
+            })
+            .collect();
+        // Then
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0].message_type(),
+            PropertyExchangeMessageType::GetPropertyDataInquiry
+        );
+        assert_eq!(messages[0].source(), Muid::new(0x0E05_21A1));
+        assert_eq!(messages[0].header_data(), br#"{"resource":"ProgramList"}"#);
+        assert_eq!(
+            messages[1].message_type(),
+            PropertyExchangeMessageType::GetPropertyDataReply
+        );
+        assert_eq!(messages[1].request_id(), u7(7));
+        assert_eq!(messages[1].header_data(), br#"{"status":200}"#);
+        assert_eq!(messages[1].property_data(), br#"[{"title":"Init"}]"#);
+    }
+
+    #[test]
+    fn interleaved_requests() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::new();
+        let notify = PropertyExchangeMessageType::Notify;
+        let a = PropertyExchangeChunker::new(u7(1), b"{}", b"aaaa", 2).collect::<Vec<_>>();
+        let b = PropertyExchangeChunker::new(u7(2), b"{}", b"bbbb", 2).collect::<Vec<_>>();
+        let muid = Muid::new(1);
+        // When
+        // Then
+        assert_eq!(assembler.feed(muid, notify, &a[0]), Ok(None));
+        assert_eq!(assembler.feed(muid, notify, &b[0]), Ok(None));
+        let msg_b = assembler.feed(muid, notify, &b[1]).unwrap().unwrap();
+        let msg_a = assembler.feed(muid, notify, &a[1]).unwrap().unwrap();
+        assert_eq!(msg_a.property_data(), b"aaaa");
+        assert_eq!(msg_b.property_data(), b"bbbb");
+    }
+
+    #[test]
+    fn missing_chunk() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::new();
+        let notify = PropertyExchangeMessageType::Notify;
+        let chunks = PropertyExchangeChunker::new(u7(1), b"{}", b"aaaaaa", 2).collect::<Vec<_>>();
+        let muid = Muid::new(1);
+        // When
+        assert_eq!(assembler.feed(muid, notify, &chunks[0]), Ok(None));
+        let result = assembler.feed(muid, notify, &chunks[2]);
+        // Then
+        assert_eq!(result, Err(PropertyExchangeAssemblyError::UnexpectedChunk));
+        assert_eq!(
+            assembler.feed(muid, notify, &chunks[1]),
+            Err(PropertyExchangeAssemblyError::UnexpectedChunk)
+        );
+    }
+
+    #[test]
+    fn rejected_first_chunk() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::new();
+        let notify = PropertyExchangeMessageType::Notify;
+        let chunks = PropertyExchangeChunker::new(u7(1), b"{}", b"aaaa", 2).collect::<Vec<_>>();
+        let invalid_first_chunk = PropertyExchangeChunk::new(u7(1), b"{}", u14(0), u14(1), b"bb");
+        let muid = Muid::new(1);
+        // When
+        assert_eq!(assembler.feed(muid, notify, &chunks[0]), Ok(None));
+        let result = assembler.feed(muid, notify, &invalid_first_chunk);
+        // Then
+        assert_eq!(result, Err(PropertyExchangeAssemblyError::UnexpectedChunk));
+        assert_eq!(
+            assembler.feed(muid, notify, &chunks[1]),
+            Err(PropertyExchangeAssemblyError::UnexpectedChunk)
+        );
+    }
+
+    #[test]
+    fn inconsistent_chunk() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::new();
+        let chunks = PropertyExchangeChunker::new(u7(1), b"{}", b"aaaa", 2).collect::<Vec<_>>();
+        let muid = Muid::new(1);
+        // When
+        assembler
+            .feed(muid, PropertyExchangeMessageType::Notify, &chunks[0])
+            .unwrap();
+        let result = assembler.feed(
+            muid,
+            PropertyExchangeMessageType::SubscriptionInquiry,
+            &chunks[1],
+        );
+        // Then
+        assert_eq!(
+            result,
+            Err(PropertyExchangeAssemblyError::InconsistentChunk)
+        );
+    }
+
+    #[test]
+    fn too_many_incomplete_messages() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::with_limits(2, 1024);
+        let notify = PropertyExchangeMessageType::Notify;
+        let muid = Muid::new(1);
+        let chunks: Vec<Vec<_>> = (0..3)
+            .map(|i| PropertyExchangeChunker::new(u7(i), b"{}", b"aaaa", 2).collect())
+            .collect();
+        // When
+        assert_eq!(assembler.feed(muid, notify, &chunks[0][0]), Ok(None));
+        assert_eq!(assembler.feed(muid, notify, &chunks[1][0]), Ok(None));
+        let result = assembler.feed(muid, notify, &chunks[2][0]);
+        // Then
+        assert_eq!(
+            result,
+            Err(PropertyExchangeAssemblyError::TooManyIncompleteMessages)
+        );
+        assert_eq!(assembler.feed(muid, notify, &chunks[0][0]), Ok(None));
+        assert!(assembler
+            .feed(muid, notify, &chunks[0][1])
+            .unwrap()
+            .is_some());
+        assert_eq!(assembler.feed(muid, notify, &chunks[2][0]), Ok(None));
+    }
+
+    #[test]
+    fn message_too_large() {
+        // Given
+        let mut assembler = PropertyExchangeAssembler::with_limits(16, 8);
+        let notify = PropertyExchangeMessageType::Notify;
+        let muid = Muid::new(1);
+        let chunks = PropertyExchangeChunker::new(u7(1), b"{}", b"aaaaaaaa", 4).collect::<Vec<_>>();
+        // When
+        assert_eq!(assembler.feed(muid, notify, &chunks[0]), Ok(None));
+        let result = assembler.feed(muid, notify, &chunks[1]);
+        // Then
+        assert_eq!(result, Err(PropertyExchangeAssemblyError::MessageTooLarge));
+        assert_eq!(
+            assembler.feed(muid, notify, &chunks[1]),
+            Err(PropertyExchangeAssemblyError::UnexpectedChunk)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+/// The JSON header of a MIDI-CI Property Exchange message.
+///
+/// Only the common header properties are modeled. Unknown properties are ignored when
+/// deserializing and absent properties are not serialized. This type is only available with the
+/// `serde` feature. Use a JSON library such as `serde_json` for parsing the header data of a
+/// [`PropertyExchangeChunk`] or [`PropertyExchangeMessage`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{PropertyExchangeCommand, PropertyExchangeHeader};
+///
+/// let header = PropertyExchangeHeader {
+///     resource: Some("ProgramList".to_string()),
+///     command: Some(PropertyExchangeCommand::Start),
+///     ..Default::default()
+/// };
+/// let json = serde_json::to_vec(&header).unwrap();
+/// assert_eq!(json, br#"{"resource":"ProgramList","command":"start"}"#);
+/// assert_eq!(serde_json::from_slice::<PropertyExchangeHeader>(&json).unwrap(), header);
+/// ```
+///
+/// [`PropertyExchangeChunk`]: struct.PropertyExchangeChunk.html
+/// [`PropertyExchangeMessage`]: struct.PropertyExchangeMessage.html
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyExchangeHeader {
+    /// The name of the resource, e.g. `"DeviceInfo"` or `"ProgramList"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    /// The ID of a specific instance of the resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub res_id: Option<String>,
+    /// The subscription command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<PropertyExchangeCommand>,
+    /// The ID of a subscription, assigned by the responder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscribe_id: Option<String>,
+    /// Whether the property data only contains changed parts of the resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_partial: Option<bool>,
+    /// The encoding of the property data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutual_encoding: Option<PropertyExchangeEncoding>,
+    /// The media type of the property data, e.g. `"application/json"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// The index of the first requested list entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// The maximum number of requested list entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// The status of a reply, using HTTP-like codes (e.g. 200 for success).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// A human-readable message explaining the status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A command within the header of a Property Exchange subscription message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyExchangeCommand {
+    /// Starts a subscription.
+    Start,
+    /// Ends a subscription.
+    End,
+    /// Notifies about a partial change of the resource.
+    Partial,
+    /// Notifies about a change of the whole resource.
+    Full,
+    /// Notifies about a change without including data. The receiver should get the resource.
+    Notify,
+}
+
+/// The encoding of the property data of a Property Exchange message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum PropertyExchangeEncoding {
+    /// Plain 7-bit ASCII (default).
+    #[serde(rename = "ASCII")]
+    Ascii,
+    /// Binary data encoded with Mcoded7 (see [`mcoded7_encode`]).
+    ///
+    /// [`mcoded7_encode`]: fn.mcoded7_encode.html
+    Mcoded7,
+    /// Binary data compressed with zlib and then encoded with Mcoded7.
+    #[serde(rename = "zlib+Mcoded7")]
+    ZlibMcoded7,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CiMessage, PropertyExchangeAssembler, SysExMessage};
+
+    /// A subscription reply as sent over the wire.
+    const SUBSCRIPTION_REPLY: &[u8] = &[
+        0xF0, 0x7E, 0x7F, 0x0D, 0x39, 0x02, 0x67, 0x0A, 0x0D, 0x09, 0x21, 0x43, 0x14, 0x70, 0x03,
+        0x32, 0x00, 0x7B, 0x22, 0x73, 0x74, 0x61, 0x74, 0x75, 0x73, 0x22, 0x3A, 0x32, 0x30, 0x30,
+        0x2C, 0x22, 0x73, 0x75, 0x62, 0x73, 0x63, 0x72, 0x69, 0x62, 0x65, 0x49, 0x64, 0x22, 0x3A,
+        0x22, 0x73, 0x75, 0x62, 0x31, 0x22, 0x2C, 0x22, 0x63, 0x61, 0x63, 0x68, 0x65, 0x54, 0x69,
+        0x6D, 0x65, 0x22, 0x3A, 0x33, 0x30, 0x7D, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0xF7,
+    ];
+
+    #[test]
+    fn parse_subscription_reply_transcript() {
+        // Given
+        let sys_ex = SysExMessage::from_bytes(SUBSCRIPTION_REPLY).unwrap();
+        let mut assembler = PropertyExchangeAssembler::new();
+        // When
+        let msg = match CiMessage::parse(sys_ex).unwrap() {
+            (
+                header,
+                CiMessage::PropertyExchange {
+                    message_type,
+                    chunk,
+                },
+            ) => assembler
+                .feed(header.source(), message_type, &chunk)
+                .unwrap()
+                .unwrap(),
+            _ => panic!("unexpected message"),
+        };
+        let header: PropertyExchangeHeader = serde_json::from_slice(msg.header_data()).unwrap();
+        // Then
+        assert_eq!(
+            header,
+            PropertyExchangeHeader {
+                status: Some(200),
+                subscribe_id: Some("sub1".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn encodings() {
+        // Given
+        let header = PropertyExchangeHeader {
+            resource: Some("State".to_string()),
+            res_id: Some("1".to_string()),
+            set_partial: Some(true),
+            mutual_encoding: Some(PropertyExchangeEncoding::ZlibMcoded7),
+            offset: Some(0),
+            limit: Some(10),
+            ..Default::default()
+        };
+        // When
+        let json = serde_json::to_value(&header).unwrap();
+        // Then
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "resource": "State",
+                "resId": "1",
+                "setPartial": true,
+                "mutualEncoding": "zlib+Mcoded7",
+                "offset": 0,
+                "limit": 10
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<PropertyExchangeHeader>(json).unwrap(),
+            header
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{FromWordsError, UmpMessage, UmpMessageFactory};
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A UMP message implemented as an array of 4 words, where words beyond the word count of the
+/// message type are zero.
+///
+/// The struct's size in memory is currently 16 bytes, no matter if it's a 32-, 64-, 96- or 128-bit
+/// message.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{RawUmpMessage, UmpMessage, UmpMessageFactory, UmpMessageType, U4};
+///
+/// let msg = RawUmpMessage::from_words(&[0x4195_3C00, 0xFFFF_0000]).unwrap();
+/// assert_eq!(msg.message_type(), UmpMessageType::Midi2ChannelVoice);
+/// assert_eq!(msg.word_count(), 2);
+/// assert_eq!(msg.words(), &[0x4195_3C00, 0xFFFF_0000]);
+/// assert_eq!(msg.group(), Some(U4::new(1)));
+/// assert_eq!(msg.channel().map(|c| c.get()), Some(5));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RawUmpMessage([u32; 4]);
+
+impl RawUmpMessage {
+    /// Returns the words which make up this message.
+    pub fn words(&self) -> &[u32] {
+        &self.0[..self.word_count()]
+    }
+}
+
+impl UmpMessageFactory for RawUmpMessage {
+    unsafe fn from_words_unchecked(words: [u32; 4]) -> Self {
+        Self(words)
+    }
+}
+
+impl TryFrom<&[u32]> for RawUmpMessage {
+    type Error = FromWordsError;
+
+    fn try_from(value: &[u32]) -> Result<Self, Self::Error> {
+        RawUmpMessage::from_words(value)
+    }
+}
+
+impl UmpMessage for RawUmpMessage {
+    fn to_words(&self) -> [u32; 4] {
+        self.0
+    }
+
+    fn first_word(&self) -> u32 {
+        self.0[0]
+    }
+}
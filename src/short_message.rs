@@ -55,9 +55,8 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 /// implementations can be made copyable just by deriving `Copy`, which is essential for passing
 /// around messages by copying rather then dealing with references.
 ///
-/// For the majority of use cases, System Exclusive messages are not necessary. Support for them can
-/// be built as a separate data structure on top of this trait and will probably added to
-/// *helgoboss-midi* in future.
+/// For the majority of use cases, System Exclusive messages are not necessary. Support for them is
+/// provided by the separate data structures [`SysExMessage`] and [`OwnedSysExMessage`].
 ///
 /// ## Why doesn't this trait support 14-bit Control Change or (N)RPN messages?
 ///
@@ -78,6 +77,8 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 /// [`ShortMessageFactory`]: trait.ShortMessageFactory.html
 /// [`RawShortMessage`]: struct.RawShortMessage.html
 /// [`StructuredShortMessage`]: enum.StructuredShortMessage.html
+/// [`SysExMessage`]: struct.SysExMessage.html
+/// [`OwnedSysExMessage`]: struct.OwnedSysExMessage.html
 pub trait ShortMessage {
     /// Returns the status byte.
     fn status_byte(&self) -> u8;
@@ -337,6 +338,37 @@ impl ShortMessageType {
             SystemExclusiveStart => SystemExclusive,
         }
     }
+
+    /// Returns the number of data bytes which follow the status byte when a message of this type
+    /// is transmitted over the wire.
+    ///
+    /// System Exclusive Start and End are reported as having no data bytes because the System
+    /// Exclusive data in between is not part of any short message.
+    pub fn data_byte_count(&self) -> u8 {
+        use ShortMessageType::*;
+        match self {
+            NoteOff
+            | NoteOn
+            | PolyphonicKeyPressure
+            | ControlChange
+            | PitchBendChange
+            | SongPositionPointer => 2,
+            ProgramChange | ChannelPressure | TimeCodeQuarterFrame | SongSelect => 1,
+            SystemExclusiveStart
+            | SystemCommonUndefined1
+            | SystemCommonUndefined2
+            | TuneRequest
+            | SystemExclusiveEnd
+            | TimingClock
+            | SystemRealTimeUndefined1
+            | Start
+            | Continue
+            | Stop
+            | SystemRealTimeUndefined2
+            | ActiveSensing
+            | SystemReset => 0,
+        }
+    }
 }
 
 /// Like [`MessageSuperType`] but without distinction between different channel messages.
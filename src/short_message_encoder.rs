@@ -0,0 +1,260 @@
+use crate::{FuzzyMessageSuperType, ShortMessage};
+
+/// An error which can occur when writing bytes into a buffer which is too small.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "buffer too small")]
+pub struct BufferTooSmallError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for BufferTooSmallError {}
+
+/// Encoder for writing short messages to a stream of raw MIDI bytes, e.g. for sending them over a
+/// serial or USB connection.
+///
+/// Unlike [`ShortMessage::to_bytes`], which always returns 3 bytes, the encoder writes only the
+/// bytes which are actually transmitted, e.g. 1 byte for a Tune Request and 2 bytes for a Program
+/// Change message. Optionally, it omits the status byte of Channel messages whenever running status
+/// permits it. Running status is cancelled by System Common and System Exclusive messages but not
+/// by System Real Time messages.
+///
+/// The encoder doesn't allocate and can be used in a real-time thread.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{note_on, program_change, tune_request};
+/// use helgoboss_midi::ShortMessageEncoder;
+///
+/// let mut encoder = ShortMessageEncoder::new(true);
+/// let mut buffer = [0; 16];
+/// let messages = [
+///     note_on(0, 64, 100),
+///     note_on(0, 65, 100),
+///     tune_request(),
+///     program_change(3, 5),
+/// ];
+/// let count = encoder.encode_all(&messages, &mut buffer).unwrap();
+/// assert_eq!(&buffer[..count], &[0x90, 64, 100, 65, 100, 0xF6, 0xC3, 5]);
+/// ```
+///
+/// [`ShortMessage::to_bytes`]: trait.ShortMessage.html#method.to_bytes
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct ShortMessageEncoder {
+    use_running_status: bool,
+    running_status: Option<u8>,
+}
+
+impl ShortMessageEncoder {
+    /// Creates a new encoder.
+    ///
+    /// If `use_running_status` is `true`, the encoder omits status bytes whenever possible.
+    pub fn new(use_running_status: bool) -> ShortMessageEncoder {
+        ShortMessageEncoder {
+            use_running_status,
+            running_status: None,
+        }
+    }
+
+    /// Writes the given message to the beginning of the given buffer.
+    ///
+    /// Returns the number of bytes written, which is between 1 and 3.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer is too small to hold the encoded message, an error is returned. In this case
+    /// nothing is written and the encoder state stays untouched.
+    pub fn encode(
+        &mut self,
+        msg: &impl ShortMessage,
+        buffer: &mut [u8],
+    ) -> Result<usize, BufferTooSmallError> {
+        let (status_byte, data_byte_1, data_byte_2) = msg.to_bytes();
+        let r#type = msg.r#type();
+        let data_byte_count = usize::from(r#type.data_byte_count());
+        let (omit_status_byte, next_running_status) = match r#type.super_type() {
+            FuzzyMessageSuperType::Channel => (
+                self.use_running_status && self.running_status == Some(status_byte),
+                Some(status_byte),
+            ),
+            FuzzyMessageSuperType::SystemRealTime => (false, self.running_status),
+            FuzzyMessageSuperType::SystemCommon | FuzzyMessageSuperType::SystemExclusive => {
+                (false, None)
+            }
+        };
+        let status_byte_count = if omit_status_byte { 0 } else { 1 };
+        let byte_count = status_byte_count + data_byte_count;
+        if buffer.len() < byte_count {
+            return Err(BufferTooSmallError(()));
+        }
+        let all_bytes = [status_byte, data_byte_1.get(), data_byte_2.get()];
+        buffer[..byte_count].copy_from_slice(&all_bytes[1 - status_byte_count..=data_byte_count]);
+        self.running_status = next_running_status;
+        Ok(byte_count)
+    }
+
+    /// Writes the given messages one after another to the beginning of the given buffer.
+    ///
+    /// Returns the total number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer is too small to hold all encoded messages, an error is returned. In this case
+    /// the buffer contains only some of the messages and the encoder is reset, so that the next
+    /// encoded message will start with a status byte.
+    pub fn encode_all<'a, T: ShortMessage + 'a>(
+        &mut self,
+        messages: impl IntoIterator<Item = &'a T>,
+        buffer: &mut [u8],
+    ) -> Result<usize, BufferTooSmallError> {
+        let mut count = 0;
+        for msg in messages {
+            match self.encode(msg, &mut buffer[count..]) {
+                Ok(c) => count += c,
+                Err(e) => {
+                    self.reset();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Resets the encoder, so that the next encoded message will start with a status byte.
+    pub fn reset(&mut self) {
+        self.running_status = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn minimal_byte_count() {
+        // Given
+        let mut encoder = ShortMessageEncoder::new(false);
+        let mut buffer = [0; 3];
+        // When
+        // Then
+        assert_eq!(encoder.encode(&note_on(1, 64, 100), &mut buffer), Ok(3));
+        assert_eq!(buffer, [0x91, 64, 100]);
+        assert_eq!(encoder.encode(&program_change(2, 5), &mut buffer), Ok(2));
+        assert_eq!(buffer[..2], [0xC2, 5]);
+        assert_eq!(encoder.encode(&channel_pressure(0, 6), &mut buffer), Ok(2));
+        assert_eq!(buffer[..2], [0xD0, 6]);
+        assert_eq!(encoder.encode(&song_select(7), &mut buffer), Ok(2));
+        assert_eq!(buffer[..2], [0xF3, 7]);
+        assert_eq!(
+            encoder.encode(&song_position_pointer(0x101), &mut buffer),
+            Ok(3)
+        );
+        assert_eq!(buffer, [0xF2, 1, 2]);
+        assert_eq!(encoder.encode(&tune_request(), &mut buffer), Ok(1));
+        assert_eq!(buffer[..1], [0xF6]);
+        assert_eq!(encoder.encode(&timing_clock(), &mut buffer), Ok(1));
+        assert_eq!(buffer[..1], [0xF8]);
+    }
+
+    #[test]
+    fn without_running_status() {
+        // Given
+        let mut encoder = ShortMessageEncoder::new(false);
+        let mut buffer = [0; 16];
+        // When
+        let result = encoder.encode_all(
+            &[note_on(0, 64, 100), note_on(0, 65, 100), note_on(0, 66, 0)],
+            &mut buffer,
+        );
+        // Then
+        assert_eq!(result, Ok(9));
+        assert_eq!(buffer[..9], [0x90, 64, 100, 0x90, 65, 100, 0x90, 66, 0]);
+    }
+
+    #[test]
+    fn with_running_status() {
+        // Given
+        let mut encoder = ShortMessageEncoder::new(true);
+        let mut buffer = [0; 16];
+        // When
+        let result = encoder.encode_all(
+            &[
+                control_change(2, 7, 100),
+                control_change(2, 7, 101),
+                timing_clock(),
+                control_change(2, 7, 102),
+                control_change(3, 7, 103),
+            ],
+            &mut buffer,
+        );
+        // Then
+        assert_eq!(result, Ok(11));
+        assert_eq!(
+            buffer[..11],
+            [0xB2, 7, 100, 7, 101, 0xF8, 7, 102, 0xB3, 7, 103]
+        );
+    }
+
+    #[test]
+    fn system_common_cancels_running_status() {
+        // Given
+        let mut encoder = ShortMessageEncoder::new(true);
+        let mut buffer = [0; 16];
+        // When
+        let result = encoder.encode_all(
+            &[
+                note_on(0, 64, 100),
+                tune_request(),
+                note_on(0, 65, 100),
+                system_exclusive_start(),
+                system_exclusive_end(),
+                note_on(0, 66, 100),
+            ],
+            &mut buffer,
+        );
+        // Then
+        assert_eq!(result, Ok(12));
+        assert_eq!(
+            buffer[..12],
+            [0x90, 64, 100, 0xF6, 0x90, 65, 100, 0xF0, 0xF7, 0x90, 66, 100]
+        );
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        // Given
+        let mut encoder = ShortMessageEncoder::new(true);
+        let mut buffer = [0; 4];
+        // When
+        let result_1 = encoder.encode(&note_on(0, 64, 100), &mut buffer[..2]);
+        let result_2 = encoder.encode_all(&[note_on(0, 64, 100), note_on(0, 65, 100)], &mut buffer);
+        let result_3 = encoder.encode(&note_on(0, 66, 100), &mut buffer);
+        // Then
+        assert_eq!(result_1, Err(BufferTooSmallError(())));
+        assert_eq!(result_2, Err(BufferTooSmallError(())));
+        assert_eq!(result_3, Ok(3));
+        assert_eq!(buffer[..3], [0x90, 66, 100]);
+    }
+
+    #[test]
+    fn round_trip_with_parser() {
+        // Given
+        let mut encoder = ShortMessageEncoder::new(true);
+        let mut parser = crate::ShortMessageParser::new();
+        let messages = [
+            note_on(4, 64, 100),
+            note_on(4, 65, 100),
+            active_sensing(),
+            pitch_bend_change(4, 1278),
+            song_select(3),
+            program_change(4, 8),
+            program_change(4, 9),
+        ];
+        let mut buffer = [0; 32];
+        // When
+        let count = encoder.encode_all(&messages, &mut buffer).unwrap();
+        let parsed: Result<Vec<_>, _> = parser.feed(&buffer[..count]).collect();
+        // Then
+        assert_eq!(parsed.unwrap(), messages.to_vec());
+    }
+}
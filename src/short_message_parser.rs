@@ -0,0 +1,482 @@
+use crate::{
+    extract_type_from_status_byte, FuzzyMessageSuperType, RawShortMessage, ShortMessageFactory, U7,
+};
+
+/// An error which can occur when parsing a stream of MIDI bytes.
+///
+/// Both errors are recoverable. The parser resynchronizes automatically with the next status byte.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, derive_more::Display)]
+pub enum ShortMessageParserError {
+    /// A data byte arrived without any status byte (explicit or running) being in effect.
+    ///
+    /// Reported only once. All following data bytes are discarded silently until the next status
+    /// byte arrives.
+    #[display(fmt = "data byte without status byte")]
+    UnexpectedDataByte,
+    /// A status byte arrived before the previous message was complete. The incomplete message has
+    /// been discarded.
+    #[display(fmt = "incomplete MIDI message")]
+    IncompleteMessage,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ShortMessageParserError {}
+
+/// Parser for extracting short messages from a raw stream of MIDI bytes, e.g. received from a
+/// serial or USB connection.
+///
+/// Bytes can be fed in chunks of arbitrary size. A message that is split across two chunks is
+/// emitted as soon as its last byte arrives. The parser supports the following:
+///
+/// - Running status (for Channel messages)
+/// - Cancellation of running status by System Common and System Exclusive messages
+/// - System Real Time messages interleaved anywhere, even in the middle of another message
+/// - System Exclusive messages (emitted as [`SystemExclusiveStart`] and [`SystemExclusiveEnd`],
+///   the data in between is skipped)
+///
+/// A status byte other than End of Exclusive or System Real Time which arrives in the middle of a
+/// System Exclusive message aborts it silently: No [`SystemExclusiveEnd`] and no error is emitted,
+/// the status byte is processed as usual.
+///
+/// The parser doesn't allocate and can be used in a real-time thread.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{control_change, note_on, timing_clock};
+/// use helgoboss_midi::ShortMessageParser;
+///
+/// let mut parser = ShortMessageParser::new();
+/// // Note On, Note On (with running status), Control Change interrupted by Timing Clock
+/// let messages: Result<Vec<_>, _> = parser
+///     .feed(&[0x90, 64, 100, 65, 100, 0xB0, 7, 0xF8, 120])
+///     .collect();
+/// assert_eq!(
+///     messages.unwrap(),
+///     vec![
+///         note_on(0, 64, 100),
+///         note_on(0, 65, 100),
+///         timing_clock(),
+///         control_change(0, 7, 120)
+///     ]
+/// );
+/// ```
+///
+/// [`SystemExclusiveStart`]: enum.ShortMessageType.html#variant.SystemExclusiveStart
+/// [`SystemExclusiveEnd`]: enum.ShortMessageType.html#variant.SystemExclusiveEnd
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct ShortMessageParser {
+    state: State,
+    /// A message which was completed by the same byte that caused an error and therefore couldn't
+    /// be emitted yet.
+    pending_message: Option<RawShortMessage>,
+}
+
+impl ShortMessageParser {
+    /// Creates a new parser.
+    pub fn new() -> ShortMessageParser {
+        Default::default()
+    }
+
+    /// Feeds the parser a chunk of bytes.
+    ///
+    /// Returns an iterator over all messages which have been completed by this chunk and over all
+    /// errors which occurred while parsing it. Bytes are only consumed as far as the iterator is
+    /// advanced.
+    pub fn feed<'a>(&'a mut self, bytes: &'a [u8]) -> ParsedShortMessages<'a> {
+        ParsedShortMessages {
+            parser: self,
+            bytes: bytes.iter(),
+        }
+    }
+
+    /// Resets the parser discarding all intermediate parsing progress, including running status.
+    pub fn reset(&mut self) {
+        self.state = Default::default();
+        self.pending_message = None;
+    }
+
+    fn process_byte(&mut self, byte: u8) -> ProcessResult {
+        if byte < 0x80 {
+            self.process_data_byte(U7(byte))
+        } else if byte >= 0xF8 {
+            // System Real Time messages don't affect the current state at all.
+            ProcessResult::message(build_message((byte, U7::MIN, U7::MIN)))
+        } else {
+            self.process_status_byte(byte)
+        }
+    }
+
+    fn process_status_byte(&mut self, status_byte: u8) -> ProcessResult {
+        let error = if self.state.has_incomplete_message() {
+            Some(ShortMessageParserError::IncompleteMessage)
+        } else {
+            None
+        };
+        let r#type = extract_type_from_status_byte(status_byte).expect("impossible");
+        let message = match r#type.super_type() {
+            FuzzyMessageSuperType::Channel => {
+                self.state = State::ReceivingData {
+                    status_byte,
+                    data_byte_1: None,
+                    is_running: false,
+                };
+                None
+            }
+            FuzzyMessageSuperType::SystemExclusive => {
+                self.state = State::ReceivingSystemExclusive;
+                Some(RawShortMessage::system_exclusive_start())
+            }
+            FuzzyMessageSuperType::SystemCommon => {
+                if r#type.data_byte_count() == 0 {
+                    self.state = Default::default();
+                    Some(build_message((status_byte, U7::MIN, U7::MIN)))
+                } else {
+                    self.state = State::ReceivingData {
+                        status_byte,
+                        data_byte_1: None,
+                        is_running: false,
+                    };
+                    None
+                }
+            }
+            FuzzyMessageSuperType::SystemRealTime => unreachable!(),
+        };
+        ProcessResult { message, error }
+    }
+
+    fn process_data_byte(&mut self, data_byte: U7) -> ProcessResult {
+        match self.state {
+            State::WaitingForStatusByte { error_reported } => {
+                if error_reported {
+                    return ProcessResult::nothing();
+                }
+                self.state = State::WaitingForStatusByte {
+                    error_reported: true,
+                };
+                ProcessResult::error(ShortMessageParserError::UnexpectedDataByte)
+            }
+            State::ReceivingSystemExclusive => ProcessResult::nothing(),
+            State::ReceivingData {
+                status_byte,
+                data_byte_1,
+                ..
+            } => {
+                let r#type = extract_type_from_status_byte(status_byte).expect("impossible");
+                let bytes = match (r#type.data_byte_count(), data_byte_1) {
+                    (2, None) => {
+                        self.state = State::ReceivingData {
+                            status_byte,
+                            data_byte_1: Some(data_byte),
+                            is_running: false,
+                        };
+                        return ProcessResult::nothing();
+                    }
+                    (2, Some(data_byte_1)) => (status_byte, data_byte_1, data_byte),
+                    _ => (status_byte, data_byte, U7::MIN),
+                };
+                self.state = if r#type.super_type() == FuzzyMessageSuperType::Channel {
+                    State::ReceivingData {
+                        status_byte,
+                        data_byte_1: None,
+                        is_running: true,
+                    }
+                } else {
+                    // System Common messages cancel running status.
+                    Default::default()
+                };
+                ProcessResult::message(build_message(bytes))
+            }
+        }
+    }
+}
+
+fn build_message(bytes: (u8, U7, U7)) -> RawShortMessage {
+    // Status bytes reaching this point are always >= 0x80, which makes them valid.
+    unsafe { RawShortMessage::from_bytes_unchecked(bytes) }
+}
+
+/// Iterator over the results of feeding a chunk of bytes to a [`ShortMessageParser`].
+///
+/// [`ShortMessageParser`]: struct.ShortMessageParser.html
+#[derive(Debug)]
+pub struct ParsedShortMessages<'a> {
+    parser: &'a mut ShortMessageParser,
+    bytes: core::slice::Iter<'a, u8>,
+}
+
+impl<'a> Iterator for ParsedShortMessages<'a> {
+    type Item = Result<RawShortMessage, ShortMessageParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(msg) = self.parser.pending_message.take() {
+            return Some(Ok(msg));
+        }
+        for byte in &mut self.bytes {
+            let result = self.parser.process_byte(*byte);
+            if let Some(error) = result.error {
+                self.parser.pending_message = result.message;
+                return Some(Err(error));
+            }
+            if let Some(msg) = result.message {
+                return Some(Ok(msg));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum State {
+    /// No status byte is in effect.
+    WaitingForStatusByte { error_reported: bool },
+    /// A status byte is in effect and we are waiting for data bytes.
+    ReceivingData {
+        status_byte: u8,
+        data_byte_1: Option<U7>,
+        /// `true` if the status byte is just left over from the previous message (running status).
+        is_running: bool,
+    },
+    /// We are in the middle of a System Exclusive message.
+    ReceivingSystemExclusive,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::WaitingForStatusByte {
+            error_reported: false,
+        }
+    }
+}
+
+impl State {
+    fn has_incomplete_message(&self) -> bool {
+        match self {
+            State::ReceivingData {
+                data_byte_1,
+                is_running,
+                ..
+            } => data_byte_1.is_some() || !is_running,
+            _ => false,
+        }
+    }
+}
+
+struct ProcessResult {
+    message: Option<RawShortMessage>,
+    error: Option<ShortMessageParserError>,
+}
+
+impl ProcessResult {
+    fn nothing() -> ProcessResult {
+        ProcessResult {
+            message: None,
+            error: None,
+        }
+    }
+
+    fn message(message: RawShortMessage) -> ProcessResult {
+        ProcessResult {
+            message: Some(message),
+            error: None,
+        }
+    }
+
+    fn error(error: ShortMessageParserError) -> ProcessResult {
+        ProcessResult {
+            message: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use ShortMessageParserError::*;
+
+    fn parse(
+        parser: &mut ShortMessageParser,
+        bytes: &[u8],
+    ) -> Vec<Result<RawShortMessage, ShortMessageParserError>> {
+        parser.feed(bytes).collect()
+    }
+
+    #[test]
+    fn complete_messages() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(
+            &mut parser,
+            &[
+                0x91, 64, 100, 0xC2, 5, 0xF2, 0x01, 0x02, 0xF6, 0xE0, 0x7E, 0x09,
+            ],
+        );
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Ok(note_on(1, 64, 100)),
+                Ok(program_change(2, 5)),
+                Ok(song_position_pointer(0x101)),
+                Ok(tune_request()),
+                Ok(pitch_bend_change(0, 1278)),
+            ]
+        );
+    }
+
+    #[test]
+    fn running_status() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(&mut parser, &[0xB3, 7, 100, 7, 101, 10, 64, 0xD0, 5, 6]);
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Ok(control_change(3, 7, 100)),
+                Ok(control_change(3, 7, 101)),
+                Ok(control_change(3, 10, 64)),
+                Ok(channel_pressure(0, 5)),
+                Ok(channel_pressure(0, 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn messages_split_across_chunks() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result_1 = parse(&mut parser, &[0x90, 64]);
+        let result_2 = parse(&mut parser, &[100, 65]);
+        let result_3 = parse(&mut parser, &[0]);
+        // Then
+        assert_eq!(result_1, vec![]);
+        assert_eq!(result_2, vec![Ok(note_on(0, 64, 100))]);
+        assert_eq!(result_3, vec![Ok(note_on(0, 65, 0))]);
+    }
+
+    #[test]
+    fn interleaved_real_time_messages() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(&mut parser, &[0x90, 0xF8, 64, 0xFA, 100, 0xFE, 65, 0xFC, 0]);
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Ok(timing_clock()),
+                Ok(start()),
+                Ok(note_on(0, 64, 100)),
+                Ok(active_sensing()),
+                Ok(stop()),
+                Ok(note_on(0, 65, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn system_common_cancels_running_status() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(&mut parser, &[0x90, 64, 100, 0xF3, 2, 65, 100, 0x80, 64, 0]);
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Ok(note_on(0, 64, 100)),
+                Ok(song_select(2)),
+                Err(UnexpectedDataByte),
+                Ok(note_off(0, 64, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn system_exclusive() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(
+            &mut parser,
+            &[0xF0, 0x7E, 0x7F, 0xF8, 0x06, 0x01, 0xF7, 0xC0, 1],
+        );
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Ok(system_exclusive_start()),
+                Ok(timing_clock()),
+                Ok(system_exclusive_end()),
+                Ok(program_change(0, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn system_exclusive_aborted_by_status_byte() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(&mut parser, &[0xF0, 0x7E, 0x7F, 0x90, 64, 100]);
+        // Then
+        assert_eq!(
+            result,
+            vec![Ok(system_exclusive_start()), Ok(note_on(0, 64, 100))]
+        );
+    }
+
+    #[test]
+    fn stray_data_bytes() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(&mut parser, &[5, 6, 7, 0xF8, 8, 0x90, 64, 100]);
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Err(UnexpectedDataByte),
+                Ok(timing_clock()),
+                Ok(note_on(0, 64, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_messages() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result = parse(&mut parser, &[0x90, 64, 0xB0, 0xF6, 0xE0, 0, 64]);
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                Err(IncompleteMessage),
+                Err(IncompleteMessage),
+                Ok(tune_request()),
+                Ok(pitch_bend_change(0, 8192)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset() {
+        // Given
+        let mut parser = ShortMessageParser::new();
+        // When
+        let result_1 = parse(&mut parser, &[0x90, 64, 100, 64]);
+        parser.reset();
+        let result_2 = parse(&mut parser, &[0]);
+        // Then
+        assert_eq!(result_1, vec![Ok(note_on(0, 64, 100))]);
+        assert_eq!(result_2, vec![Err(UnexpectedDataByte)]);
+    }
+}
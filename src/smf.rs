@@ -0,0 +1,294 @@
+use crate::{RawShortMessage, TimeCodeType};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+/// Chunk type of the header chunk.
+pub(crate) const HEADER_CHUNK_TYPE: &[u8; 4] = b"MThd";
+
+/// Chunk type of track chunks.
+pub(crate) const TRACK_CHUNK_TYPE: &[u8; 4] = b"MTrk";
+
+/// Length of the header chunk data as defined by the specification.
+pub(crate) const HEADER_CHUNK_LENGTH: u32 = 6;
+
+/// Status byte which introduces a System Exclusive event.
+pub(crate) const SYS_EX_EVENT: u8 = 0xF0;
+
+/// Status byte which introduces a System Exclusive continuation or escape event.
+pub(crate) const SYS_EX_ESCAPE_EVENT: u8 = 0xF7;
+
+/// Status byte which introduces a meta event.
+pub(crate) const META_EVENT: u8 = 0xFF;
+
+/// Meta event type of End of Track events.
+pub(crate) const END_OF_TRACK: u8 = 0x2F;
+
+/// An error which can occur when reading or writing a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, derive_more::Display)]
+pub enum SmfError {
+    /// The data doesn't start with a valid header chunk or the header to be written (e.g. its
+    /// division) can't be encoded.
+    #[display(fmt = "missing or invalid SMF header chunk")]
+    InvalidHeader,
+    /// The header chunk specifies a format other than 0, 1 or 2.
+    #[display(fmt = "unknown SMF format")]
+    UnknownFormat,
+    /// A chunk is longer than the remaining data.
+    #[display(fmt = "truncated SMF chunk")]
+    TruncatedChunk,
+    /// A track chunk ends in the middle of an event.
+    #[display(fmt = "truncated SMF event")]
+    TruncatedEvent,
+    /// A variable-length quantity is longer than 4 bytes or its value is too large to be encoded.
+    #[display(fmt = "invalid variable-length quantity")]
+    InvalidVariableLengthQuantity,
+    /// A data byte appeared without any running status being in effect.
+    #[display(fmt = "data byte without status byte")]
+    MissingRunningStatus,
+    /// A status byte which is not allowed in Standard MIDI Files (System Common or System Real Time
+    /// message).
+    #[display(fmt = "status byte not allowed in SMF")]
+    InvalidStatusByte,
+    /// A channel event contains a status byte where a data byte is expected.
+    #[display(fmt = "invalid data byte in SMF event")]
+    InvalidDataByte,
+    /// An event has been added to a track after its End of Track meta event.
+    #[display(fmt = "SMF event after End of Track")]
+    EventAfterEndOfTrack,
+    /// A track has been added although the format doesn't allow any more tracks.
+    #[display(fmt = "too many SMF tracks")]
+    TooManyTracks,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SmfError {}
+
+/// Format of a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, IntoPrimitive, TryFromPrimitive)]
+#[repr(u16)]
+pub enum SmfFormat {
+    /// Format 0: A single multi-channel track.
+    SingleTrack = 0,
+    /// Format 1: One or more simultaneous tracks of a sequence.
+    MultiTrack = 1,
+    /// Format 2: One or more sequentially independent single-track patterns.
+    MultiSong = 2,
+}
+
+/// Meaning of delta times in a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SmfDivision {
+    /// Delta times are metrical, expressed in ticks per quarter note (1 to 32767).
+    TicksPerQuarterNote(u16),
+    /// Delta times are time-code-based, expressed in subdivisions of a SMPTE frame (at least 1
+    /// tick per frame).
+    Smpte {
+        time_code_type: TimeCodeType,
+        ticks_per_frame: u8,
+    },
+}
+
+impl SmfDivision {
+    pub(crate) fn from_u16(value: u16) -> Result<SmfDivision, SmfError> {
+        if value & 0x8000 == 0 {
+            if value == 0 {
+                return Err(SmfError::InvalidHeader);
+            }
+            return Ok(SmfDivision::TicksPerQuarterNote(value));
+        }
+        let time_code_type = match ((value >> 8) as u8 as i8).wrapping_neg() {
+            24 => TimeCodeType::Fps24,
+            25 => TimeCodeType::Fps25,
+            29 => TimeCodeType::Fps30DropFrame,
+            30 => TimeCodeType::Fps30NonDrop,
+            _ => return Err(SmfError::InvalidHeader),
+        };
+        let ticks_per_frame = value as u8;
+        if ticks_per_frame == 0 {
+            return Err(SmfError::InvalidHeader);
+        }
+        Ok(SmfDivision::Smpte {
+            time_code_type,
+            ticks_per_frame,
+        })
+    }
+}
+
+/// The information contained in the header chunk of a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SmfHeader {
+    format: SmfFormat,
+    track_count: u16,
+    division: SmfDivision,
+}
+
+impl SmfHeader {
+    /// Creates a header.
+    pub fn new(format: SmfFormat, track_count: u16, division: SmfDivision) -> SmfHeader {
+        SmfHeader {
+            format,
+            track_count,
+            division,
+        }
+    }
+
+    /// Returns the format.
+    pub fn format(&self) -> SmfFormat {
+        self.format
+    }
+
+    /// Returns the number of track chunks as announced by the header.
+    pub fn track_count(&self) -> u16 {
+        self.track_count
+    }
+
+    /// Returns the meaning of delta times.
+    pub fn division(&self) -> SmfDivision {
+        self.division
+    }
+}
+
+/// An event within a track of a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SmfEvent<'a> {
+    delta_time: u32,
+    kind: SmfEventKind<'a>,
+}
+
+impl<'a> SmfEvent<'a> {
+    /// Creates an event which occurs the given number of ticks after the previous event of the
+    /// track.
+    pub fn new(delta_time: u32, kind: SmfEventKind<'a>) -> SmfEvent<'a> {
+        SmfEvent { delta_time, kind }
+    }
+
+    /// Returns the number of ticks since the previous event of the track.
+    pub fn delta_time(&self) -> u32 {
+        self.delta_time
+    }
+
+    /// Returns the actual event.
+    pub fn kind(&self) -> SmfEventKind<'a> {
+        self.kind
+    }
+}
+
+/// The different kinds of events within a track of a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SmfEventKind<'a> {
+    /// A channel message.
+    Short(RawShortMessage),
+    /// A System Exclusive message (or the first packet of it) introduced by 0xF0.
+    ///
+    /// Contains the bytes following 0xF0, usually ending with 0xF7.
+    SysEx(&'a [u8]),
+    /// A System Exclusive continuation packet or arbitrary bytes to be transmitted as they are,
+    /// introduced by 0xF7.
+    SysExEscape(&'a [u8]),
+    /// A meta event.
+    Meta {
+        /// Type of the meta event (e.g. 0x2F for End of Track).
+        meta_type: u8,
+        data: &'a [u8],
+    },
+}
+
+/// Reads a variable-length quantity from the beginning of the given bytes.
+///
+/// Returns the value and the number of bytes it occupied.
+pub(crate) fn read_variable_length_quantity(bytes: &[u8]) -> Result<(u32, usize), SmfError> {
+    let mut value: u32 = 0;
+    for (i, b) in bytes.iter().enumerate() {
+        if i == 4 {
+            return Err(SmfError::InvalidVariableLengthQuantity);
+        }
+        value = (value << 7) | u32::from(b & 0x7F);
+        if b & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    if bytes.len() >= 4 {
+        Err(SmfError::InvalidVariableLengthQuantity)
+    } else {
+        Err(SmfError::TruncatedEvent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variable_length_quantities() {
+        // Given
+        let cases: [(u32, &[u8]); 8] = [
+            (0, &[0x00]),
+            (0x40, &[0x40]),
+            (0x7F, &[0x7F]),
+            (0x80, &[0x81, 0x00]),
+            (0x2000, &[0xC0, 0x00]),
+            (0x3FFF, &[0xFF, 0x7F]),
+            (0x10_0000, &[0xC0, 0x80, 0x00]),
+            (0x0FFF_FFFF, &[0xFF, 0xFF, 0xFF, 0x7F]),
+        ];
+        // When
+        // Then
+        for (value, bytes) in cases.iter() {
+            assert_eq!(
+                read_variable_length_quantity(bytes),
+                Ok((*value, bytes.len()))
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_variable_length_quantities() {
+        // Given
+        // When
+        // Then
+        assert_eq!(
+            read_variable_length_quantity(&[0x81, 0x81, 0x81, 0x81, 0x01]),
+            Err(SmfError::InvalidVariableLengthQuantity)
+        );
+        assert_eq!(
+            read_variable_length_quantity(&[0x81, 0x81]),
+            Err(SmfError::TruncatedEvent)
+        );
+    }
+
+    #[test]
+    fn divisions() {
+        // Given
+        let cases = [
+            (0x0060, SmfDivision::TicksPerQuarterNote(96)),
+            (
+                0xE250,
+                SmfDivision::Smpte {
+                    time_code_type: TimeCodeType::Fps30NonDrop,
+                    ticks_per_frame: 80,
+                },
+            ),
+            (
+                0xE304,
+                SmfDivision::Smpte {
+                    time_code_type: TimeCodeType::Fps30DropFrame,
+                    ticks_per_frame: 4,
+                },
+            ),
+            (
+                0xE728,
+                SmfDivision::Smpte {
+                    time_code_type: TimeCodeType::Fps25,
+                    ticks_per_frame: 40,
+                },
+            ),
+        ];
+        // When
+        // Then
+        for (value, division) in cases.iter() {
+            assert_eq!(SmfDivision::from_u16(*value), Ok(*division));
+        }
+        assert_eq!(SmfDivision::from_u16(0xE000), Err(SmfError::InvalidHeader));
+        assert_eq!(SmfDivision::from_u16(0), Err(SmfError::InvalidHeader));
+        assert_eq!(SmfDivision::from_u16(0xE700), Err(SmfError::InvalidHeader));
+    }
+}
@@ -0,0 +1,510 @@
+use crate::{
+    extract_type_from_status_byte, read_variable_length_quantity, RawShortMessage,
+    ShortMessageFactory, SmfDivision, SmfError, SmfEvent, SmfEventKind, SmfFormat, SmfHeader,
+    END_OF_TRACK, HEADER_CHUNK_LENGTH, HEADER_CHUNK_TYPE, META_EVENT, SYS_EX_ESCAPE_EVENT,
+    SYS_EX_EVENT, TRACK_CHUNK_TYPE, U7,
+};
+use core::convert::TryFrom;
+
+/// Reader for Standard MIDI Files (formats 0, 1 and 2).
+///
+/// The reader works directly on the file content without copying or allocating anything. Channel
+/// events are returned as [`RawShortMessage`]s, System Exclusive and meta events as slices of the
+/// file content. Running status is resolved.
+///
+/// Chunks of unknown type are skipped as demanded by the specification. Errors are reported as
+/// soon as they are encountered, events read before remain valid.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::note_on;
+/// use helgoboss_midi::{SmfDivision, SmfEvent, SmfEventKind, SmfFormat, SmfReader};
+///
+/// let bytes = [
+///     // Header chunk
+///     b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0, 96,
+///     // Track chunk
+///     b'M', b'T', b'r', b'k', 0, 0, 0, 11,
+///     0x00, 0x90, 64, 100,     // Note On
+///     0x60, 64, 0,             // Note On with running status after 96 ticks
+///     0x00, 0xFF, 0x2F, 0x00,  // End of Track
+/// ];
+/// let reader = SmfReader::new(&bytes).unwrap();
+/// assert_eq!(reader.header().format(), SmfFormat::SingleTrack);
+/// assert_eq!(reader.header().division(), SmfDivision::TicksPerQuarterNote(96));
+/// let track = reader.tracks().next().unwrap().unwrap();
+/// let events: Result<Vec<_>, _> = track.events().collect();
+/// assert_eq!(
+///     events.unwrap(),
+///     vec![
+///         SmfEvent::new(0, SmfEventKind::Short(note_on(0, 64, 100))),
+///         SmfEvent::new(96, SmfEventKind::Short(note_on(0, 64, 0))),
+///         SmfEvent::new(0, SmfEventKind::Meta { meta_type: 0x2F, data: &[] }),
+///     ]
+/// );
+/// ```
+///
+/// [`RawShortMessage`]: struct.RawShortMessage.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SmfReader<'a> {
+    header: SmfHeader,
+    /// All chunks following the header chunk.
+    chunks: &'a [u8],
+}
+
+impl<'a> SmfReader<'a> {
+    /// Creates a reader for the given file content and reads the header chunk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content doesn't start with a valid header chunk.
+    pub fn new(bytes: &'a [u8]) -> Result<SmfReader<'a>, SmfError> {
+        if !bytes.starts_with(HEADER_CHUNK_TYPE) {
+            return Err(SmfError::InvalidHeader);
+        }
+        let (_, data, chunks) = read_chunk(bytes)?.expect("impossible");
+        if data.len() < HEADER_CHUNK_LENGTH as usize {
+            return Err(SmfError::InvalidHeader);
+        }
+        let read_u16 = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        let format = SmfFormat::try_from(read_u16(0)).map_err(|_| SmfError::UnknownFormat)?;
+        let header = SmfHeader::new(format, read_u16(2), SmfDivision::from_u16(read_u16(4))?);
+        Ok(SmfReader { header, chunks })
+    }
+
+    /// Returns the information contained in the header chunk.
+    pub fn header(&self) -> SmfHeader {
+        self.header
+    }
+
+    /// Returns an iterator over all track chunks.
+    ///
+    /// The iteration ends after the first error.
+    pub fn tracks(&self) -> SmfTracks<'a> {
+        SmfTracks {
+            remaining: self.chunks,
+        }
+    }
+}
+
+/// An iterator over the track chunks of a Standard MIDI File, returned by [`SmfReader::tracks`].
+///
+/// [`SmfReader::tracks`]: struct.SmfReader.html#method.tracks
+#[derive(Clone, Debug)]
+pub struct SmfTracks<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for SmfTracks<'a> {
+    type Item = Result<SmfTrack<'a>, SmfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (chunk_type, data, remaining) = match read_chunk(self.remaining) {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => return None,
+                Err(e) => {
+                    self.remaining = &[];
+                    return Some(Err(e));
+                }
+            };
+            self.remaining = remaining;
+            if chunk_type == TRACK_CHUNK_TYPE {
+                return Some(Ok(SmfTrack { data }));
+            }
+        }
+    }
+}
+
+/// A track chunk of a Standard MIDI File.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SmfTrack<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SmfTrack<'a> {
+    /// Returns the raw content of this track chunk (without chunk type and length).
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns an iterator over all events of this track.
+    ///
+    /// The iteration ends after the End of Track meta event, at the end of the chunk or after the
+    /// first error.
+    pub fn events(&self) -> SmfEvents<'a> {
+        SmfEvents {
+            remaining: self.data,
+            running_status: None,
+        }
+    }
+}
+
+/// An iterator over the events of a track, returned by [`SmfTrack::events`].
+///
+/// [`SmfTrack::events`]: struct.SmfTrack.html#method.events
+#[derive(Clone, Debug)]
+pub struct SmfEvents<'a> {
+    remaining: &'a [u8],
+    running_status: Option<u8>,
+}
+
+impl<'a> SmfEvents<'a> {
+    fn read_event(&mut self) -> Result<SmfEvent<'a>, SmfError> {
+        let (delta_time, len) = read_variable_length_quantity(self.remaining)?;
+        let bytes = &self.remaining[len..];
+        let (&first_byte, bytes) = bytes.split_first().ok_or(SmfError::TruncatedEvent)?;
+        let (kind, remaining) = match first_byte {
+            META_EVENT => {
+                self.running_status = None;
+                let (&meta_type, bytes) = bytes.split_first().ok_or(SmfError::TruncatedEvent)?;
+                let (data, remaining) = read_length_prefixed_data(bytes)?;
+                (SmfEventKind::Meta { meta_type, data }, remaining)
+            }
+            SYS_EX_EVENT | SYS_EX_ESCAPE_EVENT => {
+                self.running_status = None;
+                let (data, remaining) = read_length_prefixed_data(bytes)?;
+                let kind = if first_byte == SYS_EX_EVENT {
+                    SmfEventKind::SysEx(data)
+                } else {
+                    SmfEventKind::SysExEscape(data)
+                };
+                (kind, remaining)
+            }
+            0xF1..=0xFE => return Err(SmfError::InvalidStatusByte),
+            0x80..=0xEF => {
+                self.running_status = Some(first_byte);
+                let (msg, remaining) = read_channel_message(first_byte, bytes)?;
+                (SmfEventKind::Short(msg), remaining)
+            }
+            _ => {
+                let status_byte = self.running_status.ok_or(SmfError::MissingRunningStatus)?;
+                // The first byte is already the first data byte
+                let (msg, remaining) = read_channel_message(status_byte, &self.remaining[len..])?;
+                (SmfEventKind::Short(msg), remaining)
+            }
+        };
+        self.remaining = remaining;
+        Ok(SmfEvent::new(delta_time, kind))
+    }
+}
+
+impl<'a> Iterator for SmfEvents<'a> {
+    type Item = Result<SmfEvent<'a>, SmfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        let result = self.read_event();
+        let is_last = match &result {
+            Ok(event) => matches!(
+                event.kind(),
+                SmfEventKind::Meta {
+                    meta_type: END_OF_TRACK,
+                    ..
+                }
+            ),
+            Err(_) => true,
+        };
+        if is_last {
+            self.remaining = &[];
+        }
+        Some(result)
+    }
+}
+
+/// Chunk type, chunk data and the bytes following the chunk.
+type Chunk<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/// Reads the chunk at the beginning of the given bytes.
+///
+/// Returns `None` if there are no bytes.
+fn read_chunk(bytes: &[u8]) -> Result<Option<Chunk<'_>>, SmfError> {
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    if bytes.len() < 8 {
+        return Err(SmfError::TruncatedChunk);
+    }
+    let length = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let bytes_after_length = &bytes[8..];
+    if length > bytes_after_length.len() {
+        return Err(SmfError::TruncatedChunk);
+    }
+    let (data, remaining) = bytes_after_length.split_at(length);
+    Ok(Some((&bytes[..4], data, remaining)))
+}
+
+/// Reads data preceded by its length (variable-length quantity).
+///
+/// Returns the data and the remaining bytes.
+fn read_length_prefixed_data(bytes: &[u8]) -> Result<(&[u8], &[u8]), SmfError> {
+    let (length, len) = read_variable_length_quantity(bytes)?;
+    let bytes = &bytes[len..];
+    if length as usize > bytes.len() {
+        return Err(SmfError::TruncatedEvent);
+    }
+    Ok(bytes.split_at(length as usize))
+}
+
+/// Reads the data bytes of a channel message with the given status byte.
+///
+/// Returns the message and the remaining bytes.
+fn read_channel_message(
+    status_byte: u8,
+    bytes: &[u8],
+) -> Result<(RawShortMessage, &[u8]), SmfError> {
+    let r#type = extract_type_from_status_byte(status_byte).expect("impossible");
+    let count = usize::from(r#type.data_byte_count());
+    if bytes.len() < count {
+        return Err(SmfError::TruncatedEvent);
+    }
+    let (data_bytes, remaining) = bytes.split_at(count);
+    if data_bytes.iter().any(|b| *b >= 0x80) {
+        return Err(SmfError::InvalidDataByte);
+    }
+    let data_byte = |i: usize| U7(data_bytes.get(i).copied().unwrap_or(0));
+    // Status bytes reaching this point are channel status bytes, which makes them valid.
+    let msg =
+        unsafe { RawShortMessage::from_bytes_unchecked((status_byte, data_byte(0), data_byte(1))) };
+    Ok((msg, remaining))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{control_change, note_off, note_on, program_change};
+    use crate::TimeCodeType;
+
+    fn file(header: &[u8], chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut bytes = vec![b'M', b'T', b'h', b'd', 0, 0, 0, header.len() as u8];
+        bytes.extend_from_slice(header);
+        for (chunk_type, data) in chunks {
+            bytes.extend_from_slice(*chunk_type);
+            bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(data);
+        }
+        bytes
+    }
+
+    fn read_events(track: SmfTrack) -> Vec<Result<SmfEvent, SmfError>> {
+        track.events().collect()
+    }
+
+    #[test]
+    fn format_1() {
+        // Given
+        let tempo_track: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Set Tempo
+            0x00, 0xFF, 0x2F, 0x00, // End of Track
+        ];
+        let note_track: &[u8] = &[
+            0x00, 0xC1, 5, // Program Change
+            0x00, 0x91, 60, 100, // Note On
+            0x83, 0x00, 0x81, 60, 64, // Note Off after 384 ticks
+            0x00, 0xB1, 7, 127, // Control Change
+            0x00, 0xFF, 0x2F, 0x00, // End of Track
+        ];
+        let bytes = file(
+            &[0, 1, 0, 2, 0x01, 0x80],
+            &[(b"MTrk", tempo_track), (b"MTrk", note_track)],
+        );
+        // When
+        let reader = SmfReader::new(&bytes).unwrap();
+        let tracks: Vec<_> = reader.tracks().map(|t| t.unwrap()).collect();
+        // Then
+        assert_eq!(
+            reader.header(),
+            SmfHeader::new(
+                SmfFormat::MultiTrack,
+                2,
+                SmfDivision::TicksPerQuarterNote(384)
+            )
+        );
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[1].data(), note_track);
+        assert_eq!(
+            read_events(tracks[0]),
+            vec![
+                Ok(SmfEvent::new(
+                    0,
+                    SmfEventKind::Meta {
+                        meta_type: 0x51,
+                        data: &[0x07, 0xA1, 0x20]
+                    }
+                )),
+                Ok(SmfEvent::new(
+                    0,
+                    SmfEventKind::Meta {
+                        meta_type: 0x2F,
+                        data: &[]
+                    }
+                )),
+            ]
+        );
+        assert_eq!(
+            read_events(tracks[1]),
+            vec![
+                Ok(SmfEvent::new(0, SmfEventKind::Short(program_change(1, 5)))),
+                Ok(SmfEvent::new(0, SmfEventKind::Short(note_on(1, 60, 100)))),
+                Ok(SmfEvent::new(384, SmfEventKind::Short(note_off(1, 60, 64)))),
+                Ok(SmfEvent::new(
+                    0,
+                    SmfEventKind::Short(control_change(1, 7, 127))
+                )),
+                Ok(SmfEvent::new(
+                    0,
+                    SmfEventKind::Meta {
+                        meta_type: 0x2F,
+                        data: &[]
+                    }
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_2_with_smpte_division_and_unknown_chunk() {
+        // Given
+        let track: &[u8] = &[0x00, 0xFF, 0x2F, 0x00];
+        let bytes = file(
+            &[0, 2, 0, 2, 0xE7, 0x28, 0xAA],
+            &[(b"MTrk", track), (b"XYZW", &[1, 2, 3]), (b"MTrk", track)],
+        );
+        // When
+        let reader = SmfReader::new(&bytes).unwrap();
+        let tracks: Vec<_> = reader.tracks().collect();
+        // Then
+        assert_eq!(
+            reader.header(),
+            SmfHeader::new(
+                SmfFormat::MultiSong,
+                2,
+                SmfDivision::Smpte {
+                    time_code_type: TimeCodeType::Fps25,
+                    ticks_per_frame: 40
+                }
+            )
+        );
+        assert_eq!(tracks, vec![Ok(SmfTrack { data: track }); 2]);
+    }
+
+    #[test]
+    fn sys_ex_events() {
+        // Given
+        let track: &[u8] = &[
+            0x00, 0x90, 60, 100, // Note On
+            0x00, 0xF0, 0x03, 0x43, 0x12, 0x00, // First SysEx packet
+            0x10, 0xF7, 0x02, 0x07, 0xF7, // SysEx continuation packet
+            0x00, 0xF7, 0x01, 0xF8, // Escape sequence
+            0x00, 0x90, 60, 0, // Note On (running status has been cancelled)
+        ];
+        let bytes = file(&[0, 0, 0, 1, 0, 96], &[(b"MTrk", track)]);
+        let reader = SmfReader::new(&bytes).unwrap();
+        // When
+        let events = read_events(reader.tracks().next().unwrap().unwrap());
+        // Then
+        assert_eq!(
+            events,
+            vec![
+                Ok(SmfEvent::new(0, SmfEventKind::Short(note_on(0, 60, 100)))),
+                Ok(SmfEvent::new(0, SmfEventKind::SysEx(&[0x43, 0x12, 0x00]))),
+                Ok(SmfEvent::new(16, SmfEventKind::SysExEscape(&[0x07, 0xF7]))),
+                Ok(SmfEvent::new(0, SmfEventKind::SysExEscape(&[0xF8]))),
+                Ok(SmfEvent::new(0, SmfEventKind::Short(note_on(0, 60, 0)))),
+            ]
+        );
+    }
+
+    #[test]
+    fn events_after_end_of_track_are_ignored() {
+        // Given
+        let track: &[u8] = &[0x00, 0xFF, 0x2F, 0x00, 0x00, 0x90, 60, 100];
+        let bytes = file(&[0, 0, 0, 1, 0, 96], &[(b"MTrk", track)]);
+        let reader = SmfReader::new(&bytes).unwrap();
+        // When
+        let events = read_events(reader.tracks().next().unwrap().unwrap());
+        // Then
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn invalid_headers() {
+        // Given
+        let unknown_format = file(&[0, 3, 0, 1, 0, 96], &[]);
+        let short_header = file(&[0, 0, 0, 1], &[]);
+        let not_a_header = file(&[0, 0, 0, 1, 0, 96], &[]).split_off(1);
+        let mut truncated = file(&[0, 0, 0, 1, 0, 96], &[]);
+        truncated.pop();
+        let zero_division = file(&[0, 0, 0, 1, 0, 0], &[]);
+        // When
+        // Then
+        assert_eq!(
+            SmfReader::new(&unknown_format[..]),
+            Err(SmfError::UnknownFormat)
+        );
+        assert_eq!(
+            SmfReader::new(&short_header[..]),
+            Err(SmfError::InvalidHeader)
+        );
+        assert_eq!(SmfReader::new(&not_a_header), Err(SmfError::InvalidHeader));
+        assert_eq!(SmfReader::new(&truncated), Err(SmfError::TruncatedChunk));
+        assert_eq!(SmfReader::new(&[]), Err(SmfError::InvalidHeader));
+        assert_eq!(SmfReader::new(&zero_division), Err(SmfError::InvalidHeader));
+    }
+
+    #[test]
+    fn truncated_chunk() {
+        // Given
+        let mut bytes = file(
+            &[0, 1, 0, 2, 0, 96],
+            &[
+                (b"MTrk", &[0x00, 0xFF, 0x2F, 0x00]),
+                (b"MTrk", &[0x00, 0x90, 60, 100]),
+            ],
+        );
+        bytes.pop();
+        let reader = SmfReader::new(&bytes).unwrap();
+        // When
+        let tracks: Vec<_> = reader.tracks().collect();
+        // Then
+        assert_eq!(tracks.len(), 2);
+        assert!(tracks[0].is_ok());
+        assert_eq!(tracks[1], Err(SmfError::TruncatedChunk));
+    }
+
+    #[test]
+    fn invalid_events() {
+        // Given
+        let tracks: [(&[u8], SmfError); 7] = [
+            (&[0x00, 0x90, 60], SmfError::TruncatedEvent),
+            (&[0x00, 0xFF, 0x01, 0x05, b'a'], SmfError::TruncatedEvent),
+            (&[0x00, 0xFF], SmfError::TruncatedEvent),
+            (&[0x81, 0x81], SmfError::TruncatedEvent),
+            (&[0x00, 60, 100], SmfError::MissingRunningStatus),
+            (&[0x00, 0xF8], SmfError::InvalidStatusByte),
+            (&[0x00, 0x90, 60, 0x90], SmfError::InvalidDataByte),
+        ];
+        // When
+        // Then
+        for (data, error) in tracks.iter() {
+            let track = SmfTrack { data };
+            assert_eq!(read_events(track), vec![Err(*error)]);
+        }
+    }
+
+    #[test]
+    fn running_status_cancelled_by_meta_event() {
+        // Given
+        let track = SmfTrack {
+            data: &[0x00, 0x90, 60, 100, 0x00, 0xFF, 0x01, 0x00, 0x00, 60, 0],
+        };
+        // When
+        let events = read_events(track);
+        // Then
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2], Err(SmfError::MissingRunningStatus));
+    }
+}
@@ -0,0 +1,496 @@
+use crate::{
+    FuzzyMessageSuperType, MetaEvent, ShortMessage, ShortMessageEncoder, SmfDivision, SmfError,
+    SmfEvent, SmfEventKind, SmfFormat, SysExMessage, TimeCodeType, END_OF_TRACK,
+    HEADER_CHUNK_LENGTH, HEADER_CHUNK_TYPE, META_EVENT, SYS_EX_ESCAPE_EVENT, SYS_EX_EVENT,
+    TRACK_CHUNK_TYPE,
+};
+use std::io;
+use std::io::Write;
+
+/// Largest value which can be encoded as variable-length quantity.
+const MAX_VARIABLE_LENGTH_QUANTITY: u32 = 0x0FFF_FFFF;
+
+/// Builder for the events of a track of a Standard MIDI File.
+///
+/// Events are added in chronological order, each one with the number of ticks since the previous
+/// event. An End of Track meta event is added automatically when writing the file unless the track
+/// has been ended explicitly.
+///
+/// # Example
+///
+/// See [`SmfWriter`].
+///
+/// [`SmfWriter`]: struct.SmfWriter.html
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SmfTrackBuilder {
+    data: Vec<u8>,
+    encoder: ShortMessageEncoder,
+    ended: bool,
+}
+
+impl SmfTrackBuilder {
+    /// Creates an empty track.
+    ///
+    /// If `use_running_status` is `true`, status bytes of channel events are omitted whenever
+    /// possible.
+    pub fn new(use_running_status: bool) -> SmfTrackBuilder {
+        SmfTrackBuilder {
+            data: vec![],
+            encoder: ShortMessageEncoder::new(use_running_status),
+            ended: false,
+        }
+    }
+
+    /// Adds the given channel message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a channel message, if the delta time is too large or
+    /// if the track has already been ended.
+    pub fn push_short(&mut self, delta_time: u32, msg: &impl ShortMessage) -> Result<(), SmfError> {
+        if msg.r#type().super_type() != FuzzyMessageSuperType::Channel {
+            return Err(SmfError::InvalidStatusByte);
+        }
+        self.push_delta_time(delta_time)?;
+        let mut buffer = [0; 3];
+        let count = self
+            .encoder
+            .encode(msg, &mut buffer)
+            .expect("buffer too small");
+        self.data.extend_from_slice(&buffer[..count]);
+        Ok(())
+    }
+
+    /// Adds the given complete System Exclusive message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delta time is too large or if the track has already been ended.
+    pub fn push_sys_ex(&mut self, delta_time: u32, msg: SysExMessage<'_>) -> Result<(), SmfError> {
+        self.push_length_prefixed_event(delta_time, &[SYS_EX_EVENT], &msg.as_bytes()[1..])
+    }
+
+    /// Adds a meta event with the given type and data.
+    ///
+    /// Adding an End of Track meta event (type 0x2F) ends the track.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delta time is too large or if the track has already been ended.
+    pub fn push_meta(
+        &mut self,
+        delta_time: u32,
+        meta_type: u8,
+        data: &[u8],
+    ) -> Result<(), SmfError> {
+        self.push_length_prefixed_event(delta_time, &[META_EVENT, meta_type], data)?;
+        if meta_type == END_OF_TRACK {
+            self.ended = true;
+        }
+        Ok(())
+    }
+
+    /// Adds the given typed meta event.
+    ///
+    /// Adding an End of Track meta event ends the track.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delta time is too large or if the track has already been ended.
+    pub fn push_meta_event(
+        &mut self,
+        delta_time: u32,
+        event: &MetaEvent<'_>,
+    ) -> Result<(), SmfError> {
+        let mut data = vec![0; event.data_len()];
+        event.write_data(&mut data).expect("buffer too small");
+        self.push_meta(delta_time, event.meta_type(), &data)
+    }
+
+    /// Adds the given event, e.g. as returned by [`SmfReader`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event is a non-channel short message, if the delta time is too
+    /// large or if the track has already been ended.
+    ///
+    /// [`SmfReader`]: struct.SmfReader.html
+    pub fn push(&mut self, event: &SmfEvent<'_>) -> Result<(), SmfError> {
+        let delta_time = event.delta_time();
+        match event.kind() {
+            SmfEventKind::Short(msg) => self.push_short(delta_time, &msg),
+            SmfEventKind::SysEx(data) => {
+                self.push_length_prefixed_event(delta_time, &[SYS_EX_EVENT], data)
+            }
+            SmfEventKind::SysExEscape(data) => {
+                self.push_length_prefixed_event(delta_time, &[SYS_EX_ESCAPE_EVENT], data)
+            }
+            SmfEventKind::Meta { meta_type, data } => self.push_meta(delta_time, meta_type, data),
+        }
+    }
+
+    /// Ends the track by adding an End of Track meta event.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delta time is too large or if the track has already been ended.
+    pub fn end(&mut self, delta_time: u32) -> Result<(), SmfError> {
+        self.push_meta(delta_time, END_OF_TRACK, &[])
+    }
+
+    /// Returns whether the track has been ended.
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Returns the content of the track chunk written so far.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn push_length_prefixed_event(
+        &mut self,
+        delta_time: u32,
+        prefix: &[u8],
+        data: &[u8],
+    ) -> Result<(), SmfError> {
+        if data.len() > MAX_VARIABLE_LENGTH_QUANTITY as usize {
+            return Err(SmfError::InvalidVariableLengthQuantity);
+        }
+        self.push_delta_time(delta_time)?;
+        // Running status is cancelled by System Exclusive and meta events
+        self.encoder.reset();
+        self.data.extend_from_slice(prefix);
+        self.data
+            .extend_from_slice(encode_variable_length_quantity(data.len() as u32)?.as_slice());
+        self.data.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn push_delta_time(&mut self, delta_time: u32) -> Result<(), SmfError> {
+        if self.ended {
+            return Err(SmfError::EventAfterEndOfTrack);
+        }
+        let vlq = encode_variable_length_quantity(delta_time)?;
+        self.data.extend_from_slice(vlq.as_slice());
+        Ok(())
+    }
+}
+
+/// Writer for Standard MIDI Files (formats 0, 1 and 2).
+///
+/// Writing a file read by [`SmfReader`] event by event results in an identical file, provided that
+/// the original file uses running status whenever possible (or never, if running status is
+/// disabled), contains no unknown chunks and ends each track with an End of Track meta event.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{note_off, note_on};
+/// use helgoboss_midi::{SmfDivision, SmfFormat, SmfTrackBuilder, SmfWriter};
+///
+/// let mut track = SmfTrackBuilder::new(true);
+/// track.push_short(0, &note_on(0, 64, 100)).unwrap();
+/// track.push_short(96, &note_off(0, 64, 0)).unwrap();
+/// let mut writer =
+///     SmfWriter::new(SmfFormat::SingleTrack, SmfDivision::TicksPerQuarterNote(96)).unwrap();
+/// writer.add_track(track).unwrap();
+/// let mut bytes = vec![];
+/// writer.write(&mut bytes).unwrap();
+/// assert_eq!(
+///     bytes,
+///     vec![
+///         b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0, 96,
+///         b'M', b'T', b'r', b'k', 0, 0, 0, 12,
+///         0x00, 0x90, 64, 100,
+///         0x60, 0x80, 64, 0,
+///         0x00, 0xFF, 0x2F, 0x00,
+///     ]
+/// );
+/// ```
+///
+/// [`SmfReader`]: struct.SmfReader.html
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SmfWriter {
+    format: SmfFormat,
+    division: SmfDivision,
+    tracks: Vec<SmfTrackBuilder>,
+}
+
+impl SmfWriter {
+    /// Creates a writer for a file without any tracks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the division can't be encoded, that is, if the ticks per quarter note
+    /// are not within 1 to 32767 or if the ticks per frame are 0.
+    pub fn new(format: SmfFormat, division: SmfDivision) -> Result<SmfWriter, SmfError> {
+        let is_valid = match division {
+            SmfDivision::TicksPerQuarterNote(ticks) => ticks > 0 && ticks <= 0x7FFF,
+            SmfDivision::Smpte {
+                ticks_per_frame, ..
+            } => ticks_per_frame > 0,
+        };
+        if !is_valid {
+            return Err(SmfError::InvalidHeader);
+        }
+        let writer = SmfWriter {
+            format,
+            division,
+            tracks: vec![],
+        };
+        Ok(writer)
+    }
+
+    /// Adds the given track.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the format doesn't allow any more tracks.
+    pub fn add_track(&mut self, track: SmfTrackBuilder) -> Result<(), SmfError> {
+        let max_track_count = match self.format {
+            SmfFormat::SingleTrack => 1,
+            SmfFormat::MultiTrack | SmfFormat::MultiSong => usize::from(u16::MAX),
+        };
+        if self.tracks.len() >= max_track_count {
+            return Err(SmfError::TooManyTracks);
+        }
+        self.tracks.push(track);
+        Ok(())
+    }
+
+    /// Writes the complete file.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(HEADER_CHUNK_TYPE)?;
+        writer.write_all(&HEADER_CHUNK_LENGTH.to_be_bytes())?;
+        writer.write_all(&u16::from(self.format).to_be_bytes())?;
+        writer.write_all(&(self.tracks.len() as u16).to_be_bytes())?;
+        writer.write_all(&encode_division(self.division).to_be_bytes())?;
+        let end_of_track = [0x00, META_EVENT, END_OF_TRACK, 0x00];
+        for track in &self.tracks {
+            let end_of_track: &[u8] = if track.is_ended() { &[] } else { &end_of_track };
+            let length = track.data().len() + end_of_track.len();
+            if length > u32::MAX as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "track chunk too large",
+                ));
+            }
+            writer.write_all(TRACK_CHUNK_TYPE)?;
+            writer.write_all(&(length as u32).to_be_bytes())?;
+            writer.write_all(track.data())?;
+            writer.write_all(end_of_track)?;
+        }
+        Ok(())
+    }
+}
+
+/// A variable-length quantity ready to be written.
+struct VariableLengthQuantity {
+    buffer: [u8; 4],
+    len: usize,
+}
+
+impl VariableLengthQuantity {
+    fn as_slice(&self) -> &[u8] {
+        &self.buffer[4 - self.len..]
+    }
+}
+
+fn encode_variable_length_quantity(value: u32) -> Result<VariableLengthQuantity, SmfError> {
+    if value > MAX_VARIABLE_LENGTH_QUANTITY {
+        return Err(SmfError::InvalidVariableLengthQuantity);
+    }
+    let mut buffer = [0u8; 4];
+    let mut i = 3;
+    let mut remaining = value;
+    buffer[i] = (remaining & 0x7F) as u8;
+    remaining >>= 7;
+    while remaining > 0 {
+        i -= 1;
+        buffer[i] = 0x80 | (remaining & 0x7F) as u8;
+        remaining >>= 7;
+    }
+    Ok(VariableLengthQuantity { buffer, len: 4 - i })
+}
+
+fn encode_division(division: SmfDivision) -> u16 {
+    match division {
+        SmfDivision::TicksPerQuarterNote(ticks) => ticks,
+        SmfDivision::Smpte {
+            time_code_type,
+            ticks_per_frame,
+        } => {
+            let frames_per_second: i8 = match time_code_type {
+                TimeCodeType::Fps24 => 24,
+                TimeCodeType::Fps25 => 25,
+                TimeCodeType::Fps30DropFrame => 29,
+                TimeCodeType::Fps30NonDrop => 30,
+            };
+            (u16::from((-frames_per_second) as u8) << 8) | u16::from(ticks_per_frame)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{control_change, note_on, program_change, timing_clock};
+    use crate::{ManufacturerId, OwnedSysExMessage, SmfReader};
+
+    fn round_trip(bytes: &[u8], use_running_status: bool) -> Vec<u8> {
+        let reader = SmfReader::new(bytes).unwrap();
+        let header = reader.header();
+        let mut writer = SmfWriter::new(header.format(), header.division()).unwrap();
+        for track in reader.tracks() {
+            let mut builder = SmfTrackBuilder::new(use_running_status);
+            for event in track.unwrap().events() {
+                builder.push(&event.unwrap()).unwrap();
+            }
+            writer.add_track(builder).unwrap();
+        }
+        let mut result = vec![];
+        writer.write(&mut result).unwrap();
+        result
+    }
+
+    #[test]
+    fn invalid_division() {
+        // Given
+        let divisions = [
+            SmfDivision::TicksPerQuarterNote(0),
+            SmfDivision::TicksPerQuarterNote(0x8000),
+            SmfDivision::Smpte {
+                time_code_type: crate::TimeCodeType::Fps25,
+                ticks_per_frame: 0,
+            },
+        ];
+        // When
+        // Then
+        for division in divisions.iter() {
+            assert_eq!(
+                SmfWriter::new(SmfFormat::SingleTrack, *division),
+                Err(SmfError::InvalidHeader)
+            );
+        }
+    }
+
+    #[test]
+    fn variable_length_quantities() {
+        // Given
+        let cases: [(u32, &[u8]); 6] = [
+            (0, &[0x00]),
+            (0x7F, &[0x7F]),
+            (0x80, &[0x81, 0x00]),
+            (0x3FFF, &[0xFF, 0x7F]),
+            (0x10_0000, &[0xC0, 0x80, 0x00]),
+            (0x0FFF_FFFF, &[0xFF, 0xFF, 0xFF, 0x7F]),
+        ];
+        // When
+        // Then
+        for (value, bytes) in cases.iter() {
+            let vlq = encode_variable_length_quantity(*value).unwrap();
+            assert_eq!(vlq.as_slice(), *bytes);
+        }
+        assert!(encode_variable_length_quantity(0x1000_0000).is_err());
+    }
+
+    #[test]
+    fn round_trip_format_1_with_running_status() {
+        // Given
+        let bytes: &[u8] = &[
+            b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 1, 0, 2, 0x01, 0x80, // Header
+            b'M', b'T', b'r', b'k', 0, 0, 0, 15, // Tempo track
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Set Tempo
+            0x00, 0xFF, 0x03, 0x00, // Sequence/Track Name
+            0x00, 0xFF, 0x2F, 0x00, // End of Track
+            b'M', b'T', b'r', b'k', 0, 0, 0, 37, // Note track
+            0x00, 0xC1, 5, // Program Change
+            0x00, 0x91, 60, 100, // Note On
+            0x83, 0x00, 60, 0, // Note On with running status
+            0x00, 0xF0, 0x05, 0x7E, 0x7F, 0x09, 0x01, 0xF7, // GM System On
+            0x00, 0x91, 62, 100, // Note On (running status cancelled)
+            0x10, 0xF7, 0x01, 0xF8, // Escape sequence
+            0x81, 0x80, 0x00, 0x91, 62, 0, // Note On after 16384 ticks
+            0x00, 0xFF, 0x2F, 0x00, // End of Track
+        ];
+        // When
+        let result = round_trip(bytes, true);
+        // Then
+        assert_eq!(result, bytes);
+    }
+
+    #[test]
+    fn round_trip_format_0_with_smpte_division() {
+        // Given
+        let bytes: &[u8] = &[
+            b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0xE3, 0x50, // Header
+            b'M', b'T', b'r', b'k', 0, 0, 0, 12, // Track
+            0x00, 0xB0, 7, 100, // Control Change
+            0x50, 0xB0, 7, 80, // Control Change (no running status)
+            0x00, 0xFF, 0x2F, 0x00, // End of Track
+        ];
+        // When
+        let result = round_trip(bytes, false);
+        // Then
+        assert_eq!(result, bytes);
+    }
+
+    #[test]
+    fn build_tracks() {
+        // Given
+        let sys_ex_msg = OwnedSysExMessage::new(ManufacturerId::NON_COMMERCIAL, &[1, 2]).unwrap();
+        let mut track_1 = SmfTrackBuilder::new(true);
+        let mut track_2 = SmfTrackBuilder::new(true);
+        // When
+        track_1
+            .push_meta_event(0, &MetaEvent::TrackName(b"Piano"))
+            .unwrap();
+        track_1.push_sys_ex(0, sys_ex_msg.borrow()).unwrap();
+        track_1.end(10).unwrap();
+        track_2.push_short(0, &program_change(0, 1)).unwrap();
+        track_2.push_short(0, &control_change(0, 7, 1)).unwrap();
+        track_2.push_short(0, &control_change(0, 10, 64)).unwrap();
+        let mut writer =
+            SmfWriter::new(SmfFormat::MultiTrack, SmfDivision::TicksPerQuarterNote(480)).unwrap();
+        writer.add_track(track_1).unwrap();
+        writer.add_track(track_2).unwrap();
+        let mut bytes = vec![];
+        writer.write(&mut bytes).unwrap();
+        // Then
+        let expected: &[u8] = &[
+            b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 1, 0, 2, 0x01, 0xE0, // Header
+            b'M', b'T', b'r', b'k', 0, 0, 0, 20, // Track 1
+            0x00, 0xFF, 0x03, 0x05, b'P', b'i', b'a', b'n', b'o', // Track Name
+            0x00, 0xF0, 0x04, 0x7D, 1, 2, 0xF7, // SysEx
+            0x0A, 0xFF, 0x2F, 0x00, // End of Track
+            b'M', b'T', b'r', b'k', 0, 0, 0, 14, // Track 2
+            0x00, 0xC0, 1, // Program Change
+            0x00, 0xB0, 7, 1, // Control Change
+            0x00, 10, 64, // Control Change with running status
+            0x00, 0xFF, 0x2F, 0x00, // End of Track
+        ];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn errors() {
+        // Given
+        let mut track = SmfTrackBuilder::new(false);
+        let mut writer =
+            SmfWriter::new(SmfFormat::SingleTrack, SmfDivision::TicksPerQuarterNote(96)).unwrap();
+        // When
+        let result_1 = track.push_short(0, &timing_clock());
+        let result_2 = track.push_short(0x1000_0000, &note_on(0, 60, 100));
+        track.end(0).unwrap();
+        let result_3 = track.push_short(0, &note_on(0, 60, 100));
+        let result_4 = writer.add_track(track.clone());
+        let result_5 = writer.add_track(track);
+        // Then
+        assert_eq!(result_1, Err(SmfError::InvalidStatusByte));
+        assert_eq!(result_2, Err(SmfError::InvalidVariableLengthQuantity));
+        assert_eq!(result_3, Err(SmfError::EventAfterEndOfTrack));
+        assert_eq!(result_4, Ok(()));
+        assert_eq!(result_5, Err(SmfError::TooManyTracks));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{TIMING_CLOCKS_PER_QUARTER_NOTE, U14};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of Timing Clock messages per MIDI beat (sixteenth note).
+pub const TIMING_CLOCKS_PER_MIDI_BEAT: u8 = 6;
+
+/// Number of MIDI beats (sixteenth notes) per quarter note.
+const MIDI_BEATS_PER_QUARTER_NOTE: u8 =
+    TIMING_CLOCKS_PER_QUARTER_NOTE / TIMING_CLOCKS_PER_MIDI_BEAT;
+
+/// A song position as transmitted by Song Position Pointer messages, counted in MIDI beats
+/// (sixteenth notes, 6 Timing Clock ticks each) since the beginning of the song.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{song_position_pointer, u14};
+/// use helgoboss_midi::{RawShortMessage, ShortMessageFactory, SongPosition};
+///
+/// let position = SongPosition::new(u14(10));
+/// assert_eq!(position.to_ticks(), 60);
+/// assert_eq!(position.to_beats(), 2.5);
+/// assert_eq!(position.to_seconds(150.0), 1.0);
+/// let msg = RawShortMessage::song_position_pointer(position.into());
+/// assert_eq!(msg, song_position_pointer(10));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SongPosition {
+    midi_beats: U14,
+}
+
+impl SongPosition {
+    /// The beginning of the song.
+    pub const MIN: SongPosition = SongPosition {
+        midi_beats: U14::MIN,
+    };
+
+    /// The last position which can be expressed by a Song Position Pointer message.
+    pub const MAX: SongPosition = SongPosition {
+        midi_beats: U14::MAX,
+    };
+
+    /// Creates a song position from the given number of MIDI beats (sixteenth notes).
+    pub fn new(midi_beats: U14) -> SongPosition {
+        SongPosition { midi_beats }
+    }
+
+    /// Creates the song position which contains the given Timing Clock tick.
+    ///
+    /// Returns `None` if the position can't be expressed by a Song Position Pointer message.
+    pub fn from_ticks(ticks: u64) -> Option<SongPosition> {
+        let midi_beats = ticks / u64::from(TIMING_CLOCKS_PER_MIDI_BEAT);
+        if midi_beats > u64::from(U14::MAX) {
+            return None;
+        }
+        Some(SongPosition::new(U14(midi_beats as u16)))
+    }
+
+    /// Returns the number of MIDI beats (sixteenth notes).
+    pub fn midi_beats(&self) -> U14 {
+        self.midi_beats
+    }
+
+    /// Converts this position to Timing Clock ticks (24 per quarter note).
+    pub fn to_ticks(self) -> u64 {
+        u64::from(self.midi_beats) * u64::from(TIMING_CLOCKS_PER_MIDI_BEAT)
+    }
+
+    /// Converts this position to beats (quarter notes).
+    pub fn to_beats(self) -> f64 {
+        f64::from(self.midi_beats.get()) / f64::from(MIDI_BEATS_PER_QUARTER_NOTE)
+    }
+
+    /// Converts this position to seconds at the given constant tempo in beats (quarter notes) per
+    /// minute.
+    pub fn to_seconds(self, bpm: f64) -> f64 {
+        self.to_beats() * 60.0 / bpm
+    }
+}
+
+impl From<U14> for SongPosition {
+    fn from(midi_beats: U14) -> Self {
+        SongPosition::new(midi_beats)
+    }
+}
+
+impl From<SongPosition> for U14 {
+    fn from(position: SongPosition) -> Self {
+        position.midi_beats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::u14;
+
+    #[test]
+    fn conversions() {
+        // Given
+        let position = SongPosition::new(u14(6));
+        // When
+        // Then
+        assert_eq!(position.midi_beats(), u14(6));
+        assert_eq!(position.to_ticks(), 36);
+        assert_eq!(position.to_beats(), 1.5);
+        assert_eq!(position.to_seconds(90.0), 1.0);
+        assert_eq!(U14::from(position), u14(6));
+        assert_eq!(SongPosition::from(u14(6)), position);
+    }
+
+    #[test]
+    fn from_ticks() {
+        // Given
+        // When
+        // Then
+        assert_eq!(SongPosition::from_ticks(0), Some(SongPosition::MIN));
+        assert_eq!(
+            SongPosition::from_ticks(41),
+            Some(SongPosition::new(u14(6)))
+        );
+        assert_eq!(
+            SongPosition::from_ticks(16383 * 6 + 5),
+            Some(SongPosition::MAX)
+        );
+        assert_eq!(SongPosition::from_ticks(16384 * 6), None);
+    }
+}
@@ -0,0 +1,730 @@
+use crate::{
+    build_first_word, bytes_to_words, extract_message_type_from_first_word, extract_short_message,
+    words_to_bytes, FlexDataAddress, FlexDataMessage, Midi2ChannelVoiceMessage, RawUmpMessage,
+    StructuredShortMessage, UmpFormat, UmpMessage, UmpMessageFactory, UmpMessageType,
+    UmpStreamMessage, U4,
+};
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A UMP message implemented as an enum where each variant contains exactly the data which is
+/// relevant for the particular message type.
+///
+/// Like [`StructuredShortMessage`], this enum is primarily intended for read-only usage via
+/// pattern matching. Words which can't be interpreted (e.g. reserved message types or statuses) are
+/// kept as they are in the [`Unknown`] variant, so converting words to this enum and back is
+/// lossless except for reserved bits, which are ignored.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{
+///     Midi2ChannelVoiceMessage, RawUmpMessage, StructuredShortMessage, StructuredUmpMessage,
+///     UmpMessage, UmpMessageFactory,
+/// };
+///
+/// let msg = RawUmpMessage::from_words(&[0x2090_3C64]).unwrap();
+/// match msg.to_structured() {
+///     StructuredUmpMessage::Midi1ChannelVoice {
+///         group,
+///         message: StructuredShortMessage::NoteOn { key_number, .. },
+///     } => {
+///         assert_eq!(group.get(), 0);
+///         assert_eq!(key_number.get(), 60);
+///     }
+///     _ => panic!("wrong type"),
+/// };
+/// let msg = RawUmpMessage::from_words(&[0x40B0_4A00, 0x8000_0000]).unwrap();
+/// match msg.to_structured() {
+///     StructuredUmpMessage::Midi2ChannelVoice {
+///         message:
+///             Midi2ChannelVoiceMessage::ControlChange {
+///                 controller_number,
+///                 value,
+///                 ..
+///             },
+///         ..
+///     } => {
+///         assert_eq!(controller_number.get(), 74);
+///         assert_eq!(value, 0x8000_0000);
+///     }
+///     _ => panic!("wrong type"),
+/// };
+/// ```
+///
+/// [`StructuredShortMessage`]: enum.StructuredShortMessage.html
+/// [`Unknown`]: #variant.Unknown
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StructuredUmpMessage {
+    /// Message type 0x0.
+    Utility(UtilityMessage),
+    /// Message type 0x1, containing a System Common or System Real Time message.
+    ///
+    /// The UMP message type is always derived from the contained message when encoding, so if it
+    /// contains a channel message, it's encoded as MIDI 1.0 Channel Voice message. System Exclusive
+    /// start or end messages can't be represented this way and are encoded as NOOP.
+    System {
+        group: U4,
+        message: StructuredShortMessage,
+    },
+    /// Message type 0x2, containing a channel message.
+    ///
+    /// Encoded the same way as [`System`], so if it contains a System Common or System Real Time
+    /// message, it's encoded as System message.
+    ///
+    /// [`System`]: #variant.System
+    Midi1ChannelVoice {
+        group: U4,
+        message: StructuredShortMessage,
+    },
+    /// Message type 0x3 with a (part of a) System Exclusive message.
+    SysEx7 {
+        group: U4,
+        format: UmpFormat,
+        data: SysEx7Payload,
+    },
+    /// Message type 0x4.
+    Midi2ChannelVoice {
+        group: U4,
+        message: Midi2ChannelVoiceMessage,
+    },
+    /// Message type 0x5 with a (part of a) System Exclusive 8 message.
+    SysEx8 {
+        group: U4,
+        format: UmpFormat,
+        stream_id: u8,
+        data: SysEx8Payload,
+    },
+    /// Message type 0x5 with the header of a Mixed Data Set.
+    MixedDataSetHeader {
+        group: U4,
+        mds_id: U4,
+        /// Number of valid bytes in this chunk.
+        byte_count: u16,
+        /// Number of chunks in the Mixed Data Set.
+        chunk_count: u16,
+        /// Number of this chunk (starting with 1).
+        chunk_number: u16,
+        manufacturer_id: u16,
+        device_id: u16,
+        sub_id_1: u16,
+        sub_id_2: u16,
+    },
+    /// Message type 0x5 with the payload of a Mixed Data Set.
+    MixedDataSetPayload {
+        group: U4,
+        mds_id: U4,
+        data: [u8; 14],
+    },
+    /// Message type 0xD.
+    FlexData {
+        group: U4,
+        format: UmpFormat,
+        address: FlexDataAddress,
+        message: FlexDataMessage,
+    },
+    /// Message type 0xF.
+    UmpStream(UmpStreamMessage),
+    /// A message with reserved message type or status or with invalid data.
+    Unknown(RawUmpMessage),
+}
+
+impl UmpMessageFactory for StructuredUmpMessage {
+    unsafe fn from_words_unchecked(words: [u32; 4]) -> Self {
+        decode(&words).unwrap_or_else(|| {
+            StructuredUmpMessage::Unknown(RawUmpMessage::from_words_unchecked(words))
+        })
+    }
+}
+
+impl UmpMessage for StructuredUmpMessage {
+    fn to_words(&self) -> [u32; 4] {
+        use StructuredUmpMessage::*;
+        match *self {
+            Utility(msg) => msg.encode(),
+            System { group, message } | Midi1ChannelVoice { group, message } => {
+                RawUmpMessage::from_short_message(group, &message)
+                    .map(|msg| msg.to_words())
+                    .unwrap_or([0; 4])
+            }
+            SysEx7 {
+                group,
+                format,
+                data,
+            } => {
+                let mut bytes = [0; 16];
+                bytes[2..2 + data.len as usize].copy_from_slice(data.bytes());
+                let [word_0, word_1, _, _] = bytes_to_words(&bytes);
+                let word_0 = word_0
+                    | build_first_word(UmpMessageType::Data64, group)
+                    | (u32::from(u8::from(format)) << 20)
+                    | (u32::from(data.len) << 16);
+                [word_0, word_1, 0, 0]
+            }
+            Midi2ChannelVoice { group, message } => message.encode(group),
+            SysEx8 {
+                group,
+                format,
+                stream_id,
+                data,
+            } => {
+                let mut bytes = [0; 16];
+                bytes[2] = stream_id;
+                bytes[3..3 + data.len as usize].copy_from_slice(data.bytes());
+                let mut words = bytes_to_words(&bytes);
+                words[0] |= build_first_word(UmpMessageType::Data128, group)
+                    | (u32::from(u8::from(format)) << 20)
+                    | (u32::from(data.len + 1) << 16);
+                words
+            }
+            MixedDataSetHeader {
+                group,
+                mds_id,
+                byte_count,
+                chunk_count,
+                chunk_number,
+                manufacturer_id,
+                device_id,
+                sub_id_1,
+                sub_id_2,
+            } => {
+                let join = |high: u16, low: u16| (u32::from(high) << 16) | u32::from(low);
+                [
+                    build_first_word(UmpMessageType::Data128, group)
+                        | (0x8 << 20)
+                        | (u32::from(mds_id.get()) << 16)
+                        | u32::from(byte_count),
+                    join(chunk_count, chunk_number),
+                    join(manufacturer_id, device_id),
+                    join(sub_id_1, sub_id_2),
+                ]
+            }
+            MixedDataSetPayload {
+                group,
+                mds_id,
+                data,
+            } => {
+                let mut bytes = [0; 16];
+                bytes[2..].copy_from_slice(&data);
+                let mut words = bytes_to_words(&bytes);
+                words[0] |= build_first_word(UmpMessageType::Data128, group)
+                    | (0x9 << 20)
+                    | (u32::from(mds_id.get()) << 16);
+                words
+            }
+            FlexData {
+                group,
+                format,
+                address,
+                message,
+            } => message.encode(group, format, address),
+            UmpStream(msg) => msg.encode(),
+            Unknown(msg) => msg.to_words(),
+        }
+    }
+
+    // Slight optimization
+    fn to_structured(&self) -> StructuredUmpMessage {
+        *self
+    }
+}
+
+/// A Utility message, carried by a 32-bit UMP message of type 0x0.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UtilityMessage {
+    /// No operation.
+    Noop,
+    /// Jitter Reduction clock, expressed in units of 1/31250 seconds.
+    JitterReductionClock { sender_clock_time: u16 },
+    /// Jitter Reduction timestamp of the following message, expressed in units of 1/31250 seconds.
+    JitterReductionTimestamp { sender_clock_timestamp: u16 },
+    /// Resolution of subsequent Delta Clockstamps.
+    DeltaClockstampTicksPerQuarterNote { ticks_per_quarter_note: u16 },
+    /// Ticks since the last event (only the lower 20 bits are used).
+    DeltaClockstamp { ticks_since_last_event: u32 },
+}
+
+impl UtilityMessage {
+    fn decode(word: u32) -> Option<Self> {
+        use UtilityMessage::*;
+        let msg = match (word >> 20) & 0x0F {
+            0x0 => Noop,
+            0x1 => JitterReductionClock {
+                sender_clock_time: word as u16,
+            },
+            0x2 => JitterReductionTimestamp {
+                sender_clock_timestamp: word as u16,
+            },
+            0x3 => DeltaClockstampTicksPerQuarterNote {
+                ticks_per_quarter_note: word as u16,
+            },
+            0x4 => DeltaClockstamp {
+                ticks_since_last_event: word & 0x000F_FFFF,
+            },
+            _ => return None,
+        };
+        Some(msg)
+    }
+
+    fn encode(&self) -> [u32; 4] {
+        use UtilityMessage::*;
+        let (status, data) = match *self {
+            Noop => (0x0, 0),
+            JitterReductionClock { sender_clock_time } => (0x1, u32::from(sender_clock_time)),
+            JitterReductionTimestamp {
+                sender_clock_timestamp,
+            } => (0x2, u32::from(sender_clock_timestamp)),
+            DeltaClockstampTicksPerQuarterNote {
+                ticks_per_quarter_note,
+            } => (0x3, u32::from(ticks_per_quarter_note)),
+            DeltaClockstamp {
+                ticks_since_last_event,
+            } => (0x4, ticks_since_last_event & 0x000F_FFFF),
+        };
+        [(status << 20) | data, 0, 0, 0]
+    }
+}
+
+/// An error which can occur when deserializing a System Exclusive payload which has too many bytes
+/// or bytes out of range.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "System Exclusive payload is invalid")]
+pub struct InvalidPayloadError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidPayloadError {}
+
+macro_rules! ump_payload {
+    (
+        $(#[$outer:meta])*
+        name = $name: ident,
+        data = $data: ident,
+        capacity = $capacity: literal,
+        is_valid_byte = $is_valid_byte: expr
+    ) => {
+        $(#[$outer])*
+        #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+        #[cfg_attr(feature = "serde", derive(Serialize))]
+        pub struct $name {
+            len: u8,
+            bytes: [u8; $capacity],
+        }
+
+        /// Unvalidated payload fields, used for deserialization.
+        #[cfg(feature = "serde")]
+        #[derive(Deserialize)]
+        struct $data {
+            len: u8,
+            bytes: [u8; $capacity],
+        }
+
+        #[cfg(feature = "serde")]
+        impl TryFrom<$data> for $name {
+            type Error = InvalidPayloadError;
+
+            fn try_from(data: $data) -> Result<Self, Self::Error> {
+                let bytes = data
+                    .bytes
+                    .get(..data.len as usize)
+                    .ok_or(InvalidPayloadError(()))?;
+                if !bytes.iter().all(|b| ($is_valid_byte)(*b)) {
+                    return Err(InvalidPayloadError(()));
+                }
+                $name::from_slice(bytes).ok_or(InvalidPayloadError(()))
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let data = $data::deserialize(deserializer)?;
+                $name::try_from(data).map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl $name {
+            /// The maximum number of bytes.
+            pub const CAPACITY: usize = $capacity;
+
+            /// Returns the contained bytes.
+            pub fn bytes(&self) -> &[u8] {
+                &self.bytes[..self.len as usize]
+            }
+
+            /// Returns `None` if there are too many bytes.
+            pub(crate) fn from_slice(value: &[u8]) -> Option<Self> {
+                if value.len() > $capacity {
+                    return None;
+                }
+                let mut bytes = [0; $capacity];
+                bytes[..value.len()].copy_from_slice(value);
+                Some($name {
+                    len: value.len() as u8,
+                    bytes,
+                })
+            }
+        }
+    };
+}
+
+ump_payload! {
+    /// Up to 6 data bytes of a System Exclusive message contained in a single UMP message.
+    ///
+    /// Doesn't include the 0xF0 and 0xF7 status bytes.
+    name = SysEx7Payload,
+    data = SysEx7PayloadData,
+    capacity = 6,
+    is_valid_byte = |b: u8| b < 0x80
+}
+
+impl SysEx7Payload {
+    /// Creates a payload.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there are more than 6 bytes or one of the bytes is greater than 127.
+    pub fn new(bytes: &[u8]) -> SysEx7Payload {
+        assert!(bytes.iter().all(|b| *b < 0x80), "data bytes must be 7-bit");
+        SysEx7Payload::from_slice(bytes).expect("too many bytes for one UMP message")
+    }
+}
+
+ump_payload! {
+    /// Up to 13 data bytes of a System Exclusive 8 message contained in a single UMP message.
+    name = SysEx8Payload,
+    data = SysEx8PayloadData,
+    capacity = 13,
+    is_valid_byte = |_: u8| true
+}
+
+impl SysEx8Payload {
+    /// Creates a payload.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there are more than 13 bytes.
+    pub fn new(bytes: &[u8]) -> SysEx8Payload {
+        SysEx8Payload::from_slice(bytes).expect("too many bytes for one UMP message")
+    }
+}
+
+fn decode(words: &[u32; 4]) -> Option<StructuredUmpMessage> {
+    use StructuredUmpMessage::*;
+    let word_0 = words[0];
+    let group = U4(((word_0 >> 24) & 0x0F) as u8);
+    let status = ((word_0 >> 20) & 0x0F) as u8;
+    let msg = match extract_message_type_from_first_word(word_0) {
+        UmpMessageType::Utility => Utility(UtilityMessage::decode(word_0)?),
+        UmpMessageType::System => System {
+            group,
+            message: extract_short_message(UmpMessageType::System, word_0)?,
+        },
+        UmpMessageType::Midi1ChannelVoice => Midi1ChannelVoice {
+            group,
+            message: extract_short_message(UmpMessageType::Midi1ChannelVoice, word_0)?,
+        },
+        UmpMessageType::Data64 => {
+            let len = ((word_0 >> 16) & 0x0F) as usize;
+            let bytes = words_to_bytes(words);
+            let data = bytes.get(2..2 + len).filter(|_| len <= 6)?;
+            if data.iter().any(|b| *b >= 0x80) {
+                return None;
+            }
+            SysEx7 {
+                group,
+                format: UmpFormat::try_from(status).ok()?,
+                data: SysEx7Payload::from_slice(data)?,
+            }
+        }
+        UmpMessageType::Midi2ChannelVoice => Midi2ChannelVoice {
+            group,
+            message: Midi2ChannelVoiceMessage::decode(words)?,
+        },
+        UmpMessageType::Data128 => {
+            let bytes = words_to_bytes(words);
+            let low_nibble = ((word_0 >> 16) & 0x0F) as u8;
+            match status {
+                0x0..=0x3 => {
+                    let len = low_nibble as usize;
+                    if len == 0 {
+                        return None;
+                    }
+                    SysEx8 {
+                        group,
+                        format: UmpFormat::try_from(status).ok()?,
+                        stream_id: bytes[2],
+                        data: SysEx8Payload::from_slice(bytes.get(3..2 + len)?)?,
+                    }
+                }
+                0x8 => {
+                    let split = |word: u32| ((word >> 16) as u16, word as u16);
+                    let (chunk_count, chunk_number) = split(words[1]);
+                    let (manufacturer_id, device_id) = split(words[2]);
+                    let (sub_id_1, sub_id_2) = split(words[3]);
+                    MixedDataSetHeader {
+                        group,
+                        mds_id: U4(low_nibble),
+                        byte_count: word_0 as u16,
+                        chunk_count,
+                        chunk_number,
+                        manufacturer_id,
+                        device_id,
+                        sub_id_1,
+                        sub_id_2,
+                    }
+                }
+                0x9 => {
+                    let mut data = [0; 14];
+                    data.copy_from_slice(&bytes[2..]);
+                    MixedDataSetPayload {
+                        group,
+                        mds_id: U4(low_nibble),
+                        data,
+                    }
+                }
+                _ => return None,
+            }
+        }
+        UmpMessageType::FlexData => {
+            let (format, address, message) = FlexDataMessage::decode(words)?;
+            FlexData {
+                group,
+                format,
+                address,
+                message,
+            }
+        }
+        UmpMessageType::UmpStream => UmpStream(UmpStreamMessage::decode(words)?),
+        _ => return None,
+    };
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::{Midi2ChannelVoiceMessage, ShortMessage};
+
+    fn round_trip(msg: StructuredUmpMessage, words: &[u32]) {
+        assert_eq!(&msg.to_words()[..words.len()], words);
+        assert_eq!(msg.word_count(), words.len());
+        assert_eq!(StructuredUmpMessage::from_words(words), Ok(msg));
+        assert_eq!(
+            RawUmpMessage::from_words(words).unwrap().to_structured(),
+            msg
+        );
+    }
+
+    #[test]
+    fn utility() {
+        // Given
+        // When
+        // Then
+        round_trip(StructuredUmpMessage::Utility(UtilityMessage::Noop), &[0]);
+        round_trip(
+            StructuredUmpMessage::Utility(UtilityMessage::JitterReductionTimestamp {
+                sender_clock_timestamp: 0x1234,
+            }),
+            &[0x0020_1234],
+        );
+        round_trip(
+            StructuredUmpMessage::Utility(UtilityMessage::DeltaClockstamp {
+                ticks_since_last_event: 0xF_FFFF,
+            }),
+            &[0x004F_FFFF],
+        );
+    }
+
+    #[test]
+    fn system_and_midi1_channel_voice() {
+        // Given
+        // When
+        // Then
+        round_trip(
+            StructuredUmpMessage::System {
+                group: u4(15),
+                message: song_position_pointer(0x81).to_structured(),
+            },
+            &[0x1FF2_0101],
+        );
+        round_trip(
+            StructuredUmpMessage::Midi1ChannelVoice {
+                group: u4(1),
+                message: pitch_bend_change(2, 0x2000).to_structured(),
+            },
+            &[0x21E2_0040],
+        );
+    }
+
+    #[test]
+    fn system_and_midi1_channel_voice_mismatch() {
+        // Given
+        let system_with_channel_msg = StructuredUmpMessage::System {
+            group: u4(1),
+            message: note_on(0, 60, 100).to_structured(),
+        };
+        let channel_with_system_msg = StructuredUmpMessage::Midi1ChannelVoice {
+            group: u4(2),
+            message: timing_clock().to_structured(),
+        };
+        let system_with_sys_ex_start = StructuredUmpMessage::System {
+            group: u4(3),
+            message: StructuredShortMessage::SystemExclusiveStart,
+        };
+        // When
+        // Then
+        assert_eq!(system_with_channel_msg.to_words(), [0x2190_3C64, 0, 0, 0]);
+        assert_eq!(
+            system_with_channel_msg.to_other::<StructuredUmpMessage>(),
+            StructuredUmpMessage::Midi1ChannelVoice {
+                group: u4(1),
+                message: note_on(0, 60, 100).to_structured(),
+            }
+        );
+        assert_eq!(channel_with_system_msg.to_words(), [0x12F8_0000, 0, 0, 0]);
+        assert_eq!(system_with_sys_ex_start.to_words(), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn sys_ex() {
+        // Given
+        // When
+        // Then
+        round_trip(
+            StructuredUmpMessage::SysEx7 {
+                group: u4(0),
+                format: UmpFormat::Start,
+                data: SysEx7Payload::new(&[0x7E, 0x7F, 0x06, 0x01]),
+            },
+            &[0x3014_7E7F, 0x0601_0000],
+        );
+        round_trip(
+            StructuredUmpMessage::SysEx8 {
+                group: u4(0),
+                format: UmpFormat::Complete,
+                stream_id: 7,
+                data: SysEx8Payload::new(&[0xFF; 13]),
+            },
+            &[0x500E_07FF, 0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFF],
+        );
+        round_trip(
+            StructuredUmpMessage::SysEx8 {
+                group: u4(0),
+                format: UmpFormat::End,
+                stream_id: 7,
+                data: SysEx8Payload::new(&[]),
+            },
+            &[0x5031_0700, 0, 0, 0],
+        );
+    }
+
+    #[test]
+    fn mixed_data_set() {
+        // Given
+        // When
+        // Then
+        round_trip(
+            StructuredUmpMessage::MixedDataSetHeader {
+                group: u4(2),
+                mds_id: u4(1),
+                byte_count: 14,
+                chunk_count: 2,
+                chunk_number: 1,
+                manufacturer_id: 0x0041,
+                device_id: 0x0010,
+                sub_id_1: 0x0001,
+                sub_id_2: 0x0002,
+            },
+            &[0x5281_000E, 0x0002_0001, 0x0041_0010, 0x0001_0002],
+        );
+        round_trip(
+            StructuredUmpMessage::MixedDataSetPayload {
+                group: u4(2),
+                mds_id: u4(1),
+                data: [1; 14],
+            },
+            &[0x5291_0101, 0x0101_0101, 0x0101_0101, 0x0101_0101],
+        );
+    }
+
+    #[test]
+    fn midi2_channel_voice() {
+        // Given
+        // When
+        // Then
+        round_trip(
+            StructuredUmpMessage::Midi2ChannelVoice {
+                group: u4(0),
+                message: Midi2ChannelVoiceMessage::ChannelPressure {
+                    channel: channel(9),
+                    pressure: 0xFFFF_FFFF,
+                },
+            },
+            &[0x40D9_0000, 0xFFFF_FFFF],
+        );
+    }
+
+    #[test]
+    fn unknown() {
+        // Given
+        let cases: [&[u32]; 6] = [
+            // Reserved message type
+            &[0x6000_0000],
+            // Reserved utility status
+            &[0x00F0_0000],
+            // Channel message in System message
+            &[0x1090_3C64],
+            // SysEx7 with too many bytes
+            &[0x3007_0000, 0],
+            // SysEx7 with 8-bit data
+            &[0x3001_8000, 0],
+            // Reserved Data128 status
+            &[0x50A0_0000, 0, 0, 0],
+        ];
+        // When
+        // Then
+        for words in cases.iter() {
+            let raw = RawUmpMessage::from_words(words).unwrap();
+            assert_eq!(raw.to_structured(), StructuredUmpMessage::Unknown(raw));
+            assert_eq!(raw.to_structured().to_words(), raw.to_words());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn sys_ex7_payload_too_long() {
+        SysEx7Payload::new(&[0; 7]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sys_ex7_payload_8_bit() {
+        SysEx7Payload::new(&[0x80]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_payload() {
+        // Given
+        let valid = r#"{"len":2,"bytes":[1,127,0,0,0,0]}"#;
+        let too_long = r#"{"len":7,"bytes":[0,0,0,0,0,0]}"#;
+        let eight_bit = r#"{"len":1,"bytes":[128,0,0,0,0,0]}"#;
+        let sys_ex8 = r#"{"len":1,"bytes":[255,0,0,0,0,0,0,0,0,0,0,0,0]}"#;
+        // When
+        // Then
+        assert_eq!(
+            serde_json::from_str::<SysEx7Payload>(valid).unwrap(),
+            SysEx7Payload::new(&[1, 127])
+        );
+        assert!(serde_json::from_str::<SysEx7Payload>(too_long).is_err());
+        assert!(serde_json::from_str::<SysEx7Payload>(eight_bit).is_err());
+        assert_eq!(
+            serde_json::from_str::<SysEx8Payload>(sys_ex8).unwrap(),
+            SysEx8Payload::new(&[255])
+        );
+    }
+}
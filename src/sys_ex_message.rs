@@ -0,0 +1,481 @@
+use crate::U7;
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An error which can occur when trying to create a System Exclusive message.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, derive_more::Display)]
+pub enum SysExError {
+    /// The first byte is not a System Exclusive Start status byte (0xF0).
+    #[display(fmt = "System Exclusive message doesn't start with 0xF0")]
+    MissingStart,
+    /// The last byte is not a System Exclusive End status byte (0xF7).
+    #[display(fmt = "System Exclusive message doesn't end with 0xF7")]
+    MissingEnd,
+    /// One of the bytes between start and end is not a 7-bit data byte.
+    #[display(fmt = "System Exclusive message contains invalid data byte")]
+    InvalidDataByte,
+    /// The message is too short to contain a manufacturer ID or the manufacturer ID is invalid.
+    #[display(fmt = "System Exclusive message has no valid manufacturer ID")]
+    InvalidManufacturerId,
+    /// The given buffer is too small to hold the message.
+    #[display(fmt = "buffer too small for System Exclusive message")]
+    BufferTooSmall,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SysExError {}
+
+/// The manufacturer ID at the beginning of a System Exclusive message.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ManufacturerId {
+    /// A 1-byte manufacturer ID.
+    ///
+    /// Must not be 0 because 0 introduces an extended manufacturer ID.
+    Standard(U7),
+    /// A 3-byte manufacturer ID, made up by a 0 followed by the 2 contained bytes.
+    Extended(U7, U7),
+}
+
+impl ManufacturerId {
+    /// ID reserved for non-commercial use (e.g. schools, research).
+    pub const NON_COMMERCIAL: ManufacturerId = ManufacturerId::Standard(U7(0x7D));
+
+    /// ID of Universal Non-Real Time System Exclusive messages.
+    pub const UNIVERSAL_NON_REAL_TIME: ManufacturerId = ManufacturerId::Standard(U7(0x7E));
+
+    /// ID of Universal Real Time System Exclusive messages.
+    pub const UNIVERSAL_REAL_TIME: ManufacturerId = ManufacturerId::Standard(U7(0x7F));
+
+    /// Returns the number of bytes this ID takes within a System Exclusive message (1 or 3).
+    pub fn byte_count(&self) -> usize {
+        match self {
+            ManufacturerId::Standard(_) => 1,
+            ManufacturerId::Extended(_, _) => 3,
+        }
+    }
+
+    /// Returns whether this is the ID of a Universal (Real Time or Non-Real Time) System Exclusive
+    /// message.
+    pub fn is_universal(&self) -> bool {
+        *self == Self::UNIVERSAL_NON_REAL_TIME || *self == Self::UNIVERSAL_REAL_TIME
+    }
+
+    /// Parses the manufacturer ID from the beginning of the given System Exclusive data (without
+    /// leading 0xF0).
+    pub(crate) fn from_data(data: &[u8]) -> Result<ManufacturerId, SysExError> {
+        match data {
+            [0, b1, b2, ..] => Ok(ManufacturerId::Extended(U7(*b1), U7(*b2))),
+            [b, ..] if *b != 0 => Ok(ManufacturerId::Standard(U7(*b))),
+            _ => Err(SysExError::InvalidManufacturerId),
+        }
+    }
+
+    /// Writes this ID to the beginning of the given buffer and returns the number of bytes written.
+    pub(crate) fn write(&self, buffer: &mut [u8]) -> Result<usize, SysExError> {
+        let count = self.byte_count();
+        if buffer.len() < count {
+            return Err(SysExError::BufferTooSmall);
+        }
+        match self {
+            ManufacturerId::Standard(b) => {
+                if *b == U7::MIN {
+                    return Err(SysExError::InvalidManufacturerId);
+                }
+                buffer[0] = b.get();
+            }
+            ManufacturerId::Extended(b1, b2) => {
+                buffer[..3].copy_from_slice(&[0, b1.get(), b2.get()]);
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// A complete System Exclusive message, borrowed from a slice of bytes.
+///
+/// This is a zero-copy view on the bytes, which include the leading System Exclusive Start byte
+/// (0xF0) and the trailing System Exclusive End byte (0xF7). All bytes in between are guaranteed to
+/// be 7-bit data bytes, starting with a valid manufacturer ID.
+///
+/// See [`OwnedSysExMessage`] for an owned variant.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{ManufacturerId, SysExMessage, U7};
+///
+/// let msg = SysExMessage::from_bytes(&[0xF0, 0x41, 0x10, 0x42, 0x12, 0xF7]).unwrap();
+/// assert_eq!(msg.manufacturer_id(), ManufacturerId::Standard(U7::new(0x41)));
+/// assert_eq!(msg.payload(), &[0x10, 0x42, 0x12]);
+/// assert_eq!(msg.as_bytes().len(), 6);
+///
+/// let mut buffer = [0; 16];
+/// let msg = SysExMessage::write(
+///     &mut buffer,
+///     ManufacturerId::Extended(U7::new(0x20), U7::new(0x29)),
+///     &[0x02, 0x0C],
+/// )
+/// .unwrap();
+/// assert_eq!(msg.as_bytes(), &[0xF0, 0x00, 0x20, 0x29, 0x02, 0x0C, 0xF7]);
+/// ```
+///
+/// [`OwnedSysExMessage`]: struct.OwnedSysExMessage.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SysExMessage<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SysExMessage<'a> {
+    /// Creates a System Exclusive message from the given bytes, including start and end byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes don't make up a valid System Exclusive message.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<SysExMessage<'a>, SysExError> {
+        validate_sys_ex_bytes(bytes)?;
+        Ok(SysExMessage { bytes })
+    }
+
+    /// Creates a System Exclusive message from the given bytes without checking them.
+    ///
+    /// # Safety
+    ///
+    /// Callers must make sure that the given bytes make up a valid System Exclusive message.
+    pub unsafe fn from_bytes_unchecked(bytes: &'a [u8]) -> SysExMessage<'a> {
+        SysExMessage { bytes }
+    }
+
+    /// Writes a System Exclusive message with the given manufacturer ID and payload into the given
+    /// buffer and returns it.
+    ///
+    /// This is the allocation-free way of creating a new System Exclusive message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small or if the manufacturer ID or payload are
+    /// invalid.
+    pub fn write(
+        buffer: &'a mut [u8],
+        manufacturer_id: ManufacturerId,
+        payload: &[u8],
+    ) -> Result<SysExMessage<'a>, SysExError> {
+        let count = write_sys_ex_bytes(buffer, manufacturer_id, payload)?;
+        Ok(SysExMessage {
+            bytes: &buffer[..count],
+        })
+    }
+
+    /// Returns all bytes of this message, including start and end byte.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Returns all data bytes of this message, that is, all bytes without start and end byte.
+    pub fn data(&self) -> &'a [u8] {
+        &self.bytes[1..self.bytes.len() - 1]
+    }
+
+    /// Returns the manufacturer ID of this message.
+    pub fn manufacturer_id(&self) -> ManufacturerId {
+        ManufacturerId::from_data(self.data()).expect("impossible")
+    }
+
+    /// Returns the payload of this message, that is, all data bytes following the manufacturer ID.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.data()[self.manufacturer_id().byte_count()..]
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for SysExMessage<'a> {
+    type Error = SysExError;
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        SysExMessage::from_bytes(value)
+    }
+}
+
+impl<'a> AsRef<[u8]> for SysExMessage<'a> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+/// A complete System Exclusive message which owns its bytes.
+///
+/// This is the owned variant of [`SysExMessage`]. It's only available with the `std` feature
+/// because it needs heap allocation.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{ManufacturerId, OwnedSysExMessage};
+///
+/// let msg = OwnedSysExMessage::new(ManufacturerId::NON_COMMERCIAL, &[1, 2, 3]).unwrap();
+/// assert_eq!(msg.as_bytes(), &[0xF0, 0x7D, 1, 2, 3, 0xF7]);
+/// assert_eq!(msg.borrow().payload(), &[1, 2, 3]);
+/// ```
+///
+/// [`SysExMessage`]: struct.SysExMessage.html
+#[cfg(feature = "std")]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct OwnedSysExMessage {
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl OwnedSysExMessage {
+    /// Creates a System Exclusive message with the given manufacturer ID and payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manufacturer ID or payload are invalid.
+    pub fn new(
+        manufacturer_id: ManufacturerId,
+        payload: &[u8],
+    ) -> Result<OwnedSysExMessage, SysExError> {
+        let mut bytes = vec![0; 2 + manufacturer_id.byte_count() + payload.len()];
+        write_sys_ex_bytes(&mut bytes, manufacturer_id, payload)?;
+        Ok(OwnedSysExMessage { bytes })
+    }
+
+    /// Creates a System Exclusive message from the given bytes, including start and end byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes don't make up a valid System Exclusive message.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<OwnedSysExMessage, SysExError> {
+        validate_sys_ex_bytes(&bytes)?;
+        Ok(OwnedSysExMessage { bytes })
+    }
+
+    /// Returns a borrowed view on this message.
+    pub fn borrow(&self) -> SysExMessage<'_> {
+        SysExMessage { bytes: &self.bytes }
+    }
+
+    /// Returns all bytes of this message, including start and end byte.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consumes this message and returns its bytes, including start and end byte.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns the manufacturer ID of this message.
+    pub fn manufacturer_id(&self) -> ManufacturerId {
+        self.borrow().manufacturer_id()
+    }
+
+    /// Returns the payload of this message, that is, all data bytes following the manufacturer ID.
+    pub fn payload(&self) -> &[u8] {
+        self.borrow().payload()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryFrom<Vec<u8>> for OwnedSysExMessage {
+    type Error = SysExError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        OwnedSysExMessage::from_bytes(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<SysExMessage<'a>> for OwnedSysExMessage {
+    fn from(msg: SysExMessage<'a>) -> Self {
+        OwnedSysExMessage {
+            bytes: msg.bytes.to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<OwnedSysExMessage> for Vec<u8> {
+    fn from(msg: OwnedSysExMessage) -> Self {
+        msg.bytes
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<[u8]> for OwnedSysExMessage {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Start byte of each System Exclusive message.
+pub(crate) const SYS_EX_START: u8 = 0xF0;
+
+/// End byte of each System Exclusive message.
+pub(crate) const SYS_EX_END: u8 = 0xF7;
+
+fn validate_sys_ex_bytes(bytes: &[u8]) -> Result<(), SysExError> {
+    if bytes.first() != Some(&SYS_EX_START) {
+        return Err(SysExError::MissingStart);
+    }
+    if bytes.len() < 2 || bytes.last() != Some(&SYS_EX_END) {
+        return Err(SysExError::MissingEnd);
+    }
+    let data = &bytes[1..bytes.len() - 1];
+    validate_data_bytes(data)?;
+    ManufacturerId::from_data(data)?;
+    Ok(())
+}
+
+fn write_sys_ex_bytes(
+    buffer: &mut [u8],
+    manufacturer_id: ManufacturerId,
+    payload: &[u8],
+) -> Result<usize, SysExError> {
+    validate_data_bytes(payload)?;
+    let count = 2 + manufacturer_id.byte_count() + payload.len();
+    if buffer.len() < count {
+        return Err(SysExError::BufferTooSmall);
+    }
+    buffer[0] = SYS_EX_START;
+    let mid_count = manufacturer_id.write(&mut buffer[1..])?;
+    buffer[1 + mid_count..count - 1].copy_from_slice(payload);
+    buffer[count - 1] = SYS_EX_END;
+    Ok(count)
+}
+
+fn validate_data_bytes(bytes: &[u8]) -> Result<(), SysExError> {
+    if bytes.iter().any(|b| *b > 0x7F) {
+        return Err(SysExError::InvalidDataByte);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::u7;
+
+    #[test]
+    fn from_bytes_standard_manufacturer_id() {
+        // Given
+        let msg = SysExMessage::from_bytes(&[0xF0, 0x41, 0x10, 0x42, 0xF7]).unwrap();
+        // When
+        // Then
+        assert_eq!(msg.manufacturer_id(), ManufacturerId::Standard(u7(0x41)));
+        assert_eq!(msg.data(), &[0x41, 0x10, 0x42]);
+        assert_eq!(msg.payload(), &[0x10, 0x42]);
+        assert_eq!(msg.as_bytes(), &[0xF0, 0x41, 0x10, 0x42, 0xF7]);
+    }
+
+    #[test]
+    fn from_bytes_extended_manufacturer_id() {
+        // Given
+        let msg = SysExMessage::from_bytes(&[0xF0, 0x00, 0x20, 0x6B, 0x7F, 0xF7]).unwrap();
+        // When
+        // Then
+        assert_eq!(
+            msg.manufacturer_id(),
+            ManufacturerId::Extended(u7(0x20), u7(0x6B))
+        );
+        assert_eq!(msg.manufacturer_id().byte_count(), 3);
+        assert_eq!(msg.payload(), &[0x7F]);
+    }
+
+    #[test]
+    fn from_bytes_universal() {
+        // Given
+        let msg = SysExMessage::from_bytes(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]).unwrap();
+        // When
+        // Then
+        assert_eq!(
+            msg.manufacturer_id(),
+            ManufacturerId::UNIVERSAL_NON_REAL_TIME
+        );
+        assert!(msg.manufacturer_id().is_universal());
+        assert!(!ManufacturerId::NON_COMMERCIAL.is_universal());
+        assert_eq!(msg.payload(), &[0x7F, 0x06, 0x01]);
+    }
+
+    #[test]
+    fn from_bytes_err() {
+        // Given
+        // When
+        // Then
+        use SysExError::*;
+        assert_eq!(SysExMessage::from_bytes(&[]), Err(MissingStart));
+        assert_eq!(SysExMessage::from_bytes(&[0x41, 0xF7]), Err(MissingStart));
+        assert_eq!(SysExMessage::from_bytes(&[0xF0]), Err(MissingEnd));
+        assert_eq!(
+            SysExMessage::from_bytes(&[0xF0, 0x41, 0x10]),
+            Err(MissingEnd)
+        );
+        assert_eq!(
+            SysExMessage::from_bytes(&[0xF0, 0x41, 0x90, 0xF7]),
+            Err(InvalidDataByte)
+        );
+        assert_eq!(
+            SysExMessage::from_bytes(&[0xF0, 0xF7]),
+            Err(InvalidManufacturerId)
+        );
+        assert_eq!(
+            SysExMessage::from_bytes(&[0xF0, 0x00, 0x20, 0xF7]),
+            Err(InvalidManufacturerId)
+        );
+    }
+
+    #[test]
+    fn write() {
+        // Given
+        let mut buffer = [0; 8];
+        // When
+        let msg =
+            SysExMessage::write(&mut buffer, ManufacturerId::Standard(u7(0x43)), &[1, 2]).unwrap();
+        // Then
+        assert_eq!(msg.as_bytes(), &[0xF0, 0x43, 1, 2, 0xF7]);
+        assert_eq!(msg.payload(), &[1, 2]);
+    }
+
+    #[test]
+    fn write_err() {
+        // Given
+        let mut buffer = [0; 4];
+        // When
+        // Then
+        use SysExError::*;
+        assert_eq!(
+            SysExMessage::write(&mut buffer, ManufacturerId::NON_COMMERCIAL, &[1, 2, 3]),
+            Err(BufferTooSmall)
+        );
+        assert_eq!(
+            SysExMessage::write(&mut buffer, ManufacturerId::NON_COMMERCIAL, &[0x80]),
+            Err(InvalidDataByte)
+        );
+        assert_eq!(
+            SysExMessage::write(&mut buffer, ManufacturerId::Standard(u7(0)), &[]),
+            Err(InvalidManufacturerId)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn owned() {
+        // Given
+        let msg =
+            OwnedSysExMessage::new(ManufacturerId::Extended(u7(0x20), u7(0x29)), &[5, 6]).unwrap();
+        // When
+        let borrowed = msg.borrow();
+        let owned_again = OwnedSysExMessage::from(borrowed);
+        let bytes: Vec<u8> = owned_again.clone().into();
+        // Then
+        assert_eq!(msg.as_bytes(), &[0xF0, 0x00, 0x20, 0x29, 5, 6, 0xF7]);
+        assert_eq!(msg.payload(), &[5, 6]);
+        assert_eq!(msg.manufacturer_id(), borrowed.manufacturer_id());
+        assert_eq!(owned_again, msg);
+        assert_eq!(OwnedSysExMessage::try_from(bytes), Ok(msg));
+        assert_eq!(
+            OwnedSysExMessage::from_bytes(vec![0xF0, 0x7D, 0xF8, 0xF7]),
+            Err(SysExError::InvalidDataByte)
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    RawShortMessage, ShortMessageFactory, SysExError, SysExMessage, SYS_EX_END, SYS_EX_START, U7,
+};
+
+/// Storage used by a [`SysExScanner`] to accumulate the bytes of a System Exclusive message.
+///
+/// This crate provides a fixed-size implementation which doesn't allocate
+/// ([`FixedSysExBuffer`]) and, with the `std` feature enabled, a growable one (`Vec<u8>`).
+///
+/// [`SysExScanner`]: struct.SysExScanner.html
+/// [`FixedSysExBuffer`]: struct.FixedSysExBuffer.html
+pub trait SysExBuffer {
+    /// Removes all bytes.
+    fn clear(&mut self);
+
+    /// Appends the given byte.
+    ///
+    /// Returns `false` if the buffer is full.
+    fn push(&mut self, byte: u8) -> bool;
+
+    /// Returns all bytes appended since the last clear.
+    fn as_slice(&self) -> &[u8];
+}
+
+/// A [`SysExBuffer`] which is backed by caller-supplied storage of fixed size, e.g. an array or a
+/// mutable slice.
+///
+/// [`SysExBuffer`]: trait.SysExBuffer.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct FixedSysExBuffer<S> {
+    storage: S,
+    len: usize,
+}
+
+impl<S: AsRef<[u8]> + AsMut<[u8]>> FixedSysExBuffer<S> {
+    /// Creates a buffer with the given storage.
+    ///
+    /// The capacity of the buffer corresponds to the length of the storage.
+    pub fn new(storage: S) -> FixedSysExBuffer<S> {
+        FixedSysExBuffer { storage, len: 0 }
+    }
+
+    /// Consumes this buffer and returns the storage.
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+}
+
+impl<S: AsRef<[u8]> + AsMut<[u8]>> SysExBuffer for FixedSysExBuffer<S> {
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn push(&mut self, byte: u8) -> bool {
+        let storage = self.storage.as_mut();
+        if self.len >= storage.len() {
+            return false;
+        }
+        storage[self.len] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.storage.as_ref()[..self.len]
+    }
+}
+
+#[cfg(feature = "std")]
+impl SysExBuffer for Vec<u8> {
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+
+    fn push(&mut self, byte: u8) -> bool {
+        Vec::push(self, byte);
+        true
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Something that the [`SysExScanner`] detected.
+///
+/// [`SysExScanner`]: struct.SysExScanner.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SysExScannerEvent<'a> {
+    /// A System Exclusive message has been received completely.
+    Message(SysExMessage<'a>),
+    /// A System Exclusive message has been received completely but is invalid, e.g. because it
+    /// lacks a manufacturer ID.
+    Invalid(SysExError),
+    /// A System Real Time message has been received. If this happened in the middle of a System
+    /// Exclusive message, scanning of that message just continues.
+    RealTimeMessage(RawShortMessage),
+    /// The System Exclusive message currently being received doesn't fit into the buffer. Its
+    /// remaining bytes are going to be discarded.
+    Overflow,
+    /// The System Exclusive message currently being received has been aborted by a status byte
+    /// other than System Exclusive End.
+    Aborted,
+}
+
+/// Scanner for detecting System Exclusive messages in a stream of raw MIDI bytes, e.g. delivered
+/// by a driver in chunks of arbitrary size.
+///
+/// All bytes which are neither part of a System Exclusive message nor System Real Time messages
+/// are ignored. Use the [`ShortMessageParser`] on the same bytes in order to get hold of those.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{FixedSysExBuffer, SysExScanner, SysExScannerEvent};
+/// use helgoboss_midi::test_util::timing_clock;
+///
+/// let mut scanner = SysExScanner::new(FixedSysExBuffer::new([0; 64]));
+/// let chunks: [&[u8]; 2] = [&[0xF0, 0x7E, 0x7F, 0xF8], &[0x06, 0x01, 0xF7]];
+/// for chunk in chunks.iter() {
+///     for byte in chunk.iter() {
+///         match scanner.feed(*byte) {
+///             Some(SysExScannerEvent::Message(msg)) => {
+///                 assert_eq!(msg.as_bytes(), &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]);
+///             }
+///             Some(SysExScannerEvent::RealTimeMessage(msg)) => {
+///                 assert_eq!(msg, timing_clock());
+///             }
+///             Some(_) => panic!("unexpected event"),
+///             None => {}
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`ShortMessageParser`]: struct.ShortMessageParser.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SysExScanner<B> {
+    buffer: B,
+    state: State,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+enum State {
+    /// Not within a System Exclusive message.
+    Idle,
+    /// Within a System Exclusive message.
+    Receiving,
+    /// Within a System Exclusive message which doesn't fit into the buffer.
+    Overflowed,
+}
+
+impl<B: SysExBuffer> SysExScanner<B> {
+    /// Creates a new scanner which accumulates messages in the given buffer.
+    pub fn new(buffer: B) -> SysExScanner<B> {
+        SysExScanner {
+            buffer,
+            state: State::Idle,
+        }
+    }
+
+    /// Feeds the scanner a single byte.
+    ///
+    /// Returns an event if the byte completed, aborted or overflowed a System Exclusive message or
+    /// if it's a System Real Time message.
+    pub fn feed(&mut self, byte: u8) -> Option<SysExScannerEvent<'_>> {
+        if byte >= 0xF8 {
+            let msg = RawShortMessage::from_bytes((byte, U7::MIN, U7::MIN)).expect("impossible");
+            return Some(SysExScannerEvent::RealTimeMessage(msg));
+        }
+        if byte == SYS_EX_START {
+            let was_receiving = self.state == State::Receiving;
+            self.buffer.clear();
+            self.buffer.push(byte);
+            self.state = State::Receiving;
+            return if was_receiving {
+                Some(SysExScannerEvent::Aborted)
+            } else {
+                None
+            };
+        }
+        match self.state {
+            State::Idle => None,
+            State::Overflowed => {
+                if byte >= 0x80 {
+                    self.state = State::Idle;
+                }
+                None
+            }
+            State::Receiving => {
+                if byte >= 0x80 && byte != SYS_EX_END {
+                    self.state = State::Idle;
+                    return Some(SysExScannerEvent::Aborted);
+                }
+                if !self.buffer.push(byte) {
+                    self.state = if byte == SYS_EX_END {
+                        State::Idle
+                    } else {
+                        State::Overflowed
+                    };
+                    return Some(SysExScannerEvent::Overflow);
+                }
+                if byte != SYS_EX_END {
+                    return None;
+                }
+                self.state = State::Idle;
+                let event = match SysExMessage::from_bytes(self.buffer.as_slice()) {
+                    Ok(msg) => SysExScannerEvent::Message(msg),
+                    Err(e) => SysExScannerEvent::Invalid(e),
+                };
+                Some(event)
+            }
+        }
+    }
+
+    /// Resets the scanner discarding all intermediate scanning progress.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.state = State::Idle;
+    }
+
+    /// Consumes this scanner and returns the buffer.
+    pub fn into_buffer(self) -> B {
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{active_sensing, timing_clock};
+    use crate::ShortMessage;
+
+    #[derive(Debug, PartialEq)]
+    enum OwnedEvent {
+        Message(Vec<u8>),
+        Invalid(SysExError),
+        RealTimeMessage(u8),
+        Overflow,
+        Aborted,
+    }
+
+    fn scan(scanner: &mut SysExScanner<impl SysExBuffer>, bytes: &[u8]) -> Vec<OwnedEvent> {
+        bytes
+            .iter()
+            .filter_map(|b| {
+                let event = match scanner.feed(*b)? {
+                    SysExScannerEvent::Message(msg) => OwnedEvent::Message(msg.as_bytes().to_vec()),
+                    SysExScannerEvent::Invalid(e) => OwnedEvent::Invalid(e),
+                    SysExScannerEvent::RealTimeMessage(msg) => {
+                        OwnedEvent::RealTimeMessage(msg.status_byte())
+                    }
+                    SysExScannerEvent::Overflow => OwnedEvent::Overflow,
+                    SysExScannerEvent::Aborted => OwnedEvent::Aborted,
+                };
+                Some(event)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_ignore_non_contributing_bytes() {
+        // Given
+        let mut scanner = SysExScanner::new(FixedSysExBuffer::new([0; 8]));
+        // When
+        let result = scan(&mut scanner, &[0x90, 64, 100, 0xF7, 0xC0, 5]);
+        // Then
+        assert_eq!(result, vec![]);
+    }
+
+    #[test]
+    fn fragmented_message() {
+        // Given
+        let mut scanner = SysExScanner::new(FixedSysExBuffer::new([0; 8]));
+        // When
+        let result_1 = scan(&mut scanner, &[0x90, 64, 100, 0xF0, 0x41]);
+        let result_2 = scan(&mut scanner, &[0x10, 0x42]);
+        let result_3 = scan(&mut scanner, &[0xF7, 0xF0, 0x43, 0xF7]);
+        // Then
+        assert_eq!(result_1, vec![]);
+        assert_eq!(result_2, vec![]);
+        assert_eq!(
+            result_3,
+            vec![
+                OwnedEvent::Message(vec![0xF0, 0x41, 0x10, 0x42, 0xF7]),
+                OwnedEvent::Message(vec![0xF0, 0x43, 0xF7]),
+            ]
+        );
+    }
+
+    #[test]
+    fn interleaved_real_time_messages() {
+        // Given
+        let mut scanner = SysExScanner::new(FixedSysExBuffer::new([0; 8]));
+        // When
+        let result = scan(&mut scanner, &[0xFE, 0xF0, 0x41, 0xF8, 0x10, 0xF8, 0xF7]);
+        // Then
+        assert_eq!(
+            result,
+            vec![
+                OwnedEvent::RealTimeMessage(active_sensing().status_byte()),
+                OwnedEvent::RealTimeMessage(timing_clock().status_byte()),
+                OwnedEvent::RealTimeMessage(timing_clock().status_byte()),
+                OwnedEvent::Message(vec![0xF0, 0x41, 0x10, 0xF7]),
+            ]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        // Given
+        let mut buffer = [0; 4];
+        let mut scanner = SysExScanner::new(FixedSysExBuffer::new(&mut buffer[..]));
+        // When
+        let result_1 = scan(&mut scanner, &[0xF0, 0x41, 1, 2, 3, 4, 0xF7]);
+        let result_2 = scan(&mut scanner, &[0xF0, 0x41, 1, 0xF7]);
+        let result_3 = scan(&mut scanner, &[0xF0, 0x41, 1, 2, 0xF7]);
+        // Then
+        assert_eq!(result_1, vec![OwnedEvent::Overflow]);
+        assert_eq!(
+            result_2,
+            vec![OwnedEvent::Message(vec![0xF0, 0x41, 1, 0xF7])]
+        );
+        assert_eq!(result_3, vec![OwnedEvent::Overflow]);
+    }
+
+    #[test]
+    fn abort() {
+        // Given
+        let mut scanner = SysExScanner::new(FixedSysExBuffer::new([0; 8]));
+        // When
+        let result_1 = scan(&mut scanner, &[0xF0, 0x41, 1, 0x90, 64, 100, 0xF7]);
+        let result_2 = scan(&mut scanner, &[0xF0, 0x41, 1, 0xF0, 0x42, 0xF7]);
+        // Then
+        assert_eq!(result_1, vec![OwnedEvent::Aborted]);
+        assert_eq!(
+            result_2,
+            vec![
+                OwnedEvent::Aborted,
+                OwnedEvent::Message(vec![0xF0, 0x42, 0xF7])
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        // Given
+        let mut scanner = SysExScanner::new(FixedSysExBuffer::new([0; 8]));
+        // When
+        let result = scan(&mut scanner, &[0xF0, 0xF7]);
+        // Then
+        assert_eq!(
+            result,
+            vec![OwnedEvent::Invalid(SysExError::InvalidManufacturerId)]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn growable_buffer() {
+        // Given
+        let mut scanner = SysExScanner::new(Vec::new());
+        let mut bytes = vec![0xF0, 0x7D];
+        bytes.extend((0..1000).map(|i| (i % 128) as u8));
+        bytes.push(0xF7);
+        // When
+        let result = scan(&mut scanner, &bytes);
+        // Then
+        assert_eq!(result, vec![OwnedEvent::Message(bytes)]);
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{real_frame_rate, MetaEvent, SmfDivision, SmfError, SmfReader, Tempo, TimeSignature};
+use std::convert::TryFrom;
+
+/// Tolerance for rounding errors when determining bar and beat boundaries.
+const EPSILON: f64 = 1e-9;
+
+const MICROS_PER_SECOND: f64 = 1_000_000.0;
+
+/// A musical position expressed in bars and beats.
+///
+/// Bars and beats are counted from zero. The beat unit is given by the denominator of the time
+/// signature, e.g. an eighth note in 6/8.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BarBeat {
+    bar: u32,
+    beat: u32,
+    fraction: f64,
+}
+
+impl BarBeat {
+    /// Creates a musical position.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the fraction is not within `[0.0, 1.0)`.
+    pub fn new(bar: u32, beat: u32, fraction: f64) -> BarBeat {
+        assert!(
+            (0.0..1.0).contains(&fraction),
+            "fraction must be within [0.0, 1.0)"
+        );
+        BarBeat {
+            bar,
+            beat,
+            fraction,
+        }
+    }
+
+    /// Returns the bar (starting with 0).
+    pub fn bar(&self) -> u32 {
+        self.bar
+    }
+
+    /// Returns the beat within the bar (starting with 0).
+    pub fn beat(&self) -> u32 {
+        self.beat
+    }
+
+    /// Returns the position within the beat (from 0.0 to 1.0, exclusive).
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+}
+
+/// Tempo and time signature changes of a sequence, used to convert between ticks, seconds and
+/// bars/beats.
+///
+/// Works with metrical (ticks per quarter note) as well as time-code-based (SMPTE) division. In the
+/// latter case, ticks map directly to real time, and tempo changes only affect the musical position
+/// (bars/beats).
+///
+/// Until the first tempo change, the tempo is 120 BPM. Until the first time signature change, the
+/// time signature is 4/4. A time signature change in the middle of a bar starts a new bar.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{BarBeat, SmfDivision, Tempo, TempoMap, TimeSignature};
+///
+/// let mut map = TempoMap::new(SmfDivision::TicksPerQuarterNote(480));
+/// // Change to 60 BPM and 3/4 after 2 bars
+/// map.insert_tempo(3840, Tempo::from_bpm(60.0));
+/// map.insert_time_signature(3840, TimeSignature::simple(3, 4));
+/// // 2 bars at 120 BPM take 4 seconds
+/// assert_eq!(map.ticks_to_seconds(3840), 4.0);
+/// // One more quarter note at 60 BPM takes 1 second
+/// assert_eq!(map.ticks_to_seconds(4320), 5.0);
+/// assert_eq!(map.seconds_to_ticks(5.0), 4320.0);
+/// assert_eq!(map.ticks_to_bar_beat(4320), BarBeat::new(2, 1, 0.0));
+/// // Bar 3 starts after 2 bars of 4/4 and 1 bar of 3/4
+/// assert_eq!(map.bar_beat_to_ticks(BarBeat::new(3, 0, 0.5)), 5520.0);
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct TempoMap {
+    division: SmfDivision,
+    /// Tempo changes sorted by tick.
+    tempo_changes: Vec<(u64, Tempo)>,
+    /// Time signature changes sorted by tick.
+    time_signature_changes: Vec<(u64, TimeSignature)>,
+}
+
+impl TempoMap {
+    /// Creates a tempo map without any tempo or time signature changes.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the division has zero ticks per quarter note or per frame.
+    pub fn new(division: SmfDivision) -> TempoMap {
+        let ticks = match division {
+            SmfDivision::TicksPerQuarterNote(ticks) => ticks,
+            SmfDivision::Smpte {
+                ticks_per_frame, ..
+            } => u16::from(ticks_per_frame),
+        };
+        assert!(ticks > 0, "division must not be zero");
+        TempoMap {
+            division,
+            tempo_changes: vec![],
+            time_signature_changes: vec![],
+        }
+    }
+
+    /// Creates a tempo map from the Tempo and Time Signature meta events contained in the tracks
+    /// of the given Standard MIDI File.
+    ///
+    /// Events of all tracks are taken into account, so for format 2 files, this is only meaningful
+    /// if the file contains just one pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file is malformed.
+    pub fn from_smf(reader: &SmfReader<'_>) -> Result<TempoMap, SmfError> {
+        let mut map = TempoMap::new(reader.header().division());
+        for track in reader.tracks() {
+            let mut tick = 0;
+            for event in track?.events() {
+                let event = event?;
+                tick += u64::from(event.delta_time());
+                match MetaEvent::try_from(event.kind()) {
+                    Ok(MetaEvent::Tempo(tempo)) => map.insert_tempo(tick, tempo),
+                    Ok(MetaEvent::TimeSignature(ts)) => map.insert_time_signature(tick, ts),
+                    _ => {}
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Returns the meaning of ticks.
+    pub fn division(&self) -> SmfDivision {
+        self.division
+    }
+
+    /// Sets the tempo from the given tick on, replacing any tempo change at the same tick.
+    pub fn insert_tempo(&mut self, tick: u64, tempo: Tempo) {
+        insert_change(&mut self.tempo_changes, tick, tempo);
+    }
+
+    /// Sets the time signature from the given tick on, replacing any time signature change at the
+    /// same tick.
+    pub fn insert_time_signature(&mut self, tick: u64, time_signature: TimeSignature) {
+        insert_change(&mut self.time_signature_changes, tick, time_signature);
+    }
+
+    /// Returns the tempo at the given tick.
+    pub fn tempo_at(&self, tick: u64) -> Tempo {
+        value_at(&self.tempo_changes, tick).unwrap_or_default()
+    }
+
+    /// Returns the time signature at the given tick.
+    pub fn time_signature_at(&self, tick: u64) -> TimeSignature {
+        value_at(&self.time_signature_changes, tick).unwrap_or_default()
+    }
+
+    /// Converts the given tick to seconds.
+    pub fn ticks_to_seconds(&self, ticks: u64) -> f64 {
+        self.ticks_to_seconds_internal(ticks as f64)
+    }
+
+    /// Converts the given seconds to ticks (including the fractional part).
+    ///
+    /// Negative seconds result in 0.
+    pub fn seconds_to_ticks(&self, seconds: f64) -> f64 {
+        let seconds = seconds.max(0.0);
+        match self.division {
+            SmfDivision::TicksPerQuarterNote(tpq) => {
+                // Integrating microseconds instead of seconds keeps common values exact
+                let microticks = seconds * f64::from(tpq) * MICROS_PER_SECOND;
+                self.inverse_integrate(microticks, microseconds_per_quarter_note)
+            }
+            SmfDivision::Smpte { .. } => seconds / self.smpte_seconds_per_tick(),
+        }
+    }
+
+    /// Converts the given tick to bars and beats.
+    pub fn ticks_to_bar_beat(&self, ticks: u64) -> BarBeat {
+        self.ticks_to_bar_beat_internal(ticks as f64)
+    }
+
+    /// Converts the given bars and beats to ticks (including the fractional part).
+    ///
+    /// Beats exceeding the bar continue into the following bars.
+    pub fn bar_beat_to_ticks(&self, bar_beat: BarBeat) -> f64 {
+        let mut bar = 0;
+        let mut bar_start = 0.0;
+        let mut time_signature = TimeSignature::default();
+        for &(tick, next_time_signature) in &self.time_signature_changes {
+            let change = self.ticks_to_quarter_notes(tick as f64);
+            let bars_until_change =
+                ((change - bar_start) / bar_length(time_signature) - EPSILON).ceil() as u32;
+            if bar + bars_until_change > bar_beat.bar {
+                break;
+            }
+            bar += bars_until_change;
+            bar_start = change;
+            time_signature = next_time_signature;
+        }
+        let quarter_notes = bar_start
+            + f64::from(bar_beat.bar - bar) * bar_length(time_signature)
+            + (f64::from(bar_beat.beat) + bar_beat.fraction) * beat_length(time_signature);
+        self.quarter_notes_to_ticks(quarter_notes)
+    }
+
+    /// Converts the given seconds to bars and beats.
+    pub fn seconds_to_bar_beat(&self, seconds: f64) -> BarBeat {
+        self.ticks_to_bar_beat_internal(self.seconds_to_ticks(seconds))
+    }
+
+    /// Converts the given bars and beats to seconds.
+    pub fn bar_beat_to_seconds(&self, bar_beat: BarBeat) -> f64 {
+        self.ticks_to_seconds_internal(self.bar_beat_to_ticks(bar_beat))
+    }
+
+    fn ticks_to_seconds_internal(&self, ticks: f64) -> f64 {
+        match self.division {
+            SmfDivision::TicksPerQuarterNote(tpq) => {
+                self.integrate(ticks, microseconds_per_quarter_note)
+                    / (f64::from(tpq) * MICROS_PER_SECOND)
+            }
+            SmfDivision::Smpte { .. } => ticks * self.smpte_seconds_per_tick(),
+        }
+    }
+
+    fn ticks_to_quarter_notes(&self, ticks: f64) -> f64 {
+        match self.division {
+            SmfDivision::TicksPerQuarterNote(tpq) => ticks / f64::from(tpq),
+            SmfDivision::Smpte { .. } => {
+                self.integrate(ticks, |tempo| 1.0 / microseconds_per_quarter_note(tempo))
+                    * self.smpte_seconds_per_tick()
+                    * MICROS_PER_SECOND
+            }
+        }
+    }
+
+    fn quarter_notes_to_ticks(&self, quarter_notes: f64) -> f64 {
+        match self.division {
+            SmfDivision::TicksPerQuarterNote(tpq) => quarter_notes * f64::from(tpq),
+            SmfDivision::Smpte { .. } => {
+                let value = quarter_notes / (self.smpte_seconds_per_tick() * MICROS_PER_SECOND);
+                self.inverse_integrate(value, |tempo| 1.0 / microseconds_per_quarter_note(tempo))
+            }
+        }
+    }
+
+    fn ticks_to_bar_beat_internal(&self, ticks: f64) -> BarBeat {
+        let quarter_notes = self.ticks_to_quarter_notes(ticks);
+        let mut bar = 0;
+        let mut bar_start = 0.0;
+        let mut time_signature = TimeSignature::default();
+        for &(tick, next_time_signature) in &self.time_signature_changes {
+            let change = self.ticks_to_quarter_notes(tick as f64);
+            if change > quarter_notes + EPSILON {
+                break;
+            }
+            bar += ((change - bar_start) / bar_length(time_signature) - EPSILON).ceil() as u32;
+            bar_start = change;
+            time_signature = next_time_signature;
+        }
+        let bars = ((quarter_notes - bar_start) / bar_length(time_signature) + EPSILON).floor();
+        let beats = (quarter_notes - bar_start - bars * bar_length(time_signature))
+            / beat_length(time_signature);
+        let beats = beats.max(0.0);
+        let whole_beats = (beats + EPSILON).floor();
+        let fraction = beats - whole_beats;
+        let fraction = if fraction < EPSILON { 0.0 } else { fraction };
+        BarBeat::new(bar + bars as u32, whole_beats as u32, fraction)
+    }
+
+    fn smpte_seconds_per_tick(&self) -> f64 {
+        match self.division {
+            SmfDivision::Smpte {
+                time_code_type,
+                ticks_per_frame,
+            } => {
+                let (numerator, denominator) = real_frame_rate(time_code_type);
+                denominator as f64 / (numerator as f64 * f64::from(ticks_per_frame))
+            }
+            SmfDivision::TicksPerQuarterNote(_) => unreachable!(),
+        }
+    }
+
+    /// Integrates the given tempo-dependent rate per tick from tick 0 to the given tick.
+    fn integrate(&self, ticks: f64, rate: impl Fn(Tempo) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut segment_start = 0.0;
+        let mut tempo = Tempo::DEFAULT;
+        for &(tick, next_tempo) in &self.tempo_changes {
+            let tick = tick as f64;
+            if tick >= ticks {
+                break;
+            }
+            sum += (tick - segment_start) * rate(tempo);
+            segment_start = tick;
+            tempo = next_tempo;
+        }
+        sum + (ticks - segment_start) * rate(tempo)
+    }
+
+    /// Returns the tick at which the integral of the given tempo-dependent rate per tick reaches
+    /// the given value.
+    fn inverse_integrate(&self, value: f64, rate: impl Fn(Tempo) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut segment_start = 0.0;
+        let mut tempo = Tempo::DEFAULT;
+        for &(tick, next_tempo) in &self.tempo_changes {
+            let tick = tick as f64;
+            let segment_sum = (tick - segment_start) * rate(tempo);
+            if sum + segment_sum >= value {
+                break;
+            }
+            sum += segment_sum;
+            segment_start = tick;
+            tempo = next_tempo;
+        }
+        segment_start + (value - sum) / rate(tempo)
+    }
+}
+
+fn insert_change<T>(changes: &mut Vec<(u64, T)>, tick: u64, value: T) {
+    match changes.binary_search_by_key(&tick, |(t, _)| *t) {
+        Ok(i) => changes[i].1 = value,
+        Err(i) => changes.insert(i, (tick, value)),
+    }
+}
+
+fn value_at<T: Copy>(changes: &[(u64, T)], tick: u64) -> Option<T> {
+    changes
+        .iter()
+        .take_while(|(t, _)| *t <= tick)
+        .last()
+        .map(|(_, v)| *v)
+}
+
+fn microseconds_per_quarter_note(tempo: Tempo) -> f64 {
+    f64::from(tempo.microseconds_per_quarter_note())
+}
+
+/// Returns the length of a bar in quarter notes.
+fn bar_length(time_signature: TimeSignature) -> f64 {
+    f64::from(time_signature.numerator()) * beat_length(time_signature)
+}
+
+/// Returns the length of a beat in quarter notes.
+fn beat_length(time_signature: TimeSignature) -> f64 {
+    4.0 / f64::from(time_signature.denominator())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeCodeType;
+
+    fn assert_approx_eq(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} is not approximately {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_division() {
+        TempoMap::new(SmfDivision::TicksPerQuarterNote(0));
+    }
+
+    #[test]
+    fn defaults() {
+        // Given
+        let map = TempoMap::new(SmfDivision::TicksPerQuarterNote(96));
+        // When
+        // Then
+        assert_eq!(map.tempo_at(1000), Tempo::DEFAULT);
+        assert_eq!(map.time_signature_at(1000), TimeSignature::simple(4, 4));
+        assert_eq!(map.ticks_to_seconds(96), 0.5);
+        assert_eq!(map.seconds_to_ticks(1.0), 192.0);
+        assert_eq!(map.seconds_to_ticks(-1.0), 0.0);
+        assert_eq!(map.ticks_to_bar_beat(96 * 5 + 48), BarBeat::new(1, 1, 0.5));
+    }
+
+    #[test]
+    fn tempo_changes() {
+        // Given
+        let mut map = TempoMap::new(SmfDivision::TicksPerQuarterNote(100));
+        // When
+        map.insert_tempo(200, Tempo::from_bpm(60.0));
+        map.insert_tempo(100, Tempo::from_bpm(240.0));
+        map.insert_tempo(200, Tempo::from_bpm(30.0));
+        // Then
+        assert_eq!(map.tempo_at(99), Tempo::DEFAULT);
+        assert_eq!(map.tempo_at(100), Tempo::from_bpm(240.0));
+        assert_eq!(map.tempo_at(250), Tempo::from_bpm(30.0));
+        assert_eq!(map.ticks_to_seconds(100), 0.5);
+        assert_eq!(map.ticks_to_seconds(200), 0.75);
+        assert_eq!(map.ticks_to_seconds(300), 2.75);
+        assert_eq!(map.seconds_to_ticks(0.625), 150.0);
+        assert_eq!(map.seconds_to_ticks(2.75), 300.0);
+        assert_eq!(map.seconds_to_ticks(3.75), 350.0);
+    }
+
+    #[test]
+    fn time_signature_changes() {
+        // Given
+        let mut map = TempoMap::new(SmfDivision::TicksPerQuarterNote(480));
+        // When
+        // Change to 6/8 in the middle of the second bar
+        map.insert_time_signature(2400, TimeSignature::simple(6, 8));
+        // Then
+        assert_eq!(map.ticks_to_bar_beat(2160), BarBeat::new(1, 0, 0.5));
+        assert_eq!(map.ticks_to_bar_beat(2400), BarBeat::new(2, 0, 0.0));
+        assert_eq!(
+            map.ticks_to_bar_beat(2400 + 240 * 7),
+            BarBeat::new(3, 1, 0.0)
+        );
+        assert_eq!(map.bar_beat_to_ticks(BarBeat::new(1, 0, 0.5)), 2160.0);
+        assert_eq!(map.bar_beat_to_ticks(BarBeat::new(2, 0, 0.0)), 2400.0);
+        assert_eq!(map.bar_beat_to_ticks(BarBeat::new(3, 1, 0.0)), 4080.0);
+        assert_eq!(map.bar_beat_to_seconds(BarBeat::new(2, 0, 0.0)), 2.5);
+        assert_eq!(map.seconds_to_bar_beat(2.5), BarBeat::new(2, 0, 0.0));
+    }
+
+    #[test]
+    fn smpte_division() {
+        // Given
+        let mut map = TempoMap::new(SmfDivision::Smpte {
+            time_code_type: TimeCodeType::Fps25,
+            ticks_per_frame: 40,
+        });
+        // When
+        map.insert_tempo(4000, Tempo::from_bpm(60.0));
+        // Then
+        // Tempo changes don't affect real time ...
+        assert_approx_eq(map.ticks_to_seconds(1000), 1.0);
+        assert_approx_eq(map.ticks_to_seconds(5000), 5.0);
+        assert_approx_eq(map.seconds_to_ticks(5.0), 5000.0);
+        // ... but the musical position
+        assert_eq!(map.ticks_to_bar_beat(2500), BarBeat::new(1, 1, 0.0));
+        assert_eq!(map.ticks_to_bar_beat(5000), BarBeat::new(2, 1, 0.0));
+        assert_approx_eq(map.bar_beat_to_ticks(BarBeat::new(2, 1, 0.0)), 5000.0);
+    }
+
+    #[test]
+    fn smpte_division_drop_frame() {
+        // Given
+        let map = TempoMap::new(SmfDivision::Smpte {
+            time_code_type: TimeCodeType::Fps30DropFrame,
+            ticks_per_frame: 4,
+        });
+        // When
+        // Then
+        assert_approx_eq(map.ticks_to_seconds(120), 1.001);
+        assert_approx_eq(map.seconds_to_ticks(1.001), 120.0);
+    }
+
+    #[test]
+    fn from_smf() {
+        // Given
+        #[rustfmt::skip]
+        let bytes = [
+            b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 1, 0, 2, 0, 96,
+            b'M', b'T', b'r', b'k', 0, 0, 0, 26,
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20,
+            0x60, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40,
+            0x00, 0xFF, 0x58, 0x04, 0x03, 0x02, 0x18, 0x08,
+            0x00, 0xFF, 0x2F, 0x00,
+            b'M', b'T', b'r', b'k', 0, 0, 0, 12,
+            0x00, 0x90, 0x3C, 0x64,
+            0x60, 0x80, 0x3C, 0x40,
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let reader = SmfReader::new(&bytes).unwrap();
+        // When
+        let map = TempoMap::from_smf(&reader).unwrap();
+        // Then
+        assert_eq!(map.division(), SmfDivision::TicksPerQuarterNote(96));
+        assert_eq!(map.tempo_at(95), Tempo::DEFAULT);
+        assert_eq!(map.tempo_at(96), Tempo::new(1_000_000));
+        assert_eq!(map.time_signature_at(96), TimeSignature::simple(3, 4));
+        assert_eq!(map.ticks_to_seconds(192), 1.5);
+        assert_eq!(map.ticks_to_bar_beat(96 * 4), BarBeat::new(2, 0, 0.0));
+    }
+}
@@ -0,0 +1,549 @@
+use crate::TimeCodeType;
+use core::fmt;
+use core::ops::{Add, Sub};
+use core::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An error which can occur when parsing a string to a time code position.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "parsing string to time code failed")]
+pub struct ParseTimecodeError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseTimecodeError {}
+
+/// An error which can occur when creating a time code position from values which are out of range.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "time code position is invalid")]
+pub struct InvalidTimecodeError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidTimecodeError {}
+
+/// An SMPTE time code position, e.g. as transmitted via MIDI Time Code.
+///
+/// Positions wrap around after 24 hours. Arithmetic takes dropped frame numbers into account when
+/// using [`Fps30DropFrame`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{TimeCodeType, Timecode};
+///
+/// let timecode = Timecode::new(1, 2, 3, 4, TimeCodeType::Fps25);
+/// assert_eq!(timecode.hours(), 1);
+/// assert_eq!(timecode.minutes(), 2);
+/// assert_eq!(timecode.seconds(), 3);
+/// assert_eq!(timecode.frames(), 4);
+/// assert_eq!(timecode.rate(), TimeCodeType::Fps25);
+/// assert_eq!(timecode.to_string(), "01:02:03:04");
+///
+/// let timecode = Timecode::parse("00:00:59;29", TimeCodeType::Fps30DropFrame).unwrap();
+/// assert_eq!(timecode.add_frames(1).to_string(), "00:01:00;02");
+/// assert_eq!(timecode.add_frames(1).frame_count(), 1800);
+/// ```
+///
+/// [`Fps30DropFrame`]: enum.TimeCodeType.html#variant.Fps30DropFrame
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "TimecodeData")
+)]
+pub struct Timecode {
+    hours: u8,
+    minutes: u8,
+    seconds: u8,
+    frames: u8,
+    rate: TimeCodeType,
+}
+
+/// Unvalidated time code fields, used for deserialization.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct TimecodeData {
+    hours: u8,
+    minutes: u8,
+    seconds: u8,
+    frames: u8,
+    rate: TimeCodeType,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<TimecodeData> for Timecode {
+    type Error = InvalidTimecodeError;
+
+    fn try_from(data: TimecodeData) -> Result<Self, Self::Error> {
+        Timecode::try_new(
+            data.hours,
+            data.minutes,
+            data.seconds,
+            data.frames,
+            data.rate,
+        )
+        .ok_or(InvalidTimecodeError(()))
+    }
+}
+
+impl Timecode {
+    /// Creates a time code position.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if one of the given values is out of range or if the position
+    /// doesn't exist with the given rate. E.g. frames 0 and 1 are skipped at the start of each
+    /// minute except every 10th minute when using [`Fps30DropFrame`].
+    ///
+    /// [`Fps30DropFrame`]: enum.TimeCodeType.html#variant.Fps30DropFrame
+    pub fn new(hours: u8, minutes: u8, seconds: u8, frames: u8, rate: TimeCodeType) -> Timecode {
+        Timecode::try_new(hours, minutes, seconds, frames, rate).expect("invalid time code")
+    }
+
+    /// Creates the position which is the given number of frames after 00:00:00:00, wrapping
+    /// around after 24 hours (and going backwards if negative).
+    pub fn from_frame_count(frame_count: i64, rate: TimeCodeType) -> Timecode {
+        let count = frame_count.rem_euclid(i64::from(frames_per_day(rate))) as u32;
+        let count = if rate == TimeCodeType::Fps30DropFrame {
+            // Reinsert the skipped frame numbers
+            let ten_minute_blocks = count / DROP_FRAME_FRAMES_PER_10_MINUTES;
+            let remainder = count % DROP_FRAME_FRAMES_PER_10_MINUTES;
+            let skipped_in_block = if remainder < 2 {
+                0
+            } else {
+                2 * ((remainder - 2) / DROP_FRAME_FRAMES_PER_MINUTE)
+            };
+            count + 18 * ten_minute_blocks + skipped_in_block
+        } else {
+            count
+        };
+        let fps = u32::from(nominal_frames_per_second(rate));
+        let total_seconds = count / fps;
+        Timecode {
+            hours: (total_seconds / 3600) as u8,
+            minutes: (total_seconds / 60 % 60) as u8,
+            seconds: (total_seconds % 60) as u8,
+            frames: (count % fps) as u8,
+            rate,
+        }
+    }
+
+    /// Creates the position of the frame which is running at the given real time after
+    /// 00:00:00:00, wrapping around after 24 hours.
+    ///
+    /// Uses the real frame rate, that is, 29.97 frames per second for [`Fps30DropFrame`].
+    ///
+    /// [`Fps30DropFrame`]: enum.TimeCodeType.html#variant.Fps30DropFrame
+    pub fn from_duration(duration: Duration, rate: TimeCodeType) -> Timecode {
+        let (numerator, denominator) = real_frame_rate(rate);
+        let frame_count = duration.as_nanos() * numerator / (NANOS_PER_SECOND * denominator);
+        let frame_count = frame_count % u128::from(frames_per_day(rate));
+        Timecode::from_frame_count(frame_count as i64, rate)
+    }
+
+    /// Parses a position in `HH:MM:SS:FF` notation.
+    ///
+    /// With rate [`Fps30DropFrame`], the last separator may also be a semicolon (`HH:MM:SS;FF`),
+    /// which is the common notation for drop-frame time code. With other rates, it must be a colon.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string doesn't have the expected format or doesn't denote a valid
+    /// position.
+    ///
+    /// [`Fps30DropFrame`]: enum.TimeCodeType.html#variant.Fps30DropFrame
+    pub fn parse(source: &str, rate: TimeCodeType) -> Result<Timecode, ParseTimecodeError> {
+        let bytes = source.as_bytes();
+        if bytes.len() != 11 || bytes[2] != b':' || bytes[5] != b':' {
+            return Err(ParseTimecodeError(()));
+        }
+        match bytes[8] {
+            b':' => {}
+            b';' if rate == TimeCodeType::Fps30DropFrame => {}
+            _ => return Err(ParseTimecodeError(())),
+        }
+        let component = |index: usize| -> Result<u8, ParseTimecodeError> {
+            match (bytes[index], bytes[index + 1]) {
+                (d1 @ b'0'..=b'9', d2 @ b'0'..=b'9') => Ok((d1 - b'0') * 10 + (d2 - b'0')),
+                _ => Err(ParseTimecodeError(())),
+            }
+        };
+        Timecode::try_new(
+            component(0)?,
+            component(3)?,
+            component(6)?,
+            component(9)?,
+            rate,
+        )
+        .ok_or(ParseTimecodeError(()))
+    }
+
+    /// Returns the hours (0 - 23).
+    pub fn hours(&self) -> u8 {
+        self.hours
+    }
+
+    /// Returns the minutes (0 - 59).
+    pub fn minutes(&self) -> u8 {
+        self.minutes
+    }
+
+    /// Returns the seconds (0 - 59).
+    pub fn seconds(&self) -> u8 {
+        self.seconds
+    }
+
+    /// Returns the frames (0 - 23, 0 - 24 or 0 - 29, depending on the rate).
+    pub fn frames(&self) -> u8 {
+        self.frames
+    }
+
+    /// Returns the frame rate.
+    pub fn rate(&self) -> TimeCodeType {
+        self.rate
+    }
+
+    /// Returns the number of frames since 00:00:00:00.
+    pub fn frame_count(&self) -> u32 {
+        let fps = u32::from(nominal_frames_per_second(self.rate));
+        let total_minutes = u32::from(self.hours) * 60 + u32::from(self.minutes);
+        let count = (total_minutes * 60 + u32::from(self.seconds)) * fps + u32::from(self.frames);
+        if self.rate == TimeCodeType::Fps30DropFrame {
+            count - 2 * (total_minutes - total_minutes / 10)
+        } else {
+            count
+        }
+    }
+
+    /// Returns the real time since 00:00:00:00 at which the frame at this position starts.
+    ///
+    /// Uses the real frame rate, that is, 29.97 frames per second for [`Fps30DropFrame`].
+    ///
+    /// [`Fps30DropFrame`]: enum.TimeCodeType.html#variant.Fps30DropFrame
+    pub fn to_duration(self) -> Duration {
+        let (numerator, denominator) = real_frame_rate(self.rate);
+        // Round up so that converting back yields the same frame
+        let nanos = (u128::from(self.frame_count()) * NANOS_PER_SECOND * denominator + numerator
+            - 1)
+            / numerator;
+        Duration::new(
+            (nanos / NANOS_PER_SECOND) as u64,
+            (nanos % NANOS_PER_SECOND) as u32,
+        )
+    }
+
+    /// Returns the real time in seconds since 00:00:00:00 at which the frame at this position
+    /// starts.
+    pub fn to_seconds(self) -> f64 {
+        let (numerator, denominator) = real_frame_rate(self.rate);
+        f64::from(self.frame_count()) * denominator as f64 / numerator as f64
+    }
+
+    /// Returns the position which is the given number of frames later (or earlier if negative),
+    /// wrapping around after 24 hours.
+    pub fn add_frames(&self, frames: i64) -> Timecode {
+        Timecode::from_frame_count(i64::from(self.frame_count()) + frames, self.rate)
+    }
+
+    pub(crate) fn try_new(
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        rate: TimeCodeType,
+    ) -> Option<Timecode> {
+        if hours > 23 || minutes > 59 || seconds > 59 || frames >= nominal_frames_per_second(rate) {
+            return None;
+        }
+        if rate == TimeCodeType::Fps30DropFrame && seconds == 0 && frames < 2 && minutes % 10 != 0 {
+            return None;
+        }
+        let timecode = Timecode {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            rate,
+        };
+        Some(timecode)
+    }
+}
+
+/// Adds the frame count of the right-hand side, wrapping around after 24 hours.
+///
+/// # Panics
+///
+/// Panics if the rates are different.
+impl Add for Timecode {
+    type Output = Timecode;
+
+    fn add(self, rhs: Timecode) -> Timecode {
+        assert_eq!(self.rate, rhs.rate, "time code rate mismatch");
+        self.add_frames(i64::from(rhs.frame_count()))
+    }
+}
+
+/// Subtracts the frame count of the right-hand side, wrapping around after 24 hours.
+///
+/// # Panics
+///
+/// Panics if the rates are different.
+impl Sub for Timecode {
+    type Output = Timecode;
+
+    fn sub(self, rhs: Timecode) -> Timecode {
+        assert_eq!(self.rate, rhs.rate, "time code rate mismatch");
+        self.add_frames(-i64::from(rhs.frame_count()))
+    }
+}
+
+/// Formats the position in `HH:MM:SS:FF` notation or `HH:MM:SS;FF` notation if drop-frame.
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.rate == TimeCodeType::Fps30DropFrame {
+            ';'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, separator, self.frames
+        )
+    }
+}
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Number of frames in 10 minutes when using drop-frame time code.
+const DROP_FRAME_FRAMES_PER_10_MINUTES: u32 = 17982;
+
+/// Number of frames in a minute which is not a multiple of 10 when using drop-frame time code.
+const DROP_FRAME_FRAMES_PER_MINUTE: u32 = 1798;
+
+pub(crate) fn nominal_frames_per_second(rate: TimeCodeType) -> u8 {
+    use TimeCodeType::*;
+    match rate {
+        Fps24 => 24,
+        Fps25 => 25,
+        Fps30DropFrame | Fps30NonDrop => 30,
+    }
+}
+
+/// Returns the real frame rate as fraction.
+pub(crate) fn real_frame_rate(rate: TimeCodeType) -> (u128, u128) {
+    use TimeCodeType::*;
+    match rate {
+        Fps24 => (24, 1),
+        Fps25 => (25, 1),
+        Fps30DropFrame => (30000, 1001),
+        Fps30NonDrop => (30, 1),
+    }
+}
+
+fn frames_per_day(rate: TimeCodeType) -> u32 {
+    if rate == TimeCodeType::Fps30DropFrame {
+        24 * 6 * DROP_FRAME_FRAMES_PER_10_MINUTES
+    } else {
+        24 * 3600 * u32::from(nominal_frames_per_second(rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TimeCodeType::*;
+
+    #[test]
+    fn drop_frame_validation() {
+        // Given
+        // When
+        // Then
+        assert!(Timecode::try_new(0, 1, 0, 0, Fps30DropFrame).is_none());
+        assert!(Timecode::try_new(0, 1, 0, 1, Fps30DropFrame).is_none());
+        assert!(Timecode::try_new(0, 1, 0, 2, Fps30DropFrame).is_some());
+        assert!(Timecode::try_new(0, 10, 0, 0, Fps30DropFrame).is_some());
+        assert!(Timecode::try_new(0, 1, 0, 0, Fps30NonDrop).is_some());
+        assert!(Timecode::try_new(0, 0, 0, 25, Fps25).is_none());
+        assert!(Timecode::try_new(24, 0, 0, 0, Fps24).is_none());
+    }
+
+    #[test]
+    fn frame_count() {
+        // Given
+        // When
+        // Then
+        assert_eq!(Timecode::new(1, 0, 0, 0, Fps24).frame_count(), 86_400);
+        assert_eq!(Timecode::new(1, 0, 0, 0, Fps25).frame_count(), 90_000);
+        assert_eq!(
+            Timecode::new(1, 0, 0, 0, Fps30NonDrop).frame_count(),
+            108_000
+        );
+        assert_eq!(
+            Timecode::new(1, 0, 0, 0, Fps30DropFrame).frame_count(),
+            107_892
+        );
+        assert_eq!(
+            Timecode::new(0, 10, 0, 0, Fps30DropFrame).frame_count(),
+            17_982
+        );
+        assert_eq!(
+            Timecode::new(23, 59, 59, 29, Fps30DropFrame).frame_count(),
+            2_589_407
+        );
+    }
+
+    #[test]
+    fn frame_count_round_trip() {
+        // Given
+        let rates = [Fps24, Fps25, Fps30DropFrame, Fps30NonDrop];
+        // When
+        // Then
+        for rate in rates.iter() {
+            for count in (0..frames_per_day(*rate)).step_by(7) {
+                let timecode = Timecode::from_frame_count(i64::from(count), *rate);
+                assert!(Timecode::try_new(
+                    timecode.hours(),
+                    timecode.minutes(),
+                    timecode.seconds(),
+                    timecode.frames(),
+                    *rate
+                )
+                .is_some());
+                assert_eq!(timecode.frame_count(), count);
+            }
+        }
+    }
+
+    #[test]
+    fn add_and_subtract() {
+        // Given
+        let timecode = Timecode::new(0, 0, 59, 29, Fps30DropFrame);
+        // When
+        // Then
+        assert_eq!(
+            timecode.add_frames(1),
+            Timecode::new(0, 1, 0, 2, Fps30DropFrame)
+        );
+        assert_eq!(timecode.add_frames(1).add_frames(-1), timecode);
+        assert_eq!(
+            Timecode::new(0, 0, 0, 0, Fps25).add_frames(-1),
+            Timecode::new(23, 59, 59, 24, Fps25)
+        );
+        assert_eq!(
+            Timecode::new(0, 9, 59, 29, Fps30DropFrame) + Timecode::new(0, 0, 0, 1, Fps30DropFrame),
+            Timecode::new(0, 10, 0, 0, Fps30DropFrame)
+        );
+        assert_eq!(
+            Timecode::new(0, 2, 0, 2, Fps30DropFrame) - Timecode::new(0, 1, 0, 2, Fps30DropFrame),
+            Timecode::new(0, 0, 59, 28, Fps30DropFrame)
+        );
+    }
+
+    #[test]
+    fn duration() {
+        // Given
+        // When
+        // Then
+        assert_eq!(
+            Timecode::new(0, 0, 1, 12, Fps24).to_duration(),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            Timecode::new(1, 0, 0, 0, Fps30DropFrame).to_duration(),
+            Duration::from_nanos(3_599_996_400_000)
+        );
+        assert_eq!(
+            Timecode::from_duration(Duration::from_secs(3600), Fps30DropFrame),
+            Timecode::new(1, 0, 0, 0, Fps30DropFrame)
+        );
+        assert!((Timecode::new(0, 0, 2, 10, Fps25).to_seconds() - 2.4).abs() < 1e-9);
+        for rate in [Fps24, Fps25, Fps30DropFrame, Fps30NonDrop].iter() {
+            for count in (0..frames_per_day(*rate)).step_by(997) {
+                let timecode = Timecode::from_frame_count(i64::from(count), *rate);
+                assert_eq!(
+                    Timecode::from_duration(timecode.to_duration(), *rate),
+                    timecode
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_and_display() {
+        // Given
+        // When
+        // Then
+        assert_eq!(
+            Timecode::parse("01:02:03:04", Fps25),
+            Ok(Timecode::new(1, 2, 3, 4, Fps25))
+        );
+        assert_eq!(
+            Timecode::parse("00:10:00;00", Fps30DropFrame),
+            Ok(Timecode::new(0, 10, 0, 0, Fps30DropFrame))
+        );
+        assert_eq!(
+            Timecode::parse("00:10:00:00", Fps30DropFrame),
+            Ok(Timecode::new(0, 10, 0, 0, Fps30DropFrame))
+        );
+        assert!(Timecode::parse("00:01:00;00", Fps30DropFrame).is_err());
+        assert!(Timecode::parse("00:00:00;00", Fps30NonDrop).is_err());
+        assert!(Timecode::parse("00:00:00:24", Fps24).is_err());
+        assert!(Timecode::parse("0:00:00:00", Fps24).is_err());
+        assert!(Timecode::parse("00:00:00:0x", Fps24).is_err());
+        assert!(Timecode::parse("00-00-00-00", Fps24).is_err());
+        assert_eq!(
+            Timecode::new(23, 59, 59, 29, Fps30DropFrame).to_string(),
+            "23:59:59;29"
+        );
+        assert_eq!(
+            Timecode::new(1, 2, 3, 4, Fps30NonDrop).to_string(),
+            "01:02:03:04"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize() {
+        // Given
+        let timecode = Timecode::new(1, 2, 3, 4, Fps25);
+        // When
+        let j = serde_json::to_value(timecode).unwrap();
+        // Then
+        assert_eq!(
+            j,
+            serde_json::json!({
+                "hours": 1,
+                "minutes": 2,
+                "seconds": 3,
+                "frames": 4,
+                "rate": "Fps25"
+            })
+        );
+        assert_eq!(serde_json::from_value::<Timecode>(j).unwrap(), timecode);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_invalid() {
+        // Given
+        let out_of_range = serde_json::json!({
+            "hours": 200,
+            "minutes": 2,
+            "seconds": 3,
+            "frames": 4,
+            "rate": "Fps25"
+        });
+        let dropped_frame = serde_json::json!({
+            "hours": 0,
+            "minutes": 1,
+            "seconds": 0,
+            "frames": 0,
+            "rate": "Fps30DropFrame"
+        });
+        // When
+        // Then
+        assert!(serde_json::from_value::<Timecode>(out_of_range).is_err());
+        assert!(serde_json::from_value::<Timecode>(dropped_frame).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    ShortMessage, SongPosition, StructuredShortMessage, TransportState,
+    TIMING_CLOCKS_PER_QUARTER_NOTE,
+};
+
+/// Something that the [`TransportTracker`] detected.
+///
+/// [`TransportTracker`]: struct.TransportTracker.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TransportTrackerEvent {
+    /// Transport has been started from the beginning of the song.
+    Started,
+    /// Transport has been continued from the current song position.
+    Continued,
+    /// Transport has been stopped.
+    Stopped,
+    /// Song position has been changed by a Song Position Pointer message.
+    Located(SongPosition),
+}
+
+/// Tracker for the current musical position of a transport controlled by System Common and System
+/// Real Time messages.
+///
+/// Combines Song Position Pointer, Start, Continue, Stop and Timing Clock messages as described in
+/// the MIDI 1.0 specification:
+///
+/// - Start sets the position to the beginning of the song.
+/// - Song Position Pointer sets the position to the given MIDI beat.
+/// - Each Timing Clock message received while playing advances the position by 1 tick (1/24 of a
+///   quarter note). The first one after Start or Continue marks the current position, so the
+///   position advances *after* it.
+///
+/// The tracker doesn't allocate and can be used in a real-time thread. In order to get the tempo,
+/// feed the same messages into a [`ClockScanner`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{r#continue, song_position_pointer, timing_clock, u14};
+/// use helgoboss_midi::{SongPosition, TransportState, TransportTracker, TransportTrackerEvent};
+///
+/// let mut tracker = TransportTracker::new();
+/// assert_eq!(
+///     tracker.feed(&song_position_pointer(8)),
+///     Some(TransportTrackerEvent::Located(SongPosition::new(u14(8))))
+/// );
+/// assert_eq!(tracker.feed(&r#continue()), Some(TransportTrackerEvent::Continued));
+/// for _ in 0..12 {
+///     tracker.feed(&timing_clock());
+/// }
+/// assert_eq!(tracker.transport_state(), TransportState::Playing);
+/// assert_eq!(tracker.tick(), 60);
+/// assert_eq!(tracker.beats(), 2.5);
+/// assert_eq!(tracker.song_position(), Some(SongPosition::new(u14(10))));
+/// ```
+///
+/// [`ClockScanner`]: struct.ClockScanner.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct TransportTracker {
+    transport_state: TransportState,
+    /// Position in ticks which is marked by the next Timing Clock message when playing.
+    tick: u64,
+}
+
+impl TransportTracker {
+    /// Creates a new tracker, stopped at the beginning of the song.
+    pub fn new() -> TransportTracker {
+        Default::default()
+    }
+
+    /// Feeds the tracker a single short message.
+    ///
+    /// Returns an event if the message changed the transport state or jumped to another position.
+    pub fn feed(&mut self, msg: &impl ShortMessage) -> Option<TransportTrackerEvent> {
+        use StructuredShortMessage::*;
+        match msg.to_structured() {
+            TimingClock => {
+                if self.transport_state == TransportState::Playing {
+                    self.tick += 1;
+                }
+                None
+            }
+            SongPositionPointer { position } => {
+                let position = SongPosition::new(position);
+                self.tick = position.to_ticks();
+                Some(TransportTrackerEvent::Located(position))
+            }
+            Start => {
+                self.transport_state = TransportState::Playing;
+                self.tick = 0;
+                Some(TransportTrackerEvent::Started)
+            }
+            Continue => {
+                self.transport_state = TransportState::Playing;
+                Some(TransportTrackerEvent::Continued)
+            }
+            Stop => {
+                if self.transport_state == TransportState::Stopped {
+                    return None;
+                }
+                self.transport_state = TransportState::Stopped;
+                Some(TransportTrackerEvent::Stopped)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the current transport state.
+    pub fn transport_state(&self) -> TransportState {
+        self.transport_state
+    }
+
+    /// Returns the current position in Timing Clock ticks (24 per quarter note) since the beginning
+    /// of the song.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the current position in beats (quarter notes) since the beginning of the song.
+    pub fn beats(&self) -> f64 {
+        self.tick as f64 / f64::from(TIMING_CLOCKS_PER_QUARTER_NOTE)
+    }
+
+    /// Returns the song position which contains the current position.
+    ///
+    /// Returns `None` if the current position can't be expressed by a Song Position Pointer
+    /// message.
+    pub fn song_position(&self) -> Option<SongPosition> {
+        SongPosition::from_ticks(self.tick)
+    }
+
+    /// Resets the tracker to the initial state (stopped at the beginning of the song).
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{
+        note_on, r#continue, song_position_pointer, start, stop, timing_clock, u14,
+    };
+    use crate::{ClockGenerator, RawShortMessage};
+    use core::time::Duration;
+
+    fn feed_clocks(tracker: &mut TransportTracker, count: usize) {
+        for _ in 0..count {
+            tracker.feed(&timing_clock());
+        }
+    }
+
+    #[test]
+    fn start_stop_continue() {
+        // Given
+        let mut tracker = TransportTracker::new();
+        // When
+        feed_clocks(&mut tracker, 10);
+        let tick_1 = tracker.tick();
+        let event_1 = tracker.feed(&start());
+        feed_clocks(&mut tracker, 30);
+        let event_2 = tracker.feed(&stop());
+        let event_3 = tracker.feed(&stop());
+        feed_clocks(&mut tracker, 10);
+        let tick_2 = tracker.tick();
+        let event_4 = tracker.feed(&r#continue());
+        feed_clocks(&mut tracker, 6);
+        // Then
+        assert_eq!(tick_1, 0);
+        assert_eq!(event_1, Some(TransportTrackerEvent::Started));
+        assert_eq!(event_2, Some(TransportTrackerEvent::Stopped));
+        assert_eq!(event_3, None);
+        assert_eq!(tick_2, 30);
+        assert_eq!(event_4, Some(TransportTrackerEvent::Continued));
+        assert_eq!(tracker.tick(), 36);
+        assert_eq!(tracker.beats(), 1.5);
+        assert_eq!(tracker.song_position(), Some(SongPosition::new(u14(6))));
+    }
+
+    #[test]
+    fn locate_while_playing() {
+        // Given
+        let mut tracker = TransportTracker::new();
+        tracker.feed(&start());
+        feed_clocks(&mut tracker, 100);
+        // When
+        let event = tracker.feed(&song_position_pointer(16383));
+        feed_clocks(&mut tracker, 6);
+        // Then
+        assert_eq!(
+            event,
+            Some(TransportTrackerEvent::Located(SongPosition::MAX))
+        );
+        assert_eq!(tracker.tick(), 16384 * 6);
+        assert_eq!(tracker.song_position(), None);
+    }
+
+    #[test]
+    fn ignore_other_messages() {
+        // Given
+        let mut tracker = TransportTracker::new();
+        // When
+        let event = tracker.feed(&note_on(0, 64, 100));
+        // Then
+        assert_eq!(event, None);
+        assert_eq!(tracker, TransportTracker::new());
+    }
+
+    #[test]
+    fn follow_clock_generator() {
+        // Given
+        let mut generator = ClockGenerator::new(120.0);
+        let mut tracker = TransportTracker::new();
+        let mut feed = |generator: &mut ClockGenerator, until: u64| {
+            for m in generator.generate::<RawShortMessage>(Duration::from_millis(until)) {
+                tracker.feed(m.message());
+            }
+        };
+        // When
+        generator.start();
+        feed(&mut generator, 1000);
+        generator.locate(SongPosition::new(u14(100)));
+        feed(&mut generator, 2000);
+        // Then
+        assert_eq!(tracker.tick(), generator.tick());
+        assert_eq!(tracker.transport_state(), TransportState::Playing);
+    }
+
+    #[test]
+    fn reset() {
+        // Given
+        let mut tracker = TransportTracker::new();
+        tracker.feed(&start());
+        feed_clocks(&mut tracker, 3);
+        // When
+        tracker.reset();
+        // Then
+        assert_eq!(tracker.tick(), 0);
+        assert_eq!(tracker.transport_state(), TransportState::Stopped);
+    }
+}
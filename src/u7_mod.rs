@@ -50,7 +50,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "128 is not a valid U7 value")]
+    #[should_panic(expected = "not a valid U7 value")]
     fn new_failing() {
         U7::new(128);
     }
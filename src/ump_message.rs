@@ -0,0 +1,429 @@
+use crate::{
+    BufferTooSmallError, Channel, ShortMessage, ShortMessageFactory, ShortMessageType,
+    StructuredUmpMessage, U4, U7,
+};
+use core::convert::TryFrom;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde_repr")]
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// An error which can occur when trying to create a [`UmpMessage`] from raw words.
+///
+/// [`UmpMessage`]: trait.UmpMessage.html
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "invalid UMP words")]
+pub struct FromWordsError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for FromWordsError {}
+
+/// A single MIDI 2.0 Universal MIDI Packet (UMP), which is made up by 1, 2, 3 or 4 32-bit words.
+///
+/// This is the UMP counterpart of [`ShortMessage`]. Only [`to_words`] needs to be implemented, the
+/// rest is done by default methods. This crate comes with the word-based data structure
+/// [`RawUmpMessage`] and the match-friendly data structure [`StructuredUmpMessage`].
+///
+/// Like [`ShortMessage`], UMP messages are allocation-free and `Copy`, so they can be processed in
+/// real-time threads.
+///
+/// [`ShortMessage`]: trait.ShortMessage.html
+/// [`to_words`]: #tymethod.to_words
+/// [`RawUmpMessage`]: struct.RawUmpMessage.html
+/// [`StructuredUmpMessage`]: enum.StructuredUmpMessage.html
+pub trait UmpMessage {
+    /// Returns all 4 words, where words beyond the [`word_count`] are zero.
+    ///
+    /// [`word_count`]: #method.word_count
+    fn to_words(&self) -> [u32; 4];
+
+    /// Returns the first word, which contains the message type and (if applicable) the group.
+    ///
+    /// Implementations can override this default implementation if it's cheaper to get just the
+    /// first word.
+    fn first_word(&self) -> u32 {
+        self.to_words()[0]
+    }
+
+    /// Returns the message type.
+    fn message_type(&self) -> UmpMessageType {
+        extract_message_type_from_first_word(self.first_word())
+    }
+
+    /// Returns the number of words which make up this message.
+    fn word_count(&self) -> usize {
+        self.message_type().word_count()
+    }
+
+    /// Returns the group of this message if applicable.
+    ///
+    /// Utility messages and UMP Stream messages don't have a group.
+    fn group(&self) -> Option<U4> {
+        if !self.message_type().has_group() {
+            return None;
+        }
+        Some(U4(((self.first_word() >> 24) & 0x0F) as u8))
+    }
+
+    /// Returns the channel if this is a MIDI 1.0 or MIDI 2.0 Channel Voice message.
+    fn channel(&self) -> Option<Channel> {
+        match self.message_type() {
+            UmpMessageType::Midi1ChannelVoice | UmpMessageType::Midi2ChannelVoice => {
+                Some(Channel(((self.first_word() >> 16) & 0x0F) as u8))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes which make up this message, which is 4 times the word count.
+    fn byte_count(&self) -> usize {
+        4 * self.word_count()
+    }
+
+    /// Writes the words of this message as big-endian bytes to the beginning of the given buffer,
+    /// which is the byte order used by UMP transports.
+    ///
+    /// Returns the number of bytes written, which is 4, 8, 12 or 16.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer is too small to hold the message, an error is returned. In this case nothing
+    /// is written.
+    fn write_bytes(&self, buffer: &mut [u8]) -> Result<usize, BufferTooSmallError> {
+        let byte_count = self.byte_count();
+        if buffer.len() < byte_count {
+            return Err(BufferTooSmallError(()));
+        }
+        buffer[..byte_count].copy_from_slice(&words_to_bytes(&self.to_words())[..byte_count]);
+        Ok(byte_count)
+    }
+
+    /// Converts this message to a UMP message of another type.
+    fn to_other<O: UmpMessageFactory>(&self) -> O {
+        unsafe { O::from_words_unchecked(self.to_words()) }
+    }
+
+    /// Converts this message to a [`StructuredUmpMessage`], which is ideal for matching.
+    ///
+    /// [`StructuredUmpMessage`]: enum.StructuredUmpMessage.html
+    fn to_structured(&self) -> StructuredUmpMessage {
+        self.to_other()
+    }
+
+    /// Converts this message to a short message if it's a System message or a MIDI 1.0 Channel
+    /// Voice message.
+    ///
+    /// Returns `None` for all other messages and for messages with invalid status or data bytes.
+    fn to_short_message<T: ShortMessageFactory>(&self) -> Option<T> {
+        extract_short_message(self.message_type(), self.first_word())
+    }
+}
+
+/// Static methods for creating UMP messages.
+///
+/// This trait is supposed to be implemented for structs that represent a UMP message *and* also
+/// support their creation. Only one method needs to be implemented, the rest is done by default
+/// methods.
+pub trait UmpMessageFactory: UmpMessage + Sized {
+    /// Creates a UMP message from the given words without checking them.
+    ///
+    /// # Safety
+    ///
+    /// Callers must make sure that words beyond the word count of the message type are zero.
+    /// This method is usually called by [`from_words`], which checks the necessary preconditions.
+    ///
+    /// [`from_words`]: #method.from_words
+    unsafe fn from_words_unchecked(words: [u32; 4]) -> Self;
+
+    /// Creates a UMP message from the given words.
+    ///
+    /// # Errors
+    ///
+    /// If the number of words doesn't correspond to the message type contained in the first word,
+    /// an error will be returned.
+    fn from_words(words: &[u32]) -> Result<Self, FromWordsError> {
+        let first_word = *words.first().ok_or(FromWordsError(()))?;
+        let word_count = extract_message_type_from_first_word(first_word).word_count();
+        if words.len() != word_count {
+            return Err(FromWordsError(()));
+        }
+        let mut all_words = [0; 4];
+        all_words[..word_count].copy_from_slice(words);
+        Ok(unsafe { Self::from_words_unchecked(all_words) })
+    }
+
+    /// Creates a UMP message from the given big-endian bytes.
+    ///
+    /// # Errors
+    ///
+    /// If the number of bytes doesn't correspond to the message type contained in the first byte,
+    /// an error will be returned.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, FromWordsError> {
+        let first_byte = *bytes.first().ok_or(FromWordsError(()))?;
+        let byte_count =
+            4 * extract_message_type_from_first_word(u32::from(first_byte) << 24).word_count();
+        if bytes.len() != byte_count {
+            return Err(FromWordsError(()));
+        }
+        let mut all_bytes = [0; 16];
+        all_bytes[..byte_count].copy_from_slice(bytes);
+        Ok(unsafe { Self::from_words_unchecked(bytes_to_words(&all_bytes)) })
+    }
+
+    /// Creates this message from a UMP message of another type.
+    fn from_other(msg: &impl UmpMessage) -> Self {
+        msg.to_other()
+    }
+
+    /// Wraps the given short message into a System message or MIDI 1.0 Channel Voice message.
+    ///
+    /// Returns `None` if the given message is a System Exclusive start or end message, which can't
+    /// be represented this way.
+    fn from_short_message(group: U4, msg: &impl ShortMessage) -> Option<Self> {
+        let word = build_short_message_word(short_message_ump_type(msg)?, group, msg);
+        Some(unsafe { Self::from_words_unchecked([word, 0, 0, 0]) })
+    }
+}
+
+/// The message type of a UMP message, contained in the upper 4 bits of the first word.
+///
+/// The message type determines the size of the message.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, IntoPrimitive, TryFromPrimitive,
+)]
+#[cfg_attr(feature = "serde_repr", derive(Serialize_repr, Deserialize_repr))]
+#[repr(u8)]
+pub enum UmpMessageType {
+    /// Utility messages such as NOOP and Jitter Reduction timestamps (32 bits).
+    Utility = 0x0,
+    /// System Common and System Real Time messages (32 bits).
+    System = 0x1,
+    /// MIDI 1.0 Channel Voice messages (32 bits).
+    Midi1ChannelVoice = 0x2,
+    /// Data messages including System Exclusive (7-bit) (64 bits).
+    Data64 = 0x3,
+    /// MIDI 2.0 Channel Voice messages (64 bits).
+    Midi2ChannelVoice = 0x4,
+    /// Data messages including System Exclusive 8 and Mixed Data Set (128 bits).
+    Data128 = 0x5,
+    /// Reserved for future use (32 bits).
+    Reserved6 = 0x6,
+    /// Reserved for future use (32 bits).
+    Reserved7 = 0x7,
+    /// Reserved for future use (64 bits).
+    Reserved8 = 0x8,
+    /// Reserved for future use (64 bits).
+    Reserved9 = 0x9,
+    /// Reserved for future use (64 bits).
+    ReservedA = 0xA,
+    /// Reserved for future use (96 bits).
+    ReservedB = 0xB,
+    /// Reserved for future use (96 bits).
+    ReservedC = 0xC,
+    /// Flex Data messages (128 bits).
+    FlexData = 0xD,
+    /// Reserved for future use (128 bits).
+    ReservedE = 0xE,
+    /// UMP Stream messages (128 bits).
+    UmpStream = 0xF,
+}
+
+impl UmpMessageType {
+    /// Returns the number of 32-bit words of messages with this type.
+    pub fn word_count(&self) -> usize {
+        use UmpMessageType::*;
+        match self {
+            Utility | System | Midi1ChannelVoice | Reserved6 | Reserved7 => 1,
+            Data64 | Midi2ChannelVoice | Reserved8 | Reserved9 | ReservedA => 2,
+            ReservedB | ReservedC => 3,
+            Data128 | FlexData | ReservedE | UmpStream => 4,
+        }
+    }
+
+    /// Returns whether messages with this type are addressed to a group.
+    pub fn has_group(&self) -> bool {
+        !matches!(self, UmpMessageType::Utility | UmpMessageType::UmpStream)
+    }
+}
+
+/// Position of a UMP message within a message which spans multiple UMP messages, such as System
+/// Exclusive messages or longer texts.
+///
+/// This corresponds to the *status* field of System Exclusive messages and the *format* field of
+/// Flex Data and UMP Stream messages.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, IntoPrimitive, TryFromPrimitive,
+)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(u8)]
+pub enum UmpFormat {
+    /// The complete message is contained in this UMP message.
+    Complete = 0,
+    /// This UMP message starts a message.
+    Start = 1,
+    /// This UMP message continues a message.
+    Continue = 2,
+    /// This UMP message ends a message.
+    End = 3,
+}
+
+pub(crate) fn extract_message_type_from_first_word(word: u32) -> UmpMessageType {
+    UmpMessageType::try_from((word >> 28) as u8).expect("message type has 4 bits")
+}
+
+/// Builds a first word which contains just the message type and the group.
+pub(crate) fn build_first_word(message_type: UmpMessageType, group: U4) -> u32 {
+    (u32::from(u8::from(message_type)) << 28) | (u32::from(group.get()) << 24)
+}
+
+/// Builds the word of a System message or MIDI 1.0 Channel Voice message.
+pub(crate) fn build_short_message_word(
+    message_type: UmpMessageType,
+    group: U4,
+    msg: &impl ShortMessage,
+) -> u32 {
+    let (status_byte, data_byte_1, data_byte_2) = msg.to_bytes();
+    build_first_word(message_type, group)
+        | (u32::from(status_byte) << 16)
+        | (u32::from(data_byte_1.get()) << 8)
+        | u32::from(data_byte_2.get())
+}
+
+/// Extracts the short message from the word of a System message or MIDI 1.0 Channel Voice message.
+///
+/// Returns `None` if the short message doesn't fit to the message type or has invalid bytes.
+pub(crate) fn extract_short_message<T: ShortMessageFactory>(
+    message_type: UmpMessageType,
+    word: u32,
+) -> Option<T> {
+    let data_byte_1 = U7::try_from((word >> 8) as u8).ok()?;
+    let data_byte_2 = U7::try_from(word as u8).ok()?;
+    let short_message = T::from_bytes(((word >> 16) as u8, data_byte_1, data_byte_2)).ok()?;
+    if message_type != short_message_ump_type(&short_message)? {
+        return None;
+    }
+    Some(short_message)
+}
+
+/// Splits the given words into big-endian bytes.
+pub(crate) fn words_to_bytes(words: &[u32; 4]) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    for (chunk, word) in bytes.chunks_mut(4).zip(words.iter()) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+/// Joins the given big-endian bytes into words.
+pub(crate) fn bytes_to_words(bytes: &[u8; 16]) -> [u32; 4] {
+    let mut words = [0; 4];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    words
+}
+
+/// Returns the UMP message type which can carry the given short message.
+fn short_message_ump_type(msg: &impl ShortMessage) -> Option<UmpMessageType> {
+    use ShortMessageType::*;
+    match msg.r#type() {
+        SystemExclusiveStart | SystemExclusiveEnd => None,
+        t if t.super_type() == crate::FuzzyMessageSuperType::Channel => {
+            Some(UmpMessageType::Midi1ChannelVoice)
+        }
+        _ => Some(UmpMessageType::System),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use crate::{RawShortMessage, RawUmpMessage};
+
+    #[test]
+    fn word_counts() {
+        // Given
+        // When
+        // Then
+        let counts: [usize; 16] = [1, 1, 1, 2, 2, 4, 1, 1, 2, 2, 2, 3, 3, 4, 4, 4];
+        for (t, count) in counts.iter().enumerate() {
+            let message_type = UmpMessageType::try_from(t as u8).unwrap();
+            assert_eq!(message_type.word_count(), *count);
+        }
+    }
+
+    #[test]
+    fn from_words() {
+        // Given
+        // When
+        // Then
+        assert!(RawUmpMessage::from_words(&[0x2090_3C64]).is_ok());
+        assert!(RawUmpMessage::from_words(&[0x4090_3C00, 0x8000_0000]).is_ok());
+        assert_eq!(
+            RawUmpMessage::from_words(&[0x4090_3C00]),
+            Err(FromWordsError(()))
+        );
+        assert_eq!(
+            RawUmpMessage::from_words(&[0x2090_3C64, 0]),
+            Err(FromWordsError(()))
+        );
+        assert_eq!(RawUmpMessage::from_words(&[]), Err(FromWordsError(())));
+    }
+
+    #[test]
+    fn bytes() {
+        // Given
+        let msg = RawUmpMessage::from_words(&[0x4195_3C00, 0xFFFF_0000]).unwrap();
+        let mut buffer = [0; 10];
+        // When
+        let count = msg.write_bytes(&mut buffer).unwrap();
+        // Then
+        assert_eq!(count, 8);
+        assert_eq!(
+            &buffer[..count],
+            &[0x41, 0x95, 0x3C, 0x00, 0xFF, 0xFF, 0x00, 0x00]
+        );
+        assert_eq!(RawUmpMessage::from_bytes(&buffer[..count]), Ok(msg));
+        assert_eq!(
+            RawUmpMessage::from_bytes(&buffer[..4]),
+            Err(FromWordsError(()))
+        );
+        assert_eq!(RawUmpMessage::from_bytes(&[]), Err(FromWordsError(())));
+        assert_eq!(msg.write_bytes(&mut [0; 7]), Err(BufferTooSmallError(())));
+    }
+
+    #[test]
+    fn short_messages() {
+        // Given
+        let note_on: RawShortMessage = note_on(3, 60, 100);
+        let clock: RawShortMessage = timing_clock();
+        // When
+        let wrapped_note_on = RawUmpMessage::from_short_message(U4::new(5), &note_on).unwrap();
+        let wrapped_clock = RawUmpMessage::from_short_message(U4::new(1), &clock).unwrap();
+        // Then
+        assert_eq!(wrapped_note_on.to_words(), [0x2593_3C64, 0, 0, 0]);
+        assert_eq!(wrapped_note_on.group(), Some(U4::new(5)));
+        assert_eq!(wrapped_note_on.channel(), Some(channel(3)));
+        assert_eq!(wrapped_note_on.to_short_message(), Some(note_on));
+        assert_eq!(wrapped_clock.to_words(), [0x11F8_0000, 0, 0, 0]);
+        assert_eq!(wrapped_clock.channel(), None);
+        assert_eq!(wrapped_clock.to_short_message(), Some(clock));
+        let sys_ex_start: RawShortMessage = system_exclusive_start();
+        assert_eq!(
+            RawUmpMessage::from_short_message(U4::new(0), &sys_ex_start),
+            None
+        );
+    }
+
+    #[test]
+    fn short_message_type_mismatch() {
+        // Given
+        // Note On status byte within a System message
+        let msg = RawUmpMessage::from_words(&[0x1090_3C64]).unwrap();
+        // When
+        // Then
+        assert_eq!(msg.to_short_message::<RawShortMessage>(), None);
+    }
+}
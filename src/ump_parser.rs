@@ -0,0 +1,171 @@
+use crate::{extract_message_type_from_first_word, RawUmpMessage, UmpMessageFactory};
+
+/// Parser for reassembling UMP messages from a stream of 32-bit words or big-endian bytes, e.g.
+/// received from a USB or network connection.
+///
+/// Words (or bytes) can be fed in chunks of arbitrary size. A message that is split across two
+/// chunks is emitted as soon as its last word arrives. Because the message type in the first word
+/// determines the size of each message, the stream can't contain invalid data. However, if the
+/// parser gets out of sync (e.g. because the other side started to send in the middle of a
+/// message), call [`reset`] at a message boundary.
+///
+/// The parser doesn't allocate and can be used in a real-time thread.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::UmpParser;
+///
+/// let mut parser = UmpParser::new();
+/// // MIDI 1.0 Note On followed by a MIDI 2.0 Note On which is split across two chunks
+/// let first: Vec<_> = parser
+///     .feed(&[0x20, 0x90, 0x3C, 0x64, 0x40, 0x90, 0x3C, 0x00, 0xFF])
+///     .collect();
+/// let second: Vec<_> = parser.feed(&[0xFF, 0x00, 0x00]).collect();
+/// assert_eq!(first.len(), 1);
+/// assert_eq!(first[0].words(), &[0x2090_3C64]);
+/// assert_eq!(second.len(), 1);
+/// assert_eq!(second[0].words(), &[0x4090_3C00, 0xFFFF_0000]);
+/// ```
+///
+/// [`reset`]: #method.reset
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct UmpParser {
+    words: [u32; 4],
+    word_count: usize,
+    byte_buffer: [u8; 4],
+    byte_count: usize,
+}
+
+impl UmpParser {
+    /// Creates a new parser.
+    pub fn new() -> UmpParser {
+        Default::default()
+    }
+
+    /// Feeds the parser a chunk of big-endian bytes.
+    ///
+    /// Returns an iterator over all messages which have been completed by this chunk. Bytes are
+    /// only consumed as far as the iterator is advanced.
+    pub fn feed<'a>(&'a mut self, bytes: &'a [u8]) -> ParsedUmpMessages<'a> {
+        ParsedUmpMessages {
+            parser: self,
+            bytes: bytes.iter(),
+        }
+    }
+
+    /// Feeds the parser a single big-endian byte.
+    ///
+    /// Returns the message which has been completed by this byte, if any.
+    pub fn feed_byte(&mut self, byte: u8) -> Option<RawUmpMessage> {
+        self.byte_buffer[self.byte_count] = byte;
+        self.byte_count += 1;
+        if self.byte_count < 4 {
+            return None;
+        }
+        self.byte_count = 0;
+        self.feed_word(u32::from_be_bytes(self.byte_buffer))
+    }
+
+    /// Feeds the parser a single word.
+    ///
+    /// Returns the message which has been completed by this word, if any. Don't mix this with
+    /// feeding bytes unless the bytes fed so far make up complete words.
+    pub fn feed_word(&mut self, word: u32) -> Option<RawUmpMessage> {
+        self.words[self.word_count] = word;
+        self.word_count += 1;
+        let expected_word_count = extract_message_type_from_first_word(self.words[0]).word_count();
+        if self.word_count < expected_word_count {
+            return None;
+        }
+        let msg = unsafe { RawUmpMessage::from_words_unchecked(self.words) };
+        self.reset();
+        Some(msg)
+    }
+
+    /// Resets the parser discarding all intermediate parsing progress.
+    pub fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+/// An iterator over UMP messages completed by a chunk of bytes.
+///
+/// Created by [`UmpParser::feed`].
+///
+/// [`UmpParser::feed`]: struct.UmpParser.html#method.feed
+#[derive(Debug)]
+pub struct ParsedUmpMessages<'a> {
+    parser: &'a mut UmpParser,
+    bytes: core::slice::Iter<'a, u8>,
+}
+
+impl<'a> Iterator for ParsedUmpMessages<'a> {
+    type Item = RawUmpMessage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in &mut self.bytes {
+            if let Some(msg) = self.parser.feed_byte(*byte) {
+                return Some(msg);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UmpMessage;
+
+    #[test]
+    fn feed_words() {
+        // Given
+        let mut parser = UmpParser::new();
+        let words = [
+            0x2090_3C64,
+            0xF000_0000,
+            0x0000_0000,
+            0x0000_0000,
+            0x0000_0000,
+            0x4090_3C00,
+            0x8000_0000,
+        ];
+        // When
+        let messages: Vec<_> = words.iter().filter_map(|w| parser.feed_word(*w)).collect();
+        // Then
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].words(), &[0x2090_3C64]);
+        assert_eq!(messages[1].words(), &[0xF000_0000, 0, 0, 0]);
+        assert_eq!(messages[2].words(), &[0x4090_3C00, 0x8000_0000]);
+    }
+
+    #[test]
+    fn feed_bytes_in_chunks() {
+        // Given
+        let mut parser = UmpParser::new();
+        let msg = RawUmpMessage::from_words(&[0x5001_0203, 0x0405_0607, 0x0809_0A0B, 0x0C0D_0E0F])
+            .unwrap();
+        let mut bytes = [0; 16];
+        msg.write_bytes(&mut bytes).unwrap();
+        // When
+        let mut messages = Vec::new();
+        for chunk in bytes.chunks(3) {
+            messages.extend(parser.feed(chunk));
+        }
+        // Then
+        assert_eq!(messages, vec![msg]);
+    }
+
+    #[test]
+    fn reset() {
+        // Given
+        let mut parser = UmpParser::new();
+        // When
+        assert_eq!(parser.feed_word(0x4090_3C00), None);
+        parser.reset();
+        let msg = parser.feed_word(0x2090_3C64);
+        // Then
+        assert_eq!(msg.map(|m| m.to_words()), Some([0x2090_3C64, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,405 @@
+use crate::{bytes_to_words, words_to_bytes, UmpFormat, UmpMessageType, U14};
+use core::convert::TryFrom;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A UMP Stream message, carried by a 128-bit UMP message of type 0xF.
+///
+/// UMP Stream messages are used for discovering and configuring UMP endpoints and their function
+/// blocks. They are not addressed to a group.
+///
+/// This enum is usually contained in [`StructuredUmpMessage::UmpStream`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{StructuredUmpMessage, UmpMessage, UmpStreamMessage};
+///
+/// let msg = StructuredUmpMessage::UmpStream(UmpStreamMessage::EndpointDiscovery {
+///     ump_version_major: 1,
+///     ump_version_minor: 1,
+///     filter: 0x1F,
+/// });
+/// assert_eq!(msg.to_words(), [0xF000_0101, 0x0000_001F, 0, 0]);
+/// assert_eq!(msg.group(), None);
+/// ```
+///
+/// [`StructuredUmpMessage::UmpStream`]: enum.StructuredUmpMessage.html#variant.UmpStream
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UmpStreamMessage {
+    EndpointDiscovery {
+        ump_version_major: u8,
+        ump_version_minor: u8,
+        /// Bitmap of the requested notifications.
+        filter: u8,
+    },
+    EndpointInfoNotification {
+        ump_version_major: u8,
+        ump_version_minor: u8,
+        static_function_blocks: bool,
+        /// Number of function blocks (0 - 32).
+        function_block_count: u8,
+        supports_midi2_protocol: bool,
+        supports_midi1_protocol: bool,
+        receives_jr_timestamps: bool,
+        transmits_jr_timestamps: bool,
+    },
+    DeviceIdentityNotification {
+        manufacturer_id: [u8; 3],
+        device_family: U14,
+        device_family_model: U14,
+        software_revision: [u8; 4],
+    },
+    EndpointNameNotification {
+        format: UmpFormat,
+        /// UTF-8 text, padded with zeros.
+        name: [u8; 14],
+    },
+    ProductInstanceIdNotification {
+        format: UmpFormat,
+        /// ASCII text, padded with zeros.
+        id: [u8; 14],
+    },
+    StreamConfigurationRequest {
+        /// 0x01 for MIDI 1.0 protocol, 0x02 for MIDI 2.0 protocol.
+        protocol: u8,
+        receive_jr_timestamps: bool,
+        transmit_jr_timestamps: bool,
+    },
+    StreamConfigurationNotification {
+        /// 0x01 for MIDI 1.0 protocol, 0x02 for MIDI 2.0 protocol.
+        protocol: u8,
+        receive_jr_timestamps: bool,
+        transmit_jr_timestamps: bool,
+    },
+    FunctionBlockDiscovery {
+        /// Function block number or 0xFF for all function blocks.
+        function_block: u8,
+        /// Bitmap of the requested notifications.
+        filter: u8,
+    },
+    FunctionBlockInfoNotification {
+        active: bool,
+        /// Function block number (0 - 31).
+        function_block: u8,
+        /// 2-bit UI hint (receiver, sender or both).
+        ui_hint: u8,
+        /// 2-bit value describing whether this function block represents a MIDI 1.0 port.
+        midi1: u8,
+        /// 2-bit direction (input, output or bidirectional).
+        direction: u8,
+        first_group: u8,
+        group_count: u8,
+        midi_ci_version: u8,
+        max_sys_ex8_streams: u8,
+    },
+    FunctionBlockNameNotification {
+        format: UmpFormat,
+        function_block: u8,
+        /// UTF-8 text, padded with zeros.
+        name: [u8; 13],
+    },
+    StartOfClip,
+    EndOfClip,
+}
+
+impl UmpStreamMessage {
+    /// Decodes a UMP message of type 0xF.
+    ///
+    /// Returns `None` if the status is unknown or a 14-bit value has an upper bit set.
+    pub(crate) fn decode(words: &[u32; 4]) -> Option<Self> {
+        use UmpStreamMessage::*;
+        let [word_0, word_1, _, word_3] = *words;
+        let format = UmpFormat::try_from(((word_0 >> 26) & 0x03) as u8).ok()?;
+        let status = (word_0 >> 16) & 0x03FF;
+        let bytes = words_to_bytes(words);
+        let bit = |word: u32, index: u32| word & (1 << index) != 0;
+        let msg = match status {
+            0x000 => EndpointDiscovery {
+                ump_version_major: bytes[2],
+                ump_version_minor: bytes[3],
+                filter: word_1 as u8,
+            },
+            0x001 => EndpointInfoNotification {
+                ump_version_major: bytes[2],
+                ump_version_minor: bytes[3],
+                static_function_blocks: bit(word_1, 31),
+                function_block_count: bytes[4] & 0x7F,
+                supports_midi2_protocol: bit(word_1, 9),
+                supports_midi1_protocol: bit(word_1, 8),
+                receives_jr_timestamps: bit(word_1, 1),
+                transmits_jr_timestamps: bit(word_1, 0),
+            },
+            0x002 => {
+                let u14 = |lsb: u8, msb: u8| {
+                    if lsb < 0x80 && msb < 0x80 {
+                        Some(U14((u16::from(msb) << 7) | u16::from(lsb)))
+                    } else {
+                        None
+                    }
+                };
+                DeviceIdentityNotification {
+                    manufacturer_id: [bytes[5], bytes[6], bytes[7]],
+                    device_family: u14(bytes[8], bytes[9])?,
+                    device_family_model: u14(bytes[10], bytes[11])?,
+                    software_revision: word_3.to_be_bytes(),
+                }
+            }
+            0x003 => {
+                let mut name = [0; 14];
+                name.copy_from_slice(&bytes[2..]);
+                EndpointNameNotification { format, name }
+            }
+            0x004 => {
+                let mut id = [0; 14];
+                id.copy_from_slice(&bytes[2..]);
+                ProductInstanceIdNotification { format, id }
+            }
+            0x005 => StreamConfigurationRequest {
+                protocol: bytes[2],
+                receive_jr_timestamps: bit(word_0, 1),
+                transmit_jr_timestamps: bit(word_0, 0),
+            },
+            0x006 => StreamConfigurationNotification {
+                protocol: bytes[2],
+                receive_jr_timestamps: bit(word_0, 1),
+                transmit_jr_timestamps: bit(word_0, 0),
+            },
+            0x010 => FunctionBlockDiscovery {
+                function_block: bytes[2],
+                filter: bytes[3],
+            },
+            0x011 => FunctionBlockInfoNotification {
+                active: bit(word_0, 15),
+                function_block: bytes[2] & 0x7F,
+                ui_hint: (bytes[3] >> 4) & 0x03,
+                midi1: (bytes[3] >> 2) & 0x03,
+                direction: bytes[3] & 0x03,
+                first_group: bytes[4],
+                group_count: bytes[5],
+                midi_ci_version: bytes[6],
+                max_sys_ex8_streams: bytes[7],
+            },
+            0x012 => {
+                let mut name = [0; 13];
+                name.copy_from_slice(&bytes[3..]);
+                FunctionBlockNameNotification {
+                    format,
+                    function_block: bytes[2],
+                    name,
+                }
+            }
+            0x020 => StartOfClip,
+            0x021 => EndOfClip,
+            _ => return None,
+        };
+        Some(msg)
+    }
+
+    /// Encodes this message as UMP message of type 0xF.
+    pub(crate) fn encode(&self) -> [u32; 4] {
+        use UmpStreamMessage::*;
+        let mut bytes = [0u8; 16];
+        let (format, status): (UmpFormat, u16) = match *self {
+            EndpointDiscovery {
+                ump_version_major,
+                ump_version_minor,
+                filter,
+            } => {
+                bytes[2] = ump_version_major;
+                bytes[3] = ump_version_minor;
+                bytes[7] = filter;
+                (UmpFormat::Complete, 0x000)
+            }
+            EndpointInfoNotification {
+                ump_version_major,
+                ump_version_minor,
+                static_function_blocks,
+                function_block_count,
+                supports_midi2_protocol,
+                supports_midi1_protocol,
+                receives_jr_timestamps,
+                transmits_jr_timestamps,
+            } => {
+                bytes[2] = ump_version_major;
+                bytes[3] = ump_version_minor;
+                bytes[4] = ((static_function_blocks as u8) << 7) | (function_block_count & 0x7F);
+                bytes[6] = ((supports_midi2_protocol as u8) << 1) | supports_midi1_protocol as u8;
+                bytes[7] = ((receives_jr_timestamps as u8) << 1) | transmits_jr_timestamps as u8;
+                (UmpFormat::Complete, 0x001)
+            }
+            DeviceIdentityNotification {
+                manufacturer_id,
+                device_family,
+                device_family_model,
+                software_revision,
+            } => {
+                bytes[5..8].copy_from_slice(&manufacturer_id);
+                bytes[8] = (device_family.get() & 0x7F) as u8;
+                bytes[9] = (device_family.get() >> 7) as u8;
+                bytes[10] = (device_family_model.get() & 0x7F) as u8;
+                bytes[11] = (device_family_model.get() >> 7) as u8;
+                bytes[12..16].copy_from_slice(&software_revision);
+                (UmpFormat::Complete, 0x002)
+            }
+            EndpointNameNotification { format, name } => {
+                bytes[2..16].copy_from_slice(&name);
+                (format, 0x003)
+            }
+            ProductInstanceIdNotification { format, id } => {
+                bytes[2..16].copy_from_slice(&id);
+                (format, 0x004)
+            }
+            StreamConfigurationRequest {
+                protocol,
+                receive_jr_timestamps,
+                transmit_jr_timestamps,
+            } => {
+                bytes[2] = protocol;
+                bytes[3] = ((receive_jr_timestamps as u8) << 1) | transmit_jr_timestamps as u8;
+                (UmpFormat::Complete, 0x005)
+            }
+            StreamConfigurationNotification {
+                protocol,
+                receive_jr_timestamps,
+                transmit_jr_timestamps,
+            } => {
+                bytes[2] = protocol;
+                bytes[3] = ((receive_jr_timestamps as u8) << 1) | transmit_jr_timestamps as u8;
+                (UmpFormat::Complete, 0x006)
+            }
+            FunctionBlockDiscovery {
+                function_block,
+                filter,
+            } => {
+                bytes[2] = function_block;
+                bytes[3] = filter;
+                (UmpFormat::Complete, 0x010)
+            }
+            FunctionBlockInfoNotification {
+                active,
+                function_block,
+                ui_hint,
+                midi1,
+                direction,
+                first_group,
+                group_count,
+                midi_ci_version,
+                max_sys_ex8_streams,
+            } => {
+                bytes[2] = ((active as u8) << 7) | (function_block & 0x7F);
+                bytes[3] = ((ui_hint & 0x03) << 4) | ((midi1 & 0x03) << 2) | (direction & 0x03);
+                bytes[4] = first_group;
+                bytes[5] = group_count;
+                bytes[6] = midi_ci_version;
+                bytes[7] = max_sys_ex8_streams;
+                (UmpFormat::Complete, 0x011)
+            }
+            FunctionBlockNameNotification {
+                format,
+                function_block,
+                name,
+            } => {
+                bytes[2] = function_block;
+                bytes[3..16].copy_from_slice(&name);
+                (format, 0x012)
+            }
+            StartOfClip => (UmpFormat::Complete, 0x020),
+            EndOfClip => (UmpFormat::Complete, 0x021),
+        };
+        bytes[0] = (u8::from(UmpMessageType::UmpStream) << 4)
+            | (u8::from(format) << 2)
+            | (status >> 8) as u8;
+        bytes[1] = status as u8;
+        bytes_to_words(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn encode_decode() {
+        // Given
+        use UmpStreamMessage::*;
+        let cases = [
+            (
+                EndpointInfoNotification {
+                    ump_version_major: 1,
+                    ump_version_minor: 1,
+                    static_function_blocks: true,
+                    function_block_count: 2,
+                    supports_midi2_protocol: true,
+                    supports_midi1_protocol: true,
+                    receives_jr_timestamps: false,
+                    transmits_jr_timestamps: true,
+                },
+                [0xF001_0101, 0x8200_0301, 0, 0],
+            ),
+            (
+                DeviceIdentityNotification {
+                    manufacturer_id: [0x00, 0x21, 0x09],
+                    device_family: u14(0x0081),
+                    device_family_model: u14(0x0002),
+                    software_revision: [1, 2, 3, 4],
+                },
+                [0xF002_0000, 0x0000_2109, 0x0101_0200, 0x0102_0304],
+            ),
+            (
+                EndpointNameNotification {
+                    format: UmpFormat::Start,
+                    name: *b"My MIDI Device",
+                },
+                [0xF403_4D79, 0x204D_4944, 0x4920_4465, 0x7669_6365],
+            ),
+            (
+                StreamConfigurationRequest {
+                    protocol: 0x02,
+                    receive_jr_timestamps: true,
+                    transmit_jr_timestamps: false,
+                },
+                [0xF005_0202, 0, 0, 0],
+            ),
+            (
+                FunctionBlockInfoNotification {
+                    active: true,
+                    function_block: 1,
+                    ui_hint: 0x03,
+                    midi1: 0x00,
+                    direction: 0x02,
+                    first_group: 0,
+                    group_count: 4,
+                    midi_ci_version: 0x02,
+                    max_sys_ex8_streams: 0,
+                },
+                [0xF011_8132, 0x0004_0200, 0, 0],
+            ),
+            (
+                FunctionBlockNameNotification {
+                    format: UmpFormat::Complete,
+                    function_block: 1,
+                    name: *b"Synth\0\0\0\0\0\0\0\0",
+                },
+                [0xF012_0153, 0x796E_7468, 0, 0],
+            ),
+            (EndOfClip, [0xF021_0000, 0, 0, 0]),
+        ];
+        // When
+        // Then
+        for (msg, words) in cases.iter() {
+            assert_eq!(msg.encode(), *words);
+            assert_eq!(UmpStreamMessage::decode(words), Some(*msg));
+        }
+    }
+
+    #[test]
+    fn unknown_status() {
+        // Given
+        // When
+        // Then
+        assert_eq!(UmpStreamMessage::decode(&[0xF3FF_0000, 0, 0, 0]), None);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{StructuredUmpMessage, SysEx7Payload, SysEx8Payload, UmpFormat, U4};
+
+/// Iterator which splits a complete System Exclusive message into UMP messages of type 0x3
+/// (System Exclusive 7), each one containing up to 6 data bytes.
+///
+/// The data bytes must not include the 0xF0 and 0xF7 status bytes (see [`SysExMessage::data`]).
+/// If the data fits into one UMP message, a single message with format [`Complete`] is emitted.
+/// Otherwise a [`Start`] message is followed by [`Continue`] messages and an [`End`] message.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{StructuredUmpMessage, SysEx7Segmenter, SysExMessage, UmpMessage, U4};
+///
+/// let sys_ex = SysExMessage::from_bytes(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0x00, 0x01, 0x02, 0xF7])
+///     .unwrap();
+/// let messages: Vec<_> = SysEx7Segmenter::new(U4::new(0), sys_ex.data()).collect();
+/// assert_eq!(messages.len(), 2);
+/// assert_eq!(messages[0].to_words(), [0x3016_7E7F, 0x0601_0001, 0, 0]);
+/// assert_eq!(messages[1].to_words(), [0x3031_0200, 0x0000_0000, 0, 0]);
+/// ```
+///
+/// [`SysExMessage::data`]: struct.SysExMessage.html#method.data
+/// [`Complete`]: enum.UmpFormat.html#variant.Complete
+/// [`Start`]: enum.UmpFormat.html#variant.Start
+/// [`Continue`]: enum.UmpFormat.html#variant.Continue
+/// [`End`]: enum.UmpFormat.html#variant.End
+#[derive(Clone, Debug)]
+pub struct SysEx7Segmenter<'a> {
+    group: U4,
+    segments: Segments<'a>,
+}
+
+impl<'a> SysEx7Segmenter<'a> {
+    /// Creates a segmenter for the given data bytes.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if one of the bytes is greater than 127.
+    pub fn new(group: U4, data: &'a [u8]) -> SysEx7Segmenter<'a> {
+        assert!(data.iter().all(|b| *b < 0x80), "data bytes must be 7-bit");
+        SysEx7Segmenter {
+            group,
+            segments: Segments::new(data, SysEx7Payload::CAPACITY),
+        }
+    }
+}
+
+impl<'a> Iterator for SysEx7Segmenter<'a> {
+    type Item = StructuredUmpMessage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (format, chunk) = self.segments.next()?;
+        let msg = StructuredUmpMessage::SysEx7 {
+            group: self.group,
+            format,
+            data: SysEx7Payload::from_slice(chunk).expect("impossible"),
+        };
+        Some(msg)
+    }
+}
+
+/// Iterator which splits a complete System Exclusive 8 message into UMP messages of type 0x5,
+/// each one containing up to 13 data bytes.
+///
+/// If the data fits into one UMP message, a single message with format [`Complete`] is emitted.
+/// Otherwise a [`Start`] message is followed by [`Continue`] messages and an [`End`] message.
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{SysEx8Segmenter, UmpFormat, UmpMessage, U4};
+///
+/// let data: Vec<u8> = (0..20).collect();
+/// let messages: Vec<_> = SysEx8Segmenter::new(U4::new(1), 7, &data).collect();
+/// assert_eq!(messages.len(), 2);
+/// assert_eq!(messages[0].to_words()[0], 0x511E_0700);
+/// assert_eq!(messages[1].to_words()[0], 0x5138_070D);
+/// ```
+///
+/// [`Complete`]: enum.UmpFormat.html#variant.Complete
+/// [`Start`]: enum.UmpFormat.html#variant.Start
+/// [`Continue`]: enum.UmpFormat.html#variant.Continue
+/// [`End`]: enum.UmpFormat.html#variant.End
+#[derive(Clone, Debug)]
+pub struct SysEx8Segmenter<'a> {
+    group: U4,
+    stream_id: u8,
+    segments: Segments<'a>,
+}
+
+impl<'a> SysEx8Segmenter<'a> {
+    /// Creates a segmenter for the given data bytes, which are sent in the given stream.
+    pub fn new(group: U4, stream_id: u8, data: &'a [u8]) -> SysEx8Segmenter<'a> {
+        SysEx8Segmenter {
+            group,
+            stream_id,
+            segments: Segments::new(data, SysEx8Payload::CAPACITY),
+        }
+    }
+}
+
+impl<'a> Iterator for SysEx8Segmenter<'a> {
+    type Item = StructuredUmpMessage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (format, chunk) = self.segments.next()?;
+        let msg = StructuredUmpMessage::SysEx8 {
+            group: self.group,
+            format,
+            stream_id: self.stream_id,
+            data: SysEx8Payload::from_slice(chunk).expect("impossible"),
+        };
+        Some(msg)
+    }
+}
+
+/// Splits data into chunks of the given size and determines the format of each chunk.
+#[derive(Clone, Debug)]
+struct Segments<'a> {
+    remaining: &'a [u8],
+    chunk_size: usize,
+    is_first: bool,
+    is_done: bool,
+}
+
+impl<'a> Segments<'a> {
+    fn new(data: &'a [u8], chunk_size: usize) -> Segments<'a> {
+        Segments {
+            remaining: data,
+            chunk_size,
+            is_first: true,
+            is_done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = (UmpFormat, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        let is_last = self.remaining.len() <= self.chunk_size;
+        let (chunk, remaining) = self
+            .remaining
+            .split_at(self.remaining.len().min(self.chunk_size));
+        let format = match (self.is_first, is_last) {
+            (true, true) => UmpFormat::Complete,
+            (true, false) => UmpFormat::Start,
+            (false, false) => UmpFormat::Continue,
+            (false, true) => UmpFormat::End,
+        };
+        self.remaining = remaining;
+        self.is_first = false;
+        self.is_done = is_last;
+        Some((format, chunk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn formats_and_lengths(messages: &[StructuredUmpMessage]) -> Vec<(UmpFormat, usize)> {
+        messages
+            .iter()
+            .map(|msg| match msg {
+                StructuredUmpMessage::SysEx7 { format, data, .. } => (*format, data.bytes().len()),
+                StructuredUmpMessage::SysEx8 { format, data, .. } => (*format, data.bytes().len()),
+                _ => panic!("unexpected message"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sys_ex_7() {
+        // Given
+        let data = [0x7Du8; 15];
+        // When
+        let messages: Vec<_> = SysEx7Segmenter::new(u4(2), &data).collect();
+        // Then
+        assert_eq!(
+            formats_and_lengths(&messages),
+            vec![
+                (UmpFormat::Start, 6),
+                (UmpFormat::Continue, 6),
+                (UmpFormat::End, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn sys_ex_7_exact_capacity() {
+        // Given
+        let data = [0x01u8; 12];
+        // When
+        let messages: Vec<_> = SysEx7Segmenter::new(u4(0), &data).collect();
+        // Then
+        assert_eq!(
+            formats_and_lengths(&messages),
+            vec![(UmpFormat::Start, 6), (UmpFormat::End, 6)]
+        );
+    }
+
+    #[test]
+    fn sys_ex_7_empty() {
+        // Given
+        // When
+        let messages: Vec<_> = SysEx7Segmenter::new(u4(0), &[]).collect();
+        // Then
+        assert_eq!(
+            formats_and_lengths(&messages),
+            vec![(UmpFormat::Complete, 0)]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn sys_ex_7_invalid_data_byte() {
+        // Given
+        // When
+        // Then
+        SysEx7Segmenter::new(u4(0), &[0x01, 0xF7]);
+    }
+
+    #[test]
+    fn sys_ex_8() {
+        // Given
+        let data: Vec<u8> = (0..=255).collect();
+        // When
+        let messages: Vec<_> = SysEx8Segmenter::new(u4(0), 3, &data).collect();
+        // Then
+        let formats_and_lengths = formats_and_lengths(&messages);
+        assert_eq!(formats_and_lengths.len(), 20);
+        assert_eq!(formats_and_lengths[0], (UmpFormat::Start, 13));
+        assert_eq!(formats_and_lengths[1], (UmpFormat::Continue, 13));
+        assert_eq!(formats_and_lengths[19], (UmpFormat::End, 9));
+        let reassembled: Vec<u8> = messages
+            .iter()
+            .flat_map(|msg| match msg {
+                StructuredUmpMessage::SysEx8 {
+                    stream_id, data, ..
+                } => {
+                    assert_eq!(*stream_id, 3);
+                    data.bytes().to_vec()
+                }
+                _ => panic!("unexpected message"),
+            })
+            .collect();
+        assert_eq!(reassembled, data);
+    }
+}
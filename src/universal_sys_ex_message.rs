@@ -0,0 +1,427 @@
+use crate::{
+    build_14_bit_value_from_two_7_bit_values, extract_high_7_bit_value_from_14_bit_value,
+    extract_low_7_bit_value_from_14_bit_value, ManufacturerId, SysExError, SysExMessage,
+    SYS_EX_END, SYS_EX_START, U14, U7,
+};
+use core::convert::TryFrom;
+
+/// Device ID which addresses all devices ("all call").
+pub const ALL_CALL_DEVICE_ID: U7 = U7(0x7F);
+
+/// An error which can occur when trying to interpret a System Exclusive message as a specific
+/// Universal System Exclusive message.
+#[derive(Clone, Eq, PartialEq, Debug, derive_more::Display)]
+#[display(fmt = "System Exclusive message is not of the expected type")]
+pub struct UnexpectedSysExMessageError(pub(crate) ());
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnexpectedSysExMessageError {}
+
+/// Sub-ID #1 of General Information messages.
+const GENERAL_INFORMATION: u8 = 0x06;
+
+/// Sub-ID #2 of Identity Request messages.
+const IDENTITY_REQUEST: u8 = 0x01;
+
+/// Sub-ID #2 of Identity Reply messages.
+const IDENTITY_REPLY: u8 = 0x02;
+
+/// A Universal Non-Real Time Identity Request message (`F0 7E <device ID> 06 01 F7`).
+///
+/// Devices answer this request with an [`IdentityReply`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::{IdentityRequest, SysExMessage, ALL_CALL_DEVICE_ID};
+/// use std::convert::TryFrom;
+///
+/// let request = IdentityRequest::new(ALL_CALL_DEVICE_ID);
+/// let mut buffer = [0; IdentityRequest::BYTE_COUNT];
+/// let msg = request.write(&mut buffer).unwrap();
+/// assert_eq!(msg.as_bytes(), &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]);
+/// assert_eq!(IdentityRequest::try_from(msg), Ok(request));
+/// ```
+///
+/// [`IdentityReply`]: struct.IdentityReply.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct IdentityRequest {
+    device_id: U7,
+}
+
+impl IdentityRequest {
+    /// Number of bytes of this message, including start and end byte.
+    pub const BYTE_COUNT: usize = 6;
+
+    /// Creates an Identity Request addressed to the given device.
+    ///
+    /// Use [`ALL_CALL_DEVICE_ID`] in order to address all devices.
+    ///
+    /// [`ALL_CALL_DEVICE_ID`]: constant.ALL_CALL_DEVICE_ID.html
+    pub fn new(device_id: U7) -> IdentityRequest {
+        IdentityRequest { device_id }
+    }
+
+    /// Returns the ID of the addressed device.
+    pub fn device_id(&self) -> U7 {
+        self.device_id
+    }
+
+    /// Writes this request as System Exclusive message into the given buffer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small.
+    pub fn write<'a>(&self, buffer: &'a mut [u8]) -> Result<SysExMessage<'a>, SysExError> {
+        write_universal_message(
+            buffer,
+            ManufacturerId::UNIVERSAL_NON_REAL_TIME,
+            self.device_id,
+            GENERAL_INFORMATION,
+            IDENTITY_REQUEST,
+            &[],
+        )
+    }
+}
+
+impl<'a> TryFrom<SysExMessage<'a>> for IdentityRequest {
+    type Error = UnexpectedSysExMessageError;
+
+    fn try_from(msg: SysExMessage<'a>) -> Result<Self, Self::Error> {
+        match parse_universal_message(msg, ManufacturerId::UNIVERSAL_NON_REAL_TIME)? {
+            (device_id, GENERAL_INFORMATION, IDENTITY_REQUEST, []) => {
+                Ok(IdentityRequest::new(device_id))
+            }
+            _ => Err(UnexpectedSysExMessageError(())),
+        }
+    }
+}
+
+/// A Universal Non-Real Time Identity Reply message, sent by a device as answer to an
+/// [`IdentityRequest`].
+///
+/// # Example
+///
+/// ```
+/// use helgoboss_midi::test_util::{u14, u7};
+/// use helgoboss_midi::{IdentityReply, ManufacturerId, SysExMessage};
+/// use std::convert::TryFrom;
+///
+/// let msg = SysExMessage::from_bytes(&[
+///     0xF0, 0x7E, 0x10, 0x06, 0x02, 0x41, 0x0E, 0x02, 0x03, 0x00, 0x01, 0x02, 0x03, 0x04, 0xF7,
+/// ])
+/// .unwrap();
+/// let reply = IdentityReply::try_from(msg).unwrap();
+/// assert_eq!(reply.device_id(), u7(0x10));
+/// assert_eq!(reply.manufacturer_id(), ManufacturerId::Standard(u7(0x41)));
+/// assert_eq!(reply.family_code(), u14(0x010E));
+/// assert_eq!(reply.family_member_code(), u14(3));
+/// assert_eq!(reply.software_revision_level(), [u7(1), u7(2), u7(3), u7(4)]);
+/// ```
+///
+/// [`IdentityRequest`]: struct.IdentityRequest.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct IdentityReply {
+    device_id: U7,
+    manufacturer_id: ManufacturerId,
+    family_code: U14,
+    family_member_code: U14,
+    software_revision_level: [U7; 4],
+}
+
+impl IdentityReply {
+    /// Creates an Identity Reply.
+    pub fn new(
+        device_id: U7,
+        manufacturer_id: ManufacturerId,
+        family_code: U14,
+        family_member_code: U14,
+        software_revision_level: [U7; 4],
+    ) -> IdentityReply {
+        IdentityReply {
+            device_id,
+            manufacturer_id,
+            family_code,
+            family_member_code,
+            software_revision_level,
+        }
+    }
+
+    /// Returns the ID of the replying device.
+    pub fn device_id(&self) -> U7 {
+        self.device_id
+    }
+
+    /// Returns the ID of the manufacturer of the replying device.
+    pub fn manufacturer_id(&self) -> ManufacturerId {
+        self.manufacturer_id
+    }
+
+    /// Returns the device family code (transmitted LSB first).
+    pub fn family_code(&self) -> U14 {
+        self.family_code
+    }
+
+    /// Returns the device family member code (transmitted LSB first).
+    pub fn family_member_code(&self) -> U14 {
+        self.family_member_code
+    }
+
+    /// Returns the software revision level. Its format is manufacturer-specific.
+    pub fn software_revision_level(&self) -> [U7; 4] {
+        self.software_revision_level
+    }
+
+    /// Returns the number of bytes of this message, including start and end byte.
+    pub fn byte_count(&self) -> usize {
+        14 + self.manufacturer_id.byte_count()
+    }
+
+    /// Writes this reply as System Exclusive message into the given buffer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer is too small or the manufacturer ID is invalid.
+    pub fn write<'a>(&self, buffer: &'a mut [u8]) -> Result<SysExMessage<'a>, SysExError> {
+        let mut data = [0; 11];
+        let mid_count = self.manufacturer_id.write(&mut data)?;
+        let [r1, r2, r3, r4] = self.software_revision_level;
+        data[mid_count..mid_count + 8].copy_from_slice(&[
+            extract_low_7_bit_value_from_14_bit_value(self.family_code).get(),
+            extract_high_7_bit_value_from_14_bit_value(self.family_code).get(),
+            extract_low_7_bit_value_from_14_bit_value(self.family_member_code).get(),
+            extract_high_7_bit_value_from_14_bit_value(self.family_member_code).get(),
+            r1.get(),
+            r2.get(),
+            r3.get(),
+            r4.get(),
+        ]);
+        write_universal_message(
+            buffer,
+            ManufacturerId::UNIVERSAL_NON_REAL_TIME,
+            self.device_id,
+            GENERAL_INFORMATION,
+            IDENTITY_REPLY,
+            &data[..mid_count + 8],
+        )
+    }
+}
+
+impl<'a> TryFrom<SysExMessage<'a>> for IdentityReply {
+    type Error = UnexpectedSysExMessageError;
+
+    fn try_from(msg: SysExMessage<'a>) -> Result<Self, Self::Error> {
+        let (device_id, data) =
+            match parse_universal_message(msg, ManufacturerId::UNIVERSAL_NON_REAL_TIME)? {
+                (device_id, GENERAL_INFORMATION, IDENTITY_REPLY, data) => (device_id, data),
+                _ => return Err(UnexpectedSysExMessageError(())),
+            };
+        let manufacturer_id =
+            ManufacturerId::from_data(data).map_err(|_| UnexpectedSysExMessageError(()))?;
+        match data[manufacturer_id.byte_count()..] {
+            [f1, f2, m1, m2, r1, r2, r3, r4] => Ok(IdentityReply::new(
+                device_id,
+                manufacturer_id,
+                build_14_bit_value_from_two_7_bit_values(U7(f2), U7(f1)),
+                build_14_bit_value_from_two_7_bit_values(U7(m2), U7(m1)),
+                [U7(r1), U7(r2), U7(r3), U7(r4)],
+            )),
+            _ => Err(UnexpectedSysExMessageError(())),
+        }
+    }
+}
+
+/// Splits the given message into device ID, sub-ID #1, sub-ID #2 and the remaining data bytes if
+/// it's a Universal System Exclusive message with the given manufacturer ID.
+pub(crate) fn parse_universal_message(
+    msg: SysExMessage<'_>,
+    manufacturer_id: ManufacturerId,
+) -> Result<(U7, u8, u8, &[u8]), UnexpectedSysExMessageError> {
+    if msg.manufacturer_id() != manufacturer_id {
+        return Err(UnexpectedSysExMessageError(()));
+    }
+    match msg.payload() {
+        [device_id, sub_id_1, sub_id_2, data @ ..] => {
+            Ok((U7(*device_id), *sub_id_1, *sub_id_2, data))
+        }
+        _ => Err(UnexpectedSysExMessageError(())),
+    }
+}
+
+/// Writes a Universal System Exclusive message with the given manufacturer ID, header and data
+/// bytes into the given buffer.
+pub(crate) fn write_universal_message<'a>(
+    buffer: &'a mut [u8],
+    manufacturer_id: ManufacturerId,
+    device_id: U7,
+    sub_id_1: u8,
+    sub_id_2: u8,
+    data: &[u8],
+) -> Result<SysExMessage<'a>, SysExError> {
+    let header_start = 1 + manufacturer_id.byte_count();
+    let data_start = header_start + 3;
+    let count = data_start + data.len() + 1;
+    if buffer.len() < count {
+        return Err(SysExError::BufferTooSmall);
+    }
+    buffer[0] = SYS_EX_START;
+    manufacturer_id.write(&mut buffer[1..])?;
+    buffer[header_start..data_start].copy_from_slice(&[device_id.get(), sub_id_1, sub_id_2]);
+    buffer[data_start..count - 1].copy_from_slice(data);
+    buffer[count - 1] = SYS_EX_END;
+    SysExMessage::from_bytes(&buffer[..count])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{u14, u7};
+
+    #[test]
+    fn identity_request() {
+        // Given
+        let request = IdentityRequest::new(u7(0x10));
+        let mut buffer = [0; 8];
+        // When
+        let msg = request.write(&mut buffer).unwrap();
+        // Then
+        assert_eq!(msg.as_bytes(), &[0xF0, 0x7E, 0x10, 0x06, 0x01, 0xF7]);
+        assert_eq!(msg.as_bytes().len(), IdentityRequest::BYTE_COUNT);
+        assert_eq!(IdentityRequest::try_from(msg), Ok(request));
+        assert_eq!(
+            request.write(&mut buffer[..5]),
+            Err(SysExError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn long_universal_message() {
+        // Given
+        let data = [0x55; 40];
+        let mut buffer = [0; 64];
+        // When
+        let msg = write_universal_message(
+            &mut buffer,
+            ManufacturerId::UNIVERSAL_REAL_TIME,
+            u7(0x7F),
+            0x06,
+            0x01,
+            &data,
+        )
+        .unwrap();
+        // Then
+        assert_eq!(msg.as_bytes().len(), 46);
+        assert_eq!(msg.payload()[3..], data[..]);
+        assert_eq!(
+            write_universal_message(
+                &mut buffer[..45],
+                ManufacturerId::UNIVERSAL_REAL_TIME,
+                u7(0x7F),
+                0x06,
+                0x01,
+                &data,
+            ),
+            Err(SysExError::BufferTooSmall)
+        );
+        assert_eq!(
+            write_universal_message(
+                &mut buffer,
+                ManufacturerId::UNIVERSAL_REAL_TIME,
+                u7(0x7F),
+                0x06,
+                0x01,
+                &[0x80],
+            ),
+            Err(SysExError::InvalidDataByte)
+        );
+    }
+
+    #[test]
+    fn identity_request_unexpected() {
+        // Given
+        let msgs: [&[u8]; 4] = [
+            &[0xF0, 0x7F, 0x10, 0x06, 0x01, 0xF7],
+            &[0xF0, 0x7E, 0x10, 0x06, 0x02, 0xF7],
+            &[0xF0, 0x7E, 0x10, 0x06, 0x01, 0x00, 0xF7],
+            &[0xF0, 0x7E, 0x10, 0x06, 0xF7],
+        ];
+        // When
+        // Then
+        for bytes in msgs.iter() {
+            let msg = SysExMessage::from_bytes(bytes).unwrap();
+            assert_eq!(
+                IdentityRequest::try_from(msg),
+                Err(UnexpectedSysExMessageError(()))
+            );
+        }
+    }
+
+    #[test]
+    fn identity_reply_standard_manufacturer_id() {
+        // Given
+        let reply = IdentityReply::new(
+            u7(0x7F),
+            ManufacturerId::Standard(u7(0x43)),
+            u14(0x0200),
+            u14(0x3FFF),
+            [u7(0), u7(1), u7(2), u7(0x7F)],
+        );
+        let mut buffer = [0; 32];
+        // When
+        let msg = reply.write(&mut buffer).unwrap();
+        // Then
+        assert_eq!(
+            msg.as_bytes(),
+            &[
+                0xF0, 0x7E, 0x7F, 0x06, 0x02, 0x43, 0x00, 0x04, 0x7F, 0x7F, 0x00, 0x01, 0x02, 0x7F,
+                0xF7
+            ]
+        );
+        assert_eq!(msg.as_bytes().len(), reply.byte_count());
+        assert_eq!(IdentityReply::try_from(msg), Ok(reply));
+    }
+
+    #[test]
+    fn identity_reply_extended_manufacturer_id() {
+        // Given
+        let reply = IdentityReply::new(
+            u7(0x00),
+            ManufacturerId::Extended(u7(0x20), u7(0x29)),
+            u14(0x69),
+            u14(1),
+            [u7(1), u7(0), u7(0), u7(0)],
+        );
+        let mut buffer = [0; 32];
+        // When
+        let msg = reply.write(&mut buffer).unwrap();
+        // Then
+        assert_eq!(
+            msg.as_bytes(),
+            &[
+                0xF0, 0x7E, 0x00, 0x06, 0x02, 0x00, 0x20, 0x29, 0x69, 0x00, 0x01, 0x00, 0x01, 0x00,
+                0x00, 0x00, 0xF7
+            ]
+        );
+        assert_eq!(msg.as_bytes().len(), reply.byte_count());
+        assert_eq!(IdentityReply::try_from(msg), Ok(reply));
+    }
+
+    #[test]
+    fn identity_reply_unexpected() {
+        // Given
+        let msgs: [&[u8]; 3] = [
+            &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7],
+            &[0xF0, 0x7E, 0x7F, 0x06, 0x02, 0x43, 0x00, 0x04, 0x7F, 0xF7],
+            &[0xF0, 0x7E, 0x7F, 0x06, 0x02, 0x00, 0xF7],
+        ];
+        // When
+        // Then
+        for bytes in msgs.iter() {
+            let msg = SysExMessage::from_bytes(bytes).unwrap();
+            assert_eq!(
+                IdentityReply::try_from(msg),
+                Err(UnexpectedSysExMessageError(()))
+            );
+        }
+    }
+}